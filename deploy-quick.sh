@@ -43,7 +43,7 @@ echo "🦀 Step 5: Deploy Node (Rust daemon)..."
 ssh $HOST << 'ENDSSH'
 cd /opt/oneclaw/oneclaw-node
 cargo build --release
-pm2 restart oneclaw-node || pm2 start --name oneclaw-node "./target/release/oneclaw-node daemon"
+pm2 restart oneclaw-node || pm2 start --name oneclaw-node "./target/release/oneclaw-node daemon --bind 0.0.0.0"
 ENDSSH
 
 echo ""
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 /// Live agent files live in the workspace (main folder). Templates in repo are for copying only.
 fn workspace_dir() -> Option<PathBuf> {
-    dirs::home_dir().map(|h| h.join(".oneclaw").join("workspace"))
+    crate::config::workspace_dir()
 }
 
 /// Repo templates dir (for fallback / first-run when workspace is empty).
@@ -33,24 +33,111 @@ pub struct AgentOS {
     pub skills: String,
     pub playbooks: String,
     pub memory: String,
+    /// Optional WELCOME.md: greeting for a user's first message (see `welcome_message`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub welcome: Option<String>,
+}
+
+/// Marker in the stand-in text for a missing file
+const PLACEHOLDER_MARKER: &str = "(Not Found)";
+
+/// The Agent OS files, in prompt order
+pub const AGENT_OS_FILES: [&str; 5] = ["SOUL.md", "IDENTITY.md", "SKILLS.md", "PLAYBOOKS.md", "MEMORY.md"];
+
+/// The repo templates, compiled in: the fallback when no Agent OS dir exists, and what
+/// `bootstrap` copies into a fresh workspace
+const EMBEDDED_FILES: [(&str, &str); 6] = [
+    ("SOUL.md", include_str!("../templates/SOUL.md")),
+    ("IDENTITY.md", include_str!("../templates/IDENTITY.md")),
+    ("SKILLS.md", include_str!("../templates/SKILLS.md")),
+    ("PLAYBOOKS.md", include_str!("../templates/PLAYBOOKS.md")),
+    ("MEMORY.md", include_str!("../templates/MEMORY.md")),
+    ("HEARTBEAT.md", include_str!("../templates/HEARTBEAT.md")),
+];
+
+/// Copy the built-in Agent OS files into `dir`, creating it. Existing files are kept;
+/// returns the names of the files written.
+pub fn bootstrap(dir: &std::path::Path) -> anyhow::Result<Vec<&'static str>> {
+    fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dir.display(), e))?;
+    let mut written = Vec::new();
+    for (name, text) in EMBEDDED_FILES {
+        let path = dir.join(name);
+        if !path.exists() {
+            fs::write(&path, text).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+            written.push(name);
+        }
+    }
+    Ok(written)
+}
+
+/// True for empty text or the stand-in `load_file` returns for a missing file
+pub fn is_placeholder(text: &str) -> bool {
+    text.trim().is_empty() || text.contains(PLACEHOLDER_MARKER)
+}
+
+/// Substitute `{{name}}` with values from `vars`. Unknown names are left intact.
+fn render_template(text: &str, vars: &std::collections::HashMap<&str, String>) -> String {
+    static VAR: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = VAR.get_or_init(|| regex::Regex::new(r"\{\{\s*([A-Za-z_][A-Za-z0-9_]*)\s*\}\}").unwrap());
+    re.replace_all(text, |caps: &regex::Captures| match vars.get(&caps[1]) {
+        Some(value) => value.clone(),
+        None => {
+            tracing::warn!(var = %&caps[1], "Unknown Agent OS template variable; leaving as-is");
+            caps[0].to_string()
+        }
+    })
+    .into_owned()
+}
+
+/// Variables available to Agent OS files: node_id, node_name, environment,
+/// date, time, datetime (UTC) and tool_count
+fn template_vars(tool_count: usize) -> std::collections::HashMap<&'static str, String> {
+    let now = chrono::Utc::now();
+    let mut vars = std::collections::HashMap::from([
+        ("date", now.format("%Y-%m-%d").to_string()),
+        ("time", now.format("%H:%M UTC").to_string()),
+        ("datetime", now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+        ("tool_count", tool_count.to_string()),
+    ]);
+    if let Ok(config) = crate::config::load() {
+        vars.insert("node_id", config.node.id.clone());
+        vars.insert("node_name", config.node.name.clone());
+        vars.insert("environment", config.node.environment.clone());
+    }
+    vars
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Present,
+    Missing,
+    Empty,
+    /// File exists but still holds the "(Not Found)" stand-in text
+    Placeholder,
+}
+
+/// Result of checking one Agent OS file
+#[derive(Debug, Clone)]
+pub struct FileCheck {
+    pub name: &'static str,
+    pub path: PathBuf,
+    pub status: FileStatus,
 }
 
 impl AgentOS {
-    /// Load agent OS: first from main workspace (~/.oneclaw/workspace), then from repo templates.
-    pub fn load(templates_dir_override: Option<PathBuf>) -> anyhow::Result<Self> {
+    /// Directory `load` would read from, and which source it came from
+    pub fn resolve_dir(templates_dir_override: Option<PathBuf>) -> anyhow::Result<(PathBuf, &'static str)> {
         // 1) Explicit override
         if let Some(d) = templates_dir_override {
             if d.exists() {
-                tracing::info!("Agent OS dir (override): {}", d.display());
-                return Self::load_from_dir(&d);
+                return Ok((d, "override"));
             }
         }
 
-        // 2) Main folder: ~/.oneclaw/workspace (live agent, like OpenClaw)
+        // 2) Main folder: ~/.oneclaw/workspace or the configured one (live agent, like OpenClaw)
         if let Some(workspace) = workspace_dir() {
             if workspace.exists() {
-                tracing::info!("Agent OS dir (workspace): {}", workspace.display());
-                return Self::load_from_dir(&workspace);
+                return Ok((workspace, "workspace"));
             }
         }
 
@@ -59,11 +146,46 @@ impl AgentOS {
             .into_iter()
             .find(|d| d.exists())
             .ok_or_else(|| anyhow::anyhow!("No agent workspace or templates dir found. Create ~/.oneclaw/workspace/ with SOUL.md, IDENTITY.md, etc., or run from repo with oneclaw-node/templates/."))?;
+        Ok((dir, "templates fallback"))
+    }
+
+    /// The built-in files (safe mode when there's no workspace or templates dir)
+    pub fn embedded() -> Self {
+        let file = |name: &str| EMBEDDED_FILES.iter().find(|(n, _)| *n == name).map(|(_, text)| text.to_string()).unwrap_or_default();
+        Self {
+            soul: file("SOUL.md"),
+            identity: file("IDENTITY.md"),
+            skills: file("SKILLS.md"),
+            playbooks: file("PLAYBOOKS.md"),
+            memory: file("MEMORY.md"),
+            welcome: None,
+        }
+    }
 
-        tracing::info!("Agent OS dir (templates fallback): {}", dir.display());
+    /// Load agent OS: first from main workspace (~/.oneclaw/workspace), then from repo templates.
+    pub fn load(templates_dir_override: Option<PathBuf>) -> anyhow::Result<Self> {
+        let (dir, source) = Self::resolve_dir(templates_dir_override)?;
+        tracing::info!("Agent OS dir ({}): {}", source, dir.display());
         Self::load_from_dir(&dir)
     }
 
+    /// Status of each Agent OS file in `dir`
+    pub fn check_dir(dir: &std::path::Path) -> Vec<FileCheck> {
+        AGENT_OS_FILES
+            .iter()
+            .map(|&name| {
+                let path = dir.join(name);
+                let status = match fs::read_to_string(&path) {
+                    Err(_) => FileStatus::Missing,
+                    Ok(text) if text.trim().is_empty() => FileStatus::Empty,
+                    Ok(text) if text.contains(PLACEHOLDER_MARKER) => FileStatus::Placeholder,
+                    Ok(_) => FileStatus::Present,
+                };
+                FileCheck { name, path, status }
+            })
+            .collect()
+    }
+
     fn load_from_dir(dir: &PathBuf) -> anyhow::Result<Self> {
         Ok(Self {
             soul: Self::load_file(dir, "SOUL.md")?,
@@ -71,6 +193,7 @@ impl AgentOS {
             skills: Self::load_file(dir, "SKILLS.md")?,
             playbooks: Self::load_file(dir, "PLAYBOOKS.md")?,
             memory: Self::load_file(dir, "MEMORY.md")?,
+            welcome: fs::read_to_string(dir.join("WELCOME.md")).ok().filter(|text| !text.trim().is_empty()),
         })
     }
 
@@ -80,48 +203,40 @@ impl AgentOS {
             fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", name, e))
         } else {
             tracing::warn!("Agent OS file not found: {:?}", path);
-            Ok(format!("# {} (Not Found)\n\nAdd this file to your workspace or use the repo template.", name.replace(".md", "")))
+            Ok(format!("# {} {}\n\nAdd this file to your workspace or use the repo template.", name.replace(".md", ""), PLACEHOLDER_MARKER))
         }
     }
 
+    /// Greeting prepended to the reply to a user's first message: `agent.welcome` from
+    /// node.yaml, else WELCOME.md, with template variables filled in. `None` when neither is set.
+    pub fn welcome_message(&self, configured: Option<&str>, tool_count: usize) -> Option<String> {
+        let text = configured.or(self.welcome.as_deref()).filter(|text| !text.trim().is_empty())?;
+        Some(render_template(text.trim(), &template_vars(tool_count)))
+    }
+
+    /// Full system prompt. Missing/placeholder files are left out rather than
+    /// embedding "(Not Found)" text the model would take as instructions.
     pub fn build_system_prompt(&self, tool_registry: &[ToolDefinition]) -> String {
         let tools_section = self.format_tool_registry(tool_registry);
+        let vars = template_vars(tool_registry.len());
         
-        format!(
-            r#"{}
-
----
-
-# IDENTITY
-{}
-
----
-
-# SKILLS
-{}
-
----
-
-# PLAYBOOKS
-{}
-
----
-
-# MEMORY
-{}
-
----
-
-# AVAILABLE TOOLS (from Harness)
-{}
-"#,
-            self.soul,
-            self.identity,
-            self.skills,
-            self.playbooks,
-            self.memory,
-            tools_section
-        )
+        let mut sections = Vec::new();
+        if !is_placeholder(&self.soul) {
+            sections.push(render_template(&self.soul, &vars));
+        }
+        for (title, body) in [
+            ("IDENTITY", &self.identity),
+            ("SKILLS", &self.skills),
+            ("PLAYBOOKS", &self.playbooks),
+            ("MEMORY", &self.memory),
+        ] {
+            if !is_placeholder(body) {
+                sections.push(format!("# {}\n{}", title, render_template(body, &vars)));
+            }
+        }
+        sections.push(format!("# AVAILABLE TOOLS (from Harness)\n{}", tools_section));
+        
+        sections.join("\n\n---\n\n")
     }
 
     /// Minimal system prompt with SOUL personality + tools. Keeps personality while avoiding bloat.
@@ -138,10 +253,10 @@ impl AgentOS {
             .trim()
             .to_string();
         
-        let personality = if soul_core.is_empty() || soul_core.contains("Not Found") {
+        let personality = if is_placeholder(&soul_core) {
             "You are OneClaw, a helpful AI assistant.".to_string()
         } else {
-            soul_core
+            render_template(&soul_core, &template_vars(tool_registry.len()))
         };
         
         format!(
@@ -181,6 +296,7 @@ Respond naturally. If you use a tool, I'll execute it and you can summarize the
         }
         
         output.push_str("\nTo execute a harness tool:\n```tool\n{\"tool\": \"harness.execute\", \"input\": {\"executor\": \"TOOL_ID\", \"params\": {...}}}\n```\n");
+        output.push_str("\nTo check on a background job from its `job_id`, use `harness.job_status`; to stop it, `harness.cancel`:\n```tool\n{\"tool\": \"harness.job_status\", \"input\": {\"job_id\": \"JOB_ID\"}}\n```\n");
         
         output
     }
@@ -236,10 +352,32 @@ pub struct ToolDefinition {
     pub id: String,
     pub description: String,
     pub params_schema: Option<serde_json::Value>,
+    /// Shape of the tool's output, when the harness declares one
+    pub output_schema: Option<serde_json::Value>,
     pub cost_estimate: Option<f64>,
     pub tier: Option<String>,
 }
 
+/// Harness tool list shared by the daemon and heartbeat. Starts empty when the harness
+/// is down at boot and is filled in once a background refresh reaches it.
+#[derive(Debug, Clone, Default)]
+pub struct SharedTools(std::sync::Arc<std::sync::RwLock<std::sync::Arc<Vec<ToolDefinition>>>>);
+
+impl SharedTools {
+    pub fn new(tools: Vec<ToolDefinition>) -> Self {
+        Self(std::sync::Arc::new(std::sync::RwLock::new(std::sync::Arc::new(tools))))
+    }
+
+    /// Current list; cheap to take per request
+    pub fn get(&self) -> std::sync::Arc<Vec<ToolDefinition>> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, tools: Vec<ToolDefinition>) {
+        *self.0.write().unwrap() = std::sync::Arc::new(tools);
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HarnessToolRegistry {
     pub tools: Vec<ToolDefinition>,
@@ -278,3 +416,54 @@ impl HarnessToolRegistry {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_embedded_files_fill_every_section_and_bootstrap_keeps_edits() {
+        let embedded = AgentOS::embedded();
+        for text in [&embedded.soul, &embedded.identity, &embedded.skills, &embedded.playbooks, &embedded.memory] {
+            assert!(!is_placeholder(text));
+        }
+        
+        let dir = std::env::temp_dir().join(format!("oneclaw-bootstrap-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("SOUL.md"), "# Soul\n\nMine.").unwrap();
+        let written = bootstrap(&dir).unwrap();
+        assert_eq!(written, ["IDENTITY.md", "SKILLS.md", "PLAYBOOKS.md", "MEMORY.md", "HEARTBEAT.md"]);
+        assert_eq!(fs::read_to_string(dir.join("SOUL.md")).unwrap(), "# Soul\n\nMine.");
+        assert!(AgentOS::check_dir(&dir).iter().all(|c| c.status == FileStatus::Present));
+        assert!(bootstrap(&dir).unwrap().is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+    
+    #[test]
+    fn test_render_template() {
+        let vars = std::collections::HashMap::from([("node_name", "Edge".to_string()), ("date", "2025-01-01".to_string())]);
+        assert_eq!(
+            render_template("You are {{node_name}}, today is {{ date }}. {{unknown}}", &vars),
+            "You are Edge, today is 2025-01-01. {{unknown}}"
+        );
+    }
+    
+    #[test]
+    fn test_welcome_message_prefers_config() {
+        let mut agent_os = AgentOS {
+            soul: String::new(),
+            identity: String::new(),
+            skills: String::new(),
+            playbooks: String::new(),
+            memory: String::new(),
+            welcome: None,
+        };
+        assert_eq!(agent_os.welcome_message(None, 3), None);
+        assert_eq!(agent_os.welcome_message(Some("  "), 3), None);
+        
+        agent_os.welcome = Some("Hi! I can use {{tool_count}} tools.\n".to_string());
+        assert_eq!(agent_os.welcome_message(None, 3).as_deref(), Some("Hi! I can use 3 tools."));
+        assert_eq!(agent_os.welcome_message(Some("Welcome aboard."), 3).as_deref(), Some("Welcome aboard."));
+    }
+}
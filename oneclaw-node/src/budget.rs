@@ -0,0 +1,186 @@
+//! Spend Budgets
+//!
+//! Daily USD caps (see `BudgetConfig`) on paid calls: `llm.chat`, costed from its token
+//! usage, and `harness.execute`, costed from the tool's `cost_estimate`. Spend is kept
+//! in the store per UTC day for the node (`"node"`) and each chat user (`"user:<id>"`).
+//! Calls are checked before they run, so the last call of a day may overshoot a little.
+
+use crate::agent_os::SharedTools;
+use crate::config::BudgetConfig;
+use crate::executor::{DenialReason, ExecutorResult};
+use crate::store::Store;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+const NODE_SCOPE: &str = "node";
+
+pub struct Budget {
+    store: Arc<dyn Store>,
+    config: BudgetConfig,
+    /// Source of harness `cost_estimate`s
+    harness_tools: SharedTools,
+}
+
+/// Spend against one limit, as reported by `/budget`
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Spend {
+    pub spent_usd: f64,
+    pub limit_usd: Option<f64>,
+    pub remaining_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BudgetStatus {
+    /// UTC day the totals are for (`YYYY-MM-DD`)
+    pub day: String,
+    pub node: Spend,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<Spend>,
+}
+
+/// Executors whose calls cost money
+pub fn is_paid(executor_id: &str) -> bool {
+    matches!(executor_id, "llm.chat" | "harness.execute")
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+fn user_scope(user_id: &str) -> String {
+    format!("user:{}", user_id)
+}
+
+fn spend(spent_usd: f64, limit_usd: Option<f64>) -> Spend {
+    Spend { spent_usd, limit_usd, remaining_usd: limit_usd.map(|limit| (limit - spent_usd).max(0.0)) }
+}
+
+impl Budget {
+    pub fn new(store: Arc<dyn Store>, config: BudgetConfig, harness_tools: SharedTools) -> Self {
+        Self { store, config, harness_tools }
+    }
+
+    /// `Some(denial)` when the node's or `user_id`'s daily budget is used up.
+    /// A store error lets the call through rather than taking the bot down with it.
+    pub async fn check(&self, user_id: Option<&str>, executor_id: &str) -> Option<DenialReason> {
+        let day = today();
+        let mut limits = vec![(NODE_SCOPE.to_string(), self.config.daily_usd, "budget.daily_usd", "node")];
+        if let Some(user_id) = user_id {
+            limits.push((user_scope(user_id), self.config.user_limit(user_id), "budget.per_user_daily_usd", "user"));
+        }
+
+        for (scope, limit, rule, whose) in limits {
+            let Some(limit) = limit else { continue };
+            match self.store.get_spend(&scope, &day).await {
+                Ok(spent) if spent >= limit => {
+                    tracing::warn!("Budget exhausted for {}: ${:.4} of ${:.2}; denying {}", scope, spent, limit, executor_id);
+                    return Some(DenialReason {
+                        rule: rule.to_string(),
+                        attempted: executor_id.to_string(),
+                        policy: format!(
+                            "The {} daily budget of ${:.2} is used up (spent ${:.2}); paid calls resume tomorrow (UTC)",
+                            whose, limit, spent
+                        ),
+                    });
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Could not read spend for {}: {}", scope, e),
+            }
+        }
+        None
+    }
+
+    /// Record what a finished call cost against the node and `user_id`
+    pub async fn charge(&self, user_id: Option<&str>, executor_id: &str, input: &Value, result: &ExecutorResult) {
+        let usd = self.cost(executor_id, input, result);
+        if usd <= 0.0 {
+            return;
+        }
+        let day = today();
+        let mut scopes = vec![NODE_SCOPE.to_string()];
+        scopes.extend(user_id.map(user_scope));
+        for scope in scopes {
+            if let Err(e) = self.store.add_spend(&scope, &day, usd).await {
+                tracing::warn!("Could not record ${:.4} spend for {}: {}", usd, scope, e);
+            }
+        }
+    }
+
+    /// Estimated USD cost of a call; 0 for failed, cached or free calls
+    pub fn cost(&self, executor_id: &str, input: &Value, result: &ExecutorResult) -> f64 {
+        let ExecutorResult::Executed { output, .. } = result else {
+            return 0.0;
+        };
+        match executor_id {
+            "llm.chat" if output["cached"] != Value::Bool(true) => {
+                let tokens = |key: &str| output["usage"][key].as_f64().unwrap_or(0.0);
+                (tokens("input_tokens") * self.config.llm_input_usd_per_mtok
+                    + tokens("output_tokens") * self.config.llm_output_usd_per_mtok)
+                    / 1_000_000.0
+            }
+            "harness.execute" => self.harness_tool_cost(input["executor"].as_str().unwrap_or_default()),
+            _ => 0.0,
+        }
+    }
+
+    /// `cost_estimate` of a harness tool; 0 for unknown tools
+    pub fn harness_tool_cost(&self, tool: &str) -> f64 {
+        self.harness_tools.get().iter().find(|t| t.id == tool).and_then(|t| t.cost_estimate).unwrap_or(0.0)
+    }
+
+    pub async fn status(&self, user_id: Option<&str>) -> anyhow::Result<BudgetStatus> {
+        let day = today();
+        let node = spend(self.store.get_spend(NODE_SCOPE, &day).await?, self.config.daily_usd);
+        let user = match user_id {
+            Some(user_id) => Some(spend(self.store.get_spend(&user_scope(user_id), &day).await?, self.config.user_limit(user_id))),
+            None => None,
+        };
+        Ok(BudgetStatus { day, node, user_id: user_id.map(str::to_string), user })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent_os::ToolDefinition;
+
+    #[tokio::test]
+    async fn test_budget_denies_once_spent() {
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        let config = BudgetConfig { daily_usd: Some(1.0), per_user_daily_usd: Some(0.05), ..Default::default() };
+        let tools = SharedTools::new(vec![ToolDefinition {
+            id: "golf.search".to_string(),
+            description: String::new(),
+            params_schema: None,
+            output_schema: None,
+            cost_estimate: Some(0.04),
+            tier: None,
+        }]);
+        let budget = Budget::new(store, config, tools);
+
+        let llm = ExecutorResult::Executed {
+            output: serde_json::json!({ "usage": { "input_tokens": 1000, "output_tokens": 1000 } }),
+            duration_ms: 1,
+        };
+        assert!((budget.cost("llm.chat", &Value::Null, &llm) - 0.018).abs() < 1e-9);
+        let harness_input = serde_json::json!({ "executor": "golf.search", "params": {} });
+        let harness = ExecutorResult::Executed { output: Value::Null, duration_ms: 1 };
+
+        assert!(budget.check(Some("alice"), "harness.execute").await.is_none());
+        budget.charge(Some("alice"), "harness.execute", &harness_input, &harness).await;
+        budget.charge(Some("alice"), "llm.chat", &Value::Null, &llm).await;
+
+        let denial = budget.check(Some("alice"), "llm.chat").await.expect("alice is over budget");
+        assert_eq!(denial.rule, "budget.per_user_daily_usd");
+        assert!(budget.check(Some("bob"), "llm.chat").await.is_none());
+
+        let status = budget.status(Some("alice")).await.unwrap();
+        assert!((status.node.spent_usd - 0.058).abs() < 1e-9);
+        assert_eq!(status.user.unwrap().remaining_usd, Some(0.0));
+    }
+}
@@ -0,0 +1,122 @@
+//! Workflow Completion Callbacks
+//!
+//! `/run` with a `callback_url` returns at once and POSTs the outcome there when the run
+//! ends. The URL must pass `http.allowed_domains`. Each delivery carries
+//! `X-OneClaw-Timestamp` and, when `callbacks.secret_env` is set,
+//! `X-OneClaw-Signature: v1=<hex hmac-sha256 of "<timestamp>.<body>">` so the receiver
+//! can check it came from this node.
+
+use crate::config::CallbacksConfig;
+use crate::receipt::WorkflowReceipt;
+use ring::hmac;
+use serde::Serialize;
+
+pub const SIGNATURE_HEADER: &str = "x-oneclaw-signature";
+pub const TIMESTAMP_HEADER: &str = "x-oneclaw-timestamp";
+
+/// Body POSTed to the callback URL
+#[derive(Debug, Serialize)]
+pub struct RunOutcome {
+    pub run_id: String,
+    pub workflow_id: String,
+    /// The receipt's status, or `failed` / `cancelled` when the run ended without one
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<WorkflowReceipt>,
+}
+
+impl RunOutcome {
+    pub fn from_receipt(receipt: WorkflowReceipt) -> Self {
+        Self {
+            run_id: receipt.run_id.clone(),
+            workflow_id: receipt.workflow_id.clone(),
+            status: receipt.status.clone(),
+            error: None,
+            receipt: Some(receipt),
+        }
+    }
+}
+
+/// Reject callback URLs that aren't http(s) or whose host isn't in `http.allowed_domains`
+pub fn check_url(url: &str, allowed_domains: &[String]) -> anyhow::Result<()> {
+    let parsed = url::Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid callback_url: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        anyhow::bail!("callback_url must be http or https");
+    }
+    let domain = parsed.host_str().unwrap_or_default();
+    if !crate::executor::is_domain_allowed(allowed_domains, domain) {
+        anyhow::bail!("callback_url domain '{}' is not in http.allowed_domains", domain);
+    }
+    Ok(())
+}
+
+/// `v1=<hex hmac-sha256 of "<timestamp>.<body>">`
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    let tag = hmac::sign(&key, &message);
+    let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("v1={}", hex)
+}
+
+/// POST `outcome` to `url`, retrying connection errors, 429 and 5xx with backoff
+pub async fn deliver(url: &str, outcome: &RunOutcome, config: &CallbacksConfig) -> anyhow::Result<()> {
+    let body = serde_json::to_vec(outcome)?;
+    let secret = std::env::var(&config.secret_env).ok().filter(|s| !s.is_empty());
+    if secret.is_none() {
+        tracing::warn!("{} is not set; sending unsigned callback for run {}", config.secret_env, outcome.run_id);
+    }
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(config.timeout_secs))
+        .build()?;
+
+    let mut attempt = 0;
+    loop {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let mut req = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .body(body.clone());
+        if let Some(secret) = &secret {
+            req = req.header(SIGNATURE_HEADER, sign(secret, &timestamp, &body));
+        }
+
+        let failure = match req.send().await {
+            Ok(resp) if resp.status().is_success() => return Ok(()),
+            Ok(resp) if resp.status().as_u16() == 429 || resp.status().is_server_error() => format!("status {}", resp.status()),
+            Ok(resp) => anyhow::bail!("Callback to {} was rejected with {}", url, resp.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt >= config.max_retries {
+            anyhow::bail!("Callback to {} failed after {} attempt(s): {}", url, attempt + 1, failure);
+        }
+        attempt += 1;
+        let backoff = config.retry_backoff_ms.saturating_mul(1 << (attempt - 1).min(10));
+        tracing::debug!("Callback to {} failed ({}); retrying in {}ms", url, failure, backoff);
+        tokio::time::sleep(std::time::Duration::from_millis(backoff)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback_url_check_and_signature() {
+        let allowed = vec!["*.example.com".to_string()];
+        assert!(check_url("https://hooks.example.com/done", &allowed).is_ok());
+        assert!(check_url("https://evil.test/done", &allowed).is_err());
+        assert!(check_url("ftp://hooks.example.com/done", &allowed).is_err());
+
+        let signature = sign("secret", "1700000000", b"{\"run_id\":\"r1\"}");
+        let hex = signature.strip_prefix("v1=").unwrap();
+        let tag: Vec<u8> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect();
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        assert_eq!(tag.len(), 32);
+        assert!(hmac::verify(&key, b"1700000000.{\"run_id\":\"r1\"}", &tag).is_ok());
+    }
+}
@@ -0,0 +1,189 @@
+//! Request Cancellation
+//!
+//! `/chat` and `/run` do their work in a spawned task registered under a `request_id`;
+//! `POST /cancel/:request_id` aborts it at its next await point. Harness jobs the
+//! request started (executor outputs with a `job_id`) are remembered so the caller can
+//! ask the harness to stop them too.
+
+use crate::executor::ExecutorResult;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::task::AbortHandle;
+
+tokio::task_local! {
+    /// `request_id` of the cancellable task being polled
+    static CURRENT: String;
+}
+
+#[derive(Default)]
+pub struct Cancellations {
+    running: Mutex<HashMap<String, Running>>,
+    next_token: AtomicU64,
+}
+
+struct Running {
+    /// Which `Claim` owns the entry, so a finished request never removes a newer one
+    token: u64,
+    /// Set once the claimed work is spawned
+    abort: Option<AbortHandle>,
+    job_ids: Vec<String>,
+}
+
+/// A `request_id` reserved by `Cancellations::claim`. Dropping it unregisters the request
+/// and aborts its task, e.g. when the HTTP client went away first.
+pub struct Claim {
+    cancellations: Arc<Cancellations>,
+    request_id: String,
+    token: u64,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        let mut running = self.cancellations.running.lock().unwrap();
+        if running.get(&self.request_id).is_some_and(|r| r.token == self.token) {
+            if let Some(abort) = running.remove(&self.request_id).and_then(|r| r.abort) {
+                abort.abort();
+            }
+        }
+    }
+}
+
+/// What `cancel` stopped
+pub struct Cancelled {
+    /// Harness jobs the request started, which may still be running remotely
+    pub job_ids: Vec<String>,
+}
+
+/// Client-chosen ids double as run IDs (directory names), so keep them plain
+pub fn is_valid_request_id(request_id: &str) -> bool {
+    (1..=64).contains(&request_id.len()) && request_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Remember a harness job started by the current cancellable request, if any
+pub fn note_result(result: &ExecutorResult) {
+    if let Some(services) = crate::services::installed() {
+        services.cancellations.note_result(result);
+    }
+}
+
+impl Cancellations {
+    /// `note_result` for these cancellations
+    pub fn note_result(&self, result: &ExecutorResult) {
+        let ExecutorResult::Executed { output, .. } = result else {
+            return;
+        };
+        let Some(job_id) = output["job_id"].as_str() else {
+            return;
+        };
+        let _ = CURRENT.try_with(|request_id| {
+            if let Some(running) = self.running.lock().unwrap().get_mut(request_id) {
+                running.job_ids.push(job_id.to_string());
+            }
+        });
+    }
+    
+    /// Reserve `request_id` for a new request; `None` when one is already running under it
+    pub fn claim(self: &Arc<Self>, request_id: &str) -> Option<Claim> {
+        let mut running = self.running.lock().unwrap();
+        if running.contains_key(request_id) {
+            return None;
+        }
+        let token = self.next_token.fetch_add(1, Ordering::Relaxed);
+        running.insert(request_id.to_string(), Running { token, abort: None, job_ids: Vec::new() });
+        Some(Claim { cancellations: Arc::clone(self), request_id: request_id.to_string(), token })
+    }
+
+    /// Abort a running request; `None` when nothing runs under that id
+    pub fn cancel(&self, request_id: &str) -> Option<Cancelled> {
+        let running = self.running.lock().unwrap().remove(request_id)?;
+        if let Some(abort) = running.abort {
+            abort.abort();
+        }
+        tracing::info!(request_id = %request_id, "Cancelled request");
+        Some(Cancelled { job_ids: running.job_ids })
+    }
+
+    #[cfg(test)]
+    fn is_running(&self, request_id: &str) -> bool {
+        self.running.lock().unwrap().contains_key(request_id)
+    }
+}
+
+impl Claim {
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+    
+    /// Run `work` as a task that `cancel(request_id)` can abort. `None` when it was cancelled,
+    /// including before it started.
+    pub async fn run<F>(self, work: F) -> Option<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        // Spawned under the lock, so `note_result` always finds its entry
+        let task = {
+            let mut running = self.cancellations.running.lock().unwrap();
+            let entry = running.get_mut(&self.request_id).filter(|r| r.token == self.token)?;
+            let task = tokio::spawn(CURRENT.scope(self.request_id.clone(), work));
+            entry.abort = Some(task.abort_handle());
+            task
+        };
+        match task.await {
+            Ok(output) => Some(output),
+            Err(e) if e.is_cancelled() => None,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_aborts_running_request() {
+        let cancellations = Arc::new(Cancellations::default());
+
+        assert_eq!(cancellations.claim("quick").unwrap().run(async { 7 }).await, Some(7));
+        assert!(cancellations.cancel("quick").is_none());
+
+        let claim = cancellations.claim("slow").expect("slow is free");
+        assert!(cancellations.claim("slow").is_none());
+        let noted = Arc::clone(&cancellations);
+        let slow = tokio::spawn(async move {
+            claim
+                .run(async move {
+                    noted.note_result(&ExecutorResult::Executed { output: serde_json::json!({ "job_id": "job_1" }), duration_ms: 1 });
+                    tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+                })
+                .await
+        });
+        while !cancellations.is_running("slow") {
+            tokio::task::yield_now().await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        let cancelled = cancellations.cancel("slow").expect("slow is running");
+        assert_eq!(cancelled.job_ids, vec!["job_1".to_string()]);
+        assert!(slow.await.unwrap().is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_claim_owns_only_its_entry() {
+        let cancellations = Arc::new(Cancellations::default());
+        
+        // Cancelled between claim and run: the work never starts
+        let early = cancellations.claim("req").unwrap();
+        assert!(cancellations.cancel("req").is_some());
+        let newer = cancellations.claim("req").expect("free again after cancel");
+        assert_eq!(early.run(async { 1 }).await, None);
+        
+        // The stale claim finishing didn't unregister the newer request
+        assert!(cancellations.is_running("req"));
+        assert_eq!(newer.run(async { 2 }).await, Some(2));
+        assert!(!cancellations.is_running("req"));
+    }
+}
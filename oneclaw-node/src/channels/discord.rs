@@ -2,27 +2,80 @@
 //! 
 //! Connects to Discord Gateway via WebSocket for real-time message events.
 //! Handles:
-//! - Gateway connection and heartbeat
+//! - Gateway connection and heartbeat, with optional `zlib-stream` compression
+//! - Reconnects: RESUME after OP 7 (Reconnect) or a resumable OP 9 (Invalid Session),
+//!   a fresh IDENTIFY after a non-resumable one
 //! - Message events (mentions, DMs)
-//! - Sending responses back to Discord
+//! - Presence (activity/status, idle after inactivity)
+//! - Reaction acknowledgement while a message is being handled
+//! - Sending responses back to Discord, one at a time per channel and waiting out
+//!   rate limits (`X-RateLimit-*` headers, 429 `retry_after`)
 
 use async_trait::async_trait;
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, RwLock};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use tokio_tungstenite::tungstenite::Message;
 
-use super::{Channel, ChannelType, IncomingMessage, OutgoingMessage};
-use crate::config::DiscordChannelConfig;
+use super::{Attachment, Channel, ChannelType, IncomingMessage, OutgoingMessage, SentMessage};
+use crate::config::{DiscordChannelConfig, DiscordPresenceConfig};
 
 // Discord Gateway Opcodes
 const OP_DISPATCH: u8 = 0;
 const OP_HEARTBEAT: u8 = 1;
 const OP_IDENTIFY: u8 = 2;
+const OP_PRESENCE_UPDATE: u8 = 3;
+const OP_RESUME: u8 = 6;
+const OP_RECONNECT: u8 = 7;
+const OP_INVALID_SESSION: u8 = 9;
 const OP_HELLO: u8 = 10;
 const OP_HEARTBEAT_ACK: u8 = 11;
 
+const GATEWAY_URL: &str = "wss://gateway.discord.gg";
+
+/// Every complete `zlib-stream` message ends with this (a zlib sync flush)
+const ZLIB_SUFFIX: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Close code for dropping a connection we mean to resume (1000/1001 would end the session)
+const CLOSE_FOR_RESUME: u16 = 4000;
+
+/// Discord rejects message content longer than this
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// 429s tolerated for one request before the send fails (and the reply is dead-lettered)
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+/// A longer `retry_after` fails the send rather than holding up the channel's queue
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// Gateway intents by their config name
+const INTENTS: &[(&str, u64)] = &[
+    ("guilds", 1 << 0),
+    ("guild_members", 1 << 1),
+    ("guild_messages", 1 << 9),
+    ("guild_message_reactions", 1 << 10),
+    ("direct_messages", 1 << 12),
+    ("direct_message_reactions", 1 << 13),
+    ("message_content", 1 << 15),
+];
+const INTENT_MESSAGE_CONTENT: u64 = 1 << 15;
+
+/// Application flags saying the message content intent is enabled (verified or not)
+const FLAG_GATEWAY_MESSAGE_CONTENT: u64 = 1 << 18 | 1 << 19;
+
+/// Gateway close code for requesting a privileged intent the bot isn't granted
+const CLOSE_DISALLOWED_INTENTS: u16 = 4014;
+
+const MESSAGE_CONTENT_HINT: &str = "enable \"Message Content Intent\" under Bot → Privileged Gateway Intents \
+    in the Discord developer portal and keep \"message_content\" in channels.discord.intents";
+
 #[derive(Debug, Serialize, Deserialize)]
 struct GatewayPayload {
     op: u8,
@@ -40,6 +93,16 @@ struct DiscordMessage {
     #[serde(default)]
     mentions: Vec<DiscordUser>,
     guild_id: Option<String>,
+    #[serde(default)]
+    attachments: Vec<DiscordAttachment>,
+    #[serde(default)]
+    embeds: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DiscordAttachment {
+    url: String,
+    content_type: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,47 +117,270 @@ struct DiscordUser {
 struct ReadyEvent {
     user: DiscordUser,
     session_id: String,
+    #[serde(default)]
+    resume_gateway_url: Option<String>,
+}
+
+/// What outlives a gateway connection so the next one can RESUME instead of IDENTIFY
+#[derive(Debug, Default)]
+struct GatewaySession {
+    session_id: Option<String>,
+    /// `resume_gateway_url` from READY; resumes must connect there
+    resume_url: Option<String>,
+    sequence: Option<u64>,
+}
+
+impl GatewaySession {
+    fn gateway_url(&self, compress: bool) -> String {
+        let base = match (&self.session_id, &self.resume_url) {
+            (Some(_), Some(url)) => url.as_str(),
+            _ => GATEWAY_URL,
+        };
+        let compression = if compress { "&compress=zlib-stream" } else { "" };
+        format!("{}/?v=10&encoding=json{}", base.trim_end_matches('/'), compression)
+    }
+    
+    /// RESUME payload, when there is a session to resume
+    fn resume_payload(&self, token: &str) -> Option<GatewayPayload> {
+        let session_id = self.session_id.as_ref()?;
+        Some(GatewayPayload {
+            op: OP_RESUME,
+            d: Some(serde_json::json!({ "token": token, "session_id": session_id, "seq": self.sequence })),
+            s: None,
+            t: None,
+        })
+    }
+}
+
+/// Inflates a `zlib-stream` gateway connection. One zlib context spans the whole
+/// connection, and a message is complete once a frame ends with the sync-flush suffix.
+struct ZlibStream {
+    inflater: flate2::Decompress,
+    buffer: Vec<u8>,
+}
+
+impl ZlibStream {
+    fn new() -> Self {
+        Self { inflater: flate2::Decompress::new(true), buffer: Vec::new() }
+    }
+    
+    /// Add a binary frame; returns the message text once it is complete
+    fn push(&mut self, frame: &[u8]) -> anyhow::Result<Option<String>> {
+        self.buffer.extend_from_slice(frame);
+        if !self.buffer.ends_with(&ZLIB_SUFFIX) {
+            return Ok(None);
+        }
+        
+        let mut out = Vec::with_capacity(self.buffer.len() * 4 + 1024);
+        let mut offset = 0;
+        loop {
+            let (in_before, out_before) = (self.inflater.total_in(), out.len());
+            self.inflater.decompress_vec(&self.buffer[offset..], &mut out, flate2::FlushDecompress::Sync)?;
+            offset += (self.inflater.total_in() - in_before) as usize;
+            if out.len() == out.capacity() {
+                out.reserve(out.capacity());
+            } else if offset == self.buffer.len() {
+                break;
+            } else if out.len() == out_before {
+                anyhow::bail!("zlib stream stopped making progress");
+            }
+        }
+        self.buffer.clear();
+        Ok(Some(String::from_utf8(out)?))
+    }
+}
+
+/// How a gateway connection ended, i.e. what the next one should do
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConnectionEnd {
+    /// The socket closed on its own; the channel stops
+    Closed,
+    /// OP 7, or OP 9 with `d: true`: reconnect and RESUME
+    Resume,
+    /// OP 9 with `d: false`: start a new session after a short wait
+    Identify,
 }
 
 pub struct DiscordChannel {
     config: DiscordChannelConfig,
     token: String,
+    intents: u64,
     bot_user_id: Arc<RwLock<Option<String>>>,
     http_client: reqwest::Client,
+    /// Last time a message came in or a reply went out (drives idle presence)
+    last_activity: Arc<RwLock<std::time::Instant>>,
+    /// The missing-content hint is logged once, not for every message
+    content_hint_logged: AtomicBool,
+    /// Set when a compressed frame failed to inflate; later connections go uncompressed
+    compression_failed: AtomicBool,
+    rate_limits: RateLimiter,
+}
+
+/// What a REST response says about rate limits
+#[derive(Debug, PartialEq)]
+struct RateLimit {
+    /// Set on 429: wait this long, then retry
+    retry_after: Option<Duration>,
+    /// The bucket is used up (`X-RateLimit-Remaining: 0`) until this long from now
+    reset_after: Option<Duration>,
+    /// The limit is the bot-wide one rather than this route's
+    global: bool,
+}
+
+fn rate_limit(status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap, body: &serde_json::Value) -> RateLimit {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let seconds = |text: &str| text.parse::<f64>().ok().filter(|s| s.is_finite()).map(|s| Duration::from_secs_f64(s.max(0.0)));
+    let retry_after = (status == reqwest::StatusCode::TOO_MANY_REQUESTS).then(|| {
+        body["retry_after"]
+            .as_f64()
+            .map(|s| Duration::from_secs_f64(s.max(0.0)))
+            .or_else(|| header("retry-after").and_then(seconds))
+            .unwrap_or(Duration::from_secs(1))
+    });
+    let reset_after = (header("x-ratelimit-remaining") == Some("0"))
+        .then(|| header("x-ratelimit-reset-after").and_then(seconds))
+        .flatten();
+    let global = body["global"].as_bool() == Some(true)
+        || header("x-ratelimit-global").is_some()
+        || header("x-ratelimit-scope") == Some("global");
+    RateLimit { retry_after, reset_after, global }
+}
+
+/// Per-channel send queues plus the bot-wide block after a global 429
+#[derive(Default)]
+struct RateLimiter {
+    /// Holding a channel's lock is its turn to send; the value is when its bucket frees up
+    channels: std::sync::Mutex<HashMap<String, Arc<tokio::sync::Mutex<Option<Instant>>>>>,
+    global_until: std::sync::Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    fn queue(&self, channel_id: &str) -> Arc<tokio::sync::Mutex<Option<Instant>>> {
+        let mut channels = self.channels.lock().unwrap();
+        channels.retain(|_, queue| Arc::strong_count(queue) > 1);
+        channels.entry(channel_id.to_string()).or_default().clone()
+    }
+}
+
+/// Bitfield for the configured intent names
+fn intent_bits(names: &[String]) -> anyhow::Result<u64> {
+    names.iter().try_fold(0, |bits, name| {
+        let (_, bit) = INTENTS
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow::anyhow!("Unknown Discord intent '{}' (known: {})", name, INTENTS.iter().map(|(n, _)| *n).collect::<Vec<_>>().join(", ")))?;
+        Ok(bits | bit)
+    })
+}
+
+/// Nothing to read at all: without the message content intent, Discord blanks
+/// `content`, attachments and embeds of messages that don't mention the bot
+fn content_withheld(msg: &DiscordMessage) -> bool {
+    msg.content.is_empty() && msg.attachments.is_empty() && msg.embeds.is_empty()
+}
+
+/// `.../reactions/{emoji}/@me`; custom emoji are given as `name:id`
+fn reaction_url(channel_id: &str, message_id: &str, emoji: &str) -> String {
+    let emoji: String = url::form_urlencoded::byte_serialize(emoji.as_bytes()).collect();
+    format!(
+        "https://discord.com/api/v10/channels/{}/messages/{}/reactions/{}/@me",
+        channel_id, message_id, emoji
+    )
+}
+
+/// Split `content` into pieces Discord accepts, preferring line breaks as cut points
+fn split_message(content: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = content;
+    while rest.chars().count() > max_chars {
+        let limit = rest.char_indices().nth(max_chars).map(|(i, _)| i).unwrap_or(rest.len());
+        let cut = rest[..limit].rfind('\n').filter(|&i| i > 0).unwrap_or(limit);
+        chunks.push(rest[..cut].to_string());
+        rest = rest[cut..].strip_prefix('\n').unwrap_or(&rest[cut..]);
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest.to_string());
+    }
+    chunks
+}
+
+/// OP 1 payload carrying the last sequence number seen
+fn heartbeat_payload(sequence: Option<u64>) -> GatewayPayload {
+    GatewayPayload {
+        op: OP_HEARTBEAT,
+        d: sequence.map(|s| serde_json::json!(s)),
+        s: None,
+        t: None,
+    }
+}
+
+/// Waits for the next heartbeat tick; never resolves before HELLO started the timer
+async fn next_heartbeat(heartbeat: &mut Option<tokio::time::Interval>) {
+    match heartbeat {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// OP 9's `d` says whether the session can still be resumed
+fn after_invalid_session(d: Option<&serde_json::Value>, session: &GatewaySession) -> ConnectionEnd {
+    let resumable = d.and_then(serde_json::Value::as_bool).unwrap_or(false);
+    if resumable && session.session_id.is_some() {
+        ConnectionEnd::Resume
+    } else {
+        ConnectionEnd::Identify
+    }
+}
+
+/// Close with a non-1000 code so Discord keeps the session for the RESUME
+async fn close_for_reconnect<S>(write: &mut S)
+where
+    S: futures::Sink<Message> + Unpin,
+{
+    let frame = CloseFrame { code: CloseCode::from(CLOSE_FOR_RESUME), reason: "reconnecting".into() };
+    let _ = write.send(Message::Close(Some(frame))).await;
+}
+
+/// OP 3 payload; `idle` overrides the configured status
+fn presence_payload(presence: &DiscordPresenceConfig, idle: bool) -> GatewayPayload {
+    let activities = match &presence.activity {
+        Some(name) => serde_json::json!([{ "name": name, "type": 0 }]),
+        None => serde_json::json!([]),
+    };
+    GatewayPayload {
+        op: OP_PRESENCE_UPDATE,
+        d: Some(serde_json::json!({
+            "since": if idle { Some(chrono::Utc::now().timestamp_millis()) } else { None },
+            "activities": activities,
+            "status": if idle { "idle" } else { presence.status.as_str() },
+            "afk": idle,
+        })),
+        s: None,
+        t: None,
+    }
 }
 
 impl DiscordChannel {
     pub fn new(config: DiscordChannelConfig) -> anyhow::Result<Self> {
         let token = std::env::var(&config.token_env)
             .map_err(|_| anyhow::anyhow!("Discord token not found in env: {}", config.token_env))?;
+        let intents = intent_bits(&config.intents)?;
         
         Ok(Self {
             config,
             token,
+            intents,
             bot_user_id: Arc::new(RwLock::new(None)),
             http_client: reqwest::Client::new(),
+            last_activity: Arc::new(RwLock::new(std::time::Instant::now())),
+            content_hint_logged: AtomicBool::new(false),
+            compression_failed: AtomicBool::new(false),
+            rate_limits: RateLimiter::default(),
         })
     }
     
-    /// Check if a message should trigger the bot
-    fn should_respond(&self, msg: &DiscordMessage, bot_id: &str) -> bool {
-        // Ignore bot messages
-        if msg.author.bot {
-            return false;
-        }
-        
-        match self.config.trigger.as_str() {
-            "all" => true,
-            "dm_only" => msg.guild_id.is_none(),
-            "mention" | _ => {
-                // Check if bot is mentioned
-                msg.mentions.iter().any(|u| u.id == bot_id)
-                    || msg.content.contains(&format!("<@{}>", bot_id))
-                    || msg.content.contains(&format!("<@!{}>", bot_id))
-            }
-        }
-    }
-    
     /// Remove bot mention from content
     fn clean_content(&self, content: &str, bot_id: &str) -> String {
         content
@@ -104,15 +390,57 @@ impl DiscordChannel {
             .to_string()
     }
     
-    /// Send a message to a Discord channel
-    async fn send_message(&self, channel_id: &str, content: &str) -> anyhow::Result<()> {
-        let url = format!("https://discord.com/api/v10/channels/{}/messages", channel_id);
+    /// Check the bot token against `GET /users/@me`, returning the bot's identity
+    pub async fn verify(&self) -> anyhow::Result<String> {
+        let response = self.http_client
+            .get("https://discord.com/api/v10/users/@me")
+            .header("Authorization", format!("Bot {}", self.token))
+            .send()
+            .await?;
         
+        if !response.status().is_success() {
+            let status = response.status();
+            let error = response.text().await.unwrap_or_default();
+            anyhow::bail!("Discord API error {}: {}", status, error);
+        }
+        
+        let user: DiscordUser = response.json().await?;
+        Ok(format!("{} ({})", user.username, user.id))
+    }
+    
+    /// Warn at startup when message content won't be readable: the intent isn't requested,
+    /// or the application doesn't have it enabled (`GET /applications/@me` flags)
+    async fn check_message_content_intent(&self) {
+        if self.intents & INTENT_MESSAGE_CONTENT == 0 {
+            tracing::warn!("Discord intents don't include message_content: only mentions and DMs will have text. To read other messages, {}", MESSAGE_CONTENT_HINT);
+            return;
+        }
+        let application: serde_json::Value = match self.http_client
+            .get("https://discord.com/api/v10/applications/@me")
+            .header("Authorization", format!("Bot {}", self.token))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            Ok(response) => response.json().await.unwrap_or_default(),
+            Err(e) => {
+                tracing::debug!("Could not check Discord application flags: {}", e);
+                return;
+            }
+        };
+        if let Some(flags) = application["flags"].as_u64() {
+            if flags & FLAG_GATEWAY_MESSAGE_CONTENT == 0 {
+                tracing::warn!("Discord message content intent is not enabled for this bot; the gateway will refuse to connect. To fix, {}", MESSAGE_CONTENT_HINT);
+            }
+        }
+    }
+    
+    /// Add the bot's `emoji` reaction to a message
+    pub async fn add_reaction(&self, channel_id: &str, message_id: &str, emoji: &str) -> anyhow::Result<()> {
         let response = self.http_client
-            .post(&url)
+            .put(reaction_url(channel_id, message_id, emoji))
             .header("Authorization", format!("Bot {}", self.token))
-            .header("Content-Type", "application/json")
-            .json(&serde_json::json!({ "content": content }))
+            .header("Content-Length", "0")
             .send()
             .await?;
         
@@ -123,60 +451,285 @@ impl DiscordChannel {
         
         Ok(())
     }
-}
-
-#[async_trait]
-impl Channel for DiscordChannel {
-    fn channel_type(&self) -> ChannelType {
-        ChannelType::Discord
+    
+    /// Remove the bot's own `emoji` reaction from a message
+    pub async fn remove_reaction(&self, channel_id: &str, message_id: &str, emoji: &str) -> anyhow::Result<()> {
+        let response = self.http_client
+            .delete(reaction_url(channel_id, message_id, emoji))
+            .header("Authorization", format!("Bot {}", self.token))
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            anyhow::bail!("Discord API error: {}", error);
+        }
+        
+        Ok(())
     }
     
-    async fn start(&self, tx: mpsc::Sender<IncomingMessage>) -> anyhow::Result<()> {
-        // Get gateway URL
-        let gateway_url = "wss://gateway.discord.gg/?v=10&encoding=json";
+    /// Mark an incoming message as received (adds `ack_emoji`)
+    pub async fn acknowledge(&self, msg: &IncomingMessage) {
+        let Some(message_id) = &msg.reply_to else { return };
+        if self.config.ack_emoji.is_empty() {
+            return;
+        }
+        if let Err(e) = self.add_reaction(&msg.channel_id, message_id, &self.config.ack_emoji).await {
+            tracing::warn!("Failed to add Discord ack reaction: {}", e);
+        }
+    }
+    
+    /// Clear the ack reaction, leaving `error_emoji` behind if handling failed
+    pub async fn finish(&self, msg: &IncomingMessage, ok: bool) {
+        let Some(message_id) = &msg.reply_to else { return };
+        if !self.config.ack_emoji.is_empty() {
+            if let Err(e) = self.remove_reaction(&msg.channel_id, message_id, &self.config.ack_emoji).await {
+                tracing::warn!("Failed to remove Discord ack reaction: {}", e);
+            }
+        }
+        if !ok && !self.config.error_emoji.is_empty() {
+            if let Err(e) = self.add_reaction(&msg.channel_id, message_id, &self.config.error_emoji).await {
+                tracing::warn!("Failed to add Discord error reaction: {}", e);
+            }
+        }
+    }
+    
+    /// Post the configured placeholder for `msg` when `edit_placeholder` is on; returns its ID
+    pub async fn post_placeholder(&self, msg: &IncomingMessage) -> Option<String> {
+        if !self.config.edit_placeholder {
+            return None;
+        }
+        match self.send_message(&msg.channel_id, &self.config.placeholder_text, msg.reply_to.as_deref()).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                tracing::warn!("Failed to post Discord placeholder: {}", e);
+                None
+            }
+        }
+    }
+    
+    /// Deliver a reply: the first chunk replaces `placeholder` (if any), the rest follow as new messages
+    pub async fn deliver(&self, msg: &IncomingMessage, placeholder: Option<&str>, content: &str) -> anyhow::Result<SentMessage> {
+        *self.last_activity.write().await = std::time::Instant::now();
+        let content = super::format_for_channel(content, ChannelType::Discord);
+        let mut chunks = split_message(&content, MAX_MESSAGE_CHARS).into_iter();
+        let first = chunks.next().unwrap_or_default();
         
-        tracing::info!("Connecting to Discord Gateway...");
+        let edited = match placeholder {
+            Some(id) => match self.edit_message(&msg.channel_id, id, &first).await {
+                Ok(()) => Some(id.to_string()),
+                Err(e) => {
+                    tracing::warn!("Failed to edit Discord placeholder, sending instead: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let message_id = match edited {
+            Some(id) => id,
+            None => self.send_message(&msg.channel_id, &first, msg.reply_to.as_deref()).await?,
+        };
+        for chunk in chunks {
+            self.send_message(&msg.channel_id, &chunk, None).await?;
+        }
+        Ok(SentMessage { channel_type: ChannelType::Discord, message_id })
+    }
+    
+    /// Replace the content of a message the bot posted
+    pub async fn edit_message(&self, channel_id: &str, message_id: &str, content: &str) -> anyhow::Result<()> {
+        let url = format!("https://discord.com/api/v10/channels/{}/messages/{}", channel_id, message_id);
+        let body = serde_json::json!({ "content": content });
+        let response = self
+            .send_queued(channel_id, || {
+                self.http_client
+                    .patch(&url)
+                    .header("Authorization", format!("Bot {}", self.token))
+                    .json(&body)
+            })
+            .await?;
+        
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            anyhow::bail!("Discord API error: {}", error);
+        }
+        
+        Ok(())
+    }
+    
+    /// Delete a message the bot posted (e.g. a placeholder whose request failed)
+    pub async fn delete_message(&self, channel_id: &str, message_id: &str) -> anyhow::Result<()> {
+        let response = self.http_client
+            .delete(format!("https://discord.com/api/v10/channels/{}/messages/{}", channel_id, message_id))
+            .header("Authorization", format!("Bot {}", self.token))
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            anyhow::bail!("Discord API error: {}", error);
+        }
+        
+        Ok(())
+    }
+    
+    /// Send a message to a Discord channel, as a reply when `reply_to` is set;
+    /// returns the created message's ID
+    async fn send_message(&self, channel_id: &str, content: &str, reply_to: Option<&str>) -> anyhow::Result<String> {
+        let url = format!("https://discord.com/api/v10/channels/{}/messages", channel_id);
+        
+        let mut body = serde_json::json!({ "content": content });
+        if let Some(message_id) = reply_to {
+            body["message_reference"] = serde_json::json!({
+                "message_id": message_id,
+                "fail_if_not_exists": false,
+            });
+        }
         
-        let (ws_stream, _) = connect_async(gateway_url).await?;
+        let response = self
+            .send_queued(channel_id, || {
+                self.http_client
+                    .post(&url)
+                    .header("Authorization", format!("Bot {}", self.token))
+                    .header("Content-Type", "application/json")
+                    .json(&body)
+            })
+            .await?;
+        
+        if !response.status().is_success() {
+            let error = response.text().await?;
+            anyhow::bail!("Discord API error: {}", error);
+        }
+        
+        let created: serde_json::Value = response.json().await?;
+        created["id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("Discord API returned no message id"))
+    }
+    
+    /// Send a request for `channel_id` in its turn, after any rate limit has passed.
+    /// A 429 is waited out and retried; other responses are returned as they are.
+    async fn send_queued(&self, channel_id: &str, request: impl Fn() -> reqwest::RequestBuilder) -> anyhow::Result<reqwest::Response> {
+        let queue = self.rate_limits.queue(channel_id);
+        let mut bucket_free_at = queue.lock().await;
+        let mut retries = 0;
+        loop {
+            let global_free_at = *self.rate_limits.global_until.lock().unwrap();
+            if let Some(until) = bucket_free_at.into_iter().chain(global_free_at).max() {
+                tokio::time::sleep_until(until.into()).await;
+            }
+            
+            let response = request().send().await?;
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                let limit = rate_limit(response.status(), response.headers(), &serde_json::Value::Null);
+                *bucket_free_at = limit.reset_after.map(|wait| Instant::now() + wait);
+                return Ok(response);
+            }
+            
+            let headers = response.headers().clone();
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            let limit = rate_limit(reqwest::StatusCode::TOO_MANY_REQUESTS, &headers, &body);
+            let wait = limit.retry_after.unwrap_or_default();
+            if retries >= MAX_RATE_LIMIT_RETRIES || wait > MAX_RATE_LIMIT_WAIT {
+                anyhow::bail!("Discord rate limited channel {} (retry after {:.1}s)", channel_id, wait.as_secs_f64());
+            }
+            retries += 1;
+            tracing::warn!(channel_id, global = limit.global, "Discord rate limited; retrying in {:.1}s", wait.as_secs_f64());
+            let until = Instant::now() + wait;
+            if limit.global {
+                *self.rate_limits.global_until.lock().unwrap() = Some(until);
+            } else {
+                *bucket_free_at = Some(until);
+            }
+        }
+    }
+}
+
+impl DiscordChannel {
+    /// One gateway connection: IDENTIFY (or RESUME when `session` has one to resume), then
+    /// handle events until the socket closes or Discord asks for a reconnect
+    async fn run_connection(&self, session: &mut GatewaySession, tx: &mpsc::Sender<IncomingMessage>) -> anyhow::Result<ConnectionEnd> {
+        let compress = self.config.compress && !self.compression_failed.load(Ordering::Relaxed);
+        tracing::info!(compress, "Connecting to Discord Gateway...");
+        
+        let (ws_stream, _) = connect_async(session.gateway_url(compress)).await?;
+        let mut zlib = compress.then(ZlibStream::new);
         let (mut write, mut read) = ws_stream.split();
         
-        let mut sequence: Option<u64> = None;
-        let mut heartbeat_interval: u64 = 45000;
         let bot_user_id = self.bot_user_id.clone();
         let token = self.token.clone();
         let config = self.config.clone();
+        let intents = self.intents;
+        let last_activity = self.last_activity.clone();
+        let content_hint_logged = &self.content_hint_logged;
         
-        // Spawn heartbeat task
-        let heartbeat_tx = {
-            let (htx, mut hrx) = mpsc::channel::<()>(1);
-            
-            tokio::spawn(async move {
-                loop {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(heartbeat_interval)).await;
-                    if hrx.try_recv().is_ok() {
-                        break;
+        // Presence is sent after READY, then re-checked periodically for idleness
+        let mut ready = false;
+        let mut is_idle = false;
+        let mut presence_check = tokio::time::interval(tokio::time::Duration::from_secs(60));
+        
+        // Started once HELLO says how often Discord wants a heartbeat
+        let mut heartbeat: Option<tokio::time::Interval> = None;
+        
+        // Main event loop
+        loop {
+            let msg = tokio::select! {
+                msg = read.next() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                _ = next_heartbeat(&mut heartbeat) => {
+                    write.send(Message::Text(serde_json::to_string(&heartbeat_payload(session.sequence))?)).await?;
+                    continue;
+                }
+                _ = presence_check.tick() => {
+                    let idle_after = config.presence.idle_after_secs;
+                    if ready && idle_after > 0 {
+                        let idle = last_activity.read().await.elapsed().as_secs() >= idle_after;
+                        if idle != is_idle {
+                            write.send(Message::Text(serde_json::to_string(&presence_payload(&config.presence, idle))?)).await?;
+                            is_idle = idle;
+                            tracing::info!(idle, "Updated Discord presence");
+                        }
                     }
+                    continue;
                 }
-            });
+            };
+            
+            // Compressed payloads arrive as binary frames, possibly several per message
+            let msg = match (msg, zlib.as_mut()) {
+                (Ok(Message::Binary(frame)), Some(zlib)) => match zlib.push(&frame) {
+                    Ok(Some(text)) => Ok(Message::Text(text)),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::warn!("Failed to inflate Discord gateway frame ({}); reconnecting without compression", e);
+                        self.compression_failed.store(true, Ordering::Relaxed);
+                        close_for_reconnect(&mut write).await;
+                        return Ok(ConnectionEnd::Resume);
+                    }
+                },
+                (msg, _) => msg,
+            };
             
-            htx
-        };
-        
-        // Main event loop
-        while let Some(msg) = read.next().await {
             match msg {
                 Ok(Message::Text(text)) => {
                     if let Ok(payload) = serde_json::from_str::<GatewayPayload>(&text) {
                         // Update sequence
                         if let Some(s) = payload.s {
-                            sequence = Some(s);
+                            session.sequence = Some(s);
                         }
                         
                         match payload.op {
                             OP_HELLO => {
                                 // Extract heartbeat interval
-                                if let Some(d) = &payload.d {
-                                    heartbeat_interval = d["heartbeat_interval"].as_u64().unwrap_or(45000);
+                                let interval = payload.d.as_ref().and_then(|d| d["heartbeat_interval"].as_u64()).unwrap_or(45000);
+                                let period = tokio::time::Duration::from_millis(interval);
+                                heartbeat = Some(tokio::time::interval_at(tokio::time::Instant::now() + period, period));
+                                
+                                if let Some(resume) = session.resume_payload(&token) {
+                                    write.send(Message::Text(serde_json::to_string(&resume)?)).await?;
+                                    tracing::info!("Sent RESUME to Discord");
+                                    continue;
                                 }
                                 
                                 // Send IDENTIFY
@@ -184,7 +737,7 @@ impl Channel for DiscordChannel {
                                     op: OP_IDENTIFY,
                                     d: Some(serde_json::json!({
                                         "token": token,
-                                        "intents": 33281, // GUILDS + GUILD_MESSAGES + MESSAGE_CONTENT + DIRECT_MESSAGES
+                                        "intents": intents,
                                         "properties": {
                                             "os": "linux",
                                             "browser": "oneclaw",
@@ -201,35 +754,59 @@ impl Channel for DiscordChannel {
                             
                             OP_HEARTBEAT => {
                                 // Send heartbeat immediately
-                                let heartbeat = GatewayPayload {
-                                    op: OP_HEARTBEAT,
-                                    d: sequence.map(|s| serde_json::json!(s)),
-                                    s: None,
-                                    t: None,
-                                };
-                                write.send(Message::Text(serde_json::to_string(&heartbeat)?)).await?;
+                                write.send(Message::Text(serde_json::to_string(&heartbeat_payload(session.sequence))?)).await?;
                             }
                             
                             OP_HEARTBEAT_ACK => {
                                 // Heartbeat acknowledged
                             }
                             
+                            OP_RECONNECT => {
+                                tracing::info!("Discord asked the bot to reconnect");
+                                close_for_reconnect(&mut write).await;
+                                return Ok(ConnectionEnd::Resume);
+                            }
+                            
+                            OP_INVALID_SESSION => {
+                                let end = after_invalid_session(payload.d.as_ref(), session);
+                                tracing::warn!(resumable = end == ConnectionEnd::Resume, "Discord invalidated the gateway session");
+                                close_for_reconnect(&mut write).await;
+                                return Ok(end);
+                            }
+                            
                             OP_DISPATCH => {
                                 if let Some(event_name) = &payload.t {
                                     match event_name.as_str() {
                                         "READY" => {
                                             if let Some(d) = payload.d {
-                                                if let Ok(ready) = serde_json::from_value::<ReadyEvent>(d) {
-                                                    *bot_user_id.write().await = Some(ready.user.id.clone());
+                                                if let Ok(ready_event) = serde_json::from_value::<ReadyEvent>(d) {
+                                                    session.session_id = Some(ready_event.session_id.clone());
+                                                    session.resume_url = ready_event.resume_gateway_url.clone();
+                                                    *bot_user_id.write().await = Some(ready_event.user.id.clone());
+                                                    *last_activity.write().await = std::time::Instant::now();
+                                                    write.send(Message::Text(serde_json::to_string(&presence_payload(&config.presence, false))?)).await?;
+                                                    ready = true;
+                                                    is_idle = false;
                                                     tracing::info!(
-                                                        bot_name = %ready.user.username,
-                                                        bot_id = %ready.user.id,
+                                                        bot_name = %ready_event.user.username,
+                                                        bot_id = %ready_event.user.id,
                                                         "Discord bot connected"
                                                     );
+                                                    let identity = format!("{} ({})", ready_event.user.username, ready_event.user.id);
+                                                    super::status::report(|s| s.connected(ChannelType::Discord, Some(identity)));
                                                 }
                                             }
                                         }
                                         
+                                        "RESUMED" => {
+                                            // Missed events were replayed before this; the bot user is still known
+                                            write.send(Message::Text(serde_json::to_string(&presence_payload(&config.presence, false))?)).await?;
+                                            ready = true;
+                                            is_idle = false;
+                                            tracing::info!("Discord session resumed");
+                                            super::status::report(|s| s.connected(ChannelType::Discord, None));
+                                        }
+                                        
                                         "MESSAGE_CREATE" => {
                                             if let Some(d) = payload.d {
                                                 if let Ok(discord_msg) = serde_json::from_value::<DiscordMessage>(d.clone()) {
@@ -247,31 +824,32 @@ impl Channel for DiscordChannel {
                                                             || config.listen_channels.contains(&discord_msg.channel_id);
                                                         
                                                         if guild_allowed && channel_allowed {
-                                                            // Create a local reference to avoid moving self
-                                                            let should_respond = {
-                                                                // Inline the should_respond logic
-                                                                if discord_msg.author.bot {
-                                                                    false
-                                                                } else {
-                                                                    match config.trigger.as_str() {
-                                                                        "all" => true,
-                                                                        "dm_only" => discord_msg.guild_id.is_none(),
-                                                                        "mention" | _ => {
-                                                                            discord_msg.mentions.iter().any(|u| u.id == *bid)
-                                                                                || discord_msg.content.contains(&format!("<@{}>", bid))
-                                                                                || discord_msg.content.contains(&format!("<@!{}>", bid))
-                                                                        }
-                                                                    }
-                                                                }
+                                                            let context = super::TriggerContext {
+                                                                trigger: &config.trigger,
+                                                                is_dm: discord_msg.guild_id.is_none(),
+                                                                mentioned: discord_msg.mentions.iter().any(|u| u.id == *bid)
+                                                                    || discord_msg.content.contains(&format!("<@{}>", bid))
+                                                                    || discord_msg.content.contains(&format!("<@!{}>", bid)),
+                                                                from_bot: discord_msg.author.bot,
                                                             };
+                                                            let should_respond = super::should_respond(&discord_msg.content, &context);
                                                             
-                                                            if should_respond {
+                                                            if should_respond && content_withheld(&discord_msg) {
+                                                                if !content_hint_logged.swap(true, Ordering::Relaxed) {
+                                                                    tracing::warn!(
+                                                                        channel_id = %discord_msg.channel_id,
+                                                                        "Ignoring Discord message with empty content; the bot likely lacks the message content intent. To fix, {}",
+                                                                        MESSAGE_CONTENT_HINT
+                                                                    );
+                                                                } else {
+                                                                    tracing::debug!(channel_id = %discord_msg.channel_id, "Ignoring Discord message with empty content");
+                                                                }
+                                                            } else if should_respond {
                                                                 // Clean content
-                                                                let clean_content = discord_msg.content
+                                                                let without_mention = discord_msg.content
                                                                     .replace(&format!("<@{}>", bid), "")
-                                                                    .replace(&format!("<@!{}>", bid), "")
-                                                                    .trim()
-                                                                    .to_string();
+                                                                    .replace(&format!("<@!{}>", bid), "");
+                                                                let clean_content = super::strip_trigger_prefix(without_mention.trim(), &config.trigger).to_string();
                                                                 
                                                                 let incoming = IncomingMessage {
                                                                     channel_type: ChannelType::Discord,
@@ -282,8 +860,18 @@ impl Channel for DiscordChannel {
                                                                     timestamp: chrono::Utc::now(),
                                                                     reply_to: Some(discord_msg.id.clone()),
                                                                     metadata: d,
+                                                                    attachments: discord_msg.attachments.iter().map(|a| Attachment {
+                                                                        url: a.url.clone(),
+                                                                        content_type: a.content_type.clone(),
+                                                                    }).collect(),
                                                                 };
                                                                 
+                                                                *last_activity.write().await = std::time::Instant::now();
+                                                                if is_idle {
+                                                                    write.send(Message::Text(serde_json::to_string(&presence_payload(&config.presence, false))?)).await?;
+                                                                    is_idle = false;
+                                                                }
+                                                                
                                                                 if let Err(e) = tx.send(incoming).await {
                                                                     tracing::error!("Failed to send message to handler: {}", e);
                                                                 }
@@ -304,7 +892,10 @@ impl Channel for DiscordChannel {
                     }
                 }
                 
-                Ok(Message::Close(_)) => {
+                Ok(Message::Close(frame)) => {
+                    if frame.as_ref().is_some_and(|f| u16::from(f.code) == CLOSE_DISALLOWED_INTENTS) {
+                        anyhow::bail!("Discord refused the requested intents (close code 4014). To fix, {}", MESSAGE_CONTENT_HINT);
+                    }
                     tracing::warn!("Discord WebSocket closed");
                     break;
                 }
@@ -316,22 +907,55 @@ impl Channel for DiscordChannel {
                 
                 _ => {}
             }
-            
-            // Send periodic heartbeat
-            let heartbeat = GatewayPayload {
-                op: OP_HEARTBEAT,
-                d: sequence.map(|s| serde_json::json!(s)),
-                s: None,
-                t: None,
-            };
-            // Note: In production, this should be on a timer, not every message
         }
         
-        Ok(())
+        Ok(ConnectionEnd::Closed)
+    }
+}
+
+#[async_trait]
+impl Channel for DiscordChannel {
+    fn channel_type(&self) -> ChannelType {
+        ChannelType::Discord
     }
     
-    async fn send(&self, msg: OutgoingMessage) -> anyhow::Result<()> {
-        self.send_message(&msg.channel_id, &msg.content).await
+    async fn start(&self, tx: mpsc::Sender<IncomingMessage>) -> anyhow::Result<()> {
+        self.check_message_content_intent().await;
+        
+        let mut session = GatewaySession::default();
+        loop {
+            let end = self.run_connection(&mut session, &tx).await;
+            let reason = match &end {
+                Ok(ConnectionEnd::Closed) => "Gateway connection closed".to_string(),
+                Ok(_) => "Reconnecting to the gateway".to_string(),
+                Err(e) => e.to_string(),
+            };
+            super::status::report(|s| s.disconnected(ChannelType::Discord, reason));
+            match end? {
+                ConnectionEnd::Closed => return Ok(()),
+                ConnectionEnd::Resume => tracing::info!("Reconnecting to Discord Gateway to resume the session"),
+                ConnectionEnd::Identify => {
+                    // Discord asks for a random 1-5s wait before identifying again
+                    session = GatewaySession::default();
+                    let delay = rand::thread_rng().gen_range(1000..=5000);
+                    tracing::info!("Discord session invalidated; identifying again in {}ms", delay);
+                    tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+                }
+            }
+        }
+    }
+    
+    async fn send(&self, msg: OutgoingMessage) -> anyhow::Result<SentMessage> {
+        *self.last_activity.write().await = std::time::Instant::now();
+        let content = super::format_for_channel(&msg.content, msg.channel_type);
+        let mut chunks = split_message(&content, MAX_MESSAGE_CHARS).into_iter();
+        let message_id = self
+            .send_message(&msg.channel_id, &chunks.next().unwrap_or_default(), msg.reply_to.as_deref())
+            .await?;
+        for chunk in chunks {
+            self.send_message(&msg.channel_id, &chunk, None).await?;
+        }
+        Ok(SentMessage { channel_type: ChannelType::Discord, message_id })
     }
     
     async fn stop(&self) -> anyhow::Result<()> {
@@ -340,3 +964,116 @@ impl Channel for DiscordChannel {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_rate_limit_headers_and_429_body() {
+        use reqwest::header::{HeaderMap, HeaderValue};
+        
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        headers.insert("x-ratelimit-reset-after", HeaderValue::from_static("1.5"));
+        let exhausted = rate_limit(reqwest::StatusCode::OK, &headers, &serde_json::Value::Null);
+        assert_eq!(exhausted, RateLimit { retry_after: None, reset_after: Some(Duration::from_millis(1500)), global: false });
+        
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("4"));
+        assert_eq!(rate_limit(reqwest::StatusCode::OK, &headers, &serde_json::Value::Null).reset_after, None);
+        
+        let body = serde_json::json!({ "message": "You are being rate limited.", "retry_after": 0.25, "global": true });
+        let limited = rate_limit(reqwest::StatusCode::TOO_MANY_REQUESTS, &HeaderMap::new(), &body);
+        assert_eq!((limited.retry_after, limited.global), (Some(Duration::from_millis(250)), true));
+        
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_static("3"));
+        let limited = rate_limit(reqwest::StatusCode::TOO_MANY_REQUESTS, &headers, &serde_json::Value::Null);
+        assert_eq!((limited.retry_after, limited.global), (Some(Duration::from_secs(3)), false));
+        
+        let limiter = RateLimiter::default();
+        let queue = limiter.queue("c1");
+        assert!(Arc::ptr_eq(&queue, &limiter.queue("c1")));
+        drop(queue);
+        limiter.queue("c2");
+        assert_eq!(limiter.channels.lock().unwrap().len(), 1);
+    }
+    
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("short", 2000), vec!["short"]);
+        
+        let long = format!("{}\n{}", "a".repeat(1500), "b".repeat(1500));
+        assert_eq!(split_message(&long, 2000), vec!["a".repeat(1500), "b".repeat(1500)]);
+        
+        // No line break to cut at: hard split on a char boundary
+        let chunks = split_message(&"é".repeat(4500), 2000);
+        assert_eq!(chunks.iter().map(|c| c.chars().count()).collect::<Vec<_>>(), vec![2000, 2000, 500]);
+    }
+    
+    #[test]
+    fn test_intents_and_withheld_content() {
+        assert_eq!(intent_bits(&["guilds".into(), "guild_messages".into(), "message_content".into()]).unwrap(), 33281);
+        assert_eq!(intent_bits(&crate::config::DiscordChannelConfig::default().intents).unwrap(), 33281 | 1 << 12);
+        assert!(intent_bits(&["messages".into()]).is_err());
+        
+        let msg = |content: &str, attachments: Vec<serde_json::Value>| -> DiscordMessage {
+            serde_json::from_value(serde_json::json!({
+                "id": "1", "channel_id": "c", "content": content, "author": { "id": "u", "username": "ann" },
+                "attachments": attachments,
+            }))
+            .unwrap()
+        };
+        assert!(content_withheld(&msg("", vec![])));
+        assert!(!content_withheld(&msg("hi", vec![])));
+        assert!(!content_withheld(&msg("", vec![serde_json::json!({ "id": "a", "url": "https://cdn.discordapp.com/a.png" })])));
+    }
+    
+    #[test]
+    fn test_reconnect_resumes_or_identifies() {
+        let mut session = GatewaySession::default();
+        assert_eq!(session.gateway_url(false), "wss://gateway.discord.gg/?v=10&encoding=json");
+        assert!(session.resume_payload("t").is_none());
+        assert_eq!(after_invalid_session(Some(&serde_json::json!(true)), &session), ConnectionEnd::Identify);
+        
+        let ready: ReadyEvent = serde_json::from_value(serde_json::json!({
+            "user": { "id": "b", "username": "claw" },
+            "session_id": "s1",
+            "resume_gateway_url": "wss://gateway-us-east1-b.discord.gg",
+        }))
+        .unwrap();
+        session.session_id = Some(ready.session_id);
+        session.resume_url = ready.resume_gateway_url;
+        session.sequence = Some(42);
+        assert_eq!(session.gateway_url(true), "wss://gateway-us-east1-b.discord.gg/?v=10&encoding=json&compress=zlib-stream");
+        let resume = session.resume_payload("t").unwrap();
+        assert_eq!(resume.op, OP_RESUME);
+        assert_eq!(resume.d.unwrap(), serde_json::json!({ "token": "t", "session_id": "s1", "seq": 42 }));
+        
+        assert_eq!(after_invalid_session(Some(&serde_json::json!(true)), &session), ConnectionEnd::Resume);
+        assert_eq!(after_invalid_session(Some(&serde_json::json!(false)), &session), ConnectionEnd::Identify);
+        assert_eq!(after_invalid_session(None, &session), ConnectionEnd::Identify);
+    }
+    
+    #[test]
+    fn test_zlib_stream_inflates_across_frames() {
+        // One compression context for the connection, sync-flushed after each message
+        let mut deflater = flate2::Compress::new(flate2::Compression::default(), true);
+        let mut deflate = |text: &str| {
+            let mut out = Vec::with_capacity(text.len() + 64);
+            deflater.compress_vec(text.as_bytes(), &mut out, flate2::FlushCompress::Sync).unwrap();
+            out
+        };
+        let hello = deflate(r#"{"op":10,"d":{"heartbeat_interval":41250}}"#);
+        let ready = deflate(&format!(r#"{{"op":0,"t":"READY","d":{{"guilds":"{}"}}}}"#, "x".repeat(5000)));
+        
+        let mut zlib = ZlibStream::new();
+        assert_eq!(zlib.push(&hello).unwrap().as_deref(), Some(r#"{"op":10,"d":{"heartbeat_interval":41250}}"#));
+        let (first, rest) = ready.split_at(ready.len() / 2);
+        assert_eq!(zlib.push(first).unwrap(), None);
+        let text = zlib.push(rest).unwrap().expect("message complete");
+        assert_eq!(serde_json::from_str::<GatewayPayload>(&text).unwrap().t.as_deref(), Some("READY"));
+        
+        assert!(ZlibStream::new().push(&[1, 2, 3, 0x00, 0x00, 0xff, 0xff]).is_err());
+    }
+}
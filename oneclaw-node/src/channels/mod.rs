@@ -2,19 +2,24 @@
 //! 
 //! Channels provide different ways to interact with the node:
 //! - Discord: WebSocket connection to Discord Gateway
-//! - Slack: Socket Mode connection
+//! - Slack: Socket Mode connection; slash commands over HTTP (`slack.rs`)
 //! - Telegram: Long polling
 //! - HTTP: REST API (handled by daemon.rs)
+//!
+//! Each reports its connection state to `status` for `GET /channels/status`.
 
 pub mod discord;
+pub mod slack;
+pub mod status;
 pub mod telegram;
 
 use async_trait::async_trait;
+use crate::config::TriggerMode;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
 /// Channel type identifier
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, schemars::JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ChannelType {
     Discord,
@@ -45,10 +50,28 @@ pub struct IncomingMessage {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub reply_to: Option<String>,  // For threading
     pub metadata: serde_json::Value,
+    /// Files attached to the message (images are shown to vision models, see `llm.vision`)
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// A file attached to an incoming message, fetched from `url` when needed
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Attachment {
+    pub url: String,
+    /// MIME type as reported by the provider, e.g. `image/png`
+    #[serde(default)]
+    pub content_type: Option<String>,
+}
+
+impl Attachment {
+    pub fn is_image(&self) -> bool {
+        self.content_type.as_deref().is_some_and(|t| t.starts_with("image/"))
+    }
 }
 
 /// Outgoing message to send via a channel
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct OutgoingMessage {
     pub channel_type: ChannelType,
     pub channel_id: String,
@@ -57,6 +80,117 @@ pub struct OutgoingMessage {
     pub metadata: serde_json::Value,
 }
 
+/// A message the channel accepted, as identified by the provider
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentMessage {
+    pub channel_type: ChannelType,
+    /// Provider message ID (Discord snowflake, Telegram message_id), usable to edit or reply later
+    pub message_id: String,
+}
+
+/// Convert the agent's generic markdown into the target channel's dialect.
+///
+/// - Slack (mrkdwn): `**bold**` -> `*bold*`, `~~strike~~` -> `~strike~`,
+///   `[text](url)` -> `<url|text>`, headings -> bold lines
+/// - Telegram (legacy Markdown parse mode): `**bold**` -> `*bold*`, headings -> bold lines
+/// - Discord / HTTP: passthrough
+///
+/// Fenced code blocks are left untouched.
+pub fn format_for_channel(content: &str, channel_type: ChannelType) -> String {
+    if matches!(channel_type, ChannelType::Discord | ChannelType::Http) {
+        return content.to_string();
+    }
+    
+    static PATTERNS: std::sync::OnceLock<[regex::Regex; 4]> = std::sync::OnceLock::new();
+    let [bold, heading, strike, link] = PATTERNS.get_or_init(|| {
+        [
+            r"\*\*(.+?)\*\*",
+            r"(?m)^#{1,6}\s+(.+?)\s*$",
+            r"~~(.+?)~~",
+            r"\[([^\]]+)\]\((https?://[^)\s]+)\)",
+        ]
+        .map(|pattern| regex::Regex::new(pattern).unwrap())
+    });
+    
+    content
+        .split("```")
+        .enumerate()
+        .map(|(i, segment)| {
+            // Odd segments are inside a ``` fence
+            if i % 2 == 1 {
+                return segment.to_string();
+            }
+            let text = heading.replace_all(segment, "**$1**");
+            let text = bold.replace_all(&text, "*$1*");
+            match channel_type {
+                ChannelType::Slack => {
+                    let text = strike.replace_all(&text, "~$1~");
+                    link.replace_all(&text, "<$2|$1>").into_owned()
+                }
+                _ => text.into_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("```")
+}
+
+/// What a channel knows about an incoming message, for `should_respond`
+pub struct TriggerContext<'a> {
+    /// The channel's `trigger` config
+    pub trigger: &'a crate::config::TriggerConfig,
+    /// Sent in a direct message / private chat
+    pub is_dm: bool,
+    /// The bot is mentioned (Discord `<@id>`, Telegram `@username`; always for slash commands)
+    pub mentioned: bool,
+    pub from_bot: bool,
+}
+
+/// Whether the bot should answer `content`, per `context.trigger`
+pub fn should_respond(content: &str, context: &TriggerContext) -> bool {
+    if context.from_bot {
+        return false;
+    }
+    let trigger = context.trigger;
+    let by_mode = match trigger.mode {
+        TriggerMode::Mention => context.mentioned,
+        TriggerMode::All => true,
+        TriggerMode::DmOnly => context.is_dm,
+        TriggerMode::None => false,
+    };
+    by_mode
+        || matched_prefix(content, trigger).is_some()
+        || trigger.keywords.iter().any(|keyword| contains_word(content, keyword))
+}
+
+/// `content` without the trigger prefix it starts with, if any
+pub fn strip_trigger_prefix<'a>(content: &'a str, trigger: &crate::config::TriggerConfig) -> &'a str {
+    let content = content.trim_start();
+    match matched_prefix(content, trigger) {
+        Some(prefix) => content[prefix.len()..].trim_start(),
+        None => content,
+    }
+}
+
+fn matched_prefix<'a>(content: &str, trigger: &'a crate::config::TriggerConfig) -> Option<&'a str> {
+    let content = content.trim_start();
+    trigger
+        .prefixes
+        .iter()
+        .find(|prefix| !prefix.is_empty() && content.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix)))
+        .map(String::as_str)
+}
+
+/// `word` appears in `content`, case-insensitively, not as part of a longer word
+fn contains_word(content: &str, word: &str) -> bool {
+    let (content, word) = (content.to_lowercase(), word.to_lowercase());
+    !word.is_empty()
+        && content.match_indices(&word).any(|(i, _)| {
+            let before = content[..i].chars().next_back();
+            let after = content[i + word.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+}
+
 /// Channel trait - all channels implement this
 #[async_trait]
 pub trait Channel: Send + Sync {
@@ -66,13 +200,83 @@ pub trait Channel: Send + Sync {
     /// Start the channel, sending incoming messages to the provided sender
     async fn start(&self, tx: mpsc::Sender<IncomingMessage>) -> anyhow::Result<()>;
     
-    /// Send a message through this channel
-    async fn send(&self, msg: OutgoingMessage) -> anyhow::Result<()>;
+    /// Send a message through this channel, returning the provider's ID for it
+    async fn send(&self, msg: OutgoingMessage) -> anyhow::Result<SentMessage>;
     
     /// Stop the channel gracefully
     async fn stop(&self) -> anyhow::Result<()>;
 }
 
+/// Result of a credential check for one channel
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelCheck {
+    pub channel: ChannelType,
+    pub ok: bool,
+    /// Bot identity on success, error message on failure
+    pub detail: String,
+}
+
+/// Verify credentials for every enabled channel with a minimal authenticated call
+pub async fn test_credentials(config: &crate::config::ChannelsConfig) -> Vec<ChannelCheck> {
+    let mut checks = Vec::new();
+    
+    if config.discord.enabled {
+        let result = match discord::DiscordChannel::new(config.discord.clone()) {
+            Ok(channel) => channel.verify().await,
+            Err(e) => Err(e),
+        };
+        checks.push(ChannelCheck::from_result(ChannelType::Discord, result));
+    }
+    
+    if config.slack.enabled {
+        let result = match std::env::var(&config.slack.token_env) {
+            Ok(token) => verify_slack_token(&token).await,
+            Err(_) => Err(anyhow::anyhow!("Slack token not found in env: {}", config.slack.token_env)),
+        };
+        checks.push(ChannelCheck::from_result(ChannelType::Slack, result));
+    }
+    
+    if config.telegram.enabled {
+        let result = match std::env::var(&config.telegram.token_env) {
+            Ok(token) => telegram::TelegramChannel::new(token).verify().await,
+            Err(_) => Err(anyhow::anyhow!("Telegram token not found in env: {}", config.telegram.token_env)),
+        };
+        checks.push(ChannelCheck::from_result(ChannelType::Telegram, result));
+    }
+    
+    checks
+}
+
+impl ChannelCheck {
+    fn from_result(channel: ChannelType, result: anyhow::Result<String>) -> Self {
+        match result {
+            Ok(identity) => Self { channel, ok: true, detail: identity },
+            Err(e) => Self { channel, ok: false, detail: e.to_string() },
+        }
+    }
+}
+
+/// Slack `auth.test` - returns "user @ team" for a valid bot token
+async fn verify_slack_token(token: &str) -> anyhow::Result<String> {
+    let body: serde_json::Value = reqwest::Client::new()
+        .post("https://slack.com/api/auth.test")
+        .bearer_auth(token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    
+    if body["ok"].as_bool() != Some(true) {
+        anyhow::bail!("Slack auth.test failed: {}", body["error"].as_str().unwrap_or("unknown error"));
+    }
+    
+    Ok(format!(
+        "{} @ {}",
+        body["user"].as_str().unwrap_or("unknown"),
+        body["team"].as_str().unwrap_or("unknown")
+    ))
+}
+
 /// Channel manager - coordinates all active channels
 pub struct ChannelManager {
     channels: Vec<Box<dyn Channel>>,
@@ -117,7 +321,7 @@ impl ChannelManager {
     }
     
     /// Send a message to a specific channel
-    pub async fn send(&self, msg: OutgoingMessage) -> anyhow::Result<()> {
+    pub async fn send(&self, msg: OutgoingMessage) -> anyhow::Result<SentMessage> {
         for channel in &self.channels {
             if channel.channel_type() == msg.channel_type {
                 return channel.send(msg).await;
@@ -126,3 +330,52 @@ impl ChannelManager {
         anyhow::bail!("No channel found for type: {:?}", msg.channel_type)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_format_for_slack() {
+        let out = format_for_channel("## Results\n**Acme** - see [site](https://acme.com) ~~old~~", ChannelType::Slack);
+        assert_eq!(out, "*Results*\n*Acme* - see <https://acme.com|site> ~old~");
+    }
+    
+    #[test]
+    fn test_format_leaves_code_blocks_and_passthrough() {
+        let content = "**hi**\n```\n**literal**\n```";
+        assert_eq!(format_for_channel(content, ChannelType::Telegram), "*hi*\n```\n**literal**\n```");
+        assert_eq!(format_for_channel(content, ChannelType::Http), content);
+        assert_eq!(format_for_channel(content, ChannelType::Discord), content);
+    }
+    
+    #[test]
+    fn test_should_respond_to_mode_prefixes_and_keywords() {
+        let trigger = crate::config::TriggerConfig {
+            mode: TriggerMode::DmOnly,
+            prefixes: vec!["!ask".to_string()],
+            keywords: vec!["oneclaw".to_string()],
+        };
+        let context = |is_dm, from_bot| TriggerContext { trigger: &trigger, is_dm, mentioned: false, from_bot };
+        
+        assert!(should_respond("hi", &context(true, false)));
+        assert!(!should_respond("hi", &context(false, false)));
+        assert!(should_respond("!ASK what's up", &context(false, false)));
+        assert!(should_respond("is OneClaw around?", &context(false, false)));
+        assert!(!should_respond("oneclawed", &context(false, false)));
+        assert!(!should_respond("!ask from a bot", &context(false, true)));
+        assert_eq!(strip_trigger_prefix("  !Ask  what's up", &trigger), "what's up");
+        assert_eq!(strip_trigger_prefix("what's up", &trigger), "what's up");
+        
+        let mention = crate::config::TriggerConfig::default();
+        assert!(should_respond("hi", &TriggerContext { trigger: &mention, is_dm: false, mentioned: true, from_bot: false }));
+        let legacy: crate::config::DiscordChannelConfig = serde_yaml::from_str("trigger: all").unwrap();
+        assert_eq!(legacy.trigger, crate::config::TriggerConfig::mode(TriggerMode::All));
+        let full: crate::config::DiscordChannelConfig = serde_yaml::from_str("trigger: { mode: dm_only, prefixes: ['!ask'] }").unwrap();
+        assert_eq!(full.trigger.mode, TriggerMode::DmOnly);
+        for typo in ["trigger: mentoin", "trigger: { mode: mentoin }"] {
+            let err = serde_yaml::from_str::<crate::config::DiscordChannelConfig>(typo).unwrap_err().to_string();
+            assert!(err.contains("unknown variant `mentoin`"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Slack Slash Commands
+//!
+//! Slack POSTs slash commands (`/ask <question>`) to `POST /slack/commands` as a signed
+//! form. Slack shows an error unless it gets a 200 within 3 seconds, so the daemon acks
+//! right away with an ephemeral "working on it" and delivers the answer later through the
+//! command's `response_url`, falling back to `chat.postMessage` with the bot token.
+
+use crate::config::SlackChannelConfig;
+use ring::hmac;
+use std::collections::HashMap;
+
+use super::{format_for_channel, ChannelType, IncomingMessage};
+
+/// Requests signed longer ago than this are rejected as possible replays
+const MAX_SIGNATURE_AGE_SECS: i64 = 300;
+
+/// The fields of Slack's slash command payload the node uses
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlashCommand {
+    pub command: String,
+    pub text: String,
+    pub user_id: String,
+    pub user_name: Option<String>,
+    pub channel_id: String,
+    pub team_id: Option<String>,
+    pub response_url: Option<String>,
+}
+
+impl SlashCommand {
+    /// Parse the `application/x-www-form-urlencoded` body Slack sends
+    pub fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        let fields: HashMap<String, String> = url::form_urlencoded::parse(body).into_owned().collect();
+        let field = |name: &str| fields.get(name).filter(|v| !v.is_empty()).cloned();
+        let required = |name: &str| field(name).ok_or_else(|| anyhow::anyhow!("Slash command is missing '{}'", name));
+        Ok(Self {
+            command: required("command")?,
+            text: field("text").unwrap_or_default().trim().to_string(),
+            user_id: required("user_id")?,
+            user_name: field("user_name"),
+            channel_id: required("channel_id")?,
+            team_id: field("team_id"),
+            response_url: field("response_url"),
+        })
+    }
+
+    pub fn into_message(self) -> IncomingMessage {
+        IncomingMessage {
+            channel_type: ChannelType::Slack,
+            channel_id: self.channel_id,
+            provider_user_id: self.user_id,
+            username: self.user_name,
+            content: self.text,
+            timestamp: chrono::Utc::now(),
+            reply_to: None,
+            metadata: serde_json::json!({
+                "command": self.command,
+                "team_id": self.team_id,
+                "response_url": self.response_url,
+            }),
+            attachments: Vec::new(),
+        }
+    }
+}
+
+/// Check `X-Slack-Signature` (`v0=<hex hmac-sha256 of "v0:<timestamp>:<body>">`) and
+/// that `X-Slack-Request-Timestamp` is recent
+pub fn verify_signature(signing_secret: &str, timestamp: &str, signature: &str, body: &[u8], now: i64) -> anyhow::Result<()> {
+    let sent_at: i64 = timestamp.parse().map_err(|_| anyhow::anyhow!("Invalid Slack request timestamp"))?;
+    if (now - sent_at).abs() > MAX_SIGNATURE_AGE_SECS {
+        anyhow::bail!("Slack request timestamp is too old");
+    }
+    let tag = signature
+        .strip_prefix("v0=")
+        .and_then(decode_hex)
+        .ok_or_else(|| anyhow::anyhow!("Malformed Slack signature"))?;
+
+    let key = hmac::Key::new(hmac::HMAC_SHA256, signing_secret.as_bytes());
+    let mut message = format!("v0:{}:", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    hmac::verify(&key, &message, &tag).map_err(|_| anyhow::anyhow!("Slack signature mismatch"))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Whether `command` (e.g. `/ask`) is one of `config.commands`
+pub fn handles_command(config: &SlackChannelConfig, command: &str) -> bool {
+    config.commands.iter().any(|c| c.trim_start_matches('/').eq_ignore_ascii_case(command.trim_start_matches('/')))
+}
+
+/// Whether the node answers in `channel_id` (`listen_channels`; `["*"]` or empty for all)
+pub fn listens_in(config: &SlackChannelConfig, channel_id: &str) -> bool {
+    config.listen_channels.is_empty() || config.listen_channels.iter().any(|c| c == "*" || c == channel_id)
+}
+
+/// Immediate response body; ephemeral messages are only shown to the invoking user
+pub fn ephemeral(text: &str) -> serde_json::Value {
+    serde_json::json!({ "response_type": "ephemeral", "text": text })
+}
+
+/// Post the answer to a slash command: via its `response_url` when present, otherwise
+/// (or if that fails) with `chat.postMessage` using the bot token
+pub async fn deliver(config: &SlackChannelConfig, msg: &IncomingMessage, content: &str) -> anyhow::Result<()> {
+    let text = format_for_channel(content, ChannelType::Slack);
+    let client = reqwest::Client::new();
+
+    if let Some(response_url) = msg.metadata["response_url"].as_str() {
+        let result = client
+            .post(response_url)
+            .json(&serde_json::json!({ "response_type": "in_channel", "replace_original": false, "text": text }))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) => tracing::warn!("Slack response_url failed, falling back to chat.postMessage: {}", e),
+        }
+    }
+
+    let token = std::env::var(&config.token_env)
+        .map_err(|_| anyhow::anyhow!("Slack token not found in env: {}", config.token_env))?;
+    let body: serde_json::Value = client
+        .post("https://slack.com/api/chat.postMessage")
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "channel": msg.channel_id, "text": text }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    if body["ok"].as_bool() != Some(true) {
+        anyhow::bail!("Slack chat.postMessage failed: {}", body["error"].as_str().unwrap_or("unknown error"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slash_command_signature_and_parse() {
+        // Example request from Slack's "Verifying requests from Slack" docs
+        let secret = "8f742231b10e8888abcd99yyyzzz85a5";
+        let timestamp = "1531420618";
+        let body = b"token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+        let signature = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+        assert!(verify_signature(secret, timestamp, signature, body, 1531420618 + 10).is_ok());
+        assert!(verify_signature(secret, timestamp, signature, body, 1531420618 + 3600).is_err());
+        assert!(verify_signature("wrong", timestamp, signature, body, 1531420618).is_err());
+
+        let command = SlashCommand::parse(body).unwrap();
+        assert_eq!(command.command, "/webhook-collect");
+        assert_eq!(command.user_name.as_deref(), Some("roadrunner"));
+        assert_eq!(command.text, "");
+        assert!(command.response_url.unwrap().starts_with("https://hooks.slack.com/commands/"));
+
+        let config = SlackChannelConfig { commands: vec!["ask".to_string()], ..Default::default() };
+        assert!(handles_command(&config, "/ask") && !handles_command(&config, "/webhook-collect"));
+    }
+}
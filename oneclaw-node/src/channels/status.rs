@@ -0,0 +1,125 @@
+//! Channel Status
+//!
+//! Channels report their connection state here as it changes (Discord READY, Telegram
+//! `getMe`, Slack `auth.test`) and the daemon records when each last received a message.
+//! `GET /channels/status` reads it back.
+
+use super::ChannelType;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+const ALL: [ChannelType; 4] = [ChannelType::Discord, ChannelType::Slack, ChannelType::Telegram, ChannelType::Http];
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ChannelStatus {
+    pub channel: ChannelType,
+    /// Started by this daemon
+    pub enabled: bool,
+    /// Logged in to the provider (gateway READY, token accepted)
+    pub connected: bool,
+    /// Bot identity as the provider reports it, e.g. `@oneclaw_bot`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_at: Option<DateTime<Utc>>,
+    /// Why the channel isn't connected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ChannelStatus {
+    fn new(channel: ChannelType) -> Self {
+        Self { channel, enabled: false, connected: false, identity: None, last_message_at: None, error: None }
+    }
+}
+
+#[derive(Default)]
+pub struct ChannelStatuses {
+    statuses: RwLock<HashMap<ChannelType, ChannelStatus>>,
+}
+
+impl ChannelStatuses {
+    fn update(&self, channel: ChannelType, change: impl FnOnce(&mut ChannelStatus)) {
+        let mut statuses = self.statuses.write().unwrap();
+        change(statuses.entry(channel).or_insert_with(|| ChannelStatus::new(channel)));
+    }
+
+    /// The daemon started `channel`
+    pub fn enable(&self, channel: ChannelType) {
+        self.update(channel, |s| s.enabled = true);
+    }
+
+    /// `identity` of `None` keeps the one from the last connection (e.g. a resumed session)
+    pub fn connected(&self, channel: ChannelType, identity: Option<String>) {
+        self.update(channel, |s| {
+            s.connected = true;
+            s.error = None;
+            if identity.is_some() {
+                s.identity = identity;
+            }
+        });
+    }
+
+    pub fn disconnected(&self, channel: ChannelType, error: impl Into<String>) {
+        let error = error.into();
+        self.update(channel, |s| {
+            s.connected = false;
+            s.error = Some(error);
+        });
+    }
+
+    pub fn message_received(&self, channel: ChannelType) {
+        self.update(channel, |s| s.last_message_at = Some(Utc::now()));
+    }
+
+    /// Every channel type, including ones never started
+    pub fn snapshot(&self) -> Vec<ChannelStatus> {
+        let statuses = self.statuses.read().unwrap();
+        ALL.iter().map(|channel| statuses.get(channel).cloned().unwrap_or_else(|| ChannelStatus::new(*channel))).collect()
+    }
+}
+
+/// Report on the node's statuses (`services::Services::channel_statuses`)
+pub fn report(change: impl FnOnce(&ChannelStatuses)) {
+    if let Some(services) = crate::services::installed() {
+        change(&services.channel_statuses);
+    }
+}
+
+/// Record the result of a Slack `auth.test`. Slack has no long-lived connection here (slash
+/// commands arrive over HTTP), so a valid token counts as connected.
+pub async fn check_slack(statuses: &ChannelStatuses, config: &crate::config::SlackChannelConfig) {
+    let result = match std::env::var(&config.token_env) {
+        Ok(token) => super::verify_slack_token(&token).await,
+        Err(_) => Err(anyhow::anyhow!("Slack token not found in env: {}", config.token_env)),
+    };
+    match result {
+        Ok(identity) => statuses.connected(ChannelType::Slack, Some(identity)),
+        Err(e) => statuses.disconnected(ChannelType::Slack, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_follows_connection_changes() {
+        let statuses = ChannelStatuses::default();
+        statuses.enable(ChannelType::Discord);
+        statuses.connected(ChannelType::Discord, Some("claw (1)".to_string()));
+        statuses.disconnected(ChannelType::Discord, "gateway connection closed");
+        statuses.connected(ChannelType::Discord, None);
+        statuses.message_received(ChannelType::Discord);
+
+        let all = statuses.snapshot();
+        assert_eq!(all.len(), 4);
+        let discord = &all[0];
+        assert!(discord.enabled && discord.connected && discord.last_message_at.is_some());
+        assert_eq!((discord.identity.as_deref(), discord.error.as_deref()), (Some("claw (1)"), None));
+        assert_eq!(all[2], ChannelStatus::new(ChannelType::Telegram));
+    }
+}
@@ -10,13 +10,15 @@ use serde_json::Value;
 use tokio::sync::mpsc;
 use tracing::{error, info, warn};
 
-use super::{Channel, ChannelType, IncomingMessage, OutgoingMessage};
+use super::{Channel, ChannelType, IncomingMessage, OutgoingMessage, SentMessage, TriggerContext};
+use crate::config::{TriggerConfig, TriggerMode};
 
 #[derive(Debug, Clone)]
 pub struct TelegramChannel {
     bot_token: String,
     base_url: String,
     offset: std::sync::Arc<tokio::sync::Mutex<i64>>,
+    trigger: TriggerConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,6 +40,8 @@ struct TelegramUser {
     id: i64,
     first_name: String,
     username: Option<String>,
+    #[serde(default)]
+    is_bot: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -60,9 +64,32 @@ impl TelegramChannel {
             base_url: "https://api.telegram.org".to_string(),
             bot_token,
             offset: std::sync::Arc::new(tokio::sync::Mutex::new(0)),
+            trigger: TriggerConfig::mode(TriggerMode::All),
         }
     }
 
+    /// Only answer messages that pass `trigger` (commands are always answered)
+    pub fn with_trigger(mut self, trigger: TriggerConfig) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// Check the bot token with `getMe`, returning the bot's @username
+    pub async fn verify(&self) -> Result<String> {
+        let url = format!("{}/bot{}/getMe", self.base_url, self.bot_token);
+        let client = reqwest::Client::new();
+        let body: Value = client.get(&url).send().await?.json().await?;
+
+        if body["ok"].as_bool() != Some(true) {
+            anyhow::bail!(
+                "Invalid Telegram bot token: {}",
+                body["description"].as_str().unwrap_or("unknown error")
+            );
+        }
+
+        Ok(format!("@{}", body["result"]["username"].as_str().unwrap_or("unknown")))
+    }
+
     async fn get_updates(&self) -> Result<Vec<TelegramUpdate>> {
         let offset = *self.offset.lock().await;
         let url = format!(
@@ -88,7 +115,8 @@ impl TelegramChannel {
         Ok(updates)
     }
 
-    async fn send_message(&self, chat_id: i64, text: String) -> Result<()> {
+    /// Send a message and return its Telegram `message_id`
+    async fn send_message(&self, chat_id: i64, text: String) -> Result<i64> {
         let url = format!("{}/bot{}/sendMessage", self.base_url, self.bot_token);
 
         let request = SendMessageRequest {
@@ -105,7 +133,9 @@ impl TelegramChannel {
             anyhow::bail!("Failed to send message: {:?}", body);
         }
 
-        Ok(())
+        body["result"]["message_id"]
+            .as_i64()
+            .ok_or_else(|| anyhow::anyhow!("Telegram returned no message_id: {:?}", body))
     }
     
     pub async fn send_typing(&self, channel_user_id: &str) -> Result<()> {
@@ -142,6 +172,7 @@ impl Channel for TelegramChannel {
         let body: Value = response.json().await?;
 
         if body["ok"].as_bool() != Some(true) {
+            super::status::report(|s| s.disconnected(ChannelType::Telegram, "Invalid Telegram bot token"));
             anyhow::bail!("Invalid Telegram bot token");
         }
 
@@ -149,11 +180,14 @@ impl Channel for TelegramChannel {
             .as_str()
             .unwrap_or("unknown");
         info!("✅ Telegram bot connected: @{}", bot_username);
+        super::status::report(|s| s.connected(ChannelType::Telegram, Some(format!("@{}", bot_username))));
+        let mention = format!("@{}", bot_username);
 
         // Start polling loop
         loop {
             match self.get_updates().await {
                 Ok(updates) => {
+                    super::status::report(|s| s.connected(ChannelType::Telegram, None));
                     for update in updates {
                         if let Some(msg) = update.message {
                             if let Some(ref text) = msg.text {
@@ -214,15 +248,27 @@ impl Channel for TelegramChannel {
                                     continue;
                                 }
 
+                                let context = TriggerContext {
+                                    trigger: &self.trigger,
+                                    is_dm: msg.chat.chat_type == "private",
+                                    mentioned: text.contains(&mention),
+                                    from_bot: msg.from.is_bot,
+                                };
+                                if !super::should_respond(text, &context) {
+                                    continue;
+                                }
+                                let content = super::strip_trigger_prefix(text.replace(&mention, "").trim(), &self.trigger).to_string();
+
                                 let channel_msg = IncomingMessage {
                                     channel_type: ChannelType::Telegram,
                                     channel_id: msg.chat.id.to_string(),
                                     provider_user_id: msg.from.id.to_string(),
                                     username: msg.from.username.clone(),
-                                    content: text.clone(),
+                                    content,
                                     timestamp: chrono::Utc::now(),
                                     reply_to: None,
                                     metadata: serde_json::to_value(&msg).unwrap_or_default(),
+                                    attachments: Vec::new(),
                                 };
 
                                 info!(
@@ -241,15 +287,18 @@ impl Channel for TelegramChannel {
                 }
                 Err(e) => {
                     warn!("Error polling Telegram updates: {}", e);
+                    super::status::report(|s| s.disconnected(ChannelType::Telegram, format!("Polling failed: {}", e)));
                     tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
                 }
             }
         }
     }
 
-    async fn send(&self, msg: OutgoingMessage) -> Result<()> {
+    async fn send(&self, msg: OutgoingMessage) -> Result<SentMessage> {
         let chat_id: i64 = msg.channel_id.parse()?;
-        self.send_message(chat_id, msg.content).await
+        let content = super::format_for_channel(&msg.content, msg.channel_type);
+        let message_id = self.send_message(chat_id, content).await?;
+        Ok(SentMessage { channel_type: ChannelType::Telegram, message_id: message_id.to_string() })
     }
 
     async fn stop(&self) -> Result<()> {
@@ -1,9 +1,17 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::OnceLock;
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
 
 static CONFIG: OnceLock<NodeConfig> = OnceLock::new();
 
+/// The config in effect: set by `load`, replaced whole by `update` (e.g. `POST /llm/model`)
+static LIVE_CONFIG: RwLock<Option<Arc<NodeConfig>>> = RwLock::new(None);
+
+/// Workspace chosen on the command line (`daemon --workspace`); wins over `agent.workspace_path`
+static WORKSPACE_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NodeConfig {
     pub node: Node,
@@ -24,6 +32,47 @@ pub struct NodeConfig {
     
     #[serde(default)]
     pub identity: IdentityConfig,
+    
+    #[serde(default)]
+    pub conversation: ConversationConfig,
+    
+    #[serde(default)]
+    pub harness: HarnessConfig,
+    
+    #[serde(default)]
+    pub agent: AgentConfig,
+    
+    #[serde(default)]
+    pub startup: StartupConfig,
+    
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    
+    #[serde(default)]
+    pub callbacks: CallbacksConfig,
+    
+    #[serde(default)]
+    pub dead_letters: DeadLettersConfig,
+    
+    #[serde(default)]
+    pub moderation: ModerationConfig,
+    
+    #[serde(default)]
+    pub chat: ChatConfig,
+    
+    #[serde(default)]
+    pub workflow: WorkflowConfig,
+    
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+    
+    /// Workflows the daemon runs on a cron schedule
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,
+    
+    /// Named secrets available to workflows as `${secret.NAME}`
+    #[serde(default)]
+    pub secrets: HashMap<String, SecretRef>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,39 +82,229 @@ pub struct Node {
     pub environment: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct LlmConfig {
     pub provider: String,
     pub api_key_env: String,
     pub model: String,
+    
+    /// Base delay for retry backoff (doubles per attempt, full jitter)
+    #[serde(default = "default_llm_retry_base_ms")]
+    pub retry_base_ms: u64,
+    
+    /// Upper bound on any single retry delay, including `Retry-After`
+    #[serde(default = "default_llm_retry_max_ms")]
+    pub retry_max_ms: u64,
+    
+    /// Serve identical requests from an on-disk cache (for development)
+    #[serde(default)]
+    pub cache_enabled: bool,
+    
+    #[serde(default = "default_llm_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    
+    /// The model accepts images: attached images are sent along with the user's message.
+    /// When off, attachments are ignored and the model is told they were there.
+    #[serde(default)]
+    pub vision: bool,
 }
 
+fn default_llm_retry_base_ms() -> u64 { 400 }
+fn default_llm_retry_max_ms() -> u64 { 10_000 }
+fn default_llm_cache_ttl_secs() -> u64 { 86_400 }
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
+    /// `"strict"`: unknown chat tool names come back to the model as errors.
+    /// Anything else (e.g. `"lenient"`) skips them silently.
     pub mode: String,
     pub allowed_executors: Vec<String>,
+    
+    /// Executors the chat agent may dispatch tool calls to.
+    /// Falls back to `allowed_executors` when unset.
+    #[serde(default)]
+    pub chat_allowed_executors: Option<Vec<String>>,
+    
+    /// Tools/executors the chat agent may only run after a `/chat/confirm` call
+    #[serde(default)]
+    pub requires_confirmation: Vec<String>,
+    
+    /// How long a pending confirmation stays valid
+    #[serde(default = "default_confirmation_ttl_secs")]
+    pub confirmation_ttl_secs: u64,
+    
+    /// Env var holding the bearer token for admin endpoints (e.g. `POST /llm/model`).
+    /// Those endpoints are disabled while it is unset.
+    #[serde(default = "default_admin_token_env")]
+    pub admin_token_env: String,
+}
+
+fn default_confirmation_ttl_secs() -> u64 { 300 }
+fn default_admin_token_env() -> String { "ONECLAW_ADMIN_TOKEN".to_string() }
+
+impl SecurityConfig {
+    pub fn is_strict(&self) -> bool {
+        self.mode == "strict"
+    }
+    
+    /// The allowlist that applies to chat tool calls, plus the config key it came from
+    pub fn chat_allowlist(&self) -> (&[String], &'static str) {
+        match &self.chat_allowed_executors {
+            Some(list) => (list, "security.chat_allowed_executors"),
+            None => (&self.allowed_executors, "security.allowed_executors"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpConfig {
     pub allowed_domains: Vec<String>,
+    
+    /// Methods `http.request` may use, e.g. `["GET", "POST"]`; all supported methods when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<String>>,
+    
+    /// Most request headers a single call may set
+    #[serde(default = "default_http_max_headers")]
+    pub max_headers: usize,
+    
+    /// Upper bound on the combined size of header names and values
+    #[serde(default = "default_http_max_header_bytes")]
+    pub max_header_bytes: usize,
+}
+
+fn default_http_max_headers() -> usize { 32 }
+fn default_http_max_header_bytes() -> usize { 16 * 1024 }
+
+/// Limits for `harness.execute` calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarnessConfig {
+    /// Used when the step doesn't set `timeout`
+    #[serde(default = "default_harness_timeout_secs")]
+    pub timeout_secs: u64,
+    
+    /// Larger responses are rejected instead of buffered
+    #[serde(default = "default_harness_max_response_bytes")]
+    pub max_response_bytes: usize,
+    
+    /// Harness executor ids `harness.execute` may forward to (`"*"` allows all).
+    /// Tools outside the list are also left out of the agent's prompt.
+    #[serde(default = "default_harness_allowed_executors")]
+    pub allowed_executors: Vec<String>,
+}
+
+impl Default for HarnessConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_harness_timeout_secs(),
+            max_response_bytes: default_harness_max_response_bytes(),
+            allowed_executors: default_harness_allowed_executors(),
+        }
+    }
 }
 
+fn default_harness_timeout_secs() -> u64 { 300 }
+fn default_harness_max_response_bytes() -> usize { 10 * 1024 * 1024 }
+fn default_harness_allowed_executors() -> Vec<String> { vec!["*".to_string()] }
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutorsConfig {
+    /// Built-in executors to register (`"*"` for all, the default)
+    #[serde(default = "default_enabled_executors")]
     pub enabled: Vec<String>,
+    
+    /// Executors (built-in or plugin) to leave out even if enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disabled: Vec<String>,
+    
+    /// External executors (`plugin.<name>`) run as subprocesses
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginConfig>,
+    
+    /// Request timeout in seconds per executor id, e.g. `http.request: 30`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub timeouts: HashMap<String, u64>,
+    
+    /// Request timeout for HTTP executors without an entry in `timeouts`
+    #[serde(default = "default_executor_timeout_secs")]
+    pub default_timeout_secs: u64,
+    
+    /// Check chat tool output against the tool's output schema before the model sees it
+    #[serde(default)]
+    pub validate_output: bool,
+    
+    /// Output JSON Schema per tool or executor id; wins over one declared by the harness or plugin
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub output_schemas: HashMap<String, serde_json::Value>,
 }
 
+fn default_executor_timeout_secs() -> u64 { 30 }
+fn default_enabled_executors() -> Vec<String> { vec!["*".to_string()] }
+
+impl ExecutorsConfig {
+    /// Older versions of `oneclaw onboard` wrote `enabled: [http.request]` while the list was
+    /// still ignored, so such a config means every built-in. Returns whether it was rewritten.
+    pub fn migrate_legacy_enabled(&mut self) -> bool {
+        if self.enabled.len() == 1 && self.enabled[0] == "http.request" && self.disabled.is_empty() {
+            self.enabled = default_enabled_executors();
+            return true;
+        }
+        false
+    }
+}
+
+/// An executor implemented by any program speaking line-delimited JSON on stdin/stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginConfig {
+    /// Must start with `plugin.`
+    pub id: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    #[serde(default = "default_plugin_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Free-form settings passed to the plugin with every call
+    #[serde(default)]
+    pub settings: serde_json::Value,
+    /// JSON Schema its output should match (see `executors.validate_output`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+}
+
+fn default_plugin_timeout_secs() -> u64 { 30 }
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryConfig {
     pub session_max_messages: usize,
     pub preferences_path: String,
+    /// History window per channel type (`discord`, `telegram`, `http`, ...), overriding
+    /// `session_max_messages`
+    #[serde(default)]
+    pub channel_limits: HashMap<String, usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArtifactsConfig {
     pub storage: String,
     pub path: String,
+    
+    /// Header/field names whose values are replaced in stored receipts
+    /// (case-insensitive, on top of built-in secret patterns)
+    #[serde(default = "default_redact_headers")]
+    pub redact_headers: Vec<String>,
+}
+
+pub fn default_artifacts_path() -> String { "~/.oneclaw/artifacts".to_string() }
+
+pub fn default_redact_headers() -> Vec<String> {
+    ["Authorization", "Proxy-Authorization", "Cookie", "Set-Cookie", "X-Api-Key"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +317,59 @@ pub struct LoggingConfig {
 pub struct ControlPlaneConfig {
     pub url: Option<String>,
     pub token: Option<String>,
+    
+    #[serde(default = "default_cp_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    
+    /// Whole-request timeout for hosted store calls
+    #[serde(default = "default_cp_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    
+    /// Extra attempts for idempotent calls (GET/PUT/DELETE) on timeouts, connection errors and 5xx
+    #[serde(default = "default_cp_max_retries")]
+    pub max_retries: u32,
+    
+    /// First retry delay; doubles on each further attempt
+    #[serde(default = "default_cp_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for ControlPlaneConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            token: None,
+            connect_timeout_secs: default_cp_connect_timeout_secs(),
+            request_timeout_secs: default_cp_request_timeout_secs(),
+            max_retries: default_cp_max_retries(),
+            retry_backoff_ms: default_cp_retry_backoff_ms(),
+        }
+    }
+}
+
+fn default_cp_connect_timeout_secs() -> u64 { 5 }
+fn default_cp_request_timeout_secs() -> u64 { 15 }
+fn default_cp_max_retries() -> u32 { 2 }
+fn default_cp_retry_backoff_ms() -> u64 { 250 }
+
+// ============================================
+// Secrets Config
+// ============================================
+
+/// Where a named secret's value comes from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum SecretRef {
+    /// Read from a process environment variable
+    Env { var: String },
+    /// Read the (trimmed) contents of a file
+    File { path: String },
+    /// Run a command and use its trimmed stdout, e.g. `op read ...` or `vault kv get ...`
+    Command {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
 }
 
 // ============================================
@@ -113,8 +405,36 @@ pub struct DiscordChannelConfig {
     #[serde(default)]
     pub listen_channels: Vec<String>, // Channel IDs or ["*"] for all
     
-    #[serde(default = "default_trigger")]
-    pub trigger: String, // "mention" | "all" | "dm_only"
+    /// `trigger: "mention"` is short for `trigger: { mode: mention }`
+    #[serde(default, deserialize_with = "deserialize_trigger")]
+    pub trigger: TriggerConfig,
+    
+    #[serde(default)]
+    pub presence: DiscordPresenceConfig,
+    
+    /// Reaction added while a message is being handled (empty = none)
+    #[serde(default = "default_ack_emoji")]
+    pub ack_emoji: String,
+    
+    /// Reaction left on the message if handling fails (empty = none)
+    #[serde(default = "default_error_emoji")]
+    pub error_emoji: String,
+    
+    /// Post `placeholder_text` right away and edit it into the answer once ready
+    #[serde(default)]
+    pub edit_placeholder: bool,
+    
+    #[serde(default = "default_placeholder_text")]
+    pub placeholder_text: String,
+    
+    /// Gateway intents to request. `message_content` is privileged: it must also be enabled
+    /// for the bot in the developer portal, or messages arrive with empty content.
+    #[serde(default = "default_discord_intents")]
+    pub intents: Vec<String>,
+    
+    /// Ask the gateway for `zlib-stream` compression; falls back to plain JSON if inflating fails
+    #[serde(default = "default_true")]
+    pub compress: bool,
 }
 
 impl Default for DiscordChannelConfig {
@@ -124,13 +444,124 @@ impl Default for DiscordChannelConfig {
             token_env: default_discord_token_env(),
             listen_guilds: vec!["*".to_string()],
             listen_channels: vec!["*".to_string()],
-            trigger: default_trigger(),
+            trigger: TriggerConfig::default(),
+            presence: DiscordPresenceConfig::default(),
+            ack_emoji: default_ack_emoji(),
+            error_emoji: default_error_emoji(),
+            edit_placeholder: false,
+            placeholder_text: default_placeholder_text(),
+            intents: default_discord_intents(),
+            compress: true,
         }
     }
 }
 
 fn default_discord_token_env() -> String { "DISCORD_BOT_TOKEN".to_string() }
-fn default_trigger() -> String { "mention".to_string() }
+fn default_ack_emoji() -> String { "👀".to_string() }
+fn default_error_emoji() -> String { "❌".to_string() }
+fn default_placeholder_text() -> String { "🤔 Thinking…".to_string() }
+fn default_discord_intents() -> Vec<String> {
+    ["guilds", "guild_messages", "direct_messages", "message_content"].map(String::from).to_vec()
+}
+
+/// Bot presence shown in the member list once connected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscordPresenceConfig {
+    /// Shown as "Playing <activity>"; no activity when unset
+    #[serde(default = "default_presence_activity")]
+    pub activity: Option<String>,
+    
+    #[serde(default = "default_presence_status")]
+    pub status: String, // "online" | "idle" | "dnd" | "invisible"
+    
+    /// Switch to "idle" after this long without a message (0 = never)
+    #[serde(default = "default_presence_idle_after_secs")]
+    pub idle_after_secs: u64,
+}
+
+impl Default for DiscordPresenceConfig {
+    fn default() -> Self {
+        Self {
+            activity: default_presence_activity(),
+            status: default_presence_status(),
+            idle_after_secs: default_presence_idle_after_secs(),
+        }
+    }
+}
+
+/// When a channel answers a message (see `channels::should_respond`): when `mode` allows it,
+/// or the message starts with one of `prefixes`, or contains one of `keywords`. Messages from
+/// bots never trigger.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerConfig {
+    #[serde(default)]
+    pub mode: TriggerMode,
+    
+    /// e.g. `["!ask"]`, matched case-insensitively and stripped from the message
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    
+    /// Words or phrases matched case-insensitively on word boundaries, e.g. `["oneclaw"]`
+    #[serde(default)]
+    pub keywords: Vec<String>,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self::mode(TriggerMode::default())
+    }
+}
+
+impl TriggerConfig {
+    pub fn mode(mode: TriggerMode) -> Self {
+        Self { mode, prefixes: Vec::new(), keywords: Vec::new() }
+    }
+}
+
+/// Which messages a channel answers regardless of prefixes and keywords
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerMode {
+    /// Messages that mention the bot
+    #[default]
+    Mention,
+    /// Every message
+    All,
+    /// Direct messages / private chats
+    DmOnly,
+    /// Only prefixes and keywords
+    None,
+}
+
+/// A trigger given as just the mode, or in full. Not `#[serde(untagged)]`, which would
+/// hide the unknown-mode error behind "did not match any variant".
+fn deserialize_trigger<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<TriggerConfig, D::Error> {
+    use serde::de::{self, value::{MapAccessDeserializer, StrDeserializer}};
+    
+    struct TriggerVisitor;
+    
+    impl<'de> de::Visitor<'de> for TriggerVisitor {
+        type Value = TriggerConfig;
+        
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a trigger mode or a trigger map")
+        }
+        
+        fn visit_str<E: de::Error>(self, mode: &str) -> Result<TriggerConfig, E> {
+            TriggerMode::deserialize(StrDeserializer::new(mode)).map(TriggerConfig::mode)
+        }
+        
+        fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<TriggerConfig, A::Error> {
+            TriggerConfig::deserialize(MapAccessDeserializer::new(map))
+        }
+    }
+    
+    deserializer.deserialize_any(TriggerVisitor)
+}
+
+fn default_presence_activity() -> Option<String> { Some("OneClaw".to_string()) }
+fn default_presence_status() -> String { "online".to_string() }
+fn default_presence_idle_after_secs() -> u64 { 1800 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlackChannelConfig {
@@ -145,6 +576,18 @@ pub struct SlackChannelConfig {
     
     #[serde(default)]
     pub listen_channels: Vec<String>,
+    
+    /// Env var holding the app's signing secret, used to verify slash command requests
+    #[serde(default = "default_slack_signing_secret_env")]
+    pub signing_secret_env: String,
+    
+    /// Slash commands answered at `POST /slack/commands`, e.g. `["/ask"]`
+    #[serde(default = "default_slack_commands")]
+    pub commands: Vec<String>,
+    
+    /// Applied to the command text; a slash command counts as a mention
+    #[serde(default, deserialize_with = "deserialize_trigger")]
+    pub trigger: TriggerConfig,
 }
 
 impl Default for SlackChannelConfig {
@@ -154,12 +597,17 @@ impl Default for SlackChannelConfig {
             token_env: default_slack_token_env(),
             app_token_env: default_slack_app_token_env(),
             listen_channels: vec!["*".to_string()],
+            signing_secret_env: default_slack_signing_secret_env(),
+            commands: default_slack_commands(),
+            trigger: TriggerConfig::default(),
         }
     }
 }
 
 fn default_slack_token_env() -> String { "SLACK_BOT_TOKEN".to_string() }
 fn default_slack_app_token_env() -> String { "SLACK_APP_TOKEN".to_string() }
+fn default_slack_signing_secret_env() -> String { "SLACK_SIGNING_SECRET".to_string() }
+fn default_slack_commands() -> Vec<String> { vec!["/ask".to_string()] }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TelegramChannelConfig {
@@ -171,6 +619,10 @@ pub struct TelegramChannelConfig {
     
     #[serde(default)]
     pub allowed_users: Vec<String>, // User IDs or ["*"] for all
+    
+    /// Mentions are `@bot_username` in the text; answers everything by default
+    #[serde(default = "default_telegram_trigger", deserialize_with = "deserialize_trigger")]
+    pub trigger: TriggerConfig,
 }
 
 impl Default for TelegramChannelConfig {
@@ -179,11 +631,13 @@ impl Default for TelegramChannelConfig {
             enabled: false,
             token_env: default_telegram_token_env(),
             allowed_users: vec!["*".to_string()],
+            trigger: default_telegram_trigger(),
         }
     }
 }
 
 fn default_telegram_token_env() -> String { "TELEGRAM_BOT_TOKEN".to_string() }
+fn default_telegram_trigger() -> TriggerConfig { TriggerConfig::mode(TriggerMode::All) }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HttpChannelConfig {
@@ -204,7 +658,7 @@ impl Default for HttpChannelConfig {
 }
 
 fn default_true() -> bool { true }
-fn default_port() -> u16 { 9000 }  // Daemon ALWAYS on 9000, Harness on 8787
+fn default_port() -> u16 { crate::ports::DAEMON_PORT }
 
 // ============================================
 // Store Config
@@ -231,6 +685,405 @@ impl Default for StoreConfig {
 fn default_store_type() -> String { "sqlite".to_string() }
 fn default_sqlite_path() -> String { "~/.oneclaw/data.db".to_string() }
 
+// ============================================
+// Schedules Config
+// ============================================
+
+/// What to do when a schedule fires while its previous run is still going
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlapPolicy {
+    /// Drop the new run
+    #[default]
+    Skip,
+    /// Run once more after the current run finishes (further fires coalesce into that one)
+    Queue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    /// Name shown by `/schedules`; defaults to the workflow ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub workflow_id: String,
+    /// Cron expression in UTC: 5 fields (`min hour dom mon dow`) or 6 with leading seconds
+    pub cron: String,
+    #[serde(default)]
+    pub inputs: serde_json::Value,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+}
+
+impl ScheduleConfig {
+    pub fn id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.workflow_id)
+    }
+}
+
+// ============================================
+// Startup Config
+// ============================================
+
+/// Retries for dependencies the daemon needs at boot (store, harness tools), so a volume that
+/// mounts late or a control plane that's briefly down doesn't kill the process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupConfig {
+    /// Total attempts per dependency, including the first
+    #[serde(default = "default_startup_retry_attempts")]
+    pub retry_attempts: u32,
+    
+    /// First retry delay; doubles on each further attempt
+    #[serde(default = "default_startup_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    
+    /// Upper bound on a single retry delay
+    #[serde(default = "default_startup_retry_max_backoff_ms")]
+    pub retry_max_backoff_ms: u64,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            retry_attempts: default_startup_retry_attempts(),
+            retry_backoff_ms: default_startup_retry_backoff_ms(),
+            retry_max_backoff_ms: default_startup_retry_max_backoff_ms(),
+        }
+    }
+}
+
+impl StartupConfig {
+    /// Delay before retry number `attempt` (1-based)
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let ms = self.retry_backoff_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(20));
+        std::time::Duration::from_millis(ms.min(self.retry_max_backoff_ms))
+    }
+}
+
+fn default_startup_retry_attempts() -> u32 { 5 }
+fn default_startup_retry_backoff_ms() -> u64 { 1000 }
+fn default_startup_retry_max_backoff_ms() -> u64 { 30_000 }
+
+// ============================================
+// Budget Configuration
+// ============================================
+
+/// Daily spend caps (UTC days) on paid calls: `llm.chat` by token usage and
+/// `harness.execute` by the tool's `cost_estimate`. Unset limits never block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// Cap for the whole node
+    #[serde(default)]
+    pub daily_usd: Option<f64>,
+    
+    /// Cap for each chat user
+    #[serde(default)]
+    pub per_user_daily_usd: Option<f64>,
+    
+    /// Per-user caps by user id, overriding `per_user_daily_usd`
+    #[serde(default)]
+    pub users: HashMap<String, f64>,
+    
+    /// Price used to estimate LLM cost, USD per million input tokens
+    #[serde(default = "default_llm_input_usd_per_mtok")]
+    pub llm_input_usd_per_mtok: f64,
+    
+    /// USD per million output tokens
+    #[serde(default = "default_llm_output_usd_per_mtok")]
+    pub llm_output_usd_per_mtok: f64,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            daily_usd: None,
+            per_user_daily_usd: None,
+            users: HashMap::new(),
+            llm_input_usd_per_mtok: default_llm_input_usd_per_mtok(),
+            llm_output_usd_per_mtok: default_llm_output_usd_per_mtok(),
+        }
+    }
+}
+
+impl BudgetConfig {
+    pub fn user_limit(&self, user_id: &str) -> Option<f64> {
+        self.users.get(user_id).copied().or(self.per_user_daily_usd)
+    }
+}
+
+fn default_llm_input_usd_per_mtok() -> f64 { 3.0 }
+fn default_llm_output_usd_per_mtok() -> f64 { 15.0 }
+
+// ============================================
+// Workflow Callbacks
+// ============================================
+
+/// Delivery of `/run` `callback_url` notifications
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallbacksConfig {
+    /// Env var holding the HMAC key callbacks are signed with; unsigned when unset
+    #[serde(default = "default_callback_secret_env")]
+    pub secret_env: String,
+    
+    /// Extra attempts after the first, on connection errors, 429 and 5xx
+    #[serde(default = "default_callback_max_retries")]
+    pub max_retries: u32,
+    
+    /// Doubles each retry
+    #[serde(default = "default_callback_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    
+    #[serde(default = "default_callback_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for CallbacksConfig {
+    fn default() -> Self {
+        Self {
+            secret_env: default_callback_secret_env(),
+            max_retries: default_callback_max_retries(),
+            retry_backoff_ms: default_callback_retry_backoff_ms(),
+            timeout_secs: default_callback_timeout_secs(),
+        }
+    }
+}
+
+fn default_callback_secret_env() -> String { "ONECLAW_CALLBACK_SECRET".to_string() }
+fn default_callback_max_retries() -> u32 { 3 }
+fn default_callback_retry_backoff_ms() -> u64 { 1000 }
+fn default_callback_timeout_secs() -> u64 { 10 }
+
+// ============================================
+// Dead Letters
+// ============================================
+
+/// Retrying channel replies that failed to send (see `dead_letter`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLettersConfig {
+    /// Retries after the failed send before a message is marked `failed`
+    #[serde(default = "default_dead_letter_max_retries")]
+    pub max_retries: u32,
+    
+    /// Doubles each retry, up to an hour
+    #[serde(default = "default_dead_letter_retry_backoff_secs")]
+    pub retry_backoff_secs: u64,
+    
+    /// How often the retrier looks for messages that are due
+    #[serde(default = "default_dead_letter_poll_secs")]
+    pub poll_secs: u64,
+}
+
+impl Default for DeadLettersConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: default_dead_letter_max_retries(),
+            retry_backoff_secs: default_dead_letter_retry_backoff_secs(),
+            poll_secs: default_dead_letter_poll_secs(),
+        }
+    }
+}
+
+fn default_dead_letter_max_retries() -> u32 { 8 }
+fn default_dead_letter_retry_backoff_secs() -> u64 { 30 }
+fn default_dead_letter_poll_secs() -> u64 { 15 }
+
+// ============================================
+// Chat
+// ============================================
+
+/// Per-turn limits on a chat message's tool calls and LLM calls. A turn that goes over one
+/// stops and replies with what it has so far. Unset limits never stop a turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatConfig {
+    /// Tool calls run from one LLM response; the rest are dropped with a note in the reply (0 = no limit)
+    #[serde(default = "default_max_tools_per_turn")]
+    pub max_tools_per_turn: usize,
+    
+    /// Wall-clock seconds since the message arrived
+    #[serde(default)]
+    pub max_turn_secs: Option<u64>,
+    
+    /// Estimated USD spent by the turn, priced as for `budget`
+    #[serde(default)]
+    pub max_turn_usd: Option<f64>,
+}
+
+fn default_max_tools_per_turn() -> usize { 5 }
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            max_tools_per_turn: default_max_tools_per_turn(),
+            max_turn_secs: None,
+            max_turn_usd: None,
+        }
+    }
+}
+
+// ============================================
+// Workflows
+// ============================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowConfig {
+    /// Derive run IDs (`oneclaw-node run`, `/run` without `request_id`) from the workflow ID and
+    /// inputs instead of picking them at random; a rerun replaces the earlier receipt. Batch,
+    /// scheduled and replayed runs keep random IDs.
+    #[serde(default)]
+    pub deterministic_run_ids: bool,
+    
+    /// With `deterministic_run_ids`, return the receipt of an earlier successful identical run
+    /// instead of running again
+    #[serde(default)]
+    pub reuse_receipts: bool,
+}
+
+// ============================================
+// Moderation
+// ============================================
+
+/// Checks chat messages go through before reaching the LLM (see `moderation`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationConfig {
+    /// Regexes (case-insensitive); a message matching any of them is blocked
+    #[serde(default)]
+    pub blocklist: Vec<String>,
+    
+    /// Messages longer than this many characters are blocked; 0 for no cap
+    #[serde(default)]
+    pub max_chars: usize,
+    
+    /// Also ask a moderation API, after the local rules pass
+    #[serde(default)]
+    pub api: Option<ModerationApiConfig>,
+    
+    /// Sent back instead of an answer
+    #[serde(default = "default_moderation_refusal")]
+    pub refusal: String,
+    
+    /// JSON-lines file blocked messages are recorded in
+    #[serde(default = "default_denials_log")]
+    pub denials_log: String,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            blocklist: Vec::new(),
+            max_chars: 0,
+            api: None,
+            refusal: default_moderation_refusal(),
+            denials_log: default_denials_log(),
+        }
+    }
+}
+
+/// An endpoint speaking OpenAI's `/v1/moderations` format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationApiConfig {
+    #[serde(default = "default_moderation_api_url")]
+    pub url: String,
+    
+    #[serde(default = "default_moderation_api_key_env")]
+    pub api_key_env: String,
+    
+    #[serde(default = "default_moderation_api_timeout_secs")]
+    pub timeout_secs: u64,
+    
+    /// Block messages while the API is unreachable, instead of letting them through
+    #[serde(default)]
+    pub fail_closed: bool,
+}
+
+fn default_moderation_refusal() -> String { "Sorry, I can't help with that message.".to_string() }
+fn default_denials_log() -> String { "~/.oneclaw/logs/denials.jsonl".to_string() }
+fn default_moderation_api_url() -> String { "https://api.openai.com/v1/moderations".to_string() }
+fn default_moderation_api_key_env() -> String { "OPENAI_API_KEY".to_string() }
+fn default_moderation_api_timeout_secs() -> u64 { 5 }
+
+// ============================================
+// Daemon
+// ============================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    /// IP address the API listens on; `0.0.0.0` exposes it on every interface
+    #[serde(default = "default_daemon_bind")]
+    pub bind: String,
+    
+    /// Browser origins allowed to call the API (e.g. `https://app.example.com`).
+    /// Empty allows only localhost origins; `["*"]` allows any origin.
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            bind: default_daemon_bind(),
+            cors_allowed_origins: Vec::new(),
+        }
+    }
+}
+
+fn default_daemon_bind() -> String { "127.0.0.1".to_string() }
+
+// ============================================
+// Agent Config
+// ============================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Agent OS workspace (SOUL.md, IDENTITY.md, ...). Defaults to ~/.oneclaw/workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_path: Option<String>,
+    
+    /// Greeting added to the reply to each user's first message; overrides WELCOME.md
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub welcome: Option<String>,
+}
+
+/// Select the workspace for this process. Must be called before `load`.
+pub fn set_workspace_override(path: PathBuf) {
+    WORKSPACE_OVERRIDE.set(path).ok();
+}
+
+pub fn default_workspace_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".oneclaw").join("workspace"))
+}
+
+/// Agent OS workspace for this process: `--workspace`, then `agent.workspace_path`, then the default
+pub fn workspace_dir() -> Option<PathBuf> {
+    if let Some(dir) = WORKSPACE_OVERRIDE.get() {
+        return Some(dir.clone());
+    }
+    load()
+        .ok()
+        .and_then(|c| c.agent.workspace_path.as_deref())
+        .map(expand_path)
+        .or_else(default_workspace_dir)
+}
+
+/// Give a non-default workspace its own node id and (unless configured explicitly) its own
+/// database and artifacts directory, so several agents on one machine don't share
+/// conversations, receipts or run files.
+fn scope_to_workspace(config: &mut NodeConfig, workspace: &Path) {
+    if default_workspace_dir().as_deref() == Some(workspace) {
+        return;
+    }
+    let name = workspace
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "workspace".to_string());
+    
+    config.node.id = format!("{}-{}", config.node.id, name);
+    if config.store.sqlite_path == default_sqlite_path() {
+        config.store.sqlite_path = workspace.join("data.db").display().to_string();
+    }
+    if config.artifacts.path == default_artifacts_path() {
+        config.artifacts.path = workspace.join("artifacts").display().to_string();
+    }
+}
+
 // ============================================
 // Identity Config
 // ============================================
@@ -255,6 +1108,188 @@ impl Default for IdentityConfig {
 
 fn default_link_timeout() -> u32 { 15 }
 
+// ============================================
+// Conversation Config
+// ============================================
+
+/// How chat history is partitioned for a user
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConversationScope {
+    /// One history per user across every channel
+    #[default]
+    #[serde(rename = "user")]
+    User,
+    /// Separate history per (user, channel/chat id)
+    #[serde(rename = "user+channel")]
+    UserChannel,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationConfig {
+    #[serde(default)]
+    pub scope: ConversationScope,
+    
+    /// Per channel type overrides, e.g. `discord: "user+channel"`
+    #[serde(default)]
+    pub channel_scopes: HashMap<String, ConversationScope>,
+    
+    /// Title untitled conversations from their first message
+    #[serde(default = "default_true")]
+    pub auto_title: bool,
+    
+    /// Tool outputs larger than this are cut to a head/tail slice before going back to the LLM
+    #[serde(default = "default_tool_result_max_bytes")]
+    pub tool_result_max_bytes: usize,
+    
+    /// Condense oversized tool outputs with an extra LLM call instead of cutting them
+    #[serde(default)]
+    pub summarize_tool_results: bool,
+}
+
+fn default_tool_result_max_bytes() -> usize { 16_000 }
+
+impl Default for ConversationConfig {
+    fn default() -> Self {
+        Self {
+            scope: ConversationScope::default(),
+            channel_scopes: HashMap::new(),
+            auto_title: true,
+            tool_result_max_bytes: default_tool_result_max_bytes(),
+            summarize_tool_results: false,
+        }
+    }
+}
+
+impl ConversationConfig {
+    /// Thread key for a message, or `None` when history is shared across channels
+    pub fn thread_id(&self, channel_type: &str, channel_id: Option<&str>) -> Option<String> {
+        let scope = self.channel_scopes.get(channel_type).copied().unwrap_or(self.scope);
+        match (scope, channel_id) {
+            (ConversationScope::UserChannel, Some(id)) => Some(format!("{}:{}", channel_type, id)),
+            _ => None,
+        }
+    }
+}
+
+// ============================================
+// Runtime LLM Selection
+// ============================================
+
+/// LLM providers the executor knows how to call
+pub const LLM_PROVIDERS: &[&str] = &["anthropic", "openai", "openrouter"];
+
+/// API key env var and default model for a supported LLM provider
+pub fn provider_defaults(provider: &str) -> Option<(&'static str, &'static str)> {
+    match provider {
+        "anthropic" => Some(("ANTHROPIC_API_KEY", "claude-3-5-sonnet-20241022")),
+        "openrouter" => Some(("OPENROUTER_API_KEY", "anthropic/claude-3.5-sonnet")),
+        "openai" => Some(("OPENAI_API_KEY", "gpt-4o")),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LlmSelection {
+    pub provider: String,
+    pub model: String,
+    pub api_key_env: String,
+}
+
+/// The config in effect now; `None` before `load`
+pub fn current() -> Option<Arc<NodeConfig>> {
+    LIVE_CONFIG.read().unwrap().clone()
+}
+
+/// Change the running config. Readers holding the previous one keep it; every `current()`
+/// after this sees the change.
+pub fn update(change: impl FnOnce(&mut NodeConfig)) -> anyhow::Result<Arc<NodeConfig>> {
+    let mut live = LIVE_CONFIG.write().unwrap();
+    let mut config = live.as_deref().cloned().ok_or_else(|| anyhow::anyhow!("Config not loaded"))?;
+    change(&mut config);
+    let config = Arc::new(config);
+    *live = Some(Arc::clone(&config));
+    Ok(config)
+}
+
+/// Switch provider/model for the running process
+pub fn set_llm(selection: LlmSelection) -> anyhow::Result<Arc<NodeConfig>> {
+    update(|config| {
+        config.llm.provider = selection.provider;
+        config.llm.model = selection.model;
+        config.llm.api_key_env = selection.api_key_env;
+    })
+}
+
+/// `config` with the `llm` section of the config in effect, which may have changed since load
+pub fn with_live_llm(config: &NodeConfig) -> std::borrow::Cow<'_, NodeConfig> {
+    match current() {
+        Some(live) if live.llm != config.llm => {
+            let mut config = config.clone();
+            config.llm = live.llm.clone();
+            std::borrow::Cow::Owned(config)
+        }
+        _ => std::borrow::Cow::Borrowed(config),
+    }
+}
+
+/// Write a provider/model choice into node.yaml, keeping the file's comments and layout
+pub fn persist_llm(selection: &LlmSelection) -> anyhow::Result<()> {
+    let path = config_path()?;
+    let contents = std::fs::read_to_string(&path)?;
+    let updated = set_section_scalars(&contents, "llm", &[
+        ("provider", &selection.provider),
+        ("model", &selection.model),
+        ("api_key_env", &selection.api_key_env),
+    ])?;
+    std::fs::write(&path, updated)?;
+    Ok(())
+}
+
+/// Set `key: value` lines in a top-level block mapping, editing the text in place so comments
+/// survive. Keys the section lacks are added right under its header.
+fn set_section_scalars(contents: &str, section: &str, values: &[(&str, &str)]) -> anyhow::Result<String> {
+    let mut lines: Vec<String> = contents.lines().map(str::to_string).collect();
+    let header = format!("{}:", section);
+    let start = lines
+        .iter()
+        .position(|l| l.split('#').next().unwrap_or("").trim_end() == header)
+        .ok_or_else(|| anyhow::anyhow!("no block-style `{}:` section to update", section))?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| !l.trim().is_empty() && !l.starts_with([' ', '\t', '#']))
+        .map_or(lines.len(), |i| start + 1 + i);
+    let indent = lines[start + 1..end]
+        .iter()
+        .find(|l| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|l| l.len() - l.trim_start().len())
+        .unwrap_or(2);
+    
+    let mut missing = vec![];
+    for (key, value) in values {
+        let value = serde_yaml::to_string(value)?.trim_end().to_string();
+        let prefix = format!("{}{}:", " ".repeat(indent), key);
+        let existing = lines[start + 1..end].iter().position(|l| {
+            l.starts_with(&prefix) && l.len() - l.trim_start().len() == indent
+        });
+        match existing {
+            Some(i) => {
+                let line = &mut lines[start + 1 + i];
+                // Keep a trailing comment
+                let comment = line.find(" #").map(|at| line[line[..at].trim_end().len()..].to_string()).unwrap_or_default();
+                *line = format!("{} {}{}", prefix, value, comment);
+            }
+            None => missing.push(format!("{} {}", prefix, value)),
+        }
+    }
+    lines.splice(start + 1..start + 1, missing);
+    
+    let mut updated = lines.join("\n");
+    if contents.ends_with('\n') {
+        updated.push('\n');
+    }
+    Ok(updated)
+}
+
 pub fn config_path() -> anyhow::Result<PathBuf> {
     let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("No home dir"))?;
     Ok(home.join(".oneclaw").join("node.yaml"))
@@ -334,7 +1369,22 @@ pub fn load() -> anyhow::Result<&'static NodeConfig> {
         };
     }
 
+    if config.executors.migrate_legacy_enabled() {
+        tracing::warn!(
+            "executors.enabled lists only http.request, as written by older `oneclaw onboard`; enabling all built-ins. \
+             Use executors.disabled to turn some off."
+        );
+    }
+
+    if let Some(dir) = WORKSPACE_OVERRIDE.get() {
+        config.agent.workspace_path = Some(dir.display().to_string());
+    }
+    if let Some(workspace) = config.agent.workspace_path.clone() {
+        scope_to_workspace(&mut config, &expand_path(&workspace));
+    }
+
     CONFIG.set(config.clone()).ok();
+    *LIVE_CONFIG.write().unwrap() = Some(Arc::new(config.clone()));
     tracing::info!(
         node_id = %config.node.id,
         provider = %config.llm.provider,
@@ -353,3 +1403,102 @@ pub fn expand_path(path: &str) -> PathBuf {
     }
     PathBuf::from(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_workspaces_keep_runs_apart() {
+        let root = crate::store::TempPath::new("workspaces");
+        let workspace = |name: &str| -> NodeConfig {
+            let mut config: NodeConfig = serde_yaml::from_str(
+                r#"
+node: { id: node, name: test, environment: dev }
+llm: { provider: anthropic, api_key_env: ANTHROPIC_API_KEY, model: test }
+security: { mode: strict, allowed_executors: [http.request] }
+http: { allowed_domains: [] }
+executors: { enabled: [http.request] }
+memory: { session_max_messages: 10, preferences_path: /tmp/prefs.yaml }
+artifacts: { storage: local, path: ~/.oneclaw/artifacts }
+logging: { level: info, path: /tmp/logs }
+control_plane: {}
+"#,
+            )
+            .unwrap();
+            scope_to_workspace(&mut config, &root.0.join(name));
+            config
+        };
+        let (support, research) = (workspace("support"), workspace("research"));
+        assert_eq!(support.artifacts.path, root.0.join("support").join("artifacts").display().to_string());
+        assert_eq!(support.node.id, "node-support");
+        
+        let run = crate::receipt::WorkflowReceipt {
+            run_id: "run-1".to_string(),
+            workflow_id: "daily-report".to_string(),
+            node_id: support.node.id.clone(),
+            started_at: "2026-01-01T00:00:00Z".to_string(),
+            completed_at: String::new(),
+            status: "success".to_string(),
+            mode: "dev".to_string(),
+            steps: Vec::new(),
+            inputs: serde_json::json!({}),
+            outputs: serde_json::json!({}),
+            debug: crate::receipt::DebugInfo {
+                config_snapshot: "abc".to_string(),
+                executor_versions: HashMap::new(),
+                total_duration_ms: 0,
+                trace_id: None,
+            },
+            replay_of: None,
+            parent_run_id: None,
+            owner: None,
+        };
+        let support_runs = crate::receipt::open(&support, None).await.unwrap();
+        let research_runs = crate::receipt::open(&research, None).await.unwrap();
+        support_runs.write(&run).await.unwrap();
+        assert_eq!(support_runs.list().await.unwrap(), vec!["run-1"]);
+        assert!(research_runs.list().await.unwrap().is_empty());
+        assert!(research_runs.read("run-1").await.unwrap().is_none());
+        let _ = std::fs::remove_dir_all(&root.0);
+    }
+    
+    #[test]
+    fn test_set_section_scalars_keeps_comments() {
+        let yaml = "\
+# OneClaw node config
+node:
+  id: n1
+llm:
+  # switch with POST /llm/model
+  provider: anthropic   # the default
+  model: claude-3-5-sonnet-20241022
+  cache_enabled: false
+http:
+  allowed_domains: []
+";
+        let updated = set_section_scalars(yaml, "llm", &[
+            ("provider", "openrouter"),
+            ("model", "openai/gpt-4o-mini"),
+            ("api_key_env", "OPENROUTER_API_KEY"),
+        ]).unwrap();
+        assert_eq!(updated, "\
+# OneClaw node config
+node:
+  id: n1
+llm:
+  api_key_env: OPENROUTER_API_KEY
+  # switch with POST /llm/model
+  provider: openrouter   # the default
+  model: openai/gpt-4o-mini
+  cache_enabled: false
+http:
+  allowed_domains: []
+");
+        let parsed: serde_yaml::Value = serde_yaml::from_str(&updated).unwrap();
+        assert_eq!(parsed["llm"]["provider"], "openrouter");
+        assert_eq!(parsed["http"]["allowed_domains"], serde_yaml::Value::Sequence(vec![]));
+        
+        assert!(set_section_scalars("llm: { provider: openai }\n", "llm", &[("model", "gpt-4o")]).is_err());
+    }
+}
@@ -4,21 +4,47 @@
 //! - Stores messages with channel metadata
 //! - Provides context for LLM calls
 //! - Supports conversation clearing
+//! - Optional per-channel threads (`thread_id`, see `ConversationConfig`)
+//! - One-shot context notes injected into a user's next turn
+//! - Per-thread title, tags and pin flag (`ConversationMeta`)
+//! - Pinned per-user directives that outlive clears (`PinnedDirective`)
 
-use crate::store::{ConversationMessage, Store};
+use crate::store::{ConversationMessage, ConversationMeta, NewMessage, PinnedDirective, Store};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Context notes kept per user until their next turn; older ones are dropped past this
+const MAX_CONTEXT_NOTES: usize = 10;
+
+/// Auto-generated titles are cut at a word boundary below this many characters
+const MAX_AUTO_TITLE_CHARS: usize = 60;
+
+/// A first exchange (one user message plus tool/assistant replies) fits in this many messages
+const AUTO_TITLE_SCAN: usize = 20;
+
+/// `stats` and `recall` look at this many of a user's most recent messages
+const HISTORY_SCAN: usize = 1000;
+
+/// Recalled messages are clipped to this many bytes so a few hits fit in a tool result
+const RECALL_SNIPPET_BYTES: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ChatMessage {
+    /// Stored id, for `/chat`'s `reply_to`
+    #[serde(default)]
+    pub id: i64,
     pub role: String,
     pub content: String,
     pub channel: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ToolCall {
     pub tool: String,
     pub input: serde_json::Value,
@@ -30,72 +56,155 @@ pub struct ToolCall {
 pub struct ConversationManager {
     store: Arc<dyn Store>,
     max_messages: usize,
+    /// Per channel type overrides of `max_messages` (`memory.channel_limits`)
+    channel_limits: HashMap<String, usize>,
+    /// Situational notes for a user's next turn (not persisted, not shown in history)
+    context_notes: Mutex<HashMap<String, Vec<String>>>,
 }
 
 impl ConversationManager {
-    pub fn new(store: Arc<dyn Store>, max_messages: usize) -> Self {
-        Self { store, max_messages }
+    pub fn new(store: Arc<dyn Store>, max_messages: usize, channel_limits: HashMap<String, usize>) -> Self {
+        Self { store, max_messages, channel_limits, context_notes: Mutex::new(HashMap::new()) }
+    }
+    
+    /// History window for a channel type, falling back to the global `max_messages`
+    pub fn history_limit(&self, channel: Option<&str>) -> usize {
+        channel.and_then(|c| self.channel_limits.get(c).copied()).unwrap_or(self.max_messages)
     }
     
-    /// Get conversation history for a user
-    /// Returns messages in chronological order, limited to max_messages
-    pub async fn get_history(&self, user_id: &str) -> anyhow::Result<Vec<ChatMessage>> {
-        let messages = self.store.get_conversation(user_id, self.max_messages).await?;
+    /// Queue a system note for the user's next LLM turn. Returns how many are now queued.
+    pub fn add_context_note(&self, user_id: &str, note: &str) -> usize {
+        let mut notes = self.context_notes.lock().unwrap();
+        let queued = notes.entry(user_id.to_string()).or_default();
+        queued.push(note.trim().to_string());
+        if queued.len() > MAX_CONTEXT_NOTES {
+            queued.remove(0);
+        }
+        queued.len()
+    }
+    
+    /// Get conversation history for a user (optionally one thread)
+    /// Returns messages in chronological order, limited to the `channel`'s history window
+    pub async fn get_history(&self, user_id: &str, thread_id: Option<&str>, channel: Option<&str>) -> anyhow::Result<Vec<ChatMessage>> {
+        let messages = self.store.get_conversation(user_id, thread_id, self.history_limit(channel)).await?;
         
         Ok(messages.into_iter().map(|m| {
             let tool_calls = m.tool_calls.and_then(|tc| {
-                serde_json::from_str(&tc).ok()
+                serde_json::from_str(&tc)
+                    .map_err(|e| tracing::warn!(message_id = m.id, "Unreadable tool_calls on stored message: {}", e))
+                    .ok()
             });
             
             ChatMessage {
+                id: m.id,
                 role: m.role,
                 content: m.content,
                 channel: m.channel,
                 tool_calls,
+                reply_to: m.reply_to,
             }
         }).collect())
     }
     
-    /// Add a user message to the conversation
-    pub async fn add_user_message(&self, user_id: &str, content: &str, channel: &str) -> anyhow::Result<()> {
-        self.store.add_message(user_id, "user", content, channel, None).await?;
-        Ok(())
-    }
-    
     /// Add an assistant message to the conversation
     pub async fn add_assistant_message(
         &self,
         user_id: &str,
+        thread_id: Option<&str>,
         content: &str,
         channel: &str,
         tool_calls: Option<&[ToolCall]>,
     ) -> anyhow::Result<()> {
         let tool_calls_json = tool_calls.map(|tc| serde_json::to_string(tc).unwrap_or_default());
-        self.store.add_message(user_id, "assistant", content, channel, tool_calls_json.as_deref()).await?;
+        self.store.add_message(user_id, thread_id, "assistant", content, channel, tool_calls_json.as_deref()).await?;
         Ok(())
     }
     
-    /// Add a tool result message
-    pub async fn add_tool_message(&self, user_id: &str, content: &str, channel: &str) -> anyhow::Result<()> {
-        self.store.add_message(user_id, "tool", content, channel, None).await?;
-        Ok(())
+    /// Whether `id` is one of the user's messages in `thread_id` (any of them when `None`),
+    /// i.e. something a new message may reply to
+    pub async fn can_reply_to(&self, user_id: &str, thread_id: Option<&str>, id: i64) -> anyhow::Result<bool> {
+        let message = self.store.get_message(user_id, id).await?;
+        Ok(message.is_some_and(|m| thread_id.is_none() || m.thread_id.as_deref() == thread_id))
     }
     
-    /// Clear conversation history for a user
-    pub async fn clear(&self, user_id: &str) -> anyhow::Result<()> {
-        self.store.clear_conversation(user_id).await?;
-        tracing::info!(user_id = %user_id, "Cleared conversation history");
+    /// Store a whole turn (user message, tool results, reply) at once, so a failure part-way
+    /// never leaves a user message without its reply in history. Returns the stored ids.
+    pub async fn add_turn(&self, user_id: &str, thread_id: Option<&str>, channel: &str, messages: &[NewMessage]) -> anyhow::Result<Vec<i64>> {
+        self.store.add_messages(user_id, thread_id, channel, messages).await
+    }
+    
+    /// Metadata for one thread; an empty record when none has been set
+    pub async fn get_meta(&self, user_id: &str, thread_id: Option<&str>) -> anyhow::Result<ConversationMeta> {
+        Ok(self.store.get_conversation_meta(user_id, thread_id).await?.unwrap_or_else(|| ConversationMeta {
+            user_id: user_id.to_string(),
+            thread_id: thread_id.map(str::to_string),
+            ..Default::default()
+        }))
+    }
+    
+    pub async fn set_meta(&self, meta: &ConversationMeta) -> anyhow::Result<()> {
+        self.store.set_conversation_meta(meta).await
+    }
+    
+    /// Title an untitled conversation from its opening message. Only acts on the first
+    /// exchange, so conversations that predate metadata aren't named after a random turn.
+    pub async fn auto_title(&self, user_id: &str, thread_id: Option<&str>, first_message: &str) -> anyhow::Result<()> {
+        let recent = self.store.get_conversation(user_id, thread_id, AUTO_TITLE_SCAN).await?;
+        if recent.len() >= AUTO_TITLE_SCAN || recent.iter().filter(|m| m.role == "user").count() != 1 {
+            return Ok(());
+        }
+        let mut meta = self.get_meta(user_id, thread_id).await?;
+        if meta.title.is_some() {
+            return Ok(());
+        }
+        let Some(title) = title_from_message(first_message) else {
+            return Ok(());
+        };
+        meta.title = Some(title);
+        self.set_meta(&meta).await
+    }
+    
+    /// Clear conversation history for a user (`None` clears every thread).
+    /// Pinned directives are kept unless `include_directives` is set; clearing one
+    /// thread then removes only the directives pinned to that thread.
+    pub async fn clear(&self, user_id: &str, thread_id: Option<&str>, include_directives: bool) -> anyhow::Result<()> {
+        self.store.clear_conversation(user_id, thread_id).await?;
+        if include_directives {
+            for directive in self.store.get_directives(user_id).await? {
+                if thread_id.is_none() || directive.thread_id.as_deref() == thread_id {
+                    self.store.remove_directive(user_id, directive.id).await?;
+                }
+            }
+        }
+        tracing::info!(user_id = %user_id, include_directives, "Cleared conversation history");
         Ok(())
     }
     
+    pub async fn get_directives(&self, user_id: &str) -> anyhow::Result<Vec<PinnedDirective>> {
+        self.store.get_directives(user_id).await
+    }
+    
+    /// Pin an instruction for the user's future turns, in one thread or (`None`) all of them.
+    /// Returns its id.
+    pub async fn add_directive(&self, user_id: &str, thread_id: Option<&str>, content: &str) -> anyhow::Result<i64> {
+        self.store.add_directive(user_id, thread_id, content.trim()).await
+    }
+    
+    pub async fn remove_directive(&self, user_id: &str, id: i64) -> anyhow::Result<bool> {
+        self.store.remove_directive(user_id, id).await
+    }
+    
     /// Build messages array for LLM API call
-    /// Includes system prompt and conversation history
+    /// Includes system prompt, pinned directives, pending context notes (consumed here)
+    /// and conversation history
     pub async fn build_llm_messages(
         &self,
         user_id: &str,
+        thread_id: Option<&str>,
+        channel: &str,
         system_prompt: &str,
     ) -> anyhow::Result<Vec<serde_json::Value>> {
-        let history = self.get_history(user_id).await?;
+        let history = self.get_history(user_id, thread_id, Some(channel)).await?;
         
         let mut messages = vec![
             serde_json::json!({
@@ -104,6 +213,29 @@ impl ConversationManager {
             })
         ];
         
+        let directives: Vec<PinnedDirective> = self
+            .store
+            .get_directives(user_id)
+            .await?
+            .into_iter()
+            .filter(|d| d.thread_id.is_none() || d.thread_id.as_deref() == thread_id)
+            .collect();
+        if !directives.is_empty() {
+            let lines: Vec<String> = directives.iter().map(|d| format!("- {}", d.content)).collect();
+            messages.push(serde_json::json!({
+                "role": "system",
+                "content": format!("## Standing instructions for this user\n{}", lines.join("\n"))
+            }));
+        }
+        
+        let notes = self.context_notes.lock().unwrap().remove(user_id).unwrap_or_default();
+        if !notes.is_empty() {
+            messages.push(serde_json::json!({
+                "role": "system",
+                "content": format!("## Context for this turn\n{}", notes.join("\n"))
+            }));
+        }
+        
         for msg in history {
             messages.push(serde_json::json!({
                 "role": msg.role,
@@ -116,7 +248,7 @@ impl ConversationManager {
     
     /// Get conversation stats
     pub async fn stats(&self, user_id: &str) -> anyhow::Result<ConversationStats> {
-        let history = self.store.get_conversation(user_id, 1000).await?;
+        let history = self.store.get_conversation(user_id, None, HISTORY_SCAN).await?;
         
         let user_count = history.iter().filter(|m| m.role == "user").count();
         let assistant_count = history.iter().filter(|m| m.role == "assistant").count();
@@ -132,6 +264,31 @@ impl ConversationManager {
             channels_used: channels.into_iter().collect(),
         })
     }
+    
+    /// Recent messages (newest first, any thread) containing every word of `query`, ignoring case
+    pub async fn recall(&self, user_id: &str, query: &str, limit: usize) -> anyhow::Result<Vec<RecalledMessage>> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if words.is_empty() {
+            anyhow::bail!("query is empty");
+        }
+        let history = self.store.get_conversation(user_id, None, HISTORY_SCAN).await?;
+        Ok(history
+            .into_iter()
+            .rev()
+            .filter(|m| m.role != "tool")
+            .filter(|m| {
+                let content = m.content.to_lowercase();
+                words.iter().all(|w| content.contains(w.as_str()))
+            })
+            .take(limit)
+            .map(|m| RecalledMessage {
+                role: m.role,
+                content: clip_middle(&m.content, RECALL_SNIPPET_BYTES),
+                channel: m.channel,
+                created_at: m.created_at,
+            })
+            .collect())
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -143,6 +300,23 @@ pub struct ConversationStats {
     pub channels_used: Vec<String>,
 }
 
+/// A past message matched by `ConversationManager::recall`
+#[derive(Debug, Clone, Serialize)]
+pub struct RecalledMessage {
+    pub role: String,
+    pub content: String,
+    pub channel: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Assistant reply for `ConversationManager::add_turn`, with the tool calls it made
+pub fn assistant_message(content: &str, tool_calls: Option<&[ToolCall]>) -> NewMessage {
+    NewMessage {
+        tool_calls: tool_calls.map(|tc| serde_json::to_string(tc).unwrap_or_default()),
+        ..NewMessage::new("assistant", content)
+    }
+}
+
 /// Convert stored messages to LLM format
 pub fn messages_to_llm_format(messages: &[ConversationMessage]) -> Vec<serde_json::Value> {
     messages.iter().map(|m| {
@@ -152,3 +326,214 @@ pub fn messages_to_llm_format(messages: &[ConversationMessage]) -> Vec<serde_jso
         })
     }).collect()
 }
+
+/// Keep the start and end of `text` within about `max_bytes`, marking what was cut.
+/// Used for tool outputs too large to hand back to the LLM whole.
+pub fn clip_middle(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let floor = |mut i: usize| {
+        while !text.is_char_boundary(i) {
+            i -= 1;
+        }
+        i
+    };
+    let head = floor(max_bytes / 2);
+    let tail = floor(text.len() - (max_bytes - head));
+    format!("{}\n...[{} bytes truncated]...\n{}", &text[..head], tail - head, &text[tail..])
+}
+
+/// First line of a message, whitespace collapsed, shortened to `MAX_AUTO_TITLE_CHARS`
+fn title_from_message(message: &str) -> Option<String> {
+    let line = message.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    
+    let mut title = String::new();
+    for word in &words {
+        let len = title.chars().count() + word.chars().count() + usize::from(!title.is_empty());
+        if len > MAX_AUTO_TITLE_CHARS {
+            if title.is_empty() {
+                title = word.chars().take(MAX_AUTO_TITLE_CHARS).collect();
+            }
+            title.push('…');
+            return Some(title);
+        }
+        if !title.is_empty() {
+            title.push(' ');
+        }
+        title.push_str(word);
+    }
+    Some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_tool_calls_round_trip() {
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        store.create_user("u1").await.unwrap();
+        let manager = ConversationManager::new(store, 50, HashMap::from([("discord".to_string(), 1)]));
+        
+        let calls = vec![ToolCall {
+            tool: "http.request".to_string(),
+            input: serde_json::json!({ "url": "https://example.com", "headers": { "accept": "text/\"html\"" } }),
+            output: Some(serde_json::json!({ "status": 200, "body": "ok\n✓" })),
+            success: true,
+            duration_ms: 42,
+        }];
+        manager.add_turn("u1", None, "http", &[NewMessage::new("user", "fetch it")]).await.unwrap();
+        manager.add_assistant_message("u1", None, "Done", "http", Some(&calls)).await.unwrap();
+        
+        let history = manager.get_history("u1", None, Some("http")).await.unwrap();
+        assert!(history[0].tool_calls.is_none());
+        let stored = history[1].tool_calls.as_ref().expect("tool calls stored");
+        assert_eq!(serde_json::to_value(stored).unwrap(), serde_json::to_value(&calls).unwrap());
+        
+        // A channel with its own window sees only the latest messages
+        let short = manager.get_history("u1", None, Some("discord")).await.unwrap();
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].content, "Done");
+    }
+    
+    #[tokio::test]
+    async fn test_turn_records_reply_links() {
+        use crate::store::ReplyTo;
+        
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        store.create_user("u1").await.unwrap();
+        let manager = ConversationManager::new(Arc::clone(&store), 50, HashMap::new());
+        
+        let reply = |content: &str| assistant_message(content, None).replying_to(Some(ReplyTo::Batch(0)));
+        let first = manager.add_turn("u1", None, "http", &[NewMessage::new("user", "hi"), reply("Hello!")]).await.unwrap();
+        let second = manager
+            .add_turn("u1", None, "http", &[
+                NewMessage::new("user", "say that again").replying_to(Some(ReplyTo::Message(first[1]))),
+                NewMessage::new("tool", "[echo result]"),
+                reply("Hello!"),
+            ])
+            .await
+            .unwrap();
+        
+        let stored = store.get_conversation("u1", None, 10).await.unwrap();
+        let links: Vec<(i64, Option<i64>)> = stored.iter().map(|m| (m.id, m.reply_to)).collect();
+        assert_eq!(links, [
+            (first[0], None),
+            (first[1], Some(first[0])),
+            (second[0], Some(first[1])),
+            (second[1], None),
+            (second[2], Some(second[0])),
+        ]);
+        
+        store.create_user("u2").await.unwrap();
+        let other_thread = manager.add_turn("u1", Some("discord:9"), "discord", &[NewMessage::new("user", "elsewhere")]).await.unwrap();
+        assert!(manager.can_reply_to("u1", None, first[1]).await.unwrap());
+        assert!(!manager.can_reply_to("u2", None, first[1]).await.unwrap());
+        assert!(!manager.can_reply_to("u1", Some("discord:1"), other_thread[0]).await.unwrap());
+        assert!(manager.can_reply_to("u1", Some("discord:9"), other_thread[0]).await.unwrap());
+        assert!(!manager.can_reply_to("u1", None, other_thread[0] + 100).await.unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_stats_and_recall() {
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        store.create_user("u1").await.unwrap();
+        let manager = ConversationManager::new(store, 50, HashMap::new());
+        
+        manager.add_turn("u1", None, "discord", &[NewMessage::new("user", "Book a Tee Time for Saturday")]).await.unwrap();
+        manager.add_turn("u1", None, "discord", &[NewMessage::new("tool", "tee time results")]).await.unwrap();
+        manager.add_assistant_message("u1", None, "Booked your tee time at 9am", "discord", None).await.unwrap();
+        manager.add_turn("u1", None, "http", &[NewMessage::new("user", "what's the weather")]).await.unwrap();
+        
+        let stats = manager.stats("u1").await.unwrap();
+        assert_eq!((stats.total_messages, stats.user_messages, stats.tool_messages), (4, 2, 1));
+        
+        let hits = manager.recall("u1", "TEE time", 5).await.unwrap();
+        let contents: Vec<&str> = hits.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["Booked your tee time at 9am", "Book a Tee Time for Saturday"]);
+        assert_eq!(manager.recall("u1", "tee weather", 5).await.unwrap().len(), 0);
+        assert!(manager.recall("u1", "  ", 5).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_auto_title_first_exchange_only() {
+        let long = "Can you find me a tee time at Riverdale this Saturday morning for four players please";
+        let title = title_from_message(long).unwrap();
+        assert!(title.ends_with('…') && title.chars().count() <= MAX_AUTO_TITLE_CHARS + 1);
+        assert_eq!(title_from_message("\n  hi   there \nsecond line").as_deref(), Some("hi there"));
+        assert!(title_from_message("   ").is_none());
+        
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        store.create_user("u1").await.unwrap();
+        let manager = ConversationManager::new(store, 50, HashMap::new());
+        
+        manager.add_turn("u1", None, "http", &[NewMessage::new("user", "plan my week")]).await.unwrap();
+        manager.add_assistant_message("u1", None, "Sure", "http", None).await.unwrap();
+        manager.auto_title("u1", None, "plan my week").await.unwrap();
+        assert_eq!(manager.get_meta("u1", None).await.unwrap().title.as_deref(), Some("plan my week"));
+        
+        // Later turns never rename, and a cleared title stays cleared
+        manager.set_meta(&ConversationMeta { user_id: "u1".to_string(), ..Default::default() }).await.unwrap();
+        manager.add_turn("u1", None, "http", &[NewMessage::new("user", "and next week?")]).await.unwrap();
+        manager.auto_title("u1", None, "and next week?").await.unwrap();
+        assert!(manager.get_meta("u1", None).await.unwrap().title.is_none());
+    }
+    
+    #[test]
+    fn test_clip_middle_keeps_head_and_tail() {
+        assert_eq!(clip_middle("short", 10), "short");
+        
+        let text = format!("{}{}", "a".repeat(100), "é".repeat(100));
+        let clipped = clip_middle(&text, 50);
+        assert!(clipped.starts_with(&"a".repeat(25)) && clipped.ends_with("éé"), "{}", clipped);
+        assert!(clipped.contains("...[249 bytes truncated]..."), "{}", clipped);
+    }
+    
+    #[tokio::test]
+    async fn test_directives_survive_clear() {
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        store.create_user("u1").await.unwrap();
+        let manager = ConversationManager::new(store, 50, HashMap::new());
+        
+        let id = manager.add_directive("u1", None, "  Always answer in French ").await.unwrap();
+        manager.add_turn("u1", None, "http", &[NewMessage::new("user", "hello")]).await.unwrap();
+        manager.clear("u1", None, false).await.unwrap();
+        
+        let messages = manager.build_llm_messages("u1", None, "http", "You are OneClaw").await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0]["content"], "You are OneClaw");
+        assert!(messages[1]["content"].as_str().unwrap().ends_with("- Always answer in French"));
+        
+        assert!(!manager.remove_directive("u1", id + 1).await.unwrap());
+        manager.clear("u1", None, true).await.unwrap();
+        assert!(manager.get_directives("u1").await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_thread_directives_cleared_with_their_thread() {
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        store.create_user("u1").await.unwrap();
+        let manager = ConversationManager::new(store, 50, HashMap::new());
+        
+        manager.add_directive("u1", None, "Always answer in French").await.unwrap();
+        manager.add_directive("u1", Some("discord:1"), "Keep it short").await.unwrap();
+        manager.add_directive("u1", Some("discord:2"), "Use bullet points").await.unwrap();
+        
+        let messages = manager.build_llm_messages("u1", Some("discord:1"), "discord", "You are OneClaw").await.unwrap();
+        let pinned = messages[1]["content"].as_str().unwrap();
+        assert!(pinned.contains("Always answer in French") && pinned.contains("Keep it short"), "{}", pinned);
+        assert!(!pinned.contains("Use bullet points"), "{}", pinned);
+        
+        manager.clear("u1", Some("discord:1"), true).await.unwrap();
+        let left: Vec<String> = manager.get_directives("u1").await.unwrap().into_iter().map(|d| d.content).collect();
+        assert_eq!(left, vec!["Always answer in French", "Use bullet points"]);
+    }
+}
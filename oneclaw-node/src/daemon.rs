@@ -1,8 +1,10 @@
 use axum::{extract::{Query, State}, http::StatusCode, response::Html, routing::{get, post}, Json, Router};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use schemars::JsonSchema;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tower_http::cors::CorsLayer;
-use crate::{agent_os, config, conversation, executor, heartbeat, identity, integration, memory, monitor, oauth_config, receipt, store, workflow};
+use crate::{agent_os, budget, callback, cancel, config, conversation, dead_letter, events, executor, export, heartbeat, identity, integration, memory, moderation, monitor, oauth_config, receipt, scheduler, services, store, trace, workflow};
 
 pub struct AppState {
     pub config: &'static config::NodeConfig,
@@ -11,31 +13,194 @@ pub struct AppState {
     pub identity_manager: Arc<identity::IdentityManager>,
     pub conversation_manager: Arc<conversation::ConversationManager>,
     pub agent_os: agent_os::AgentOS,
-    pub harness_tools: Vec<agent_os::ToolDefinition>,
+    /// Refreshed in the background while empty (harness down at startup)
+    pub harness_tools: agent_os::SharedTools,
     pub job_monitor: monitor::JobMonitor,
+    /// Tool calls waiting on `/chat/confirm`, keyed by token
+    pub pending_confirmations: Mutex<HashMap<String, PendingConfirmation>>,
+    /// Serializes chat turns per user so double-sends can't interleave history
+    pub user_locks: UserLocks,
+    pub scheduler: Arc<scheduler::Scheduler>,
+    pub budget: Arc<budget::Budget>,
+    /// In-flight `/chat` and `/run` requests, for `/cancel/:request_id`
+    pub cancellations: Arc<cancel::Cancellations>,
+    /// Workflow and chat activity, streamed by `/events`
+    pub events: Arc<events::Events>,
+    /// Channel replies that failed to send, retried in the background
+    pub dead_letters: Arc<dead_letter::DeadLetters>,
+    /// Screens chat messages before they reach the LLM (`moderation` in node.yaml)
+    pub moderator: moderation::Moderator,
+    /// Connection state of each channel, for `/channels/status`
+    pub channel_statuses: Arc<crate::channels::status::ChannelStatuses>,
 }
 
-pub async fn start(port: u16) -> anyhow::Result<()> {
-    let config = config::load()?;
-    let executor_registry = executor::Registry::load()?;
-    
-    // Initialize store based on config
-    let store_instance: Arc<dyn store::Store> = match config.store.store_type.as_str() {
-        "hosted" => {
-            let api_url = config.control_plane.url.clone()
-                .unwrap_or_else(|| "http://localhost:3000".to_string());
-            let token = config.control_plane.token.clone()
-                .unwrap_or_default();
-            Arc::new(store::HostedStore::new(api_url, token))
+/// Keyed async mutexes, one per user. Entries nobody holds or waits on are
+/// dropped on the next acquire, so the map only grows with concurrent users.
+#[derive(Default)]
+pub struct UserLocks {
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl UserLocks {
+    /// Wait for this user's previous turn to finish; the guard ends the turn
+    pub async fn lock(&self, user_id: &str) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            locks.retain(|_, l| Arc::strong_count(l) > 1);
+            locks.entry(user_id.to_string()).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
+
+/// Run a startup step until it succeeds or `startup.retry_attempts` is used up, backing off between tries
+async fn with_startup_retry<T, F, Fut>(what: &str, startup: &config::StartupConfig, mut step: F) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<T>>,
+{
+    let attempts = startup.retry_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match step().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= attempts => {
+                return Err(e.context(format!("{} unavailable after {} attempts", what, attempt)));
+            }
+            Err(e) => {
+                let delay = startup.backoff(attempt);
+                tracing::warn!("{} unavailable (attempt {}/{}): {:#}. Retrying in {:?}", what, attempt, attempts, e, delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
         }
-        _ => {
-            // Default to SQLite
-            let path = config::expand_path(&config.store.sqlite_path);
-            let sqlite_store = store::SqliteStore::new(path).await?;
-            Arc::new(sqlite_store)
+    }
+}
+
+/// How long one `GET <harness>/tools` may take before it counts as a failed attempt
+const HARNESS_TOOLS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// `GET <harness>/tools`, keeping the tools in `allowed` (`harness.allowed_executors`).
+/// Only a harness that can't be reached (or answers with an error) fails; an empty list is its answer.
+async fn fetch_harness_tools(harness_url: &str, allowed: &[String]) -> anyhow::Result<Vec<agent_os::ToolDefinition>> {
+    let client = reqwest::Client::builder().timeout(HARNESS_TOOLS_TIMEOUT).build()?;
+    let parsed: serde_json::Value = client
+        .get(format!("{}/tools", harness_url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let tools: Vec<_> = parsed["tools"]
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Harness /tools response has no `tools` array"))?
+        .iter()
+        .filter_map(|t| {
+            Some(agent_os::ToolDefinition {
+                id: t["id"].as_str()?.to_string(),
+                description: t["description"].as_str().unwrap_or("").to_string(),
+                params_schema: t.get("paramsSchema").cloned(),
+                output_schema: t.get("outputSchema").cloned(),
+                cost_estimate: t["estimatedCostUsd"].as_f64(),
+                tier: t["tier"].as_str().map(|s| s.to_string()),
+            })
+        })
+        .collect();
+    
+    let total = tools.len();
+    let tools: Vec<_> = tools.into_iter().filter(|t| executor::is_executor_allowed(allowed, &t.id)).collect();
+    if tools.len() < total {
+        tracing::info!("harness.allowed_executors hides {} of {} harness tools", total - tools.len(), total);
+    }
+    Ok(tools)
+}
+
+async fn sync_skills(tools: &[agent_os::ToolDefinition]) {
+    if let Err(e) = crate::daemon_skills_sync::sync_skills_md(tools).await {
+        tracing::warn!("Failed to sync SKILLS.md with harness tools: {}", e);
+    } else {
+        tracing::info!("✅ Synced SKILLS.md with {} harness tools", tools.len());
+    }
+}
+
+/// Keep fetching harness tools (with the startup backoff, capped at `retry_max_backoff_ms`)
+/// until the harness answers, then publish them so chat picks them up without a restart
+fn spawn_harness_tools_refresh(tools: agent_os::SharedTools, harness_url: String, config: &'static config::NodeConfig) {
+    tokio::spawn(async move {
+        let mut attempt = 1;
+        loop {
+            tokio::time::sleep(config.startup.backoff(attempt)).await;
+            match fetch_harness_tools(&harness_url, &config.harness.allowed_executors).await {
+                Ok(fetched) => {
+                    tracing::info!("Harness is back: {} tools now available", fetched.len());
+                    sync_skills(&fetched).await;
+                    tools.set(fetched);
+                    return;
+                }
+                Err(e) => {
+                    tracing::debug!("Harness tools still unavailable (background attempt {}): {:#}", attempt, e);
+                    attempt = attempt.saturating_add(1);
+                }
+            }
         }
-    };
+    });
+}
+
+/// A gated tool call held until a human confirms it
+#[derive(Debug)]
+pub struct PendingConfirmation {
+    tool: String,
+    input: serde_json::Value,
+    /// Whose budget the call is charged to
+    user_id: Option<String>,
+    expires_at: std::time::Instant,
+}
+
+/// `--bind` wins over `daemon.bind`; either must be a plain IP address
+fn listen_addr(bind: Option<&str>, config: &config::DaemonConfig, port: u16) -> anyhow::Result<std::net::SocketAddr> {
+    let bind = bind.unwrap_or(&config.bind);
+    let ip: std::net::IpAddr = bind
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid bind address '{}': expected an IP such as 127.0.0.1 or 0.0.0.0", bind))?;
+    Ok(std::net::SocketAddr::new(ip, port))
+}
+
+/// `--port` wins over `channels.http.port`; also returns which one was used, for the banner.
+/// Configs written by older onboarding carry the harness port (9000) there, which the
+/// daemon can't share, so that value falls back to the default.
+fn daemon_port(cli_port: Option<u16>, http: &config::HttpChannelConfig) -> (u16, &'static str) {
+    match cli_port {
+        Some(port) => (port, "--port"),
+        None if http.port == crate::ports::HARNESS_PORT => {
+            tracing::warn!(
+                "channels.http.port is {}, the harness port; using {} instead",
+                http.port,
+                crate::ports::DAEMON_PORT
+            );
+            (crate::ports::DAEMON_PORT, "default")
+        }
+        None => (http.port, "channels.http.port"),
+    }
+}
+
+pub async fn start(cli_port: Option<u16>, bind: Option<String>) -> anyhow::Result<()> {
+    let config = config::load()?;
+    let (port, port_source) = daemon_port(cli_port, &config.channels.http);
+    let addr = listen_addr(bind.as_deref(), &config.daemon, port)?;
+    let executor_registry = executor::Registry::load(&config.executors)?;
     
+    // Initialize store based on config; the hosted store must also answer a health check
+    let store_instance: Arc<dyn store::Store> = Arc::from(
+        with_startup_retry("store", &config.startup, || async {
+            let store = store::create_store(store::StoreType::from_config(config)).await?;
+            if config.store.store_type == "hosted" {
+                store.ping().await?;
+            }
+            Ok(store)
+        })
+        .await?,
+    );
     // Initialize managers
     let identity_manager = identity::IdentityManager::new(
         store_instance.clone(),
@@ -45,91 +210,91 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
     let conversation_manager = conversation::ConversationManager::new(
         store_instance.clone(),
         config.memory.session_max_messages,
+        config.memory.channel_limits.clone(),
     );
     
     // Load Agent OS (SOUL.md, IDENTITY.md, etc.)
     let agent_os = agent_os::AgentOS::load(None).unwrap_or_else(|e| {
-        tracing::warn!("Failed to load Agent OS templates: {}", e);
-        agent_os::AgentOS {
-            soul: "You are OneClaw, a helpful AI agent.".to_string(),
-            identity: "".to_string(),
-            skills: "".to_string(),
-            playbooks: "".to_string(),
-            memory: "".to_string(),
-        }
+        tracing::warn!("{} Using the built-in Agent OS files (safe mode); run `oneclaw-node agentos init` to copy them into the workspace and edit them.", e);
+        agent_os::AgentOS::embedded()
     });
-    let soul_loaded = !agent_os.soul.is_empty() && !agent_os.soul.contains("Not Found");
+    let soul_loaded = !agent_os::is_placeholder(&agent_os.soul);
     tracing::info!(
         "Agent OS: SOUL={} IDENTITY={} SKILLS={} PLAYBOOKS={} MEMORY={}",
         if soul_loaded { "loaded" } else { "fallback" },
-        if agent_os.identity.contains("Not Found") { "missing" } else { "loaded" },
-        if agent_os.skills.contains("Not Found") { "missing" } else { "loaded" },
-        if agent_os.playbooks.contains("Not Found") { "missing" } else { "loaded" },
-        if agent_os.memory.contains("Not Found") { "missing" } else { "loaded" },
+        if agent_os::is_placeholder(&agent_os.identity) { "missing" } else { "loaded" },
+        if agent_os::is_placeholder(&agent_os.skills) { "missing" } else { "loaded" },
+        if agent_os::is_placeholder(&agent_os.playbooks) { "missing" } else { "loaded" },
+        if agent_os::is_placeholder(&agent_os.memory) { "missing" } else { "loaded" },
     );
     
     // HARDCODED - no env vars
     let harness_url = crate::ports::HARNESS_URL.to_string();
-    let harness_url_clone = harness_url.clone();
-    let harness_tools = tokio::task::spawn_blocking(move || {
-        let max_retries = 5;
-        let mut retry_delay = std::time::Duration::from_secs(2);
-        
-        for attempt in 1..=max_retries {
-            match reqwest::blocking::get(format!("{}/tools", harness_url_clone)) {
-                Ok(resp) => {
-                    if let Ok(body) = resp.text() {
-                        if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&body) {
-                            if let Some(tools_arr) = parsed["tools"].as_array() {
-                                let tools: Vec<_> = tools_arr.iter().filter_map(|t| {
-                                    Some(agent_os::ToolDefinition {
-                                        id: t["id"].as_str()?.to_string(),
-                                        description: t["description"].as_str().unwrap_or("").to_string(),
-                                        params_schema: t.get("paramsSchema").cloned(),
-                                        cost_estimate: t["estimatedCostUsd"].as_f64(),
-                                        tier: t["tier"].as_str().map(|s| s.to_string()),
-                                    })
-                                }).collect();
-                                if !tools.is_empty() {
-                                    return tools;
-                                }
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    tracing::warn!("Could not fetch harness tools (attempt {}/{}): {}", attempt, max_retries, e);
-                }
-            }
-            
-            if attempt < max_retries {
-                tracing::info!("Retrying harness tools fetch in {:?}...", retry_delay);
-                std::thread::sleep(retry_delay);
-                retry_delay *= 2; // exponential backoff
-            }
+    let (harness_tools, refresh_needed) = match with_startup_retry("harness tools", &config.startup, || fetch_harness_tools(&harness_url, &config.harness.allowed_executors)).await {
+        Ok(tools) => (tools, false),
+        Err(e) => {
+            tracing::error!("{:#}; starting without harness tools and retrying in the background", e);
+            (Vec::new(), true)
         }
-        
-        tracing::error!("Failed to fetch harness tools after {} attempts", max_retries);
-        vec![]
-    })
-    .await
-    .unwrap_or_else(|_| vec![]);
+    };
     
     tracing::info!("Harness URL: {} (set HARNESS_URL to override)", harness_url);
     tracing::info!("Loaded {} harness tools", harness_tools.len());
     
     // Sync harness tools to SKILLS.md
     if !harness_tools.is_empty() {
-        if let Err(e) = crate::daemon_skills_sync::sync_skills_md(&harness_tools).await {
-            tracing::warn!("Failed to sync SKILLS.md with harness tools: {}", e);
-        } else {
-            tracing::info!("✅ Synced SKILLS.md with {} harness tools", harness_tools.len());
-        }
+        sync_skills(&harness_tools).await;
+    }
+    let harness_tools = agent_os::SharedTools::new(harness_tools);
+    if refresh_needed {
+        spawn_harness_tools_refresh(harness_tools.clone(), harness_url.clone(), config);
     }
     
     // Initialize job monitor
     let job_monitor = monitor::JobMonitor::default();
     
+    // Spend caps for chat and workflow runs (`budget` in node.yaml)
+    let budget = Arc::new(budget::Budget::new(store_instance.clone(), config.budget.clone(), harness_tools.clone()));
+    let cancellations = Arc::new(cancel::Cancellations::default());
+    let events = Arc::new(events::Events::default());
+    
+    let dead_letters = Arc::new(dead_letter::DeadLetters::new(store_instance.clone(), config.dead_letters.clone()));
+    Arc::clone(&dead_letters).start();
+    
+    let moderator = moderation::Moderator::new(&config.moderation)?;
+    
+    // Channels report their connection state here as they connect; the HTTP API is up with the daemon
+    let channel_statuses = Arc::new(crate::channels::status::ChannelStatuses::default());
+    channel_statuses.enable(crate::channels::ChannelType::Http);
+    channel_statuses.connected(crate::channels::ChannelType::Http, None);
+    if config.channels.slack.enabled {
+        channel_statuses.enable(crate::channels::ChannelType::Slack);
+        let statuses = Arc::clone(&channel_statuses);
+        tokio::spawn(async move { crate::channels::status::check_slack(&statuses, &config.channels.slack).await });
+    }
+    
+    // Workflow runs, executors and channels reach the same instances through `services`
+    services::install(services::Services {
+        store: store_instance.clone(),
+        receipts: receipt::open(config, Some(store_instance.clone())).await?,
+        budget: Arc::clone(&budget),
+        cancellations: Arc::clone(&cancellations),
+        events: Arc::clone(&events),
+        channel_statuses: Arc::clone(&channel_statuses),
+    });
+    
+    // A run a previous daemon left "running" died with it; CLI runs may still be going
+    receipt::mark_daemon_process();
+    match receipt::mark_interrupted_receipts(&config.node.id).await {
+        Ok(0) => {}
+        Ok(n) => tracing::warn!("Marked {} unfinished workflow receipt(s) as interrupted", n),
+        Err(e) => tracing::warn!("Failed to scan receipts for interrupted runs: {}", e),
+    }
+    
+    // Scheduled workflows (`schedules` in node.yaml)
+    let scheduler = Arc::new(scheduler::Scheduler::new(&config.schedules)?);
+    scheduler.start();
+    
     // Wrap managers in Arc for sharing with heartbeat
     let executor_registry = Arc::new(executor_registry);
     let identity_manager = Arc::new(identity_manager);
@@ -144,6 +309,15 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
         agent_os: agent_os.clone(),
         harness_tools: harness_tools.clone(),
         job_monitor,
+        pending_confirmations: Mutex::new(HashMap::new()),
+        user_locks: UserLocks::default(),
+        scheduler,
+        budget,
+        cancellations,
+        events,
+        dead_letters,
+        moderator,
+        channel_statuses,
     });
 
     // Start heartbeat service in background
@@ -168,7 +342,9 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
         if !bot_token.is_empty() && bot_token != "your_telegram_bot_token_here" {
             use crate::channels::{telegram::TelegramChannel, Channel};
             
-            let telegram = TelegramChannel::new(bot_token);
+            let telegram = TelegramChannel::new(bot_token).with_trigger(state.config.channels.telegram.trigger.clone());
+            state.dead_letters.register(Arc::new(telegram.clone()));
+            state.channel_statuses.enable(crate::channels::ChannelType::Telegram);
             let (tx, mut rx) = tokio::sync::mpsc::channel(100);
             
             // Spawn Telegram listener
@@ -184,6 +360,7 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
             let telegram_clone = telegram.clone();
             tokio::spawn(async move {
                 while let Some(msg) = rx.recv().await {
+                    state_clone.channel_statuses.message_received(crate::channels::ChannelType::Telegram);
                     tracing::info!("📨 Telegram message from {} (chat_id: {}): {}", 
                         msg.username.as_deref().unwrap_or("unknown"),
                         msg.channel_id,
@@ -202,7 +379,7 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                     });
                     
                     // Resolve user identity
-                    let (user_id, _) = match state_clone
+                    let (user_id, is_new_user) = match state_clone
                         .identity_manager
                         .resolve("telegram", &msg.provider_user_id, msg.username.as_deref())
                         .await {
@@ -214,20 +391,41 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                             }
                         };
                     
-                    // Store user message
-                    let _ = state_clone
-                        .conversation_manager
-                        .add_user_message(&user_id, &msg.content, "telegram")
-                        .await;
+                    // First contact: the welcome goes out ahead of the reply
+                    if let Some(welcome) = is_new_user.then(|| welcome_message(&state_clone)).flatten() {
+                        let _ = state_clone.dead_letters.send(&telegram_clone, crate::channels::OutgoingMessage {
+                            channel_type: crate::channels::ChannelType::Telegram,
+                            channel_id: msg.channel_id.clone(),
+                            content: welcome,
+                            reply_to: None,
+                            metadata: serde_json::json!({}),
+                        }).await;
+                    }
+                    
+                    if let Some(block) = state_clone.moderator.check(&msg.content).await {
+                        typing_task.abort();
+                        state_clone.moderator.log_denial(&user_id, "telegram", &msg.content, &block);
+                        let _ = state_clone.dead_letters.send(&telegram_clone, crate::channels::OutgoingMessage {
+                            channel_type: crate::channels::ChannelType::Telegram,
+                            channel_id: msg.channel_id,
+                            content: state_clone.moderator.refusal().to_string(),
+                            reply_to: None,
+                            metadata: serde_json::json!({}),
+                        }).await;
+                        continue;
+                    }
+                    
+                    let _turn = state_clone.user_locks.lock(&user_id).await;
+                    let thread_id = state_clone.config.conversation.thread_id("telegram", Some(&msg.channel_id));
                     
                     // Build system prompt with Telegram formatting instructions
-                    let mut system_prompt = state_clone.agent_os.build_system_prompt(&state_clone.harness_tools);
+                    let mut system_prompt = state_clone.agent_os.build_system_prompt(&chat_tools(&state_clone));
                     system_prompt.push_str("\n\n## Response Format\nYou are communicating via Telegram. Format your responses to be:\n- Clear and easy to read on mobile\n- Use simple bullet points for lists\n- Avoid special formatting (no bold, no emojis)\n- Present information in a straightforward way\n");
                     
-                    // Build messages
-                    let messages = match state_clone
+                    // Build messages. The user message is only stored with the rest of the turn.
+                    let mut messages = match state_clone
                         .conversation_manager
-                        .build_llm_messages(&user_id, &system_prompt)
+                        .build_llm_messages(&user_id, thread_id.as_deref(), "telegram", &system_prompt)
                         .await {
                             Ok(msgs) => msgs,
                             Err(e) => {
@@ -235,9 +433,10 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                                 continue;
                             }
                         };
+                    messages.push(serde_json::json!({ "role": "user", "content": msg.content }));
                     
                     // Convert harness tools to Claude format
-                    let claude_tools: Vec<serde_json::Value> = state_clone.harness_tools
+                    let claude_tools: Vec<serde_json::Value> = chat_tools(&state_clone)
                         .iter()
                         .map(|tool| {
                             serde_json::json!({
@@ -253,7 +452,7 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                         "tools": claude_tools
                     });
                     
-                    match run_llm_with_timeout(Arc::clone(&state_clone), input, "main").await {
+                    match run_llm_with_timeout(Arc::clone(&state_clone), input, "main", Some(&user_id)).await {
                         Ok(result) => {
                             tracing::info!("✅ LLM response received");
                             
@@ -268,7 +467,8 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                             
                             let content = extract_content(&result);
                             tracing::info!("✅ Content extracted, looking for tools...");
-                            let tool_results = find_and_execute_tools(&state_clone, &content, &result).await;
+                            let mut guard = TurnGuard::new(&state_clone.config.chat, std::time::Instant::now());
+                            let (tool_results, dropped_tools) = find_and_execute_tools(&state_clone, &content, &result, &mut Milestones::new(), Some(&user_id), &mut guard).await;
                             tracing::info!("✅ Tools executed: {} results", tool_results.len());
                             
                             // Stop typing indicator
@@ -324,7 +524,15 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                                                     
                                                     let telegram_for_polling = telegram_clone.clone();
                                                     let user_id_for_conv = user_id.clone();
+                                                    let thread_for_conv = thread_id.clone();
                                                     let conv_manager = state_clone.conversation_manager.clone();
+                                                    let dead_letters = state_clone.dead_letters.clone();
+                                                    
+                                                    // The reply comes when the job finishes; the request is history now
+                                                    let request = [store::NewMessage::new("user", msg.content.as_str())];
+                                                    if let Err(e) = conv_manager.add_turn(&user_id, thread_id.as_deref(), "telegram", &request).await {
+                                                        tracing::warn!("Could not store Telegram message: {}", e);
+                                                    }
                                                     
                                                     tokio::spawn(async move {
                                                         match poller.run_until_complete(Arc::new(telegram_for_polling.clone())).await {
@@ -337,12 +545,13 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                                                                 // Save to conversation
                                                                 let _ = conv_manager.add_assistant_message(
                                                                     &user_id_for_conv,
+                                                                    thread_for_conv.as_deref(),
                                                                     &formatted,
                                                                     "telegram",
                                                                     None
                                                                 ).await;
                                                                 
-                                                                let _ = telegram_for_polling.send(crate::channels::OutgoingMessage {
+                                                                let _ = dead_letters.send(&telegram_for_polling, crate::channels::OutgoingMessage {
                                                                     channel_type: crate::channels::ChannelType::Telegram,
                                                                     channel_id: msg.channel_id.clone(),
                                                                     content: formatted,
@@ -352,7 +561,7 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                                                             }
                                                             Err(e) => {
                                                                 tracing::error!("❌ Job execution failed: {}", e);
-                                                                let _ = telegram_for_polling.send(crate::channels::OutgoingMessage {
+                                                                let _ = dead_letters.send(&telegram_for_polling, crate::channels::OutgoingMessage {
                                                                     channel_type: crate::channels::ChannelType::Telegram,
                                                                     channel_id: msg.channel_id.clone(),
                                                                     content: format!("❌ Job failed: {}\n\nTry `/logs` for details.", e),
@@ -437,18 +646,6 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                             } else {
                                 tracing::info!("Tool results found, formatting for Telegram...");
                                 
-                                // Save tool results to conversation
-                                for result in &tool_results {
-                                    let _ = state_clone
-                                        .conversation_manager
-                                        .add_tool_message(
-                                            &user_id,
-                                            &format!("[{} result]", result.tool),
-                                            "telegram",
-                                        )
-                                        .await;
-                                }
-                                
                                 // Special handling for execute-code: show actual output if successful
                                 let first_result = &tool_results[0];
                                 if first_result.tool == "execute-code" {
@@ -536,7 +733,7 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                                         "messages": followup_messages
                                     });
                                     
-                                    match run_llm_with_timeout(Arc::clone(&state_clone), simple_input, "format").await {
+                                    match run_llm_with_timeout(Arc::clone(&state_clone), simple_input, "format", Some(&user_id)).await {
                                         Ok(result) => {
                                             tracing::info!("✅ Formatting complete");
                                             extract_content(&result)
@@ -556,17 +753,32 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                             } else {
                                 final_content
                             };
+                            let final_content = if dropped_tools > 0 {
+                                final_content + &dropped_tools_note(dropped_tools, state_clone.config.chat.max_tools_per_turn)
+                            } else {
+                                final_content
+                            };
+                            let final_content = match guard.note() {
+                                Some(note) => final_content + &note,
+                                None => final_content,
+                            };
                             
                             tracing::info!("Saving conversation messages...");
-                            // Save assistant message
-                            let _ = state_clone
-                                .conversation_manager
-                                .add_assistant_message(&user_id, &final_content, "telegram", None)
-                                .await;
+                            // Save the whole turn at once, like `/chat`
+                            let tool_records: Vec<conversation::ToolCall> = tool_results.iter().map(ToolCallResult::to_record).collect();
+                            let mut turn = vec![store::NewMessage::new("user", msg.content.as_str())];
+                            turn.extend(tool_results.iter().map(|result| store::NewMessage::new("tool", format!("[{} result]", result.tool))));
+                            turn.push(
+                                conversation::assistant_message(&final_content, (!tool_records.is_empty()).then_some(tool_records.as_slice()))
+                                    .replying_to(Some(store::ReplyTo::Batch(0))),
+                            );
+                            if let Err(e) = state_clone.conversation_manager.add_turn(&user_id, thread_id.as_deref(), "telegram", &turn).await {
+                                tracing::warn!("Could not store Telegram turn: {}", e);
+                            }
                             
                             tracing::info!("Sending final response to Telegram...");
                             // Send reply via Telegram
-                            let _ = telegram_clone.send(crate::channels::OutgoingMessage {
+                            let _ = state_clone.dead_letters.send(&telegram_clone, crate::channels::OutgoingMessage {
                                 channel_type: crate::channels::ChannelType::Telegram,
                                 channel_id: msg.channel_id,
                                 content: final_content,
@@ -583,7 +795,7 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
                             let error_msg = format!("❌ **Error Processing Request**\n\n{}\n\nCheck `/logs` for details.", 
                                 e.to_string().chars().take(200).collect::<String>());
                             
-                            let _ = telegram_clone.send(crate::channels::OutgoingMessage {
+                            let _ = state_clone.dead_letters.send(&telegram_clone, crate::channels::OutgoingMessage {
                                 channel_type: crate::channels::ChannelType::Telegram,
                                 channel_id: msg.channel_id,
                                 content: error_msg,
@@ -599,6 +811,97 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
         }
     }
 
+    // Initialize Discord channel if enabled in node.yaml
+    if config.channels.discord.enabled {
+        use crate::channels::{discord::DiscordChannel, Channel};
+        
+        state.channel_statuses.enable(crate::channels::ChannelType::Discord);
+        match DiscordChannel::new(config.channels.discord.clone()) {
+            Ok(discord) => {
+                let discord = Arc::new(discord);
+                state.dead_letters.register(discord.clone());
+                let (tx, mut rx) = tokio::sync::mpsc::channel(100);
+                
+                let discord_clone = discord.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = discord_clone.start(tx).await {
+                        tracing::error!("Discord channel error: {}", e);
+                    }
+                });
+                
+                let state_clone = state.clone();
+                tokio::spawn(async move {
+                    while let Some(msg) = rx.recv().await {
+                        state_clone.channel_statuses.message_received(crate::channels::ChannelType::Discord);
+                        tracing::info!("📨 Discord message from {} (channel: {})",
+                            msg.username.as_deref().unwrap_or("unknown"),
+                            msg.channel_id
+                        );
+                        
+                        // Handle each message on its own task so a slow reply doesn't hold up the rest
+                        let discord = discord.clone();
+                        let state_clone = state_clone.clone();
+                        tokio::spawn(async move {
+                            // React immediately so the user knows the mention landed
+                            discord.acknowledge(&msg).await;
+                            let placeholder = discord.post_placeholder(&msg).await;
+                            
+                            let req = ChatRequest {
+                                message: msg.content.clone(),
+                                channel: "discord".to_string(),
+                                provider: Some("discord".to_string()),
+                                provider_id: Some(msg.provider_user_id.clone()),
+                                username: msg.username.clone(),
+                                channel_id: Some(msg.channel_id.clone()),
+                                request_id: None,
+                                attachments: msg.attachments.clone(),
+                                reply_to: None,
+                            };
+                            match trace::scope(trace::new_id(), process_chat(state_clone.clone(), req)).await {
+                                Ok(response) => {
+                                    let delivered = discord.deliver(&msg, placeholder.as_deref(), &response.response).await;
+                                    discord.finish(&msg, delivered.is_ok()).await;
+                                    if let Err(e) = delivered {
+                                        // The dead letter resends the whole reply, so don't leave the placeholder
+                                        // (or a first chunk edited into it) behind
+                                        if let Some(id) = &placeholder {
+                                            if let Err(e) = discord.delete_message(&msg.channel_id, id).await {
+                                                tracing::warn!("Failed to delete Discord placeholder: {}", e);
+                                            }
+                                        }
+                                        let reply = crate::channels::OutgoingMessage {
+                                            channel_type: crate::channels::ChannelType::Discord,
+                                            channel_id: msg.channel_id.clone(),
+                                            content: response.response,
+                                            reply_to: msg.reply_to.clone(),
+                                            metadata: serde_json::json!({}),
+                                        };
+                                        state_clone.dead_letters.enqueue(reply, &e.to_string()).await;
+                                    }
+                                }
+                                Err((_, e)) => {
+                                    tracing::error!("❌ Discord chat error: {}", e);
+                                    discord.finish(&msg, false).await;
+                                    if let Some(id) = placeholder {
+                                        if let Err(e) = discord.delete_message(&msg.channel_id, &id).await {
+                                            tracing::warn!("Failed to delete Discord placeholder: {}", e);
+                                        }
+                                    }
+                                }
+                            }
+                        });
+                    }
+                });
+                
+                tracing::info!("✅ Discord channel initialized");
+            }
+            Err(e) => {
+                tracing::warn!("Discord channel enabled but not started: {}", e);
+                state.channel_statuses.disconnected(crate::channels::ChannelType::Discord, e.to_string());
+            }
+        }
+    }
+
     let app = Router::new()
         .route("/", get(ui_dashboard))
         .route("/chat.html", get(ui_chat))
@@ -608,13 +911,30 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
         .route("/static/style.css", get(ui_style))
         .route("/health", get(health))
         .route("/config", get(get_config))
+        .route("/openapi.json", get(get_openapi))
         .route("/run", post(run_workflow))
+        .route("/run/batch", post(run_workflow_batch))
         .route("/chat", post(chat))
+        .route("/cancel/:request_id", post(cancel_request))
+        .route("/events", get(stream_events))
+        .route("/dead-letters", get(list_dead_letters))
+        .route("/channels/status", get(channels_status))
+        .route("/slack/commands", post(slack_command))
         .route("/chat/history", get(get_chat_history))
         .route("/chat/clear", post(clear_chat))
+        .route("/chat/context", post(add_chat_context))
+        .route("/chat/confirm", post(confirm_tool_call))
+        .route("/chat/meta", get(get_chat_meta).post(set_chat_meta))
+        .route("/chat/directive", get(list_directives).post(add_directive).delete(remove_directive))
+        .route("/export", get(export_user))
         .route("/receipts", get(list_receipts))
+        .route("/receipts/:run_id/artifacts/:name", get(download_artifact))
         .route("/memory/preferences", get(get_preferences))
         .route("/executors", get(list_executors))
+        .route("/tools", get(list_tools))
+        .route("/schedules", get(list_schedules))
+        .route("/budget", get(get_budget))
+        .route("/llm/model", get(get_llm_model).post(set_llm_model))
         .route("/integrations", get(get_integrations))
         .route("/integrations/gmail/connect", get(connect_gmail))
         .route("/integrations/gmail/status", get(gmail_status))
@@ -623,11 +943,13 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
         .route("/integrations/gmail/send", post(send_email))
         .route("/integrations/gmail/disconnect", post(disconnect_gmail))
         .route("/api/oauth/config", post(oauth_config::save_oauth_config_handler))
+        .route("/oauth/tokens", post(save_oauth_token))
         .route("/gmail/senders", get(gmail_senders_proxy))
         .route("/api/gmail/senders", get(api_gmail_senders_proxy))
         .route("/oauth/google", get(oauth_google_proxy))
         .route("/oauth/google/callback", get(oauth_callback_proxy))
-        .layer(CorsLayer::permissive())
+        .layer(cors_layer(&config.daemon.cors_allowed_origins))
+        .layer(axum::middleware::from_fn(access_log))
         .with_state(state);
 
     // HARDCODED - no env vars
@@ -636,16 +958,346 @@ pub async fn start(port: u16) -> anyhow::Result<()> {
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!("  Node:   {} ({})", config.node.name, config.node.id);
     println!("  Mode:   {}", config.node.environment);
-    println!("  UI:     http://localhost:{}", port);
+    if config.channels.http.enabled {
+        println!("  UI:     http://localhost:{}", port);
+        println!("  Listen: {} (port from {}; --port > channels.http.port > 8787)", addr, port_source);
+    } else {
+        println!("  HTTP:   disabled (channels.http.enabled: false); channels only");
+    }
     println!("  Harness: {} (tools execute here)", harness_url);
     println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
     println!("\nPress Ctrl+C to stop\n");
 
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    if !config.channels.http.enabled {
+        tokio::signal::ctrl_c().await?;
+        return Ok(());
+    }
+    if addr.ip().is_unspecified() {
+        tracing::warn!("Listening on {}: the API is reachable from other machines", addr);
+    }
+    let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
     Ok(())
 }
 
+/// CORS for `daemon.cors_allowed_origins`: only those origins (localhost when empty), the
+/// methods the API uses and the headers it reads. `["*"]` opts into allowing any origin.
+fn cors_layer(allowed_origins: &[String]) -> CorsLayer {
+    use axum::http::{header, HeaderName, HeaderValue, Method};
+    use tower_http::cors::AllowOrigin;
+
+    if allowed_origins.iter().any(|o| o == "*") {
+        tracing::warn!("daemon.cors_allowed_origins is [\"*\"]: any website can call this node's API");
+        return CorsLayer::permissive();
+    }
+    let allow_origin = if allowed_origins.is_empty() {
+        AllowOrigin::predicate(|origin, _| is_localhost_origin(origin.to_str().unwrap_or_default()))
+    } else {
+        let origins: Vec<HeaderValue> = allowed_origins
+            .iter()
+            .filter_map(|o| match HeaderValue::from_str(o.trim_end_matches('/')) {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    tracing::warn!("Ignoring invalid daemon.cors_allowed_origins entry: {}", o);
+                    None
+                }
+            })
+            .collect();
+        AllowOrigin::list(origins)
+    };
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::DELETE])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static(trace::TRACE_ID_HEADER),
+        ])
+        .expose_headers([HeaderName::from_static(trace::TRACE_ID_HEADER)])
+}
+
+/// `http(s)://localhost`, `127.0.0.1` or `[::1]`, on any port
+fn is_localhost_origin(origin: &str) -> bool {
+    url::Url::parse(origin)
+        .map(|url| matches!(url.scheme(), "http" | "https") && matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")))
+        .unwrap_or(false)
+}
+
+// ============================================
+// OpenAPI
+// ============================================
+
+/// OpenAPI 3 description of the routes above (UI pages excluded); keep in step with the router
+pub fn openapi_document() -> serde_json::Value {
+    use serde_json::Value;
+
+    let mut doc = crate::openapi::ApiDoc::new();
+    doc.get("/health", "Liveness, or readiness with ?deep=1")
+        .query("deep", "Also check the store and harness")
+        .json::<HealthResponse>()
+        .status(503, "A critical dependency is down (deep only)")
+        .done();
+    doc.get("/config", "Loaded node configuration").json::<Value>().done();
+    doc.get("/openapi.json", "This document").json::<Value>().done();
+    doc.post("/run", "Run a workflow")
+        .body::<RunRequest>()
+        .json::<receipt::WorkflowReceipt>()
+        .json_status::<RunAccepted>(202, "Started; the outcome is POSTed to callback_url")
+        .status(400, "Invalid workflow inputs, request_id or callback_url")
+        .status(409, "Cancelled, request_id already running, or an identical run still going (deterministic_run_ids)")
+        .status(500, "Run failed")
+        .done();
+    doc.post("/run/batch", "Run several workflows")
+        .query("max_concurrency", "Runs at a time (default 4, max 16)")
+        .body::<Vec<BatchRunItem>>()
+        .json::<Vec<BatchRunResult>>()
+        .status(400, "Empty or oversized batch")
+        .done();
+    doc.post("/chat", "Send a chat message")
+        .body::<ChatRequest>()
+        .json::<ChatResponse>()
+        .status(400, "Invalid request_id")
+        .status(409, "Cancelled, or request_id already running")
+        .done();
+    doc.post("/slack/commands", "Slack slash command (signed form body)")
+        .json::<Value>()
+        .status(401, "Bad or stale Slack signature")
+        .status(404, "Slack channel not enabled")
+        .done();
+    doc.post("/cancel/:request_id", "Cancel a running /chat or /run")
+        .json::<Value>()
+        .status(404, "No running request with that id")
+        .done();
+    doc.get("/chat/history", "Conversation history")
+        .query("user_id", "User (defaults to the anonymous HTTP user)")
+        .query("thread_id", "Limit to one thread")
+        .query("channel", "Channel type whose history window applies (memory.channel_limits)")
+        .json::<Vec<conversation::ChatMessage>>()
+        .done();
+    doc.post("/chat/clear", "Clear conversation history")
+        .query("user_id", "User (defaults to the anonymous HTTP user)")
+        .query("thread_id", "Limit to one thread")
+        .query("include_directives", "Also remove pinned directives, only the thread's with thread_id (true/false)")
+        .json::<Value>()
+        .done();
+    doc.post("/chat/context", "Queue a system note for the user's next turn")
+        .body::<ContextRequest>()
+        .json::<Value>()
+        .status(400, "Empty note")
+        .done();
+    doc.get("/chat/meta", "Conversation title, tags and pin flag")
+        .query("user_id", "User (defaults to the anonymous HTTP user)")
+        .query("thread_id", "Thread (the unthreaded history when unset)")
+        .json::<store::ConversationMeta>()
+        .done();
+    doc.post("/chat/meta", "Update conversation title, tags or pin flag")
+        .body::<ChatMetaUpdate>()
+        .json::<store::ConversationMeta>()
+        .done();
+    doc.get("/chat/directive", "Pinned directives")
+        .query("user_id", "User (defaults to the anonymous HTTP user)")
+        .json::<Vec<store::PinnedDirective>>()
+        .done();
+    doc.post("/chat/directive", "Pin a directive that survives clears")
+        .body::<DirectiveRequest>()
+        .json::<Value>()
+        .status(400, "Empty directive")
+        .done();
+    doc.delete("/chat/directive", "Remove a pinned directive")
+        .query("user_id", "User (defaults to the anonymous HTTP user)")
+        .query("id", "Directive id")
+        .json::<Value>()
+        .status(404, "Unknown directive")
+        .done();
+    doc.post("/chat/confirm", "Run a tool call held for confirmation")
+        .body::<ConfirmRequest>()
+        .json::<Value>()
+        .status(404, "Unknown or expired token")
+        .done();
+    doc.get("/export", "Export a user's data")
+        .query("user_id", "User (defaults to the anonymous HTTP user)")
+        .json::<export::UserExport>()
+        .status(404, "Unknown user")
+        .done();
+    doc.get("/receipts", "Stored receipt ids").json::<Vec<String>>().done();
+    doc.get("/receipts/:run_id/artifacts/:name", "Download a step artifact")
+        .content("application/octet-stream", "Artifact file")
+        .status(404, "No such artifact")
+        .done();
+    doc.get("/memory/preferences", "Preferences file").json::<memory::Preferences>().done();
+    fn tools_query(op: crate::openapi::Operation<'_>) -> crate::openapi::Operation<'_> {
+        op.query("q", "Case-insensitive substring of the id or description")
+            .query("permission", "Only tools that require this permission")
+            .query("tier", "Only harness tools of this tier")
+            .query("offset", "Skip this many matches (default 0)")
+            .query("limit", "Return at most this many matches")
+    }
+    tools_query(doc.get("/executors", "Local executors")).json::<Vec<executor::ExecutorManifest>>().done();
+    tools_query(doc.get("/tools", "Local executors and harness tools")).json::<Vec<ToolInfo>>().done();
+    doc.get("/schedules", "Configured schedules").json::<Vec<scheduler::ScheduleStatus>>().done();
+    doc.get("/events", "Workflow and chat events as they happen")
+        .content("text/event-stream", "SSE stream; event names are the `type` of each JSON event")
+        .done();
+    doc.get("/dead-letters", "Channel replies that failed to send (admin token)")
+        .query("status", "Only `pending`, `delivered` or `failed` ones")
+        .query("limit", "Maximum number returned (default 100)")
+        .json::<Vec<store::DeadLetter>>()
+        .status(400, "Unknown status")
+        .status(401, "Missing or invalid admin token")
+        .done();
+    doc.get("/channels/status", "Whether each channel is enabled and connected, its bot identity and last message")
+        .json::<Vec<crate::channels::status::ChannelStatus>>()
+        .done();
+    doc.get("/budget", "Today's spend against the daily limits")
+        .query("user_id", "Also report this user's spend")
+        .json::<budget::BudgetStatus>()
+        .done();
+    doc.get("/llm/model", "LLM provider/model in use").json::<LlmModelInfo>().done();
+    doc.post("/llm/model", "Switch LLM provider/model (admin token)")
+        .body::<SetLlmModelRequest>()
+        .json::<LlmModelInfo>()
+        .status(400, "Unknown provider or missing API key")
+        .status(401, "Missing or invalid admin token")
+        .done();
+    doc.get("/integrations", "Integration status").json::<Value>().done();
+    doc.get("/integrations/gmail/connect", "Start Gmail OAuth").content("text/html", "Redirect page").done();
+    doc.get("/integrations/gmail/status", "Gmail connection status").json::<Value>().done();
+    doc.get("/integrations/gmail/accounts", "Connected Gmail accounts").json::<Value>().done();
+    doc.post("/integrations/gmail/send-test", "Send a test email to yourself")
+        .body::<SendTestEmailRequest>()
+        .json::<Value>()
+        .done();
+    doc.post("/integrations/gmail/send", "Send an email").body::<SendEmailRequest>().json::<Value>().done();
+    doc.post("/integrations/gmail/disconnect", "Disconnect a Gmail account")
+        .body::<DisconnectGmailRequest>()
+        .json::<Value>()
+        .done();
+    doc.post("/api/oauth/config", "Save OAuth app credentials")
+        .body::<oauth_config::OAuthConfigRequest>()
+        .json::<oauth_config::OAuthConfigResponse>()
+        .status(400, "Invalid credentials")
+        .done();
+    doc.post("/oauth/tokens", "Store a user's OAuth token for executors (admin token)")
+        .body::<SaveOAuthTokenRequest>()
+        .json::<SaveOAuthTokenResponse>()
+        .status(400, "Unknown provider or empty token")
+        .status(401, "Missing or invalid admin token")
+        .done();
+    doc.get("/gmail/senders", "Gmail senders (harness proxy)").json::<Value>().done();
+    doc.get("/api/gmail/senders", "Gmail senders (harness proxy)").json::<Value>().done();
+    doc.get("/oauth/google", "Google OAuth start (harness proxy)").content("text/html", "Redirect").done();
+    doc.get("/oauth/google/callback", "Google OAuth callback (harness proxy)").content("text/html", "Result page").done();
+
+    doc.finish("OneClaw Node", env!("CARGO_PKG_VERSION"))
+}
+
+/// GET /openapi.json
+async fn get_openapi() -> Json<serde_json::Value> {
+    Json(openapi_document())
+}
+
+// ============================================
+// Access Log
+// ============================================
+
+/// Request ID header, accepted from the client (if sane) and always set on the response
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Bodies larger than this (or of unknown size) are never buffered for trace logging
+const MAX_LOGGED_BODY_BYTES: u64 = 64 * 1024;
+
+/// One line per request (method, path, status, latency) under a span carrying the request ID,
+/// so handler logs correlate. At trace level headers and JSON bodies are dumped, with secrets redacted.
+async fn access_log(req: axum::extract::Request, next: axum::middleware::Next) -> axum::response::Response {
+    use tracing::Instrument;
+    
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .map(str::to_string)
+        .unwrap_or_else(|| nanoid::nanoid!(12));
+    // Path only: query strings can carry OAuth codes and tokens
+    let span = tracing::info_span!("request", request_id = %request_id, method = %req.method(), path = %req.uri().path());
+    
+    async move {
+        let start = std::time::Instant::now();
+        let req = if tracing::enabled!(tracing::Level::TRACE) {
+            let (parts, body) = req.into_parts();
+            let headers: serde_json::Map<String, serde_json::Value> = parts
+                .headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), serde_json::json!(v.to_str().unwrap_or("<binary>"))))
+                .collect();
+            let headers = receipt::redact_sensitive(serde_json::Value::Object(headers), &log_redact_headers());
+            tracing::trace!(headers = %headers, "request headers");
+            trace_body("request", body)
+                .await
+                .map(|body| axum::extract::Request::from_parts(parts, body))
+                .map_err(axum::response::IntoResponse::into_response)
+        } else {
+            Ok(req)
+        };
+        
+        let mut response = match req {
+            Ok(req) => next.run(req).await,
+            Err(rejection) => rejection,
+        };
+        
+        if tracing::enabled!(tracing::Level::TRACE) {
+            let (parts, body) = response.into_parts();
+            response = match trace_body("response", body).await {
+                Ok(body) => axum::response::Response::from_parts(parts, body),
+                Err(_) => axum::response::IntoResponse::into_response(StatusCode::INTERNAL_SERVER_ERROR),
+            };
+        }
+        tracing::info!(
+            status = response.status().as_u16(),
+            latency_ms = start.elapsed().as_millis() as u64,
+            "request handled"
+        );
+        if let Ok(value) = axum::http::HeaderValue::from_str(&request_id) {
+            response.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        response
+    }
+    .instrument(span)
+    .await
+}
+
+fn log_redact_headers() -> Vec<String> {
+    config::load()
+        .map(|c| c.artifacts.redact_headers.clone())
+        .unwrap_or_else(|_| config::default_redact_headers())
+}
+
+/// Log a small body at trace level (JSON redacted, anything else by size) and hand back an equivalent one.
+/// A body that can't be read is rejected (400, or 413 past the default body limit) rather than
+/// being passed on empty.
+async fn trace_body(
+    label: &str,
+    body: axum::body::Body,
+) -> Result<axum::body::Body, axum::extract::rejection::BytesRejection> {
+    use axum::body::HttpBody;
+    use axum::extract::FromRequest;
+    
+    let size = body.size_hint().upper();
+    if size.is_none_or(|n| n > MAX_LOGGED_BODY_BYTES) {
+        tracing::trace!("{} body not logged (streaming or larger than {} bytes)", label, MAX_LOGGED_BODY_BYTES);
+        return Ok(body);
+    }
+    let bytes = axum::body::Bytes::from_request(axum::extract::Request::new(body), &())
+        .await
+        .inspect_err(|e| tracing::trace!("{} body could not be read: {}", label, e))?;
+    match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(json) => tracing::trace!(body = %receipt::redact_sensitive(json, &log_redact_headers()), "{} body", label),
+        Err(_) if !bytes.is_empty() => tracing::trace!("{} body: <{} bytes, not JSON>", label, bytes.len()),
+        Err(_) => {}
+    }
+    Ok(axum::body::Body::from(bytes))
+}
+
 async fn ui_dashboard() -> Html<&'static str> { Html(include_str!("ui/index.html")) }
 async fn ui_chat() -> Html<&'static str> { Html(include_str!("ui/chat.html")) }
 async fn ui_setup() -> Html<&'static str> { Html(include_str!("ui/setup.html")) }
@@ -655,103 +1307,857 @@ async fn ui_style() -> ([(&'static str, &'static str); 1], &'static str) {
     ([("content-type", "text/css")], include_str!("ui/style.css"))
 }
 
-#[derive(Serialize)]
-struct HealthResponse { status: String, node_id: String, node_name: String }
-
-async fn health(State(state): State<Arc<AppState>>) -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok".to_string(),
-        node_id: state.config.node.id.clone(),
-        node_name: state.config.node.name.clone(),
-    })
+#[derive(Serialize, JsonSchema)]
+struct HealthResponse {
+    status: String,
+    node_id: String,
+    node_name: String,
+    /// Only present for `?deep=1`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checks: Option<std::collections::BTreeMap<String, DependencyCheck>>,
 }
 
-async fn get_config(State(state): State<Arc<AppState>>) -> Json<&'static config::NodeConfig> {
-    Json(state.config)
+#[derive(Serialize, JsonSchema)]
+struct DependencyCheck {
+    ok: bool,
+    /// A failing critical dependency turns the whole response into a 503
+    critical: bool,
+    latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
 #[derive(Deserialize)]
-struct RunRequest { workflow_id: String, inputs: serde_json::Value }
-
-async fn run_workflow(State(_state): State<Arc<AppState>>, Json(req): Json<RunRequest>) -> Result<Json<receipt::WorkflowReceipt>, (StatusCode, String)> {
-    workflow::run(&req.workflow_id, req.inputs).await
-        .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+struct HealthQuery {
+    #[serde(default)]
+    deep: Option<String>,
 }
 
-async fn list_receipts() -> Result<Json<Vec<String>>, (StatusCode, String)> {
-    receipt::list_receipts().map(Json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+const HEALTH_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Shallow by default (liveness). `?deep=1` also checks the store and harness
+/// (readiness) and returns 503 if a critical dependency is down.
+async fn health(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HealthQuery>,
+) -> (StatusCode, Json<HealthResponse>) {
+    let deep = matches!(query.deep.as_deref(), Some("1") | Some("true"));
+    let mut response = HealthResponse {
+        status: "ok".to_string(),
+        node_id: state.config.node.id.clone(),
+        node_name: state.config.node.name.clone(),
+        checks: None,
+    };
+    if !deep {
+        return (StatusCode::OK, Json(response));
+    }
+
+    let (store_check, harness_check) = tokio::join!(check_store(&state), check_harness());
+    // Only checks the key is configured; a real provider call would cost tokens
+    let llm_key_env = &config::with_live_llm(state.config).llm.api_key_env;
+    let llm_key_set = std::env::var(llm_key_env).map(|k| !k.is_empty()).unwrap_or(false);
+    let llm_check = DependencyCheck {
+        ok: llm_key_set,
+        critical: false,
+        latency_ms: 0,
+        error: (!llm_key_set).then(|| format!("{} not set", llm_key_env)),
+    };
+
+    let checks = std::collections::BTreeMap::from([
+        ("store".to_string(), store_check),
+        ("harness".to_string(), harness_check),
+        ("llm".to_string(), llm_check),
+    ]);
+    let healthy = checks.values().all(|c| c.ok || !c.critical);
+    response.status = if healthy { "ok" } else { "unavailable" }.to_string();
+    response.checks = Some(checks);
+
+    let code = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, Json(response))
 }
 
-async fn get_preferences() -> Result<Json<memory::Preferences>, (StatusCode, String)> {
-    memory::load_preferences().map(Json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+async fn check_store(state: &Arc<AppState>) -> DependencyCheck {
+    let start = std::time::Instant::now();
+    let result = match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, state.store.ping()).await {
+        Ok(r) => r,
+        Err(_) => Err(anyhow::anyhow!("timed out")),
+    };
+    DependencyCheck {
+        ok: result.is_ok(),
+        critical: true,
+        latency_ms: start.elapsed().as_millis() as u64,
+        error: result.err().map(|e| e.to_string()),
+    }
 }
 
-async fn list_executors(State(state): State<Arc<AppState>>) -> Json<Vec<executor::ExecutorManifest>> {
-    Json(state.executor_registry.list())
+async fn check_harness() -> DependencyCheck {
+    let start = std::time::Instant::now();
+    let result = async {
+        let resp = reqwest::Client::new()
+            .get(format!("{}/health", crate::ports::HARNESS_URL))
+            .timeout(HEALTH_CHECK_TIMEOUT)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("HTTP {}", resp.status());
+        }
+        Ok(())
+    }
+    .await;
+    DependencyCheck {
+        ok: result.is_ok(),
+        critical: true,
+        latency_ms: start.elapsed().as_millis() as u64,
+        error: result.err().map(|e| e.to_string()),
+    }
 }
 
-// ============================================
-// Chat Endpoint
-// ============================================
+async fn get_config(State(state): State<Arc<AppState>>) -> Json<&'static config::NodeConfig> {
+    Json(state.config)
+}
 
-#[derive(Deserialize)]
-struct ChatRequest {
-    message: String,
-    #[serde(default = "default_channel")]
-    channel: String,
-    #[serde(default)]
-    provider: Option<String>,      // e.g., "discord", "http"
+#[derive(Deserialize, JsonSchema)]
+struct RunRequest {
+    workflow_id: String,
+    inputs: serde_json::Value,
+    /// Id for `/cancel/:request_id`, also used as the run ID (so it must be new); generated when
+    /// unset (from the workflow and inputs with `workflow.deterministic_run_ids`)
     #[serde(default)]
-    provider_id: Option<String>,   // e.g., "397102686660591616"
+    request_id: Option<String>,
+    /// Return `202` at once and POST the outcome here when the run ends (signed, see `callbacks`)
     #[serde(default)]
-    username: Option<String>,
+    callback_url: Option<String>,
 }
 
-fn default_channel() -> String { "http".to_string() }
-
-#[derive(Serialize)]
-struct ChatResponse {
-    response: String,
-    tool_calls: Vec<ToolCallResult>,
-    milestones: Vec<String>,
-    duration_ms: u64,
+/// Reserve the request id (random when not given), rejecting client-chosen ones that are
+/// malformed or already running
+fn claim_request_id(state: &AppState, request_id: Option<String>) -> Result<cancel::Claim, (StatusCode, String)> {
+    let request_id = request_id.unwrap_or_else(|| nanoid::nanoid!());
+    if !cancel::is_valid_request_id(&request_id) {
+        return Err((StatusCode::BAD_REQUEST, "request_id must be 1-64 letters, digits, '-' or '_'".to_string()));
+    }
+    state
+        .cancellations
+        .claim(&request_id)
+        .ok_or_else(|| (StatusCode::CONFLICT, format!("Request {} is already running", request_id)))
 }
 
-#[derive(Serialize, Clone)]
-struct ToolCallResult {
-    tool: String,
-    input: serde_json::Value,
-    output: serde_json::Value,
-    duration_ms: u64,
+fn cancelled_error(request_id: &str) -> (StatusCode, String) {
+    (StatusCode::CONFLICT, format!("cancelled: request {} was cancelled", request_id))
 }
 
-fn llm_timeout_secs() -> u64 {
-    std::env::var("LLM_CALL_TIMEOUT_SECS")
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .filter(|v| *v >= 5)
-        .unwrap_or(65)
+/// `/run` reply when a `callback_url` was given: the outcome is POSTed there later
+#[derive(Serialize, JsonSchema)]
+struct RunAccepted {
+    run_id: String,
+    status: String,
 }
 
-async fn run_llm_with_timeout(
-    state: Arc<AppState>,
-    input: serde_json::Value,
-    phase: &'static str,
-) -> Result<executor::ExecutorResult, String> {
-    let timeout_secs = llm_timeout_secs();
-    let task = tokio::task::spawn_blocking(move || {
-        match state.executor_registry.get("llm.chat") {
-            Some(exec) => exec.execute(input, state.config),
-            None => executor::ExecutorResult::Error { error: "LLM executor not found".to_string() },
+async fn run_workflow(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<RunRequest>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    use axum::response::IntoResponse;
+
+    if let Some(url) = &req.callback_url {
+        callback::check_url(url, &state.config.http.allowed_domains).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        // Nobody is waiting on the response, so catch an unknown workflow now
+        workflow::load_spec(&req.workflow_id).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    }
+    let client_chosen = req.request_id.is_some();
+    let request_id = match req.request_id {
+        None if state.config.workflow.deterministic_run_ids => {
+            match workflow::new_run(&req.workflow_id, &req.inputs, &state.config.workflow).await.map_err(run_error)? {
+                workflow::NewRun::Id(run_id) => Some(run_id),
+                workflow::NewRun::Reuse(earlier) => {
+                    let Some(callback_url) = req.callback_url else {
+                        return Ok(Json(*earlier).into_response());
+                    };
+                    let accepted = RunAccepted { run_id: earlier.run_id.clone(), status: earlier.status.clone() };
+                    let state = Arc::clone(&state);
+                    tokio::spawn(async move {
+                        let run_id = earlier.run_id.clone();
+                        match callback::deliver(&callback_url, &callback::RunOutcome::from_receipt(*earlier), &state.config.callbacks).await {
+                            Ok(()) => tracing::info!(run_id = %run_id, "Delivered run callback"),
+                            Err(e) => tracing::warn!(run_id = %run_id, "Run callback failed: {}", e),
+                        }
+                    });
+                    return Ok((StatusCode::ACCEPTED, Json(accepted)).into_response());
+                }
+            }
         }
+        request_id => request_id,
+    };
+    let claim = claim_request_id(&state, request_id)?;
+    let request_id = claim.request_id().to_string();
+    // The request id becomes the run ID, so a client can't point a new run at an earlier receipt
+    if client_chosen {
+        let existing = receipt::read_receipt(&request_id).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if existing.is_some() {
+            return Err((StatusCode::CONFLICT, format!("Run {} already exists; use a new request_id", request_id)));
+        }
+    }
+    let run_id = request_id.clone();
+    let workflow_id = req.workflow_id.clone();
+    let work = trace::scope(trace::from_headers(&headers), async move {
+        workflow::run_with_id(run_id, &req.workflow_id, req.inputs).await
     });
 
-    match tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), task).await {
-        Ok(joined) => joined.map_err(|e| format!("{} join error: {}", phase, e)),
-        Err(_) => Err(format!("{} timed out after {}s", phase, timeout_secs)),
+    if let Some(callback_url) = req.callback_url {
+        let state = Arc::clone(&state);
+        let run_id = request_id.clone();
+        tokio::spawn(async move {
+            let result = claim.run(work).await;
+            let outcome = run_outcome(&run_id, &workflow_id, result).await;
+            match callback::deliver(&callback_url, &outcome, &state.config.callbacks).await {
+                Ok(()) => tracing::info!(run_id = %run_id, "Delivered run callback"),
+                Err(e) => tracing::warn!(run_id = %run_id, "Run callback failed: {}", e),
+            }
+        });
+        let accepted = RunAccepted { run_id: request_id, status: "running".to_string() };
+        return Ok((StatusCode::ACCEPTED, Json(accepted)).into_response());
     }
-}
+
+    let Some(result) = claim.run(work).await else {
+        mark_run_cancelled(&request_id).await;
+        return Err(cancelled_error(&request_id));
+    };
+    result.map(|receipt| Json(receipt).into_response()).map_err(run_error)
+}
+
+fn run_error(e: anyhow::Error) -> (StatusCode, String) {
+    let status = if e.is::<workflow::InputValidationError>() {
+        StatusCode::BAD_REQUEST
+    } else if e.is::<workflow::RunInProgress>() {
+        StatusCode::CONFLICT
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    (status, e.to_string())
+}
+
+async fn mark_run_cancelled(run_id: &str) {
+    if let Err(e) = receipt::mark_cancelled(run_id).await {
+        tracing::warn!(run_id = %run_id, "Failed to mark receipt cancelled: {}", e);
+    }
+}
+
+/// What a callback reports for a finished run (`None` = cancelled). Runs that failed
+/// part-way still have a stored receipt, which is sent along with the error.
+async fn run_outcome(run_id: &str, workflow_id: &str, result: Option<anyhow::Result<receipt::WorkflowReceipt>>) -> callback::RunOutcome {
+    let (status, error) = match result {
+        Some(Ok(receipt)) => return callback::RunOutcome::from_receipt(receipt),
+        Some(Err(e)) => ("failed", Some(e.to_string())),
+        None => {
+            mark_run_cancelled(run_id).await;
+            ("cancelled", None)
+        }
+    };
+    let receipt = receipt::read_receipt(run_id).await.ok().flatten();
+    callback::RunOutcome {
+        run_id: run_id.to_string(),
+        workflow_id: workflow_id.to_string(),
+        status: receipt.as_ref().map_or(status, |r| r.status.as_str()).to_string(),
+        error,
+        receipt,
+    }
+}
+
+const MAX_BATCH_RUNS: usize = 50;
+const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+const MAX_BATCH_CONCURRENCY: usize = 16;
+
+#[derive(Deserialize, JsonSchema)]
+struct BatchRunItem {
+    workflow_id: String,
+    #[serde(default)]
+    inputs: serde_json::Value,
+    /// Resubmitting the same key returns the earlier run's receipt instead of running again
+    #[serde(default)]
+    idempotency_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BatchRunQuery {
+    #[serde(default)]
+    max_concurrency: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct BatchRunResult {
+    workflow_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    idempotency_key: Option<String>,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    receipt: Option<receipt::WorkflowReceipt>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Run ID derived from a workflow and an idempotency key, so a retry finds the earlier
+/// receipt. Keys are scoped to the workflow: the same key for another workflow is another run.
+fn idempotent_run_id(workflow_id: &str, key: &str) -> String {
+    format!("idem_{:x}", md5::compute(serde_json::json!([workflow_id, key]).to_string().as_bytes()))
+}
+
+/// POST /run/batch?max_concurrency= - Run an array of workflows with bounded concurrency.
+/// Results come back in request order; one failed run doesn't fail the batch.
+async fn run_workflow_batch(
+    State(_state): State<Arc<AppState>>,
+    Query(query): Query<BatchRunQuery>,
+    Json(runs): Json<Vec<BatchRunItem>>,
+) -> Result<Json<Vec<BatchRunResult>>, (StatusCode, String)> {
+    use futures::StreamExt;
+    
+    if runs.is_empty() || runs.len() > MAX_BATCH_RUNS {
+        return Err((StatusCode::BAD_REQUEST, format!("Batch must contain 1-{} runs", MAX_BATCH_RUNS)));
+    }
+    let concurrency = query.max_concurrency.unwrap_or(DEFAULT_BATCH_CONCURRENCY).clamp(1, MAX_BATCH_CONCURRENCY);
+    
+    let mut seen_keys = std::collections::HashSet::new();
+    let results = futures::stream::iter(runs.into_iter().map(|item| {
+        let duplicate = item.idempotency_key.as_ref().is_some_and(|k| !seen_keys.insert((item.workflow_id.clone(), k.clone())));
+        async move {
+            let BatchRunItem { workflow_id, inputs, idempotency_key } = item;
+            let outcome = if duplicate {
+                Err("Duplicate idempotency_key in batch".to_string())
+            } else {
+                run_batch_item(&workflow_id, inputs, idempotency_key.as_deref()).await
+            };
+            let (receipt, error) = match outcome {
+                Ok(receipt) => (Some(receipt), None),
+                Err(e) => (None, Some(e)),
+            };
+            BatchRunResult { workflow_id, idempotency_key, ok: error.is_none(), receipt, error }
+        }
+    }))
+    .buffered(concurrency)
+    .collect::<Vec<_>>()
+    .await;
+    
+    Ok(Json(results))
+}
+
+async fn run_batch_item(workflow_id: &str, inputs: serde_json::Value, idempotency_key: Option<&str>) -> Result<receipt::WorkflowReceipt, String> {
+    let run_id = match idempotency_key {
+        Some(key) => {
+            // Bad requests fail here rather than leaving a claimed ID without a receipt
+            workflow::check_inputs(workflow_id, &inputs).map_err(|e| e.to_string())?;
+            let run_id = idempotent_run_id(workflow_id, key);
+            // Whoever claims the ID runs it; everyone else gets that run's receipt once it's done
+            if !receipt::claim_run_id(&run_id).await.map_err(|e| e.to_string())? {
+                return match receipt::read_receipt(&run_id).await.map_err(|e| e.to_string())? {
+                    Some(existing) if existing.status != "running" => Ok(existing),
+                    _ => Err(format!("Conflict: the run for this idempotency_key ({}) is still in progress; retry later", run_id)),
+                };
+            }
+            run_id
+        }
+        None => nanoid::nanoid!(),
+    };
+    
+    // Executors block, so each run gets its own task to actually run in parallel
+    let workflow_id = workflow_id.to_string();
+    tokio::spawn(async move { workflow::run_with_id(run_id, &workflow_id, inputs).await })
+        .await
+        .map_err(|e| format!("Run task failed: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+async fn list_receipts() -> Result<Json<Vec<String>>, (StatusCode, String)> {
+    receipt::list_receipts().await.map(Json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// GET /receipts/:run_id/artifacts/:name - Download a file a workflow step produced
+async fn download_artifact(
+    axum::extract::Path((run_id, name)): axum::extract::Path<(String, String)>,
+) -> Result<axum::response::Response, (StatusCode, String)> {
+    let path = receipt::artifact_file(&run_id, &name)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
+        .ok_or((StatusCode::NOT_FOUND, format!("Artifact not found: {}/{}", run_id, name)))?;
+    let data = tokio::fs::read(&path)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let content_type = mime_guess::from_path(&path).first_or_octet_stream().to_string();
+    
+    axum::response::Response::builder()
+        .header("content-type", content_type)
+        .header("content-disposition", format!("attachment; filename=\"{}\"", name))
+        .body(axum::body::Body::from(data))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+async fn get_preferences() -> Result<Json<memory::Preferences>, (StatusCode, String)> {
+    memory::load_preferences().map(Json).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Filters and paging shared by `/executors` and `/tools`
+#[derive(Deserialize, Default)]
+struct ToolsQuery {
+    q: Option<String>,
+    permission: Option<String>,
+    tier: Option<String>,
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl ToolsQuery {
+    fn matches(&self, id: &str, description: &str, permissions: &[String], tier: Option<&str>) -> bool {
+        let q = self.q.as_deref().map(str::to_lowercase).filter(|q| !q.is_empty());
+        q.is_none_or(|q| id.to_lowercase().contains(&q) || description.to_lowercase().contains(&q))
+            && self.permission.as_deref().filter(|p| !p.is_empty()).is_none_or(|p| permissions.iter().any(|have| have == p))
+            && self.tier.as_deref().filter(|t| !t.is_empty()).is_none_or(|t| tier == Some(t))
+    }
+    
+    fn page<T>(&self, matches: impl Iterator<Item = T>) -> Vec<T> {
+        matches.skip(self.offset).take(self.limit.unwrap_or(usize::MAX)).collect()
+    }
+}
+
+/// GET /executors - Local executor manifests by id, filtered and paged by `ToolsQuery`
+async fn list_executors(State(state): State<Arc<AppState>>, Query(query): Query<ToolsQuery>) -> Json<Vec<executor::ExecutorManifest>> {
+    let mut manifests = state.executor_registry.list();
+    manifests.sort_by(|a, b| a.id.cmp(&b.id));
+    Json(query.page(manifests.into_iter().filter(|m| query.matches(&m.id, &m.description, &m.permissions, None))))
+}
+
+/// Check `Authorization: Bearer <token>` against `security.admin_token_env`
+fn require_admin(state: &AppState, headers: &axum::http::HeaderMap) -> Result<(), (StatusCode, String)> {
+    let env = &state.config.security.admin_token_env;
+    let expected = std::env::var(env).ok().filter(|t| !t.is_empty()).ok_or((
+        StatusCode::FORBIDDEN,
+        format!("Admin endpoints are disabled; set {} to enable them", env),
+    ))?;
+    let provided = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    match provided {
+        Some(provided) if tokens_match(provided, &expected) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "Missing or invalid admin token".to_string())),
+    }
+}
+
+/// Compare secrets in constant time: both sides are MACed under a throwaway key and the tags
+/// checked with `hmac::verify`, so neither the content nor the length leaks through timing
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    use ring::hmac;
+    let Ok(key) = hmac::Key::generate(hmac::HMAC_SHA256, &ring::rand::SystemRandom::new()) else {
+        return false;
+    };
+    let tag = hmac::sign(&key, expected.as_bytes());
+    hmac::verify(&key, provided.as_bytes(), tag.as_ref()).is_ok()
+}
+
+#[derive(Serialize, JsonSchema)]
+struct LlmModelInfo {
+    provider: String,
+    model: String,
+    api_key_env: String,
+    /// Set by `POST /llm/model` rather than node.yaml/env
+    runtime_override: bool,
+    providers: Vec<LlmProviderInfo>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct LlmProviderInfo {
+    provider: &'static str,
+    default_model: &'static str,
+    api_key_env: &'static str,
+}
+
+fn llm_model_info(config: &config::NodeConfig) -> LlmModelInfo {
+    let effective = config::with_live_llm(config);
+    LlmModelInfo {
+        provider: effective.llm.provider.clone(),
+        model: effective.llm.model.clone(),
+        api_key_env: effective.llm.api_key_env.clone(),
+        runtime_override: matches!(effective, std::borrow::Cow::Owned(_)),
+        providers: config::LLM_PROVIDERS
+            .iter()
+            .filter_map(|&provider| {
+                let (api_key_env, default_model) = config::provider_defaults(provider)?;
+                Some(LlmProviderInfo { provider, default_model, api_key_env })
+            })
+            .collect(),
+    }
+}
+
+/// GET /llm/model - Provider/model in use and the providers that can be switched to
+async fn get_llm_model(State(state): State<Arc<AppState>>) -> Json<LlmModelInfo> {
+    Json(llm_model_info(state.config))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SetLlmModelRequest {
+    /// Defaults to the current provider
+    provider: Option<String>,
+    /// Defaults to the provider's default model when switching provider
+    model: Option<String>,
+    /// Also write the choice to node.yaml
+    #[serde(default)]
+    persist: bool,
+}
+
+/// POST /llm/model - Switch provider/model for the running process (admin token required)
+async fn set_llm_model(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<SetLlmModelRequest>,
+) -> Result<Json<LlmModelInfo>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    
+    let current = config::with_live_llm(state.config).llm.clone();
+    let provider = req.provider.map(|p| p.trim().to_string()).unwrap_or_else(|| current.provider.clone());
+    let (default_key_env, default_model) = config::provider_defaults(&provider).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!("Unknown LLM provider '{}' (expected one of: {})", provider, config::LLM_PROVIDERS.join(", ")),
+    ))?;
+    let switching = provider != current.provider;
+    let model = match req.model.map(|m| m.trim().to_string()) {
+        Some(m) if !m.is_empty() => m,
+        Some(_) => return Err((StatusCode::BAD_REQUEST, "model must not be empty".to_string())),
+        None if switching => default_model.to_string(),
+        None => return Err((StatusCode::BAD_REQUEST, "Provide a model, a provider, or both".to_string())),
+    };
+    let api_key_env = if switching { default_key_env.to_string() } else { current.api_key_env };
+    // Refuse a switch that would leave chat without credentials
+    if std::env::var(&api_key_env).map(|k| k.is_empty()).unwrap_or(true) {
+        return Err((StatusCode::BAD_REQUEST, format!("{} is not set; can't switch to {}", api_key_env, provider)));
+    }
+    
+    let selection = config::LlmSelection { provider, model, api_key_env };
+    if req.persist {
+        config::persist_llm(&selection)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update node.yaml: {}", e)))?;
+    }
+    tracing::info!(provider = %selection.provider, model = %selection.model, persist = req.persist, "LLM model switched");
+    config::set_llm(selection).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    
+    Ok(Json(llm_model_info(state.config)))
+}
+
+/// GET /schedules - Configured schedules with their next and last run
+async fn list_schedules(State(state): State<Arc<AppState>>) -> Json<Vec<scheduler::ScheduleStatus>> {
+    Json(state.scheduler.list())
+}
+
+#[derive(Deserialize)]
+struct BudgetQuery {
+    /// Also report this user's spend (`provider:id` or a user id)
+    #[serde(default)]
+    user_id: Option<String>,
+}
+
+/// GET /budget - Today's spend against the node (and optionally a user's) daily limit
+async fn get_budget(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BudgetQuery>,
+) -> Result<Json<budget::BudgetStatus>, (StatusCode, String)> {
+    let user_id = match query.user_id {
+        Some(user_id) => Some(resolve_query_user_id(&state, Some(user_id)).await),
+        None => None,
+    };
+    state
+        .budget
+        .status(user_id.as_deref())
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// One entry in the merged tool catalog served by `/tools`
+#[derive(Serialize, JsonSchema)]
+struct ToolInfo {
+    id: String,
+    /// "local" (built-in executor) or "harness"
+    source: &'static str,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    permissions: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cost_estimate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    params_schema: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tier: Option<String>,
+}
+
+/// GET /tools - Local executors, node tools and harness tools, i.e. everything the agent can call.
+/// Filtered and paged by `ToolsQuery`.
+async fn list_tools(State(state): State<Arc<AppState>>, Query(query): Query<ToolsQuery>) -> Json<Vec<ToolInfo>> {
+    let mut local = state.executor_registry.list();
+    local.sort_by(|a, b| a.id.cmp(&b.id));
+    let harness = state.harness_tools.get();
+    
+    let tools = local
+        .into_iter()
+        .map(|m| ToolInfo {
+            id: m.id,
+            source: "local",
+            description: m.description,
+            version: Some(m.version),
+            permissions: m.permissions,
+            cost_estimate: None,
+            params_schema: None,
+            tier: None,
+        })
+        .chain(node_tools().into_iter().filter(|t| !executor::is_executor_allowed(&state.config.executors.disabled, &t.id)).map(|t| ToolInfo {
+            id: t.id,
+            source: "node",
+            description: t.description,
+            version: None,
+            permissions: Vec::new(),
+            cost_estimate: None,
+            params_schema: t.params_schema,
+            tier: None,
+        }))
+        .chain(harness.iter().map(|t| ToolInfo {
+            id: t.id.clone(),
+            source: "harness",
+            description: t.description.clone(),
+            version: None,
+            permissions: Vec::new(),
+            cost_estimate: t.cost_estimate,
+            params_schema: t.params_schema.clone(),
+            tier: t.tier.clone(),
+        }))
+        .filter(|t| query.matches(&t.id, &t.description, &t.permissions, t.tier.as_deref()));
+    
+    Json(query.page(tools))
+}
+
+// ============================================
+// Chat Endpoint
+// ============================================
+
+#[derive(Deserialize, JsonSchema)]
+struct ChatRequest {
+    message: String,
+    #[serde(default = "default_channel")]
+    channel: String,
+    #[serde(default)]
+    provider: Option<String>,      // e.g., "discord", "http"
+    #[serde(default)]
+    provider_id: Option<String>,   // e.g., "397102686660591616"
+    #[serde(default)]
+    username: Option<String>,
+    /// Channel/chat id within the provider; selects the thread under `user+channel` scope
+    #[serde(default)]
+    channel_id: Option<String>,
+    /// Id for `/cancel/:request_id`; generated when unset
+    #[serde(default)]
+    request_id: Option<String>,
+    /// Images among these go to the model with the message when `llm.vision` is on
+    #[serde(default)]
+    attachments: Vec<crate::channels::Attachment>,
+    /// Stored id of the message this one replies to (a `message_id` or an id from `/chat/history`)
+    #[serde(default)]
+    reply_to: Option<i64>,
+}
+
+fn default_channel() -> String { "http".to_string() }
+
+#[derive(Serialize, JsonSchema)]
+struct ChatResponse {
+    response: String,
+    tool_calls: Vec<ToolCallResult>,
+    milestones: Vec<Milestone>,
+    duration_ms: u64,
+    /// Set for `/chat` requests (cancellable)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    /// Sent as `X-Trace-Id` on the harness and LLM calls this turn made
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
+    /// Stored id of this reply, for a later request's `reply_to`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message_id: Option<i64>,
+    /// Stored id of the user message this reply answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<i64>,
+    /// Limit (`chat.max_turn_secs` or `chat.max_turn_usd`) that cut this turn short
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stopped_early: Option<String>,
+}
+
+#[derive(Serialize, Clone, JsonSchema)]
+struct Milestone {
+    label: String,
+    at: chrono::DateTime<chrono::Utc>,
+    /// Time since the request started
+    elapsed_ms: u64,
+}
+
+/// Tracks a chat turn against `chat.max_turn_secs` and `chat.max_turn_usd`
+struct TurnGuard {
+    start: std::time::Instant,
+    limits: config::ChatConfig,
+    spent_usd: f64,
+    /// Limit that stopped the turn; once set, the turn makes no further calls
+    stopped: Option<&'static str>,
+}
+
+impl TurnGuard {
+    fn new(limits: &config::ChatConfig, start: std::time::Instant) -> Self {
+        Self { start, limits: limits.clone(), spent_usd: 0.0, stopped: None }
+    }
+
+    fn charge(&mut self, usd: f64) {
+        self.spent_usd += usd;
+    }
+
+    /// Whether the turn may make another tool or LLM call
+    fn may_continue(&mut self) -> bool {
+        if self.stopped.is_none() {
+            if self.limits.max_turn_secs.is_some_and(|max| self.start.elapsed() >= std::time::Duration::from_secs(max)) {
+                self.stopped = Some("chat.max_turn_secs");
+            } else if self.limits.max_turn_usd.is_some_and(|max| self.spent_usd >= max) {
+                self.stopped = Some("chat.max_turn_usd");
+            }
+            if let Some(limit) = self.stopped {
+                tracing::warn!("Stopping chat turn early: {} reached after {:?}, ${:.4} spent", limit, self.start.elapsed(), self.spent_usd);
+            }
+        }
+        self.stopped.is_none()
+    }
+
+    /// Appended to the reply of a turn that was stopped
+    fn note(&self) -> Option<String> {
+        let limit = match self.stopped? {
+            "chat.max_turn_secs" => format!("time limit of {}s", self.limits.max_turn_secs.unwrap_or_default()),
+            _ => format!("cost limit of ${:.2}", self.limits.max_turn_usd.unwrap_or_default()),
+        };
+        Some(format!("\n\n_(Stopped early: this message reached its {}. This is what was done so far.)_", limit))
+    }
+}
+
+/// Progress markers collected while a chat request runs
+struct Milestones {
+    start: std::time::Instant,
+    items: Vec<Milestone>,
+}
+
+impl Milestones {
+    fn new() -> Self {
+        Self { start: std::time::Instant::now(), items: Vec::new() }
+    }
+
+    fn push(&mut self, label: impl Into<String>) {
+        self.items.push(Milestone {
+            label: label.into(),
+            at: chrono::Utc::now(),
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+        });
+    }
+}
+
+#[derive(Serialize, Clone, JsonSchema)]
+struct ToolCallResult {
+    tool: String,
+    input: serde_json::Value,
+    output: serde_json::Value,
+    duration_ms: u64,
+}
+
+/// Stored tool outputs longer than this (as JSON) are kept as a truncated preview
+const MAX_STORED_TOOL_OUTPUT_CHARS: usize = 8000;
+
+impl ToolCallResult {
+    /// Errors, denials and calls held for confirmation report that in `output`
+    fn succeeded(&self) -> bool {
+        !["error", "denied", "pending_confirmation"]
+            .iter()
+            .any(|key| self.output.get(key).is_some_and(|v| !v.is_null()))
+    }
+    
+    /// Record kept with the assistant message in conversation history
+    fn to_record(&self) -> conversation::ToolCall {
+        let serialized = self.output.to_string();
+        let output = if serialized.chars().count() > MAX_STORED_TOOL_OUTPUT_CHARS {
+            serde_json::json!({
+                "truncated": true,
+                "preview": serialized.chars().take(MAX_STORED_TOOL_OUTPUT_CHARS).collect::<String>(),
+            })
+        } else {
+            self.output.clone()
+        };
+        conversation::ToolCall {
+            tool: self.tool.clone(),
+            input: self.input.clone(),
+            output: Some(output),
+            success: self.succeeded(),
+            duration_ms: self.duration_ms,
+        }
+    }
+}
+
+fn llm_timeout_secs() -> u64 {
+    std::env::var("LLM_CALL_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v >= 5)
+        .unwrap_or(65)
+}
+
+/// Call `llm.chat` on behalf of `user_id`, refusing once their (or the node's) budget is spent
+async fn run_llm_with_timeout(
+    state: Arc<AppState>,
+    input: serde_json::Value,
+    phase: &'static str,
+    user_id: Option<&str>,
+) -> Result<executor::ExecutorResult, String> {
+    let start = std::time::Instant::now();
+    let llm = config::with_live_llm(state.config).llm.clone();
+    let events = Arc::clone(&state.events);
+    let report = |status: &str| {
+        events.emit(events::Event::LlmCall {
+            user_id: user_id.map(str::to_string),
+            phase: phase.to_string(),
+            provider: llm.provider.clone(),
+            model: llm.model.clone(),
+            status: status.to_string(),
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    };
+    if let Some(denial_reason) = state.budget.check(user_id, "llm.chat").await {
+        report("denied");
+        return Ok(executor::ExecutorResult::Denied { denial_reason });
+    }
+    let budget = Arc::clone(&state.budget);
+    let timeout_secs = llm_timeout_secs();
+    let task = trace::spawn_blocking(move || {
+        match state.executor_registry.get("llm.chat") {
+            Some(exec) => exec.execute(input, state.config),
+            None => state.executor_registry.unavailable("llm.chat"),
+        }
+    });
+
+    match tokio::time::timeout(std::time::Duration::from_secs(timeout_secs), task).await {
+        Ok(Ok(result)) => {
+            report(match &result {
+                executor::ExecutorResult::Executed { .. } => "executed",
+                executor::ExecutorResult::Denied { .. } => "denied",
+                executor::ExecutorResult::Error { .. } => "error",
+            });
+            budget.charge(user_id, "llm.chat", &serde_json::Value::Null, &result).await;
+            Ok(result)
+        }
+        Ok(Err(e)) => {
+            report("error");
+            Err(format!("{} join error: {}", phase, e))
+        }
+        Err(_) => {
+            report("timeout");
+            Err(format!("{} timed out after {}s", phase, timeout_secs))
+        }
+    }
+}
 
 fn format_tools(tools: &[agent_os::ToolDefinition]) -> String {
     if tools.is_empty() {
@@ -790,7 +2196,7 @@ fn extract_content(result: &executor::ExecutorResult) -> String {
             
             String::new()
         }
-        executor::ExecutorResult::Error { error } => format!("Error: {}", error),
+        executor::ExecutorResult::Error { error, .. } => format!("Error: {}", error),
         executor::ExecutorResult::Denied { denial_reason } => format!("Denied: {}", denial_reason.policy),
     }
 }
@@ -799,8 +2205,9 @@ async fn execute_tool(
     state: &Arc<AppState>,
     tool_name: &str,
     tool_input: serde_json::Value,
+    user_id: Option<&str>,
 ) -> Option<ToolCallResult> {
-    execute_tool_internal(state, tool_name, tool_input, None).await
+    execute_tool_internal(state, tool_name, tool_input, None, user_id).await
 }
 
 async fn execute_tool_with_progress(
@@ -809,8 +2216,9 @@ async fn execute_tool_with_progress(
     tool_input: serde_json::Value,
     telegram_sender: &crate::channels::telegram::TelegramChannel,
     chat_id: &str,
+    user_id: Option<&str>,
 ) -> Option<ToolCallResult> {
-    execute_tool_internal(state, tool_name, tool_input, Some((telegram_sender, chat_id))).await
+    execute_tool_internal(state, tool_name, tool_input, Some((telegram_sender, chat_id)), user_id).await
 }
 
 async fn execute_tool_internal(
@@ -818,73 +2226,304 @@ async fn execute_tool_internal(
     tool_name: &str,
     tool_input: serde_json::Value,
     progress_callback: Option<(&crate::channels::telegram::TelegramChannel, &str)>,
+    user_id: Option<&str>,
+) -> Option<ToolCallResult> {
+    let is_harness_tool = state.harness_tools.get().iter().any(|t| t.id == tool_name);
+    let dispatch_executor = if is_harness_tool { "harness.execute" } else { tool_name };
+    
+    // Destructive tools wait for a human: hand back a token instead of running
+    let gated = &state.config.security.requires_confirmation;
+    if executor::is_executor_allowed(gated, tool_name) || executor::is_executor_allowed(gated, dispatch_executor) {
+        let token = nanoid::nanoid!();
+        let ttl = state.config.security.confirmation_ttl_secs;
+        {
+            let mut pending = state.pending_confirmations.lock().unwrap();
+            let now = std::time::Instant::now();
+            pending.retain(|_, p| p.expires_at > now);
+            pending.insert(token.clone(), PendingConfirmation {
+                tool: tool_name.to_string(),
+                input: tool_input.clone(),
+                user_id: user_id.map(str::to_string),
+                expires_at: now + std::time::Duration::from_secs(ttl),
+            });
+        }
+        tracing::info!("Tool '{}' requires confirmation (token {})", tool_name, token);
+        return Some(ToolCallResult {
+            tool: tool_name.to_string(),
+            input: tool_input,
+            output: serde_json::json!({
+                "pending_confirmation": { "token": token, "expires_in_secs": ttl },
+                "message": "Not run yet: this action needs the user's confirmation",
+            }),
+            duration_ms: 0,
+        });
+    }
+    
+    run_tool(state, tool_name, tool_input, progress_callback, user_id).await
+}
+
+/// Allowlist check and dispatch of a tool call, with no confirmation gate
+async fn run_tool(
+    state: &Arc<AppState>,
+    tool_name: &str,
+    tool_input: serde_json::Value,
+    progress_callback: Option<(&crate::channels::telegram::TelegramChannel, &str)>,
+    user_id: Option<&str>,
+) -> Option<ToolCallResult> {
+    let result = dispatch_tool(state, tool_name, tool_input, progress_callback, user_id).await;
+    if let Some(result) = &result {
+        state.events.emit(events::Event::ToolCalled {
+            user_id: user_id.map(str::to_string),
+            tool: result.tool.clone(),
+            success: result.succeeded(),
+            duration_ms: result.duration_ms,
+        });
+    }
+    result
+}
+
+async fn dispatch_tool(
+    state: &Arc<AppState>,
+    tool_name: &str,
+    tool_input: serde_json::Value,
+    _progress_callback: Option<(&crate::channels::telegram::TelegramChannel, &str)>,
+    user_id: Option<&str>,
 ) -> Option<ToolCallResult> {
     let state = Arc::clone(state);
-    let tool_name_owned = tool_name.to_string();
     let tool_input_for_result = tool_input.clone();
 
     // Check if this is a harness tool (if it's in harness_tools list)
-    let is_harness_tool = state.harness_tools.iter().any(|t| t.id == tool_name);
+    let is_harness_tool = state.harness_tools.get().iter().any(|t| t.id == tool_name);
+    let dispatch_executor = if is_harness_tool { "harness.execute" } else { tool_name };
+    if state.executor_registry.is_disabled(dispatch_executor) {
+        return Some(tool_call_result(tool_name, tool_input_for_result, state.executor_registry.unavailable(dispatch_executor)));
+    }
+    if is_node_tool(tool_name) {
+        let result = match node_tool_blocked(&state.config.executors, &state.config.security, tool_name) {
+            Some(blocked) => blocked,
+            None => run_node_tool(&state, tool_name, &tool_input, user_id).await,
+        };
+        return Some(tool_call_result(tool_name, tool_input_for_result, result));
+    }
+    if !is_harness_tool && state.executor_registry.get(tool_name).is_none() {
+        return unknown_tool_result(tool_name, tool_input_for_result, state.config.security.is_strict());
+    }
+    
+    // Same allowlist policy as workflow steps, applied to the executor we'd dispatch to
+    if let Some(denial) = chat_denial(&state.config.security, dispatch_executor) {
+        tracing::warn!("Tool '{}' denied: executor '{}' not allowed for chat", tool_name, dispatch_executor);
+        return Some(ToolCallResult {
+            tool: tool_name.to_string(),
+            input: tool_input_for_result,
+            output: serde_json::json!({ "denied": denial.policy, "denial_reason": denial }),
+            duration_ms: 0,
+        });
+    }
     
-    let result = if is_harness_tool {
+    let exec_input = if is_harness_tool {
         // Execute via harness - use "executor" key (harness expects this)
-        let harness_input = serde_json::json!({
+        serde_json::json!({
             "executor": tool_name,
             "params": tool_input
-        });
-        tokio::task::spawn_blocking(move || {
-            state
-                .executor_registry
-                .get("harness.execute")
-                .map(|exec| exec.execute(harness_input, state.config))
         })
-        .await
-        .ok()
-        .flatten()?
     } else {
-        // Execute via direct executor
-        tokio::task::spawn_blocking(move || {
-            state
-                .executor_registry
-                .get(&tool_name_owned)
-                .map(|exec| exec.execute(tool_input, state.config))
-        })
-        .await
-        .ok()
-        .flatten()?
+        // Chat may only use the calling user's own connected integrations
+        let mut input = tool_input;
+        if let Some(oauth) = input.get_mut("oauth").and_then(serde_json::Value::as_object_mut) {
+            oauth.insert("user_id".to_string(), user_id.map(serde_json::Value::from).unwrap_or(serde_json::Value::Null));
+        }
+        input
     };
-
-    match result {
-        executor::ExecutorResult::Executed { output, duration_ms } => Some(ToolCallResult {
-            tool: tool_name.to_string(),
-            input: tool_input_for_result,
-            output,
-            duration_ms,
-        }),
-        executor::ExecutorResult::Error { error } => {
-            tracing::warn!("Tool error: {}", error);
-            Some(ToolCallResult {
+    
+    let paid = budget::is_paid(dispatch_executor);
+    if paid {
+        if let Some(denial) = state.budget.check(user_id, dispatch_executor).await {
+            return Some(ToolCallResult {
                 tool: tool_name.to_string(),
                 input: tool_input_for_result,
-                output: serde_json::json!({ "error": error }),
+                output: serde_json::json!({ "denied": denial.policy, "denial_reason": denial }),
                 duration_ms: 0,
-            })
+            });
         }
-        executor::ExecutorResult::Denied { denial_reason } => Some(ToolCallResult {
-            tool: tool_name.to_string(),
-            input: tool_input_for_result,
-            output: serde_json::json!({ "denied": denial_reason.policy }),
-            duration_ms: 0,
-        }),
     }
+    let charge_input = paid.then(|| exec_input.clone());
+    let budget = Arc::clone(&state.budget);
+    let config = state.config;
+    let harness_tools = state.harness_tools.get();
+    let dispatch = dispatch_executor.to_string();
+    let result = trace::spawn_blocking(move || {
+        state
+            .executor_registry
+            .get(&dispatch)
+            .map(|exec| exec.execute(exec_input, state.config))
+    })
+    .await
+    .ok()
+    .flatten()?;
+    cancel::note_result(&result);
+    if let Some(input) = charge_input {
+        budget.charge(user_id, dispatch_executor, &input, &result).await;
+    }
+    let result = match output_schema(config, &harness_tools, tool_name) {
+        Some(schema) if config.executors.validate_output => executor::check_output_schema(tool_name, &schema, result),
+        _ => result,
+    };
+
+    Some(tool_call_result(tool_name, tool_input_for_result, result))
 }
 
-async fn find_and_execute_tools(
-    state: &Arc<AppState>,
-    content: &str,
-    llm_result: &executor::ExecutorResult,
-) -> Vec<ToolCallResult> {
-    let mut results = Vec::new();
+/// Why a chat tool call dispatched to `executor_id` is denied, if it's outside the chat allowlist
+fn chat_denial(security: &config::SecurityConfig, executor_id: &str) -> Option<executor::DenialReason> {
+    let (allowed, rule) = security.chat_allowlist();
+    (!executor::is_executor_allowed(allowed, executor_id)).then(|| executor::DenialReason {
+        rule: rule.to_string(),
+        attempted: executor_id.to_string(),
+        policy: format!("Executor '{}' is not in {} list", executor_id, rule),
+    })
+}
+
+/// Output schema for a chat tool: `executors.output_schemas`, else the one its harness
+/// tool or plugin declares
+fn output_schema(config: &config::NodeConfig, harness_tools: &[agent_os::ToolDefinition], tool_name: &str) -> Option<serde_json::Value> {
+    config
+        .executors
+        .output_schemas
+        .get(tool_name)
+        .or_else(|| harness_tools.iter().find(|t| t.id == tool_name)?.output_schema.as_ref())
+        .or_else(|| config.executors.plugins.iter().find(|p| p.id == tool_name)?.output_schema.as_ref())
+        .cloned()
+}
+
+const CONVERSATION_STATS_TOOL: &str = "conversation.stats";
+const CONVERSATION_RECALL_TOOL: &str = "conversation.recall";
+const MAX_RECALL_RESULTS: usize = 20;
+
+/// Tools the node answers itself, scoped to the calling user. Like executors, they are subject to
+/// `executors.disabled` and the chat allowlist.
+fn node_tools() -> Vec<agent_os::ToolDefinition> {
+    vec![
+        agent_os::ToolDefinition {
+            id: CONVERSATION_STATS_TOOL.to_string(),
+            description: "Counts of this user's past messages by role, and the channels they used".to_string(),
+            params_schema: Some(serde_json::json!({ "type": "object", "properties": {} })),
+            output_schema: None,
+            cost_estimate: None,
+            tier: None,
+        },
+        agent_os::ToolDefinition {
+            id: CONVERSATION_RECALL_TOOL.to_string(),
+            description: "Find this user's past messages containing all the given words, newest first".to_string(),
+            params_schema: Some(serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Words to look for" },
+                    "limit": { "type": "integer", "description": "Most messages to return (default 5, max 20)" }
+                },
+                "required": ["query"]
+            })),
+            output_schema: None,
+            cost_estimate: None,
+            tier: None,
+        },
+    ]
+}
+
+/// Greeting for a user's first message, if one is configured
+fn welcome_message(state: &AppState) -> Option<String> {
+    state.agent_os.welcome_message(state.config.agent.welcome.as_deref(), chat_tools(state).len())
+}
+
+/// Prepend the first-contact greeting to a reply. Only the sent reply carries it, not the stored history.
+fn with_welcome(welcome: Option<String>, reply: String) -> String {
+    match welcome {
+        Some(welcome) => format!("{}\n\n{}", welcome, reply),
+        None => reply,
+    }
+}
+
+/// Harness tools plus the enabled node tools: what the chat model is told it can call
+fn chat_tools(state: &AppState) -> Vec<agent_os::ToolDefinition> {
+    let mut tools = state.harness_tools.get().as_ref().clone();
+    tools.extend(node_tools().into_iter().filter(|t| node_tool_blocked(&state.config.executors, &state.config.security, &t.id).is_none()));
+    tools
+}
+
+fn is_node_tool(tool_name: &str) -> bool {
+    tool_name == CONVERSATION_STATS_TOOL || tool_name == CONVERSATION_RECALL_TOOL
+}
+
+/// Why chat may not call a node tool: it's in `executors.disabled` or outside the chat allowlist
+fn node_tool_blocked(executors: &config::ExecutorsConfig, security: &config::SecurityConfig, tool_name: &str) -> Option<executor::ExecutorResult> {
+    if executor::is_executor_allowed(&executors.disabled, tool_name) {
+        return Some(executor::ExecutorResult::error(
+            executor::ExecutorError::Denied,
+            format!("executor disabled: {} (see executors.enabled / executors.disabled)", tool_name),
+        ));
+    }
+    chat_denial(security, tool_name).map(|denial_reason| executor::ExecutorResult::Denied { denial_reason })
+}
+
+/// Run node tool `tool_name` for the calling user
+async fn run_node_tool(state: &AppState, tool_name: &str, input: &serde_json::Value, user_id: Option<&str>) -> executor::ExecutorResult {
+    let Some(user_id) = user_id else {
+        return executor::ExecutorResult::error(executor::ExecutorError::BadInput, format!("{} needs a user", tool_name));
+    };
+    let started = std::time::Instant::now();
+    let output = if tool_name == CONVERSATION_STATS_TOOL {
+        state.conversation_manager.stats(user_id).await.map(|stats| serde_json::json!(stats))
+    } else {
+        let Some(query) = input["query"].as_str().filter(|q| !q.trim().is_empty()) else {
+            return executor::ExecutorResult::error(executor::ExecutorError::BadInput, "query is required");
+        };
+        let limit = input["limit"].as_u64().map_or(5, |n| (n as usize).clamp(1, MAX_RECALL_RESULTS));
+        state.conversation_manager.recall(user_id, query, limit).await.map(|messages| serde_json::json!({ "messages": messages }))
+    };
+    match output {
+        Ok(output) => executor::ExecutorResult::Executed { output, duration_ms: started.elapsed().as_millis() as u64 },
+        Err(e) => executor::ExecutorResult::error(executor::ExecutorError::Internal, e.to_string()),
+    }
+}
+
+fn tool_call_result(tool_name: &str, input: serde_json::Value, result: executor::ExecutorResult) -> ToolCallResult {
+    let (output, duration_ms) = match result {
+        executor::ExecutorResult::Executed { output, duration_ms } => (output, duration_ms),
+        executor::ExecutorResult::Error { error, code } => {
+            tracing::warn!("Tool error ({:?}): {}", code, error);
+            (serde_json::json!({ "error": error, "code": code }), 0)
+        }
+        executor::ExecutorResult::Denied { denial_reason } => {
+            (serde_json::json!({ "denied": denial_reason.policy, "code": executor::ExecutorError::Denied }), 0)
+        }
+    };
+    ToolCallResult { tool: tool_name.to_string(), input, output, duration_ms }
+}
+
+/// A tool name that is neither a harness tool nor an executor (usually hallucinated). Strict
+/// mode reports it, so the model sees the error in its follow-up and can correct itself;
+/// otherwise the call is skipped.
+fn unknown_tool_result(tool_name: &str, input: serde_json::Value, strict: bool) -> Option<ToolCallResult> {
+    if !strict {
+        tracing::debug!("Skipping unknown tool '{}'", tool_name);
+        return None;
+    }
+    tracing::warn!("Model called unknown tool '{}'", tool_name);
+    Some(ToolCallResult {
+        tool: tool_name.to_string(),
+        input,
+        output: serde_json::json!({ "error": format!("unknown tool: {}", tool_name), "code": executor::ExecutorError::BadInput }),
+        duration_ms: 0,
+    })
+}
+
+/// Tool calls found in an LLM response, and how many were dropped past `max_tools_per_turn`
+struct ToolPlan {
+    calls: Vec<(String, serde_json::Value)>,
+    dropped: usize,
+}
+
+/// Tool calls the response asks for, in order, capped at `max` (0 = no cap)
+fn plan_tool_calls(content: &str, llm_result: &executor::ExecutorResult, max: usize) -> ToolPlan {
+    let mut calls = Vec::new();
 
     tracing::debug!("Parsing content for tool calls (length: {})", content.len());
 
@@ -898,81 +2537,101 @@ async fn find_and_execute_tools(
                         block.get("input")
                     ) {
                         tracing::info!("Found tool call (Claude native format): {}", tool_name);
-                        if let Some(result) = execute_tool(state, tool_name, tool_input.clone()).await {
-                            results.push(result);
-                        }
+                        calls.push((tool_name.to_string(), tool_input.clone()));
                     }
                 }
             }
         }
     }
 
-    // If Claude native format found tools, return early
-    if !results.is_empty() {
-        return results;
-    }
-
-    // Try multiple parsing strategies for maximum LLM compatibility
-    
-    // Strategy 1: Standard ```tool blocks or <tool> XML
-    let tool_regex = regex::Regex::new(
-        r"```tool\s*\n?([\s\S]*?)\n?```|<tool>\s*([\s\S]*?)\s*</tool>",
-    )
-    .unwrap();
-
-    for cap in tool_regex.captures_iter(content) {
-        let tool_json = match cap.get(1).or_else(|| cap.get(2)) {
-            Some(m) => m.as_str(),
-            None => continue,
-        };
-        tracing::info!("Found tool call (standard format)");
-        if let Some(result) = parse_and_execute_tool(state, tool_json).await {
-            results.push(result);
+    // Try multiple parsing strategies for maximum LLM compatibility, unless Claude native format found tools
+    if calls.is_empty() {
+        // Strategy 1: Standard ```tool blocks or <tool> XML
+        let tool_regex = regex::Regex::new(
+            r"```tool\s*\n?([\s\S]*?)\n?```|<tool>\s*([\s\S]*?)\s*</tool>",
+        )
+        .unwrap();
+
+        for cap in tool_regex.captures_iter(content) {
+            let tool_json = match cap.get(1).or_else(|| cap.get(2)) {
+                Some(m) => m.as_str(),
+                None => continue,
+            };
+            tracing::info!("Found tool call (standard format)");
+            calls.extend(parse_tool_call(tool_json));
         }
-    }
-    
-    // Strategy 2: Minimax's <minimax:tool_call> format
-    let minimax_regex = regex::Regex::new(
-        r"<minimax:tool_call>\s*([\s\S]*?)\s*</tool>",
-    )
-    .unwrap();
-    
-    for cap in minimax_regex.captures_iter(content) {
-        if let Some(tool_json) = cap.get(1) {
-            tracing::info!("Found tool call (minimax format)");
-            if let Some(result) = parse_and_execute_tool(state, tool_json.as_str()).await {
-                results.push(result);
+        
+        // Strategy 2: Minimax's <minimax:tool_call> format
+        let minimax_regex = regex::Regex::new(
+            r"<minimax:tool_call>\s*([\s\S]*?)\s*</tool>",
+        )
+        .unwrap();
+        
+        for cap in minimax_regex.captures_iter(content) {
+            if let Some(tool_json) = cap.get(1) {
+                tracing::info!("Found tool call (minimax format)");
+                calls.extend(parse_tool_call(tool_json.as_str()));
             }
         }
-    }
-    
-    // Strategy 3: [TOOL_CALL] markers
-    let bracket_regex = regex::Regex::new(
-        r"\[TOOL_CALL\]\s*([\s\S]*?)\s*\[/TOOL_CALL\]",
-    )
-    .unwrap();
-    
-    tracing::debug!("Testing bracket regex on content");
-    for cap in bracket_regex.captures_iter(content) {
-        if let Some(tool_json) = cap.get(1) {
-            tracing::info!("Found tool call (bracket format), length: {}", tool_json.as_str().len());
-            if let Some(result) = parse_and_execute_tool(state, tool_json.as_str()).await {
-                results.push(result);
+        
+        // Strategy 3: [TOOL_CALL] markers
+        let bracket_regex = regex::Regex::new(
+            r"\[TOOL_CALL\]\s*([\s\S]*?)\s*\[/TOOL_CALL\]",
+        )
+        .unwrap();
+        
+        tracing::debug!("Testing bracket regex on content");
+        for cap in bracket_regex.captures_iter(content) {
+            if let Some(tool_json) = cap.get(1) {
+                tracing::info!("Found tool call (bracket format), length: {}", tool_json.as_str().len());
+                calls.extend(parse_tool_call(tool_json.as_str()));
             }
         }
+        
+        if calls.is_empty() {
+            tracing::debug!("No tool calls found. Content preview: {}", &content[..200.min(content.len())]);
+        }
     }
     
-    if results.is_empty() {
-        tracing::debug!("No tool calls found. Content preview: {}", &content[..200.min(content.len())]);
+    let dropped = if max > 0 { calls.len().saturating_sub(max) } else { 0 };
+    if dropped > 0 {
+        let skipped: Vec<&str> = calls[max..].iter().map(|(tool, _)| tool.as_str()).collect();
+        tracing::warn!("Dropping {} tool call(s) over the limit of {} per turn: {}", dropped, max, skipped.join(", "));
+        calls.truncate(max);
     }
-    
-    results
+    ToolPlan { calls, dropped }
 }
 
-async fn parse_and_execute_tool(
+/// Run the tool calls in an LLM response (up to `chat.max_tools_per_turn`), stopping
+/// once `guard` runs out. Also returns how many calls were dropped over the per-turn limit.
+async fn find_and_execute_tools(
     state: &Arc<AppState>,
-    tool_json: &str,
-) -> Option<ToolCallResult> {
+    content: &str,
+    llm_result: &executor::ExecutorResult,
+    milestones: &mut Milestones,
+    user_id: Option<&str>,
+    guard: &mut TurnGuard,
+) -> (Vec<ToolCallResult>, usize) {
+    let plan = plan_tool_calls(content, llm_result, state.config.chat.max_tools_per_turn);
+    let mut results = Vec::new();
+    for (tool_name, tool_input) in plan.calls {
+        if !guard.may_continue() {
+            break;
+        }
+        tracing::info!("Executing tool: {}", tool_name);
+        milestones.push(format!("Running {}", tool_name));
+        if let Some(result) = execute_tool(state, &tool_name, tool_input, user_id).await {
+            if result.succeeded() {
+                guard.charge(state.budget.harness_tool_cost(&tool_name));
+            }
+            results.push(result);
+        }
+    }
+    (results, plan.dropped)
+}
+
+/// `(tool, input)` from the JSON body of a text-format tool call
+fn parse_tool_call(tool_json: &str) -> Option<(String, serde_json::Value)> {
     // Clean up common LLM format issues
     let cleaned = tool_json
         .replace(" => ", ": ")  // Minimax uses => instead of :
@@ -990,16 +2649,99 @@ async fn parse_and_execute_tool(
     };
     
     let tool_name = tool_call["tool"].as_str()?;
-    let tool_input = tool_call["input"].clone();
+    Some((tool_name.to_string(), tool_call["input"].clone()))
+}
+
+/// Appended to a reply when tool calls were dropped over `max_tools_per_turn`
+fn dropped_tools_note(dropped: usize, max: usize) -> String {
+    format!(
+        "\n\n_({} more tool call{} skipped: at most {} run per message.)_",
+        dropped,
+        if dropped == 1 { " was" } else { "s were" },
+        max
+    )
+}
+
+/// Assistant text for the turn that requested tools. Native tool_use responses
+/// often carry no text, and providers reject empty assistant messages.
+fn tool_call_turn(assistant_content: &str, tool_results: &[ToolCallResult]) -> String {
+    if !assistant_content.trim().is_empty() {
+        return assistant_content.to_string();
+    }
+    let tools: Vec<&str> = tool_results.iter().map(|r| r.tool.as_str()).collect();
+    format!("[Called tools: {}]", tools.join(", "))
+}
+
+/// Wrap tool outputs (`(tool, text)`) so the model can tell them apart from something the user typed
+fn tool_results_turn(outputs: &[(String, String)]) -> String {
+    let mut out = String::from(
+        "<tool_results>\nResults of the tools you called (not written by the user):\n",
+    );
+    for (tool, text) in outputs {
+        out.push_str(&format!("<tool_result tool=\"{}\">\n{}\n</tool_result>\n", tool, text));
+    }
+    out.push_str("</tool_results>");
+    out
+}
+
+/// Oversized outputs read at most this many times `tool_result_max_bytes` into a summary call
+const SUMMARIZE_INPUT_FACTOR: usize = 8;
+
+/// A tool's output as text for the follow-up turn, kept under
+/// `conversation.tool_result_max_bytes` by summarizing (when enabled) or clipping it
+async fn tool_output_for_followup(
+    state: &Arc<AppState>,
+    result: &ToolCallResult,
+    request: &str,
+    user_id: Option<&str>,
+) -> String {
+    let text = serde_json::to_string_pretty(&result.output).unwrap_or_default();
+    let max_bytes = state.config.conversation.tool_result_max_bytes;
+    if text.len() <= max_bytes {
+        return text;
+    }
+    tracing::info!("Tool output from {} is {} bytes (max {})", result.tool, text.len(), max_bytes);
     
-    tracing::info!("Executing tool: {}", tool_name);
-    execute_tool(state, tool_name, tool_input).await
+    if state.config.conversation.summarize_tool_results {
+        let input = serde_json::json!({
+            "messages": [
+                {
+                    "role": "system",
+                    "content": format!(
+                        "Condense the tool output below to under {} characters. Keep every fact, number, name and link \
+                         that could help answer the user's request; drop markup and boilerplate. Reply with the condensed output only.",
+                        max_bytes
+                    )
+                },
+                {
+                    "role": "user",
+                    "content": format!(
+                        "User request: {}\n\nOutput of {}:\n{}",
+                        request,
+                        result.tool,
+                        conversation::clip_middle(&text, max_bytes * SUMMARIZE_INPUT_FACTOR)
+                    )
+                }
+            ]
+        });
+        match run_llm_with_timeout(Arc::clone(state), input, "summarize", user_id).await {
+            Ok(executor::ExecutorResult::Executed { output, .. }) if output["content"].as_str().is_some_and(|c| !c.trim().is_empty()) => {
+                let summary = output["content"].as_str().unwrap_or_default();
+                return conversation::clip_middle(&format!("[Summary of a {} byte output]\n{}", text.len(), summary), max_bytes);
+            }
+            other => tracing::warn!("Could not summarize {} output, clipping it instead: {:?}", result.tool, other),
+        }
+    }
+    conversation::clip_middle(&text, max_bytes)
 }
 
 async fn get_followup_response(
     state: &Arc<AppState>,
     messages: &[serde_json::Value],
+    assistant_content: &str,
     tool_results: &[ToolCallResult],
+    milestones: &mut Milestones,
+    user_id: Option<&str>,
 ) -> String {
     // Check if any tool result has a formattedResponse - if so, use it directly
     for result in tool_results {
@@ -1015,25 +2757,33 @@ async fn get_followup_response(
         }
     }
     
-    // No formatted response - ask Claude to summarize
-    let mut new_messages = messages.to_vec();
+    // No formatted response - hand the results back to the model as a
+    // follow-up turn. Extra `system` messages would be dropped or would replace
+    // the real system prompt on some providers, so results go in a user turn
+    // after the assistant turn that asked for them.
+    let request = messages
+        .iter()
+        .rev()
+        .find(|m| m["role"] == "user")
+        .and_then(|m| m["content"].as_str())
+        .unwrap_or_default();
+    let mut outputs = Vec::new();
     for result in tool_results {
-        let result_msg = format!(
-            "[Tool Result: {}]\n{}",
-            result.tool,
-            serde_json::to_string_pretty(&result.output).unwrap_or_default()
-        );
-        new_messages.push(serde_json::json!({
-            "role": "system",
-            "content": result_msg
-        }));
+        outputs.push((result.tool.clone(), tool_output_for_followup(state, result, request, user_id).await));
     }
+    
+    let mut new_messages = messages.to_vec();
+    new_messages.push(serde_json::json!({
+        "role": "assistant",
+        "content": tool_call_turn(assistant_content, tool_results)
+    }));
     new_messages.push(serde_json::json!({
         "role": "user",
-        "content": "Summarize these results for the user in plain language. No tool blocks."
+        "content": tool_results_turn(&outputs)
     }));
     let input = serde_json::json!({ "messages": new_messages });
-    match run_llm_with_timeout(Arc::clone(state), input, "followup").await {
+    milestones.push("Thinking");
+    match run_llm_with_timeout(Arc::clone(state), input, "followup", user_id).await {
         Ok(executor::ExecutorResult::Executed { output, .. }) => {
             output["content"].as_str().unwrap_or("").to_string()
         }
@@ -1043,10 +2793,167 @@ async fn get_followup_response(
 
 async fn chat(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ChatRequest>,
+    headers: axum::http::HeaderMap,
+    Json(mut req): Json<ChatRequest>,
 ) -> Result<Json<ChatResponse>, (StatusCode, String)> {
+    let claim = claim_request_id(&state, req.request_id.take())?;
+    let request_id = claim.request_id().to_string();
+    state.channel_statuses.message_received(crate::channels::ChannelType::Http);
+    let work = trace::scope(trace::from_headers(&headers), process_chat(Arc::clone(&state), req));
+    let Some(result) = claim.run(work).await else {
+        return Err(cancelled_error(&request_id));
+    };
+    result.map(|response| Json(ChatResponse { request_id: Some(request_id), ..response }))
+}
+
+/// POST /slack/commands - Slack slash commands. Acks within Slack's 3s deadline and
+/// answers in the background via `response_url` / `chat.postMessage`.
+async fn slack_command(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    use crate::channels::slack;
+    
+    let slack_config = &state.config.channels.slack;
+    if !slack_config.enabled {
+        return Err((StatusCode::NOT_FOUND, "Slack channel is not enabled".to_string()));
+    }
+    let secret = std::env::var(&slack_config.signing_secret_env).map_err(|_| {
+        tracing::error!("Slack signing secret not found in env: {}", slack_config.signing_secret_env);
+        (StatusCode::INTERNAL_SERVER_ERROR, "Slack signing secret is not configured".to_string())
+    })?;
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    slack::verify_signature(&secret, header("x-slack-request-timestamp"), header("x-slack-signature"), &body, chrono::Utc::now().timestamp())
+        .map_err(|e| (StatusCode::UNAUTHORIZED, e.to_string()))?;
+    
+    let mut command = slack::SlashCommand::parse(&body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    if !slack::handles_command(slack_config, &command.command) {
+        return Ok(Json(slack::ephemeral(&format!("{} isn't handled here.", command.command))));
+    }
+    if !slack::listens_in(slack_config, &command.channel_id) {
+        return Ok(Json(slack::ephemeral("I'm not listening in this channel.")));
+    }
+    let context = crate::channels::TriggerContext {
+        trigger: &slack_config.trigger,
+        is_dm: command.channel_id.starts_with('D'),
+        mentioned: true,
+        from_bot: false,
+    };
+    if !crate::channels::should_respond(&command.text, &context) {
+        return Ok(Json(slack::ephemeral("I'm not answering that here.")));
+    }
+    command.text = crate::channels::strip_trigger_prefix(&command.text, &slack_config.trigger).to_string();
+    if command.text.is_empty() {
+        return Ok(Json(slack::ephemeral(&format!("Usage: {} <question>", command.command))));
+    }
+    tracing::info!("📨 Slack {} from {}", command.command, command.user_name.as_deref().unwrap_or(&command.user_id));
+    state.channel_statuses.message_received(crate::channels::ChannelType::Slack);
+    
+    let msg = command.into_message();
+    tokio::spawn(async move {
+        let req = ChatRequest {
+            message: msg.content.clone(),
+            channel: "slack".to_string(),
+            provider: Some("slack".to_string()),
+            provider_id: Some(msg.provider_user_id.clone()),
+            username: msg.username.clone(),
+            channel_id: Some(msg.channel_id.clone()),
+            request_id: None,
+            attachments: msg.attachments.clone(),
+            reply_to: None,
+        };
+        let reply = match trace::scope(trace::new_id(), process_chat(Arc::clone(&state), req)).await {
+            Ok(response) => response.response,
+            Err((_, e)) => {
+                tracing::error!("❌ Slack chat error: {}", e);
+                "Sorry, something went wrong answering that.".to_string()
+            }
+        };
+        if let Err(e) = slack::deliver(&state.config.channels.slack, &msg, &reply).await {
+            tracing::error!("Failed to send Slack reply: {}", e);
+        }
+    });
+    
+    Ok(Json(slack::ephemeral("Working on it…")))
+}
+
+#[derive(Deserialize)]
+struct DeadLettersQuery {
+    status: Option<String>,
+    limit: Option<usize>,
+}
+
+/// GET /dead-letters - Channel replies that failed to send, soonest retry first (admin token required)
+async fn list_dead_letters(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Query(query): Query<DeadLettersQuery>,
+) -> Result<Json<Vec<store::DeadLetter>>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    let status = query.status.filter(|s| !s.is_empty());
+    if let Some(status) = &status {
+        if ![dead_letter::PENDING, dead_letter::DELIVERED, dead_letter::FAILED].contains(&status.as_str()) {
+            return Err((StatusCode::BAD_REQUEST, format!("Unknown status '{}' (expected pending, delivered or failed)", status)));
+        }
+    }
+    state
+        .store
+        .list_dead_letters(status.as_deref(), query.limit.unwrap_or(100).min(1000))
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// GET /channels/status - Connection state of every channel type
+async fn channels_status(State(state): State<Arc<AppState>>) -> Json<Vec<crate::channels::status::ChannelStatus>> {
+    Json(state.channel_statuses.snapshot())
+}
+
+/// GET /events - Stream workflow and chat events (see `events::Event`) as server-sent events
+async fn stream_events(
+    State(state): State<Arc<AppState>>,
+) -> axum::response::sse::Sse<impl futures::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>> {
+    use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let stream = futures::stream::unfold(state.events.subscribe(), |mut rx| async move {
+        let sse = match rx.recv().await {
+            Ok(event) => SseEvent::default().event(event.name()).json_data(&event).unwrap_or_default(),
+            // A slow client skips what it missed rather than holding up everyone else
+            Err(RecvError::Lagged(skipped)) => SseEvent::default().event("lagged").data(format!("{{\"skipped\":{}}}", skipped)),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(sse), rx))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// POST /cancel/:request_id - Abort a running `/chat` or `/run` and the harness jobs it started
+async fn cancel_request(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(request_id): axum::extract::Path<String>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let Some(cancelled) = state.cancellations.cancel(&request_id) else {
+        return Err((StatusCode::NOT_FOUND, format!("No running request {}", request_id)));
+    };
+    for job_id in &cancelled.job_ids {
+        let abort = monitor::MonitorAction::Abort { reason: format!("Request {} cancelled", request_id) };
+        if let Err(e) = state.job_monitor.handle_action(abort, crate::ports::HARNESS_URL, job_id).await {
+            tracing::warn!("Failed to cancel harness job {}: {}", job_id, e);
+        }
+    }
+    Ok(Json(serde_json::json!({ "cancelled": true, "request_id": request_id, "harness_jobs": cancelled.job_ids })))
+}
+
+/// Shared chat pipeline for the HTTP endpoint and gateway channels (Discord)
+async fn process_chat(
+    state: Arc<AppState>,
+    req: ChatRequest,
+) -> Result<ChatResponse, (StatusCode, String)> {
     let start = std::time::Instant::now();
-    let milestones = vec!["Received your message".to_string()];
+    let mut milestones = Milestones::new();
+    milestones.push("Received your message");
 
     let msg_preview = req.message.chars().take(60).collect::<String>();
     tracing::info!("Chat: \"{}\"", msg_preview);
@@ -1055,33 +2962,69 @@ async fn chat(
     let provider = req.provider.as_deref().unwrap_or("http");
     let provider_id = req.provider_id.as_deref().unwrap_or("anonymous");
 
-    let (user_id, _) = state
+    let (user_id, is_new_user) = state
         .identity_manager
         .resolve(provider, provider_id, req.username.as_deref())
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    // Store user message
-    let _ = state
-        .conversation_manager
-        .add_user_message(&user_id, &req.message, &req.channel)
-        .await;
+    // Shown once: a user is only new on the message that created them
+    let welcome = if is_new_user { welcome_message(&state) } else { None };
+    
+    // Blocked messages get the refusal without an LLM call and stay out of the history
+    if let Some(block) = state.moderator.check(&req.message).await {
+        state.moderator.log_denial(&user_id, &req.channel, &req.message, &block);
+        milestones.push("Blocked by moderation");
+        return Ok(ChatResponse {
+            response: with_welcome(welcome, state.moderator.refusal().to_string()),
+            tool_calls: Vec::new(),
+            milestones: milestones.items,
+            duration_ms: start.elapsed().as_millis() as u64,
+            request_id: None,
+            trace_id: trace::current(),
+            message_id: None,
+            reply_to: None,
+            stopped_early: None,
+        });
+    }
+    // Held until this turn's reply is stored, so a double-send waits its turn
+    let _turn = state.user_locks.lock(&user_id).await;
+    let thread_id = state.config.conversation.thread_id(&req.channel, req.channel_id.as_deref());
+    if let Some(reply_to) = req.reply_to {
+        let found = state
+            .conversation_manager
+            .can_reply_to(&user_id, thread_id.as_deref(), reply_to)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !found {
+            return Err((StatusCode::BAD_REQUEST, format!("reply_to {} is not a message in this conversation", reply_to)));
+        }
+    }
 
     // Build system prompt using FULL Agent OS (SOUL, IDENTITY, SKILLS, PLAYBOOKS, MEMORY + tools)
-    let system_prompt = state.agent_os.build_system_prompt(&state.harness_tools);
+    let system_prompt = state.agent_os.build_system_prompt(&chat_tools(&state));
 
-    // Build messages
-    let messages = state
+    // Build messages. The user message is only stored with the rest of the turn, at the end.
+    let mut messages = state
         .conversation_manager
-        .build_llm_messages(&user_id, &system_prompt)
+        .build_llm_messages(&user_id, thread_id.as_deref(), &req.channel, &system_prompt)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let mut user_message = serde_json::json!({ "role": "user", "content": req.message });
+    let images: Vec<&crate::channels::Attachment> = req.attachments.iter().filter(|a| a.is_image()).collect();
+    if images.len() < req.attachments.len() {
+        tracing::debug!("Ignoring {} non-image attachment(s)", req.attachments.len() - images.len());
+    }
+    if !images.is_empty() {
+        // The LLM executor turns these into provider image blocks (see `executor::attach_images`)
+        user_message["images"] = serde_json::json!(images);
+    }
+    messages.push(user_message);
 
     // Call LLM
     tracing::info!("Calling LLM...");
     
     // Convert harness tools to Claude format
-    let claude_tools: Vec<serde_json::Value> = state.harness_tools
+    let claude_tools: Vec<serde_json::Value> = chat_tools(&state)
         .iter()
         .map(|tool| {
             serde_json::json!({
@@ -1099,28 +3042,25 @@ async fn chat(
         "messages": messages,
         "tools": claude_tools
     });
-    let result = run_llm_with_timeout(Arc::clone(&state), input, "main")
+    milestones.push("Thinking");
+    let mut guard = TurnGuard::new(&state.config.chat, start);
+    let result = run_llm_with_timeout(Arc::clone(&state), input, "main", Some(&user_id))
         .await
         .map_err(|e| (StatusCode::GATEWAY_TIMEOUT, e))?;
+    guard.charge(state.budget.cost("llm.chat", &serde_json::Value::Null, &result));
 
     let content = extract_content(&result);
-    let tool_results = find_and_execute_tools(&state, &content, &result).await;
+    let (tool_results, dropped_tools) = find_and_execute_tools(&state, &content, &result, &mut milestones, Some(&user_id), &mut guard).await;
 
     // Get final response
     let final_content = if tool_results.is_empty() {
         content
+    } else if guard.may_continue() {
+        get_followup_response(&state, &messages, &content, &tool_results, &mut milestones, Some(&user_id)).await
     } else {
-        for result in &tool_results {
-            let _ = state
-                .conversation_manager
-                .add_tool_message(
-                    &user_id,
-                    &format!("[{} result]", result.tool),
-                    &req.channel,
-                )
-                .await;
-        }
-        get_followup_response(&state, &messages, &tool_results).await
+        // No follow-up call: the tool results go back in `tool_calls` as they are
+        milestones.push("Stopped early");
+        tool_call_turn(&content, &tool_results)
     };
 
     let final_content = if final_content.trim().is_empty() {
@@ -1128,11 +3068,35 @@ async fn chat(
     } else {
         final_content
     };
+    let final_content = if dropped_tools > 0 {
+        final_content + &dropped_tools_note(dropped_tools, state.config.chat.max_tools_per_turn)
+    } else {
+        final_content
+    };
+    let final_content = match guard.note() {
+        Some(note) => final_content + &note,
+        None => final_content,
+    };
 
-    let _ = state
-        .conversation_manager
-        .add_assistant_message(&user_id, &final_content, &req.channel, None)
-        .await;
+    let tool_records: Vec<conversation::ToolCall> = tool_results.iter().map(ToolCallResult::to_record).collect();
+    let mut turn = vec![store::NewMessage::new("user", req.message.as_str()).replying_to(req.reply_to.map(store::ReplyTo::Message))];
+    turn.extend(tool_results.iter().map(|result| store::NewMessage::new("tool", format!("[{} result]", result.tool))));
+    turn.push(
+        conversation::assistant_message(&final_content, (!tool_records.is_empty()).then_some(tool_records.as_slice()))
+            .replying_to(Some(store::ReplyTo::Batch(0))),
+    );
+    let stored_ids = match state.conversation_manager.add_turn(&user_id, thread_id.as_deref(), &req.channel, &turn).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("Could not store chat turn: {}", e);
+            Vec::new()
+        }
+    };
+    if state.config.conversation.auto_title {
+        if let Err(e) = state.conversation_manager.auto_title(&user_id, thread_id.as_deref(), &req.message).await {
+            tracing::warn!("Could not title conversation: {}", e);
+        }
+    }
 
     // Learning phase: reflect on the interaction
     if !tool_results.is_empty() {
@@ -1150,7 +3114,7 @@ async fn chat(
                     tool: r.tool.clone(),
                     input: r.input.clone(),
                     output: r.output.to_string(),
-                    success: true,
+                    success: r.succeeded(),
                     duration_ms: r.duration_ms,
                 })
                 .collect();
@@ -1172,32 +3136,44 @@ async fn chat(
         });
     }
 
+    milestones.push("Done");
     let duration_ms = start.elapsed().as_millis() as u64;
     tracing::info!("Chat done in {}ms ({} tools)", duration_ms, tool_results.len());
 
-    Ok(Json(ChatResponse {
-        response: final_content,
+    Ok(ChatResponse {
+        response: with_welcome(welcome, final_content),
         tool_calls: tool_results,
-        milestones,
+        milestones: milestones.items,
         duration_ms,
-    }))
+        request_id: None,
+        trace_id: trace::current(),
+        message_id: stored_ids.last().copied(),
+        reply_to: stored_ids.first().copied(),
+        stopped_early: guard.stopped.map(str::to_string),
+    })
 }
 
 #[derive(Deserialize)]
 struct HistoryQuery {
     #[serde(default)]
     user_id: Option<String>,
+    /// Limit history/clear to one thread (`"discord:<channel_id>"`); all threads when unset
+    #[serde(default)]
+    thread_id: Option<String>,
+    /// Channel type whose `memory.channel_limits` window applies to history
+    #[serde(default)]
+    channel: Option<String>,
+    /// `/chat/clear` also removes the user's pinned directives
+    #[serde(default)]
+    include_directives: bool,
 }
 
-async fn get_chat_history(
-    State(state): State<Arc<AppState>>,
-    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
-) -> Result<Json<Vec<conversation::ChatMessage>>, (StatusCode, String)> {
+/// Resolve a `user_id` query param, accepting either an internal id or `provider:provider_id`
+async fn resolve_query_user_id(state: &AppState, user_id: Option<String>) -> String {
     // For HTTP channel, use provided user_id or default to anonymous
-    let user_id = query.user_id.unwrap_or_else(|| "http:anonymous".to_string());
+    let user_id = user_id.unwrap_or_else(|| "http:anonymous".to_string());
     
-    // First, resolve the user_id if it looks like a provider:id format
-    let actual_user_id = if user_id.contains(':') {
+    if user_id.contains(':') {
         let parts: Vec<&str> = user_id.split(':').collect();
         if parts.len() == 2 {
             match state.identity_manager.resolve(parts[0], parts[1], None).await {
@@ -1209,10 +3185,17 @@ async fn get_chat_history(
         }
     } else {
         user_id.clone()
-    };
+    }
+}
+
+async fn get_chat_history(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
+) -> Result<Json<Vec<conversation::ChatMessage>>, (StatusCode, String)> {
+    let actual_user_id = resolve_query_user_id(&state, query.user_id).await;
     
     let history = state.conversation_manager
-        .get_history(&actual_user_id)
+        .get_history(&actual_user_id, query.thread_id.as_deref(), query.channel.as_deref())
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
@@ -1223,31 +3206,228 @@ async fn clear_chat(
     State(state): State<Arc<AppState>>,
     axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let user_id = query.user_id.unwrap_or_else(|| "http:anonymous".to_string());
-    
-    // Resolve user_id
-    let actual_user_id = if user_id.contains(':') {
-        let parts: Vec<&str> = user_id.split(':').collect();
-        if parts.len() == 2 {
-            match state.identity_manager.resolve(parts[0], parts[1], None).await {
-                Ok((uid, _)) => uid,
-                Err(_) => user_id.clone(),
-            }
-        } else {
-            user_id.clone()
-        }
-    } else {
-        user_id.clone()
-    };
+    let actual_user_id = resolve_query_user_id(&state, query.user_id).await;
     
     state.conversation_manager
-        .clear(&actual_user_id)
+        .clear(&actual_user_id, query.thread_id.as_deref(), query.include_directives)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
     Ok(Json(serde_json::json!({ "cleared": true, "user_id": actual_user_id })))
 }
 
+/// GET /chat/directive - A user's pinned directives, oldest first
+async fn list_directives(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
+) -> Result<Json<Vec<store::PinnedDirective>>, (StatusCode, String)> {
+    let actual_user_id = resolve_query_user_id(&state, query.user_id).await;
+    state
+        .conversation_manager
+        .get_directives(&actual_user_id)
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct DirectiveRequest {
+    #[serde(default)]
+    user_id: Option<String>,
+    /// Pin to one thread (`"discord:<channel_id>"`); every thread when unset
+    #[serde(default)]
+    thread_id: Option<String>,
+    /// Instruction added to every LLM turn for the user, e.g. "Always answer in French"
+    content: String,
+}
+
+/// POST /chat/directive - Pin a directive that survives `/chat/clear`
+async fn add_directive(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<DirectiveRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if req.content.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "content must not be empty".to_string()));
+    }
+    let actual_user_id = resolve_query_user_id(&state, req.user_id).await;
+    let id = state
+        .conversation_manager
+        .add_directive(&actual_user_id, req.thread_id.as_deref(), &req.content)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(serde_json::json!({ "id": id, "user_id": actual_user_id })))
+}
+
+#[derive(Deserialize)]
+struct DirectiveQuery {
+    #[serde(default)]
+    user_id: Option<String>,
+    id: i64,
+}
+
+/// DELETE /chat/directive - Remove one pinned directive by id
+async fn remove_directive(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<DirectiveQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let actual_user_id = resolve_query_user_id(&state, query.user_id).await;
+    let removed = state
+        .conversation_manager
+        .remove_directive(&actual_user_id, query.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if !removed {
+        return Err((StatusCode::NOT_FOUND, format!("No directive {} for this user", query.id)));
+    }
+    Ok(Json(serde_json::json!({ "removed": true, "id": query.id })))
+}
+
+/// GET /chat/meta - Title, tags and pin flag of a conversation (one thread with `thread_id`)
+async fn get_chat_meta(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
+) -> Result<Json<store::ConversationMeta>, (StatusCode, String)> {
+    let actual_user_id = resolve_query_user_id(&state, query.user_id).await;
+    state
+        .conversation_manager
+        .get_meta(&actual_user_id, query.thread_id.as_deref())
+        .await
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Fields left out are kept; an empty `title` clears it
+#[derive(Deserialize, JsonSchema)]
+struct ChatMetaUpdate {
+    #[serde(default)]
+    user_id: Option<String>,
+    #[serde(default)]
+    thread_id: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    #[serde(default)]
+    pinned: Option<bool>,
+}
+
+/// POST /chat/meta - Update a conversation's title, tags or pin flag
+async fn set_chat_meta(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ChatMetaUpdate>,
+) -> Result<Json<store::ConversationMeta>, (StatusCode, String)> {
+    let actual_user_id = resolve_query_user_id(&state, req.user_id).await;
+    let internal = |e: anyhow::Error| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    
+    let mut meta = state
+        .conversation_manager
+        .get_meta(&actual_user_id, req.thread_id.as_deref())
+        .await
+        .map_err(internal)?;
+    if let Some(title) = req.title {
+        let title = title.trim();
+        meta.title = (!title.is_empty()).then(|| title.to_string());
+    }
+    if let Some(tags) = req.tags {
+        let mut seen = std::collections::HashSet::new();
+        meta.tags = tags
+            .iter()
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty() && seen.insert(t.clone()))
+            .collect();
+    }
+    if let Some(pinned) = req.pinned {
+        meta.pinned = pinned;
+    }
+    
+    state.conversation_manager.set_meta(&meta).await.map_err(internal)?;
+    state
+        .conversation_manager
+        .get_meta(&actual_user_id, req.thread_id.as_deref())
+        .await
+        .map(Json)
+        .map_err(internal)
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ContextRequest {
+    user_id: Option<String>,
+    note: String,
+}
+
+/// POST /chat/context - Hidden system note for the user's next turn (e.g. "the user just connected Gmail")
+async fn add_chat_context(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ContextRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if req.note.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "note must not be empty".to_string()));
+    }
+    let actual_user_id = resolve_query_user_id(&state, req.user_id).await;
+    let queued = state.conversation_manager.add_context_note(&actual_user_id, &req.note);
+    
+    Ok(Json(serde_json::json!({ "queued": queued, "user_id": actual_user_id })))
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ConfirmRequest {
+    token: String,
+    /// Must be the user the call was held for (`provider:id` or a user id)
+    user_id: Option<String>,
+}
+
+/// Remove and return the held call for `token` if it belongs to `user_id`. Someone else's
+/// token is reported as unknown and stays usable by its owner.
+fn take_confirmation(
+    pending: &mut HashMap<String, PendingConfirmation>,
+    token: &str,
+    user_id: &str,
+) -> Result<PendingConfirmation, (StatusCode, String)> {
+    let unknown = || (StatusCode::NOT_FOUND, "Unknown or already used confirmation token".to_string());
+    let owned = pending.get(token).ok_or_else(unknown)?.user_id.as_deref().is_none_or(|owner| owner == user_id);
+    if !owned {
+        return Err(unknown());
+    }
+    let confirmation = pending.remove(token).ok_or_else(unknown)?;
+    if confirmation.expires_at <= std::time::Instant::now() {
+        return Err((StatusCode::GONE, "Confirmation token expired".to_string()));
+    }
+    Ok(confirmation)
+}
+
+/// POST /chat/confirm - Run a tool call that was held for confirmation
+async fn confirm_tool_call(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ConfirmRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let actual_user_id = resolve_query_user_id(&state, req.user_id).await;
+    let pending = take_confirmation(&mut state.pending_confirmations.lock().unwrap(), &req.token, &actual_user_id)?;
+    
+    tracing::info!("Confirmed tool '{}'", pending.tool);
+    let result = run_tool(&state, &pending.tool, pending.input, None, pending.user_id.as_deref())
+        .await
+        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, format!("Tool '{}' could not be executed", pending.tool)))?;
+    
+    Ok(Json(serde_json::json!({ "tool_call": result })))
+}
+
+/// GET /export?user_id= - Portable JSON dump of a user's data
+async fn export_user(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<HistoryQuery>,
+) -> Result<Json<export::UserExport>, (StatusCode, String)> {
+    let actual_user_id = resolve_query_user_id(&state, query.user_id).await;
+    
+    match export::export_user(state.store.as_ref(), &actual_user_id).await {
+        Ok(Some(export)) => Ok(Json(export)),
+        Ok(None) => Err((StatusCode::NOT_FOUND, format!("User not found: {}", actual_user_id))),
+        Err(e) => {
+            tracing::error!(user_id = %actual_user_id, "Export failed: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, format!("Export failed: {}", e)))
+        }
+    }
+}
+
 // ============================================
 // Integration Endpoints
 // ============================================
@@ -1371,7 +3551,7 @@ async fn gmail_accounts(
     }))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct SendTestEmailRequest {
     from_email: String,
 }
@@ -1422,7 +3602,7 @@ async fn send_test_email(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct SendEmailRequest {
     from_email: String,
     to: String,
@@ -1474,7 +3654,7 @@ async fn send_email(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 struct DisconnectGmailRequest {
     email: String,
 }
@@ -1518,6 +3698,65 @@ async fn disconnect_gmail(
     }
 }
 
+#[derive(Deserialize, JsonSchema)]
+struct SaveOAuthTokenRequest {
+    user_id: String,
+    /// `google`, `slack` or `github`
+    provider: String,
+    access_token: String,
+    /// Lets the node refresh the token itself when it expires or is rejected
+    refresh_token: Option<String>,
+    /// Lifetime of `access_token`, as in the provider's token response
+    expires_in_secs: Option<i64>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct SaveOAuthTokenResponse {
+    user_id: String,
+    provider: String,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    refreshable: bool,
+}
+
+/// POST /oauth/tokens - Store a user's token so `http.request` can use it via `oauth` (admin token required)
+async fn save_oauth_token(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<SaveOAuthTokenRequest>,
+) -> Result<Json<SaveOAuthTokenResponse>, (StatusCode, String)> {
+    require_admin(&state, &headers)?;
+    
+    let provider = req.provider.trim().to_lowercase();
+    if oauth_config::OAuthProvider::parse(&provider).is_none() {
+        return Err((StatusCode::BAD_REQUEST, format!("Unknown OAuth provider '{}' (expected google, slack or github)", req.provider)));
+    }
+    if req.access_token.trim().is_empty() || req.user_id.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "user_id and access_token must not be empty".to_string()));
+    }
+    let now = chrono::Utc::now();
+    let token = store::OAuthToken {
+        user_id: req.user_id.trim().to_string(),
+        provider,
+        access_token: req.access_token.trim().to_string(),
+        refresh_token: req.refresh_token.filter(|t| !t.trim().is_empty()),
+        expires_at: req.expires_in_secs.map(|secs| now + chrono::Duration::seconds(secs)),
+        updated_at: now,
+    };
+    state
+        .store
+        .set_oauth_token(&token)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to save token: {}", e)))?;
+    tracing::info!(user_id = %token.user_id, provider = %token.provider, "OAuth token stored");
+    
+    Ok(Json(SaveOAuthTokenResponse {
+        refreshable: token.refresh_token.is_some(),
+        user_id: token.user_id,
+        provider: token.provider,
+        expires_at: token.expires_at,
+    }))
+}
+
 /// Proxy to harness /gmail/senders page
 async fn gmail_senders_proxy(
     Query(params): Query<std::collections::HashMap<String, String>>,
@@ -1646,3 +3885,182 @@ async fn oauth_callback_proxy(
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_calls_capped_per_turn() {
+        let block = |n: usize| format!("```tool\n{{\"tool\": \"tool_{}\", \"input\": {{}}}}\n```\n", n);
+        let content: String = (1..=4).map(block).collect();
+        let llm_result = executor::ExecutorResult::Executed { output: serde_json::json!({}), duration_ms: 1 };
+
+        let plan = plan_tool_calls(&content, &llm_result, 3);
+        let tools: Vec<&str> = plan.calls.iter().map(|(tool, _)| tool.as_str()).collect();
+        assert_eq!(tools, vec!["tool_1", "tool_2", "tool_3"]);
+        assert_eq!(plan.dropped, 1);
+
+        let unlimited = plan_tool_calls(&content, &llm_result, 0);
+        assert_eq!((unlimited.calls.len(), unlimited.dropped), (4, 0));
+    }
+
+    #[test]
+    fn test_max_tools_per_turn_read_from_chat() {
+        let chat: config::ChatConfig = serde_yaml::from_str("max_tools_per_turn: 2").unwrap();
+        assert_eq!(chat.max_tools_per_turn, 2);
+        assert_eq!(config::ChatConfig::default().max_tools_per_turn, 5);
+        let unset: config::ChatConfig = serde_yaml::from_str("max_turn_secs: 60").unwrap();
+        assert_eq!(unset.max_tools_per_turn, 5);
+    }
+
+    #[test]
+    fn test_idempotency_keys_scoped_to_workflow() {
+        assert_eq!(idempotent_run_id("check-email", "morning"), idempotent_run_id("check-email", "morning"));
+        assert_ne!(idempotent_run_id("check-email", "morning"), idempotent_run_id("send-report", "morning"));
+        assert_ne!(idempotent_run_id("a", "b\0c"), idempotent_run_id("a\0b", "c"));
+    }
+    
+    #[test]
+    fn test_confirmation_only_by_its_user() {
+        let held = |user_id: &str, ttl_secs| PendingConfirmation {
+            tool: "gmail.send".to_string(),
+            input: serde_json::json!({}),
+            user_id: Some(user_id.to_string()),
+            expires_at: std::time::Instant::now() + std::time::Duration::from_secs(ttl_secs),
+        };
+        let mut pending = HashMap::from([("t1".to_string(), held("alice", 60)), ("t2".to_string(), held("alice", 0))]);
+        
+        let stolen = take_confirmation(&mut pending, "t1", "mallory").unwrap_err();
+        assert_eq!(stolen.0, StatusCode::NOT_FOUND);
+        assert!(pending.contains_key("t1"), "someone else's attempt must not use up the token");
+        assert_eq!(take_confirmation(&mut pending, "t1", "alice").unwrap().tool, "gmail.send");
+        assert_eq!(take_confirmation(&mut pending, "t1", "alice").unwrap_err().0, StatusCode::NOT_FOUND);
+        assert_eq!(take_confirmation(&mut pending, "t2", "alice").unwrap_err().0, StatusCode::GONE);
+    }
+    
+    #[test]
+    fn test_admin_token_comparison() {
+        assert!(tokens_match("s3cret-token", "s3cret-token"));
+        assert!(!tokens_match("s3cret-tokem", "s3cret-token"));
+        assert!(!tokens_match("s3cret", "s3cret-token"));
+        assert!(!tokens_match("", "s3cret-token"));
+    }
+    
+    #[test]
+    fn test_tools_query_filters_and_pages() {
+        let query = |q: &str| Query::<ToolsQuery>::try_from_uri(&format!("/tools?{}", q).parse().unwrap()).unwrap().0;
+        let net = vec!["network".to_string()];
+        assert!(query("q=HTTP").matches("http.request", "Make HTTP requests", &net, None));
+        assert!(query("q=leads").matches("harness.find", "Find leads near a city", &[], Some("basic")));
+        assert!(!query("q=leads&tier=premium").matches("harness.find", "Find leads near a city", &[], Some("basic")));
+        assert!(query("permission=network&q=").matches("http.request", "", &net, None));
+        assert!(!query("permission=filesystem").matches("http.request", "", &net, None));
+        assert_eq!(query("offset=1&limit=2").page(1..=5), vec![2, 3]);
+        assert_eq!(query("").page(1..=3), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_turn_guard_stops_at_first_limit() {
+        let limits = config::ChatConfig { max_turn_secs: Some(60), max_turn_usd: Some(0.10), ..Default::default() };
+        let mut guard = TurnGuard::new(&limits, std::time::Instant::now());
+        guard.charge(0.06);
+        assert!(guard.may_continue());
+        assert_eq!(guard.note(), None);
+        guard.charge(0.06);
+        assert!(!guard.may_continue());
+        assert_eq!(guard.stopped, Some("chat.max_turn_usd"));
+        assert!(guard.note().unwrap().contains("cost limit of $0.10"));
+
+        let started = std::time::Instant::now() - std::time::Duration::from_secs(61);
+        let mut slow = TurnGuard::new(&limits, started);
+        assert!(!slow.may_continue());
+        assert_eq!(slow.stopped, Some("chat.max_turn_secs"));
+        assert!(TurnGuard::new(&config::ChatConfig::default(), started).may_continue());
+    }
+
+    #[test]
+    fn test_unknown_tool_reported_only_in_strict_mode() {
+        let result = unknown_tool_result("web.serch", serde_json::json!({ "q": "x" }), true).expect("strict mode reports it");
+        assert_eq!(result.output["error"], "unknown tool: web.serch");
+        assert!(!result.succeeded());
+        assert!(unknown_tool_result("web.serch", serde_json::json!({}), false).is_none());
+    }
+
+    #[test]
+    fn test_node_tools_follow_disabled_and_chat_allowlist() {
+        let security = |yaml: &str| -> config::SecurityConfig { serde_yaml::from_str(yaml).unwrap() };
+        let executors = |yaml: &str| -> config::ExecutorsConfig { serde_yaml::from_str(yaml).unwrap() };
+        let open = security("{ mode: strict, allowed_executors: ['*'] }");
+        assert!(node_tool_blocked(&executors("{}"), &open, CONVERSATION_RECALL_TOOL).is_none());
+        
+        match node_tool_blocked(&executors("disabled: [conversation.recall]"), &open, CONVERSATION_RECALL_TOOL) {
+            Some(executor::ExecutorResult::Error { error, code }) => {
+                assert_eq!(code, executor::ExecutorError::Denied);
+                assert!(error.starts_with("executor disabled: conversation.recall"));
+            }
+            other => panic!("expected disabled, got {:?}", other),
+        }
+        
+        let chat_only_http = security("{ mode: strict, allowed_executors: ['*'], chat_allowed_executors: [http.request] }");
+        match node_tool_blocked(&executors("{}"), &chat_only_http, CONVERSATION_STATS_TOOL) {
+            Some(executor::ExecutorResult::Denied { denial_reason }) => {
+                assert_eq!(denial_reason.rule, "security.chat_allowed_executors");
+                assert_eq!(denial_reason.attempted, CONVERSATION_STATS_TOOL);
+            }
+            other => panic!("expected denied, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_listen_addr_defaults_to_loopback() {
+        let config = config::DaemonConfig::default();
+        assert_eq!(listen_addr(None, &config, 8787).unwrap().to_string(), "127.0.0.1:8787");
+        assert_eq!(listen_addr(Some("0.0.0.0"), &config, 8787).unwrap().to_string(), "0.0.0.0:8787");
+        assert_eq!(listen_addr(Some("::1"), &config, 8787).unwrap().to_string(), "[::1]:8787");
+        assert!(listen_addr(Some("localhost:80"), &config, 8787).is_err());
+    }
+
+    #[test]
+    fn test_port_flag_wins_over_config() {
+        let mut http = config::HttpChannelConfig::default();
+        assert_eq!(daemon_port(None, &http), (8787, "channels.http.port"));
+        http.port = 8800;
+        assert_eq!(daemon_port(None, &http), (8800, "channels.http.port"));
+        assert_eq!(daemon_port(Some(9100), &http), (9100, "--port"));
+        http.port = crate::ports::HARNESS_PORT;
+        assert_eq!(daemon_port(None, &http), (8787, "default"));
+    }
+
+    #[tokio::test]
+    async fn test_cors_allows_only_configured_origins() {
+        use tower::Service;
+
+        async fn allowed_origin(layer: CorsLayer, origin: &str) -> Option<String> {
+            let mut app = Router::new().route("/health", get(|| async { "ok" })).layer(layer);
+            let req = axum::http::Request::get("/health").header("origin", origin).body(axum::body::Body::empty()).unwrap();
+            let resp = app.call(req).await.unwrap();
+            resp.headers().get("access-control-allow-origin").map(|v| v.to_str().unwrap().to_string())
+        }
+
+        assert_eq!(allowed_origin(cors_layer(&[]), "http://localhost:3000").await.as_deref(), Some("http://localhost:3000"));
+        assert_eq!(allowed_origin(cors_layer(&[]), "https://evil.example").await, None);
+
+        let configured = vec!["https://app.example.com/".to_string()];
+        assert!(allowed_origin(cors_layer(&configured), "https://app.example.com").await.is_some());
+        assert_eq!(allowed_origin(cors_layer(&configured), "http://localhost:3000").await, None);
+
+        assert_eq!(allowed_origin(cors_layer(&["*".to_string()]), "https://evil.example").await.as_deref(), Some("*"));
+    }
+
+    #[tokio::test]
+    async fn test_harness_with_no_tools_is_not_a_failure() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let harness_url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Router::new().route("/tools", get(|| async { Json(serde_json::json!({ "tools": [] })) }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let tools = fetch_harness_tools(&harness_url, &["*".to_string()]).await.unwrap();
+        assert!(tools.is_empty());
+        assert!(fetch_harness_tools("http://127.0.0.1:9", &[]).await.is_err());
+    }
+}
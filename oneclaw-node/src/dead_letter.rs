@@ -0,0 +1,228 @@
+//! Dead Letters
+//!
+//! A channel reply that fails to send (Discord 5xx, rate limit, network blip) is saved in
+//! the store rather than dropped, and a background task retries it with backoff until it
+//! is delivered or `dead_letters.max_retries` is used up. `GET /dead-letters` lists them.
+
+use crate::channels::{Channel, ChannelType, OutgoingMessage, SentMessage};
+use crate::config::DeadLettersConfig;
+use crate::store::{DeadLetter, Store};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+pub const PENDING: &str = "pending";
+pub const DELIVERED: &str = "delivered";
+pub const FAILED: &str = "failed";
+
+/// Cap on the doubling backoff
+const MAX_BACKOFF_SECS: u64 = 3600;
+
+/// Messages looked at per retry pass
+const RETRY_BATCH: usize = 50;
+
+pub struct DeadLetters {
+    store: Arc<dyn Store>,
+    config: DeadLettersConfig,
+    channels: RwLock<HashMap<ChannelType, Arc<dyn Channel>>>,
+}
+
+impl DeadLetters {
+    pub fn new(store: Arc<dyn Store>, config: DeadLettersConfig) -> Self {
+        Self { store, config, channels: RwLock::new(HashMap::new()) }
+    }
+
+    /// Let the retrier deliver through `channel`. Messages for channels that aren't
+    /// registered (e.g. disabled since) stay pending, rescheduled without using up attempts.
+    pub fn register(&self, channel: Arc<dyn Channel>) {
+        self.channels.write().unwrap().insert(channel.channel_type(), channel);
+    }
+
+    /// Send through `channel`, queueing the message for retry if that fails
+    pub async fn send(&self, channel: &dyn Channel, msg: OutgoingMessage) -> Option<SentMessage> {
+        match channel.send(msg.clone()).await {
+            Ok(sent) => Some(sent),
+            Err(e) => {
+                self.enqueue(msg, &e.to_string()).await;
+                None
+            }
+        }
+    }
+
+    /// Queue a message whose first send failed with `error`
+    pub async fn enqueue(&self, msg: OutgoingMessage, error: &str) {
+        let now = Utc::now();
+        let letter = DeadLetter {
+            id: nanoid::nanoid!(),
+            message: msg,
+            error: error.to_string(),
+            attempts: 1,
+            status: PENDING.to_string(),
+            next_attempt_at: now + backoff(&self.config, 1),
+            created_at: now,
+            updated_at: now,
+        };
+        tracing::warn!(channel = %letter.message.channel_type, id = %letter.id, "Channel send failed, queued for retry: {}", error);
+        if let Err(e) = self.store.save_dead_letter(&letter).await {
+            tracing::error!("Failed to save dead letter, reply is lost: {}", e);
+        }
+    }
+
+    /// Retry due messages every `dead_letters.poll_secs`
+    pub fn start(self: Arc<Self>) {
+        let poll = std::time::Duration::from_secs(self.config.poll_secs.max(1));
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(poll).await;
+                if let Err(e) = self.retry_due(Utc::now()).await {
+                    tracing::warn!("Dead letter retry pass failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// One pass over pending messages due by `now`. After a failure the rest of that
+    /// channel's messages wait for the next pass, so a channel that is down or rate
+    /// limiting isn't sent a burst. Messages that can't be sent now are moved back, so
+    /// they don't fill every batch ahead of other channels' messages.
+    async fn retry_due(&self, now: DateTime<Utc>) -> anyhow::Result<()> {
+        let due = self.store.list_dead_letters(Some(PENDING), RETRY_BATCH).await?;
+        let mut failing = HashSet::new();
+        for mut letter in due.into_iter().take_while(|l| l.next_attempt_at <= now) {
+            let channel_type = letter.message.channel_type;
+            let channel = self.channels.read().unwrap().get(&channel_type).cloned();
+            let Some(channel) = channel.filter(|_| !failing.contains(&channel_type)) else {
+                let wait = if failing.contains(&channel_type) {
+                    chrono::Duration::seconds(self.config.poll_secs.max(1) as i64)
+                } else {
+                    backoff(&self.config, letter.attempts)
+                };
+                letter.next_attempt_at = now + wait;
+                letter.updated_at = Utc::now();
+                self.store.save_dead_letter(&letter).await?;
+                continue;
+            };
+
+            letter.attempts += 1;
+            letter.updated_at = Utc::now();
+            match channel.send(letter.message.clone()).await {
+                Ok(_) => {
+                    letter.status = DELIVERED.to_string();
+                    tracing::info!(channel = %channel_type, id = %letter.id, attempts = letter.attempts, "Delivered dead letter");
+                }
+                Err(e) => {
+                    failing.insert(channel_type);
+                    letter.error = e.to_string();
+                    if letter.attempts > self.config.max_retries {
+                        letter.status = FAILED.to_string();
+                        tracing::error!(channel = %channel_type, id = %letter.id, "Giving up on reply after {} attempts: {}", letter.attempts, e);
+                    } else {
+                        letter.next_attempt_at = letter.updated_at + backoff(&self.config, letter.attempts);
+                    }
+                }
+            }
+            self.store.save_dead_letter(&letter).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Wait before the retry that follows attempt number `attempts`
+fn backoff(config: &DeadLettersConfig, attempts: u32) -> chrono::Duration {
+    let secs = config
+        .retry_backoff_secs
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF_SECS);
+    chrono::Duration::seconds(secs as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails the next `failures` sends
+    struct FlakyChannel {
+        failures: AtomicU32,
+        sent: AtomicU32,
+    }
+
+    #[async_trait::async_trait]
+    impl Channel for FlakyChannel {
+        fn channel_type(&self) -> ChannelType {
+            ChannelType::Telegram
+        }
+
+        async fn start(&self, _tx: tokio::sync::mpsc::Sender<crate::channels::IncomingMessage>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn send(&self, _msg: OutgoingMessage) -> anyhow::Result<SentMessage> {
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                anyhow::bail!("Telegram API error: 502 Bad Gateway");
+            }
+            let id = self.sent.fetch_add(1, Ordering::SeqCst);
+            Ok(SentMessage { channel_type: ChannelType::Telegram, message_id: id.to_string() })
+        }
+
+        async fn stop(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_sends_are_retried_then_given_up() {
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        let config = DeadLettersConfig { max_retries: 1, retry_backoff_secs: 30, poll_secs: 15 };
+        let dead_letters = DeadLetters::new(Arc::clone(&store), config.clone());
+        let channel = Arc::new(FlakyChannel { failures: AtomicU32::new(1), sent: AtomicU32::new(0) });
+        dead_letters.register(channel.clone());
+        let msg = |content: &str| OutgoingMessage {
+            channel_type: ChannelType::Telegram,
+            channel_id: "42".to_string(),
+            content: content.to_string(),
+            reply_to: None,
+            metadata: serde_json::json!({}),
+        };
+
+        assert!(dead_letters.send(channel.as_ref(), msg("answer")).await.is_none());
+        let queued = store.list_dead_letters(Some(PENDING), 10).await.unwrap();
+        assert_eq!((queued.len(), queued[0].attempts), (1, 1));
+        assert!(queued[0].error.contains("502"));
+
+        // Not due yet, then due and the channel has recovered
+        dead_letters.retry_due(Utc::now()).await.unwrap();
+        assert_eq!(channel.sent.load(Ordering::SeqCst), 0);
+        dead_letters.retry_due(Utc::now() + chrono::Duration::minutes(1)).await.unwrap();
+        let delivered = store.list_dead_letters(Some(DELIVERED), 10).await.unwrap();
+        assert_eq!((delivered.len(), delivered[0].message.content.as_str(), delivered[0].attempts), (1, "answer", 2));
+
+        channel.failures.store(5, Ordering::SeqCst);
+        assert!(dead_letters.send(channel.as_ref(), msg("second")).await.is_none());
+        dead_letters.retry_due(Utc::now() + chrono::Duration::minutes(1)).await.unwrap();
+        let failed = store.list_dead_letters(Some(FAILED), 10).await.unwrap();
+        assert_eq!((failed.len(), failed[0].attempts), (1, 2));
+        assert_eq!(store.list_dead_letters(None, 10).await.unwrap().len(), 2);
+
+        // A full batch of replies for a channel that's no longer registered doesn't hold others back
+        channel.failures.store(0, Ordering::SeqCst);
+        for n in 0..RETRY_BATCH {
+            let mut stuck = msg(&format!("discord {}", n));
+            stuck.channel_type = ChannelType::Discord;
+            dead_letters.enqueue(stuck, "Discord API error: 503").await;
+        }
+        dead_letters.enqueue(msg("third"), "Telegram API error: 502").await;
+        dead_letters.retry_due(Utc::now() + chrono::Duration::minutes(1)).await.unwrap();
+        dead_letters.retry_due(Utc::now() + chrono::Duration::minutes(2)).await.unwrap();
+        let delivered = store.list_dead_letters(Some(DELIVERED), 10).await.unwrap();
+        assert!(delivered.iter().any(|l| l.message.content == "third"));
+        let stuck = store.list_dead_letters(Some(PENDING), RETRY_BATCH).await.unwrap();
+        assert!(stuck.iter().all(|l| l.message.channel_type == ChannelType::Discord && l.attempts == 1));
+
+        assert_eq!(backoff(&config, 1).num_seconds(), 30);
+        assert_eq!(backoff(&config, 3).num_seconds(), 120);
+        assert_eq!(backoff(&config, 20).num_seconds(), 3600);
+    }
+}
@@ -0,0 +1,116 @@
+//! Node Events
+//!
+//! Workflow runs and chat turns report what they do as typed events on one broadcast
+//! channel, so the `/events` SSE stream, metrics and logs can all follow the same
+//! activity without hooking into the code paths themselves. Events carry ids, names,
+//! statuses and timings, never inputs, outputs or message text.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Events buffered per subscriber; one that falls further behind skips the oldest
+const CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    StepStarted {
+        run_id: String,
+        step_id: String,
+        executor: String,
+    },
+    /// Sent for every step in the receipt, including skipped and denied ones
+    StepCompleted {
+        run_id: String,
+        step_id: String,
+        executor: String,
+        status: String,
+        duration_ms: u64,
+    },
+    RunCompleted {
+        run_id: String,
+        workflow_id: String,
+        status: String,
+        duration_ms: u64,
+    },
+    /// A chat tool call that ran (or was refused); calls held for confirmation are reported once confirmed
+    ToolCalled {
+        user_id: Option<String>,
+        tool: String,
+        success: bool,
+        duration_ms: u64,
+    },
+    LlmCall {
+        user_id: Option<String>,
+        /// Which call of the chat turn, e.g. `main` or `followup`
+        phase: String,
+        provider: String,
+        model: String,
+        /// `executed`, `error`, `denied` or `timeout`
+        status: String,
+        duration_ms: u64,
+    },
+}
+
+impl Event {
+    /// SSE event name, the same as the serialized `type`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::StepStarted { .. } => "step_started",
+            Event::StepCompleted { .. } => "step_completed",
+            Event::RunCompleted { .. } => "run_completed",
+            Event::ToolCalled { .. } => "tool_called",
+            Event::LlmCall { .. } => "llm_call",
+        }
+    }
+}
+
+pub struct Events {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self { sender: broadcast::channel(CAPACITY).0 }
+    }
+}
+
+impl Events {
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Publish to current subscribers; dropped when nobody is listening
+    pub fn emit(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Publish on the node's channel (`services::Services::events`)
+pub fn emit(event: Event) {
+    if let Some(services) = crate::services::installed() {
+        services.events.emit(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_every_subscriber_sees_each_event() {
+        let events = Events::default();
+        events.emit(Event::RunCompleted { run_id: "r0".into(), workflow_id: "w".into(), status: "success".into(), duration_ms: 1 });
+
+        let (mut ui, mut metrics) = (events.subscribe(), events.subscribe());
+        let started = Event::StepStarted { run_id: "r1".into(), step_id: "fetch".into(), executor: "http.request".into() };
+        events.emit(started.clone());
+        assert_eq!(ui.recv().await.unwrap(), started);
+        assert_eq!(metrics.recv().await.unwrap(), started);
+        assert!(ui.try_recv().is_err());
+
+        let json = serde_json::to_value(&started).unwrap();
+        assert_eq!(json["type"], started.name());
+        assert_eq!(json["step_id"], "fetch");
+    }
+}
@@ -1,8 +1,14 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::sync::OnceLock;
+use std::time::Duration;
+use crate::provider_response::{self, ProviderResponse};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExecutorManifest {
     pub id: String,
     pub version: String,
@@ -18,22 +24,127 @@ pub enum ExecutorResult {
     #[serde(rename = "denied")]
     Denied { denial_reason: DenialReason },
     #[serde(rename = "error")]
-    Error { error: String },
+    Error {
+        /// Human-readable message
+        error: String,
+        /// Machine-readable category for retry/routing decisions
+        #[serde(default)]
+        code: ExecutorError,
+    },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ExecutorResult {
+    pub fn error(code: ExecutorError, message: impl Into<String>) -> Self {
+        ExecutorResult::Error { error: message.into(), code }
+    }
+}
+
+/// Why an executor failed. Serialized as `"timeout"`, `"rate_limited"`, ...,
+/// or `{"upstream": {"status": 502}}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutorError {
+    Timeout,
+    Unauthorized,
+    RateLimited,
+    BadInput,
+    Upstream { status: u16 },
+    Network,
+    Denied,
+    /// Ran, but the output doesn't have the expected shape (output schema, JSON `response_format`)
+    UnexpectedOutput,
+    /// Local failure that fits no other category (e.g. misconfiguration)
+    #[default]
+    Internal,
+}
+
+impl ExecutorError {
+    /// Category for an HTTP error status from an upstream service
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ExecutorError::Unauthorized,
+            408 | 504 => ExecutorError::Timeout,
+            429 => ExecutorError::RateLimited,
+            _ => ExecutorError::Upstream { status },
+        }
+    }
+    
+    /// Category for a failed reqwest call
+    pub fn from_reqwest(e: &reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ExecutorError::Timeout
+        } else if let Some(status) = e.status() {
+            Self::from_status(status.as_u16())
+        } else if e.is_builder() {
+            ExecutorError::BadInput
+        } else {
+            ExecutorError::Network
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DenialReason {
     pub rule: String,
     pub attempted: String,
     pub policy: String,
 }
 
+/// Shared allowlist check for workflow steps, chat tool calls and the other executor lists
+/// (`executors.enabled`/`disabled`, `security.requires_confirmation`). Entries match an id exactly;
+/// `"*"` matches every executor, and there are no partial wildcards.
+pub fn is_executor_allowed(allowed: &[String], executor_id: &str) -> bool {
+    allowed.iter().any(|e| e == "*" || e == executor_id)
+}
+
+/// `domain` matches an `http.allowed_domains` entry: `"*"`, the exact host, or `*.suffix`
+pub fn is_domain_allowed(allowed: &[String], domain: &str) -> bool {
+    allowed.iter().any(|p| p == "*" || p == domain || (p.starts_with("*.") && domain.ends_with(&p[1..])))
+}
+
+/// At most this many schema mismatches are listed in an unexpected-output error
+const MAX_SCHEMA_ERRORS: usize = 5;
+
+/// Check `output` against a tool's output JSON Schema. A mismatch becomes an
+/// `UnexpectedOutput` error naming where the shape differs; other results pass through.
+pub fn check_output_schema(tool_id: &str, schema: &Value, result: ExecutorResult) -> ExecutorResult {
+    let ExecutorResult::Executed { output, .. } = &result else {
+        return result;
+    };
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            tracing::warn!("Ignoring invalid output schema for '{}': {}", tool_id, e);
+            return result;
+        }
+    };
+    let problems: Vec<String> = validator
+        .iter_errors(output)
+        .take(MAX_SCHEMA_ERRORS)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            format!("{}: {}", if path.is_empty() { "(root)" } else { &path }, e)
+        })
+        .collect();
+    if problems.is_empty() {
+        return result;
+    }
+    ExecutorResult::error(
+        ExecutorError::UnexpectedOutput,
+        format!("tool {} returned unexpected shape: {}", tool_id, problems.join("; ")),
+    )
+}
+
 pub struct Registry {
     executors: HashMap<String, Box<dyn Executor + Send + Sync>>,
+    /// Executors left out by `executors.enabled` / `executors.disabled`
+    disabled: HashSet<String>,
 }
 
 impl Registry {
-    pub fn load() -> anyhow::Result<Self> {
+    /// Built-ins listed in `executors.enabled` plus configured plugins, minus anything in
+    /// `executors.disabled`. Both lists accept `"*"`.
+    pub fn load(config: &crate::config::ExecutorsConfig) -> anyhow::Result<Self> {
         let harness_url = std::env::var("HARNESS_URL")
             .unwrap_or_else(|_| {
                 if cfg!(debug_assertions) {
@@ -43,29 +154,161 @@ impl Registry {
                 }
             });
         
+        let client = HttpClient::shared();
         let mut executors: HashMap<String, Box<dyn Executor + Send + Sync>> = HashMap::new();
-        executors.insert("http.request".to_string(), Box::new(HttpExecutor));
-        executors.insert("llm.chat".to_string(), Box::new(LlmExecutor));
-        executors.insert("google.gmail".to_string(), Box::new(GoogleGmailExecutor));
-        executors.insert("harness.execute".to_string(), Box::new(HarnessExecutor::new(harness_url)));
-        Ok(Self { executors })
+        executors.insert("http.request".to_string(), Box::new(HttpExecutor { client }));
+        executors.insert("http.graphql".to_string(), Box::new(GraphqlExecutor { http: HttpExecutor { client } }));
+        executors.insert("llm.chat".to_string(), Box::new(LlmExecutor { client }));
+        executors.insert("google.gmail".to_string(), Box::new(GoogleGmailExecutor { client }));
+        executors.insert("harness.job_status".to_string(), Box::new(HarnessJobExecutor { harness_url: harness_url.clone(), client, cancel: false }));
+        executors.insert("harness.cancel".to_string(), Box::new(HarnessJobExecutor { harness_url: harness_url.clone(), client, cancel: true }));
+        executors.insert("harness.execute".to_string(), Box::new(HarnessExecutor::new(harness_url, client)));
+        executors.insert("json.transform".to_string(), Box::new(JsonTransformExecutor));
+        executors.insert("workflow.call".to_string(), Box::new(WorkflowCallExecutor));
+        
+        let mut disabled: HashSet<String> = executors
+            .keys()
+            .filter(|id| !is_executor_allowed(&config.enabled, id))
+            .cloned()
+            .collect();
+        
+        // Plugins are opted into by configuring them, so only `disabled` applies
+        for plugin in &config.plugins {
+            if !plugin.id.starts_with("plugin.") {
+                tracing::warn!("Ignoring plugin '{}': plugin ids must start with 'plugin.'", plugin.id);
+                continue;
+            }
+            executors.insert(plugin.id.clone(), Box::new(ExternalExecutor { plugin: plugin.clone() }));
+        }
+        disabled.extend(executors.keys().filter(|id| is_executor_allowed(&config.disabled, id)).cloned());
+        executors.retain(|id, _| !disabled.contains(id));
+        
+        if !disabled.is_empty() {
+            let mut ids: Vec<&str> = disabled.iter().map(String::as_str).collect();
+            ids.sort_unstable();
+            tracing::info!("Executors disabled by config: {}", ids.join(", "));
+        }
+        if disabled.contains("llm.chat") {
+            tracing::warn!("llm.chat is not enabled (executors.enabled), so chat and other LLM features won't work");
+        }
+        Ok(Self { executors, disabled })
     }
 
     pub fn get(&self, id: &str) -> Option<&(dyn Executor + Send + Sync)> {
         self.executors.get(id).map(|e| e.as_ref())
     }
+    
+    /// Turned off in config, as opposed to not existing
+    pub fn is_disabled(&self, id: &str) -> bool {
+        self.disabled.contains(id)
+    }
+    
+    /// Result for a call to an executor this registry doesn't have
+    pub fn unavailable(&self, id: &str) -> ExecutorResult {
+        if self.is_disabled(id) {
+            ExecutorResult::error(ExecutorError::Denied, format!("executor disabled: {} (see executors.enabled / executors.disabled)", id))
+        } else {
+            ExecutorResult::error(ExecutorError::BadInput, format!("Executor not found: {}", id))
+        }
+    }
 
     pub fn list(&self) -> Vec<ExecutorManifest> {
         self.executors.values().map(|e| e.manifest()).collect()
     }
 }
 
+/// What the workflow engine tells an executor about the step it runs. It never comes from step
+/// input, which a user or the chat model may have written.
+#[derive(Debug, Clone)]
+pub struct StepContext {
+    pub run_id: String,
+    pub step_id: String,
+    /// `workflow.call` nesting of the run, 0 for a top-level run
+    pub depth: u32,
+    /// The step's `timeout`, in seconds
+    pub timeout_secs: Option<u64>,
+    /// Where the step writes the files it produces (see `receipt::write_artifact`)
+    pub artifacts_dir: Option<std::path::PathBuf>,
+}
+
+thread_local! {
+    static STEP_CONTEXT: RefCell<Option<StepContext>> = const { RefCell::new(None) };
+}
+
+/// Restores the previous step context, so nested runs (`workflow.call`) unwind correctly
+struct StepContextGuard(Option<StepContext>);
+
+impl Drop for StepContextGuard {
+    fn drop(&mut self) {
+        STEP_CONTEXT.with(|c| *c.borrow_mut() = self.0.take());
+    }
+}
+
+/// Call `f` (an executor call) with `context` visible to it through `step_context`
+pub fn with_step_context<T>(context: StepContext, f: impl FnOnce() -> T) -> T {
+    let _guard = StepContextGuard(STEP_CONTEXT.with(|c| c.replace(Some(context))));
+    f()
+}
+
+/// The workflow step being executed on this thread; `None` for chat tool calls
+pub fn step_context() -> Option<StepContext> {
+    STEP_CONTEXT.with(|c| c.borrow().clone())
+}
+
+/// Receives generated text as it arrives; `Break` stops the call early
+pub type OnDelta<'a> = &'a mut dyn FnMut(&str) -> ControlFlow<()>;
+
 pub trait Executor {
     fn manifest(&self) -> ExecutorManifest;
     fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult;
+
+    /// `execute`, passing output text to `on_delta` while it is generated. A call stopped
+    /// with `Break` returns what was generated so far. Executors that don't stream ignore
+    /// `on_delta`.
+    fn execute_streaming(&self, input: Value, config: &crate::config::NodeConfig, on_delta: OnDelta) -> ExecutorResult {
+        let _ = on_delta;
+        self.execute(input, config)
+    }
 }
 
-pub struct HttpExecutor;
+/// Connect timeout for requests made by the built-in executors
+const HTTP_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Blocking HTTP client shared by the built-in executors, so connections are reused
+/// across calls and registries. Built on first use: a blocking client can't be created
+/// on an async runtime thread, and executors run on blocking threads.
+#[derive(Clone, Copy)]
+pub struct HttpClient(&'static OnceLock<reqwest::blocking::Client>);
+
+impl HttpClient {
+    pub fn shared() -> Self {
+        static CLIENT: OnceLock<reqwest::blocking::Client> = OnceLock::new();
+        HttpClient(&CLIENT)
+    }
+
+    pub fn get(&self) -> &reqwest::blocking::Client {
+        self.0.get_or_init(|| {
+            reqwest::blocking::Client::builder()
+                .connect_timeout(Duration::from_secs(HTTP_CONNECT_TIMEOUT_SECS))
+                .build()
+                .unwrap_or_else(|_| reqwest::blocking::Client::new())
+        })
+    }
+}
+
+/// Per-request timeout: the workflow step's `timeout`, else `executors.timeouts[executor_id]`,
+/// else `default_secs`
+fn request_timeout(config: &crate::config::NodeConfig, executor_id: &str, default_secs: u64) -> Duration {
+    let secs = step_context()
+        .and_then(|step| step.timeout_secs)
+        .or_else(|| config.executors.timeouts.get(executor_id).copied())
+        .unwrap_or(default_secs);
+    Duration::from_secs(secs)
+}
+
+pub struct HttpExecutor {
+    pub client: HttpClient,
+}
 
 impl Executor for HttpExecutor {
     fn manifest(&self) -> ExecutorManifest {
@@ -79,17 +322,55 @@ impl Executor for HttpExecutor {
 
     fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult {
         let start = std::time::Instant::now();
-        let method = input["method"].as_str().unwrap_or("GET");
         let url = match input["url"].as_str() {
             Some(u) => u,
-            None => return ExecutorResult::Error { error: "url required".to_string() },
+            None => return ExecutorResult::error(ExecutorError::BadInput, "url required"),
         };
+        let method = match parse_http_method(input["method"].as_str().unwrap_or("GET")) {
+            Ok(m) => m,
+            Err(e) => return ExecutorResult::error(ExecutorError::BadInput, e),
+        };
+        // File name and the step's artifacts directory to save the body in
+        let save_as = match (input["save_as"].as_str(), step_context().and_then(|step| step.artifacts_dir)) {
+            (Some(name), Some(dir)) => Some((dir, name.to_string())),
+            (Some(_), None) => return ExecutorResult::error(ExecutorError::BadInput, "save_as is only available in workflow steps"),
+            (None, _) => None,
+        };
+        
+        if let Some(allowed) = &config.http.allowed_methods {
+            if !allowed.iter().any(|m| m.eq_ignore_ascii_case(method.as_str())) {
+                return ExecutorResult::Denied {
+                    denial_reason: DenialReason {
+                        rule: "http.allowed_methods".to_string(),
+                        attempted: method.to_string(),
+                        policy: format!("Method '{}' not allowed", method),
+                    },
+                };
+            }
+        }
+        
+        let headers: Vec<(&str, &str)> = input["headers"]
+            .as_object()
+            .map(|h| h.iter().filter_map(|(k, v)| Some((k.as_str(), v.as_str()?))).collect())
+            .unwrap_or_default();
+        if headers.len() > config.http.max_headers {
+            return ExecutorResult::error(
+                ExecutorError::BadInput,
+                format!("Too many headers: {} (max {})", headers.len(), config.http.max_headers),
+            );
+        }
+        let header_bytes: usize = headers.iter().map(|(k, v)| k.len() + v.len()).sum();
+        if header_bytes > config.http.max_header_bytes {
+            return ExecutorResult::error(
+                ExecutorError::BadInput,
+                format!("Headers too large: {} bytes (max {})", header_bytes, config.http.max_header_bytes),
+            );
+        }
 
         // Domain check
         if let Ok(parsed) = url::Url::parse(url) {
             let domain = parsed.host_str().unwrap_or("");
-            let allowed = config.http.allowed_domains.iter().any(|p| p == "*" || p == domain || (p.starts_with("*.") && domain.ends_with(&p[1..])));
-            if !allowed {
+            if !is_domain_allowed(&config.http.allowed_domains, domain) {
                 return ExecutorResult::Denied {
                     denial_reason: DenialReason {
                         rule: "http.allowed_domains".to_string(),
@@ -100,27 +381,69 @@ impl Executor for HttpExecutor {
             }
         }
 
-        let client = reqwest::blocking::Client::new();
-        let mut req = match method {
-            "POST" => client.post(url),
-            "PUT" => client.put(url),
-            "DELETE" => client.delete(url),
-            _ => client.get(url),
+        let oauth = match oauth_target(&input) {
+            Ok(oauth) => oauth,
+            Err(e) => return ExecutorResult::error(ExecutorError::BadInput, e),
+        };
+        if let Some((_, provider)) = &oauth {
+            if let Some(denial) = oauth_host_denial(provider, url) {
+                return ExecutorResult::Denied { denial_reason: denial };
+            }
+        }
+        let token = match &oauth {
+            Some((user_id, provider)) => match crate::oauth::bearer_blocking(user_id, provider, false) {
+                Ok(token) => Some(token),
+                Err(e) => return ExecutorResult::error(ExecutorError::Unauthorized, e.to_string()),
+            },
+            None => None,
         };
 
-        if let Some(headers) = input["headers"].as_object() {
-            for (k, v) in headers {
-                if let Some(val) = v.as_str() {
-                    req = req.header(k.as_str(), val);
+        let timeout = request_timeout(config, "http.request", config.executors.default_timeout_secs);
+        // With `oauth` the stored token replaces any Authorization header the caller sent
+        let send = |token: Option<&str>| {
+            let mut req = self.client.get().request(method.clone(), url).timeout(timeout);
+            for &(k, v) in &headers {
+                if token.is_none() || !k.eq_ignore_ascii_case("authorization") {
+                    req = req.header(k, v);
                 }
             }
-        }
-        if let Some(body) = input["body"].as_str() {
-            req = req.body(body.to_string());
-        }
+            if let Some(token) = token {
+                req = req.bearer_auth(token);
+            }
+            if let Some(body) = input["body"].as_str() {
+                req = req.body(body.to_string());
+            }
+            req.send()
+        };
 
-        match req.send() {
-            Ok(resp) => {
+        let result = send_with_refresh(token.as_deref(), send, || match &oauth {
+            Some((user_id, provider)) => crate::oauth::bearer_blocking(user_id, provider, true),
+            None => Err(anyhow::anyhow!("no OAuth token to refresh")),
+        });
+
+        match (result, save_as) {
+            // `save_as` keeps a successful body as a run artifact instead of inlining it in the
+            // output; an error response is inlined as usual
+            (Ok(resp), Some((dir, name))) if resp.status().is_success() => {
+                let status = resp.status().as_u16();
+                let content_type = resp
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|v| v.to_str().ok())
+                    .map(str::to_string);
+                let data = match resp.bytes() {
+                    Ok(data) => data,
+                    Err(e) => return ExecutorResult::error(ExecutorError::from_reqwest(&e), e.to_string()),
+                };
+                match crate::receipt::write_artifact(&dir, &name, &data, content_type.as_deref()) {
+                    Ok(artifact) => ExecutorResult::Executed {
+                        output: serde_json::json!({ "status": status, "artifacts": [artifact] }),
+                        duration_ms: start.elapsed().as_millis() as u64,
+                    },
+                    Err(e) => ExecutorResult::error(ExecutorError::BadInput, format!("Failed to save artifact: {}", e)),
+                }
+            }
+            (Ok(resp), _) => {
                 let status = resp.status().as_u16();
                 let body = resp.text().unwrap_or_default();
                 ExecutorResult::Executed {
@@ -128,16 +451,418 @@ impl Executor for HttpExecutor {
                     duration_ms: start.elapsed().as_millis() as u64,
                 }
             }
-            Err(e) => ExecutorResult::Error { error: e.to_string() },
+            (Err(e), _) => ExecutorResult::error(ExecutorError::from_reqwest(&e), e.to_string()),
+        }
+    }
+}
+
+/// GraphQL over `http.request`: `{ "url", "query", "variables", "headers" }` is POSTed
+/// as a standard `{ "query", "variables" }` JSON body, under the same domain, method and
+/// header policy. The output is the response's `data`; a response carrying `errors`
+/// (even with status 200) is an error result, so workflows don't mistake it for success.
+pub struct GraphqlExecutor {
+    pub http: HttpExecutor,
+}
+
+impl Executor for GraphqlExecutor {
+    fn manifest(&self) -> ExecutorManifest {
+        ExecutorManifest {
+            id: "http.graphql".to_string(),
+            version: "0.1.0".to_string(),
+            description: "GraphQL queries and mutations over HTTP".to_string(),
+            permissions: vec!["network".to_string()],
+        }
+    }
+
+    fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult {
+        let Some(query) = input["query"].as_str() else {
+            return ExecutorResult::error(ExecutorError::BadInput, "query required");
+        };
+        let mut headers = input["headers"].as_object().cloned().unwrap_or_default();
+        if !headers.keys().any(|k| k.eq_ignore_ascii_case("content-type")) {
+            headers.insert("Content-Type".to_string(), Value::from("application/json"));
+        }
+        if !headers.keys().any(|k| k.eq_ignore_ascii_case("accept")) {
+            headers.insert("Accept".to_string(), Value::from("application/json"));
+        }
+        let body = serde_json::json!({
+            "query": query,
+            "variables": input.get("variables").cloned().unwrap_or_else(|| serde_json::json!({})),
+        });
+        let timeout = request_timeout(config, "http.graphql", config.executors.default_timeout_secs);
+        let request = serde_json::json!({
+            "url": input["url"],
+            "method": "POST",
+            "headers": headers,
+            "body": body.to_string(),
+            "timeout_secs": timeout.as_secs(),
+            "oauth": input["oauth"],
+        });
+
+        match self.http.execute(request, config) {
+            ExecutorResult::Executed { output, duration_ms } => {
+                let status = output["status"].as_u64().unwrap_or_default() as u16;
+                match graphql_data(status, output["body"].as_str().unwrap_or_default()) {
+                    Ok(data) => ExecutorResult::Executed { output: data, duration_ms },
+                    Err((code, error)) => ExecutorResult::error(code, error),
+                }
+            }
+            other => other,
         }
     }
 }
 
+/// `data` from a GraphQL response, or the error for a failed status, a non-JSON body or
+/// a non-empty `errors` list
+fn graphql_data(status: u16, body: &str) -> Result<Value, (ExecutorError, String)> {
+    let parsed: Option<Value> = serde_json::from_str(body).ok();
+    let errors = parsed.as_ref().and_then(|p| p["errors"].as_array()).filter(|e| !e.is_empty());
+    if let Some(errors) = errors {
+        let messages: Vec<&str> = errors.iter().map(|e| e["message"].as_str().unwrap_or("unknown error")).collect();
+        let code = if (200..300).contains(&status) { ExecutorError::BadInput } else { ExecutorError::from_status(status) };
+        return Err((code, format!("GraphQL errors: {}", messages.join("; "))));
+    }
+    if !(200..300).contains(&status) {
+        return Err((ExecutorError::from_status(status), format!("GraphQL endpoint returned HTTP {}", status)));
+    }
+    match parsed {
+        Some(mut response) => Ok(response["data"].take()),
+        None => Err((ExecutorError::Upstream { status }, "GraphQL endpoint returned a non-JSON body".to_string())),
+    }
+}
+
+// ============================================
+// External Executor - subprocess plugins
+// ============================================
+
+/// Stderr kept for error messages (the tail, where the failure usually is)
+const PLUGIN_STDERR_TAIL_CHARS: usize = 2000;
+
+/// Runs `executors.plugins` entries. Per call the command is spawned, sent one JSON
+/// line `{ "input": ..., "config": { "node": ..., "settings": ... } }` on stdin
+/// (then stdin is closed), and must print one `ExecutorResult` JSON line on stdout,
+/// e.g. `{"status": "executed", "output": {...}, "duration_ms": 12}`.
+pub struct ExternalExecutor {
+    plugin: crate::config::PluginConfig,
+}
+
+impl Executor for ExternalExecutor {
+    fn manifest(&self) -> ExecutorManifest {
+        ExecutorManifest {
+            id: self.plugin.id.clone(),
+            version: "external".to_string(),
+            description: self.plugin.description.clone(),
+            permissions: self.plugin.permissions.clone(),
+        }
+    }
+
+    fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult {
+        use std::io::{Read, Write};
+        use std::process::{Command, Stdio};
+        
+        let start = std::time::Instant::now();
+        let request = serde_json::json!({
+            "input": input,
+            "config": { "node": config.node, "settings": self.plugin.settings },
+        });
+        
+        let mut child = match Command::new(&self.plugin.command)
+            .args(&self.plugin.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => return ExecutorResult::error(ExecutorError::Internal, format!("Failed to start plugin '{}': {}", self.plugin.id, e)),
+        };
+        
+        // Read both pipes on threads so a chatty plugin can't block on a full pipe. Results come
+        // back over channels: a leftover grandchild can hold a pipe open long after the plugin exits.
+        let read_pipe = |mut pipe: Box<dyn Read + Send>| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                let mut buf = String::new();
+                let _ = pipe.read_to_string(&mut buf);
+                let _ = tx.send(buf);
+            });
+            rx
+        };
+        let stdout_rx = read_pipe(Box::new(child.stdout.take().expect("piped stdout")));
+        let stderr_rx = read_pipe(Box::new(child.stderr.take().expect("piped stderr")));
+        
+        // Written on a thread too: a plugin that never reads a large input would otherwise block
+        // us before the deadline is enforced. Killing the plugin ends the write with a broken pipe.
+        if let Some(mut stdin) = child.stdin.take() {
+            std::thread::spawn(move || {
+                // A plugin that exits without reading its input is reported below via its output
+                let _ = writeln!(stdin, "{}", request);
+            });
+        }
+        
+        let deadline = start + std::time::Duration::from_secs(self.plugin.timeout_secs);
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if std::time::Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break None;
+                }
+                Ok(None) => std::thread::sleep(std::time::Duration::from_millis(10)),
+                Err(e) => return ExecutorResult::error(ExecutorError::Internal, format!("Plugin '{}' wait failed: {}", self.plugin.id, e)),
+            }
+        };
+        let grace = std::time::Duration::from_millis(500);
+        let stdout = stdout_rx.recv_timeout(grace).unwrap_or_default();
+        let stderr = stderr_rx.recv_timeout(grace).unwrap_or_default();
+        if !stderr.trim().is_empty() {
+            tracing::debug!(plugin = %self.plugin.id, "Plugin stderr: {}", stderr.trim());
+        }
+        let stderr_tail = || {
+            let chars: Vec<char> = stderr.trim().chars().collect();
+            chars[chars.len().saturating_sub(PLUGIN_STDERR_TAIL_CHARS)..].iter().collect::<String>()
+        };
+        
+        let Some(status) = status else {
+            return ExecutorResult::error(
+                ExecutorError::Timeout,
+                format!("Plugin '{}' timed out after {}s. stderr: {}", self.plugin.id, self.plugin.timeout_secs, stderr_tail()),
+            );
+        };
+        
+        match stdout.lines().find(|l| !l.trim().is_empty()).map(serde_json::from_str::<ExecutorResult>) {
+            Some(Ok(result)) => result,
+            Some(Err(e)) => ExecutorResult::error(
+                ExecutorError::Internal,
+                format!("Plugin '{}' returned an invalid response ({}). stderr: {}", self.plugin.id, e, stderr_tail()),
+            ),
+            None => ExecutorResult::error(
+                ExecutorError::Internal,
+                format!("Plugin '{}' exited ({}) without a response. stderr: {}", self.plugin.id, status, stderr_tail()),
+            ),
+        }
+    }
+}
+
+/// `"oauth": { "provider", "user_id" }` in an `http.request` input: authenticate as that
+/// user's connected integration instead of a caller-supplied Authorization header
+fn oauth_target(input: &Value) -> Result<Option<(String, String)>, String> {
+    let oauth = &input["oauth"];
+    if oauth.is_null() {
+        return Ok(None);
+    }
+    match (oauth["provider"].as_str(), oauth["user_id"].as_str()) {
+        (Some(provider), Some(user_id)) => Ok(Some((user_id.to_string(), provider.to_lowercase()))),
+        _ => Err("oauth needs a provider and a user_id".to_string()),
+    }
+}
+
+/// Denial for sending `provider`'s token anywhere but its own API hosts, whatever
+/// `http.allowed_domains` allows
+fn oauth_host_denial(provider: &str, url: &str) -> Option<DenialReason> {
+    let hosts = crate::oauth_config::OAuthProvider::parse(provider).map(|p| p.api_hosts()).unwrap_or_default();
+    let domain = url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_default();
+    let allowed: Vec<String> = hosts.iter().map(|h| h.to_string()).collect();
+    if is_domain_allowed(&allowed, &domain) {
+        return None;
+    }
+    Some(DenialReason {
+        rule: "oauth.api_hosts".to_string(),
+        attempted: domain.clone(),
+        policy: format!("{} tokens are only sent to {}", provider, hosts.join(", ")),
+    })
+}
+
+/// Send with the OAuth `token`; after a 401, get a fresh one from `refresh` and send once more
+fn send_with_refresh(
+    token: Option<&str>,
+    send: impl Fn(Option<&str>) -> reqwest::Result<reqwest::blocking::Response>,
+    refresh: impl FnOnce() -> anyhow::Result<String>,
+) -> reqwest::Result<reqwest::blocking::Response> {
+    let result = send(token);
+    let rejected = token.is_some() && result.as_ref().is_ok_and(|resp| resp.status() == reqwest::StatusCode::UNAUTHORIZED);
+    if !rejected {
+        return result;
+    }
+    match refresh() {
+        Ok(fresh) => send(Some(&fresh)),
+        Err(e) => {
+            tracing::warn!("Refreshing the OAuth token after a 401 failed: {}", e);
+            result
+        }
+    }
+}
+
+/// Methods `http.request` supports; anything else is an error rather than a silent GET
+fn parse_http_method(method: &str) -> Result<reqwest::Method, String> {
+    match method.trim().to_ascii_uppercase().as_str() {
+        "GET" => Ok(reqwest::Method::GET),
+        "POST" => Ok(reqwest::Method::POST),
+        "PUT" => Ok(reqwest::Method::PUT),
+        "PATCH" => Ok(reqwest::Method::PATCH),
+        "DELETE" => Ok(reqwest::Method::DELETE),
+        "HEAD" => Ok(reqwest::Method::HEAD),
+        "OPTIONS" => Ok(reqwest::Method::OPTIONS),
+        _ => Err(format!("Unsupported HTTP method '{}' (expected GET, POST, PUT, PATCH, DELETE, HEAD or OPTIONS)", method)),
+    }
+}
+
 // ============================================
 // LLM Executor - Chat with AI
 // ============================================
 
-pub struct LlmExecutor;
+/// Reshape a value with a JSONPath (RFC 9535) query, e.g. `$.items[*].id` or
+/// `$.items[?@.active == true].name`.
+///
+/// Input: `{ "input": <value>, "expr": "<jsonpath>", "first": false }`. A string
+/// `input` holding JSON (like an `http.request` body) is parsed first. The output
+/// is the array of matches, or the first match (`null` if none) when `first` is set.
+pub struct JsonTransformExecutor;
+
+impl Executor for JsonTransformExecutor {
+    fn manifest(&self) -> ExecutorManifest {
+        ExecutorManifest {
+            id: "json.transform".to_string(),
+            version: "0.1.0".to_string(),
+            description: "Select/reshape JSON with a JSONPath expression".to_string(),
+            permissions: vec![],
+        }
+    }
+
+    fn execute(&self, input: Value, _config: &crate::config::NodeConfig) -> ExecutorResult {
+        use jsonpath_rust::JsonPath;
+
+        let start = std::time::Instant::now();
+        let expr = match input["expr"].as_str() {
+            Some(e) => e,
+            None => return ExecutorResult::error(ExecutorError::BadInput, "expr required"),
+        };
+        let data = match &input["input"] {
+            Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| Value::String(s.clone())),
+            other => other.clone(),
+        };
+
+        let matches = match data.query(expr) {
+            Ok(m) => m,
+            Err(e) => return ExecutorResult::error(ExecutorError::BadInput, format!("Invalid JSONPath '{}': {}", expr, e)),
+        };
+        let output = if input["first"].as_bool().unwrap_or(false) {
+            matches.first().map(|v| (*v).clone()).unwrap_or(Value::Null)
+        } else {
+            Value::Array(matches.into_iter().cloned().collect())
+        };
+
+        ExecutorResult::Executed {
+            output,
+            duration_ms: start.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+/// Input: `{ "workflow_id": "...", "inputs": {...} }`. Runs the workflow and outputs its
+/// `outputs`; a run that doesn't end in `success` fails the step. Inside a workflow the
+/// step also gets `call` (the sub-run's ID and the caller's depth, see `workflow::MAX_CALL_DEPTH`),
+/// and the sub-run's receipt names the caller in `parent_run_id`.
+pub struct WorkflowCallExecutor;
+
+impl Executor for WorkflowCallExecutor {
+    fn manifest(&self) -> ExecutorManifest {
+        ExecutorManifest {
+            id: "workflow.call".to_string(),
+            version: "0.1.0".to_string(),
+            description: "Run another workflow as a single step".to_string(),
+            permissions: vec!["workflow".to_string()],
+        }
+    }
+
+    fn execute(&self, input: Value, _config: &crate::config::NodeConfig) -> ExecutorResult {
+        let start = std::time::Instant::now();
+        let Some(workflow_id) = input["workflow_id"].as_str() else {
+            return ExecutorResult::error(ExecutorError::BadInput, "workflow_id required");
+        };
+        let (run_id, call) = sub_run(step_context().as_ref());
+        if call.depth > crate::workflow::MAX_CALL_DEPTH {
+            return ExecutorResult::Denied {
+                denial_reason: DenialReason {
+                    rule: "workflow.max_call_depth".to_string(),
+                    attempted: workflow_id.to_string(),
+                    policy: format!("workflow.call can nest at most {} workflows deep", crate::workflow::MAX_CALL_DEPTH),
+                },
+            };
+        }
+        let inputs = match &input["inputs"] {
+            Value::Null => serde_json::json!({}),
+            other => other.clone(),
+        };
+        
+        // Executors run synchronously, so the sub-run is driven on the node's runtime
+        let Some(handle) = tokio::runtime::Handle::try_current()
+            .ok()
+            .filter(|h| h.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread)
+        else {
+            return ExecutorResult::error(ExecutorError::Internal, "workflow.call needs the node's multi-threaded runtime");
+        };
+        let result = tokio::task::block_in_place(|| {
+            handle.block_on(crate::workflow::run_call(run_id.clone(), workflow_id, inputs, call))
+        });
+        match result {
+            Ok(receipt) if receipt.status == "success" => ExecutorResult::Executed {
+                output: receipt.outputs,
+                duration_ms: start.elapsed().as_millis() as u64,
+            },
+            Ok(receipt) => ExecutorResult::error(
+                ExecutorError::Internal,
+                format!("Workflow {} finished {} (run {})", workflow_id, receipt.status, receipt.run_id),
+            ),
+            Err(e) => {
+                let code = if e.is::<crate::workflow::InputValidationError>() { ExecutorError::BadInput } else { ExecutorError::Internal };
+                ExecutorResult::error(code, format!("Workflow {} failed (run {}): {:#}", workflow_id, run_id, e))
+            }
+        }
+    }
+}
+
+/// Run ID and call info for a workflow started by `workflow.call`. Within a workflow it's derived
+/// from the calling step, so the step's run finds it; a chat tool call starts a top-level run.
+fn sub_run(context: Option<&StepContext>) -> (String, crate::workflow::Call) {
+    match context {
+        Some(step) => (
+            format!("{}-{}", step.run_id, step.step_id),
+            crate::workflow::Call { parent_run_id: step.run_id.clone(), depth: step.depth + 1 },
+        ),
+        None => (nanoid::nanoid!(), crate::workflow::Call { parent_run_id: String::new(), depth: 1 }),
+    }
+}
+
+/// Backoff before retry `attempt` (1-based): the provider's `Retry-After` if
+/// given, otherwise full jitter over `retry_base_ms * 2^(attempt-1)`; both capped
+/// at `retry_max_ms` so concurrent callers don't retry in lockstep.
+fn retry_delay(attempt: u32, llm: &crate::config::LlmConfig, retry_after: Option<std::time::Duration>) -> std::time::Duration {
+    use rand::Rng;
+    
+    let cap = std::time::Duration::from_millis(llm.retry_max_ms);
+    if let Some(wait) = retry_after {
+        return wait.min(cap);
+    }
+    let exp = llm.retry_base_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(20));
+    let ceiling = exp.min(llm.retry_max_ms);
+    std::time::Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling))
+}
+
+/// `Retry-After` as delay-seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<std::time::Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(std::time::Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+}
+
+pub struct LlmExecutor {
+    pub client: HttpClient,
+}
 
 fn extract_text_from_value(value: &Value) -> String {
     match value {
@@ -193,11 +918,21 @@ fn extract_text_from_value(value: &Value) -> String {
     }
 }
 
+/// Keys whose subtrees never hold a user-facing answer: error objects, model reasoning, metadata
+const DEEP_SCAN_SKIP_KEYS: &[&str] = &[
+    "error", "reasoning", "reasoning_content", "thinking", "id", "model", "object", "type", "role",
+    "finish_reason", "stop_reason", "system_fingerprint", "usage",
+];
+
+/// Upper bound on what the deep-scan fallback may return
+const DEEP_SCAN_MAX_CHARS: usize = 4000;
+
 fn collect_string_values(value: &Value, out: &mut Vec<String>) {
     match value {
         Value::String(s) => {
             let trimmed = s.trim();
-            if !trimmed.is_empty() {
+            // Prose has whitespace; tokens, keys, ids and URLs don't
+            if trimmed.len() >= 8 && trimmed.contains(char::is_whitespace) {
                 out.push(trimmed.to_string());
             }
         }
@@ -207,14 +942,107 @@ fn collect_string_values(value: &Value, out: &mut Vec<String>) {
             }
         }
         Value::Object(map) => {
-            for (_k, v) in map {
-                collect_string_values(v, out);
+            for (k, v) in map {
+                if !DEEP_SCAN_SKIP_KEYS.contains(&k.as_str()) {
+                    collect_string_values(v, out);
+                }
             }
         }
         _ => {}
     }
 }
 
+/// Shown instead of an answer when the provider returned nothing usable
+const EMPTY_COMPLETION_MESSAGE: &str = "I wasn't able to produce a response to that. Please try rephrasing your request.";
+const FILTERED_COMPLETION_MESSAGE: &str = "The model provider declined to answer this request (content filter). Please try rephrasing it.";
+
+/// Detects completions that were blocked by the provider rather than answered.
+/// Returns the reason and the provider's refusal text, if it sent one.
+fn blocked_completion(response: &ProviderResponse) -> Option<(&'static str, Option<String>)> {
+    if let Some(refusal) = &response.refusal {
+        return Some(("refusal", Some(refusal.trim().to_string())));
+    }
+    match response.finish_reason.as_deref() {
+        Some("content_filter") => Some(("content_filter", None)),
+        // Anthropic
+        Some("refusal") => Some(("refusal", None)),
+        _ => None,
+    }
+}
+
+/// Native tool calls legitimately come back with no text content
+fn has_native_tool_calls(parsed: &Value) -> bool {
+    let anthropic = parsed["content"]
+        .as_array()
+        .is_some_and(|blocks| blocks.iter().any(|b| b["type"].as_str() == Some("tool_use")));
+    let openai = parsed["choices"][0]["message"]["tool_calls"]
+        .as_array()
+        .is_some_and(|calls| !calls.is_empty());
+    anthropic || openai
+}
+
+/// Typed parse for known providers; the heuristic scan only for shapes they don't recognize
+fn parse_response(parsed: &Value, provider: &str) -> ProviderResponse {
+    if let Some(response) = provider_response::parse(provider, parsed) {
+        return response;
+    }
+    tracing::debug!("Unrecognized {} response shape, using heuristic extraction", provider);
+    
+    let choice = &parsed["choices"][0];
+    ProviderResponse {
+        content: extract_assistant_content(parsed, provider),
+        finish_reason: choice["finish_reason"].as_str().or(parsed["stop_reason"].as_str()).map(str::to_string),
+        refusal: choice["message"]["refusal"].as_str().filter(|r| !r.trim().is_empty()).map(str::to_string),
+        has_tool_calls: has_native_tool_calls(parsed),
+        usage: None,
+    }
+}
+
+/// The text to show the user, plus why it's a stand-in (`content_filter`, `refusal`, `empty`) when
+/// the provider didn't actually answer.
+fn reply_text(response: &ProviderResponse) -> (String, Option<&'static str>) {
+    if let Some((reason, refusal)) = blocked_completion(response) {
+        let text = refusal.unwrap_or_else(|| FILTERED_COMPLETION_MESSAGE.to_string());
+        // A refusal may still carry a partial answer; prefer it when present
+        let content = if response.content.trim().is_empty() { text } else { response.content.clone() };
+        return (content, Some(reason));
+    }
+    
+    if response.content.trim().is_empty() && !response.has_tool_calls {
+        return (EMPTY_COMPLETION_MESSAGE.to_string(), Some("empty"));
+    }
+    (response.content.clone(), None)
+}
+
+/// Output of a successful LLM call; `filtered` is only present when the reply is a stand-in
+fn llm_output(parsed: Value, model: &str, provider: &str) -> Value {
+    let response = parse_response(&parsed, provider);
+    response_output(&response, model, provider, parsed)
+}
+
+fn response_output(response: &ProviderResponse, model: &str, provider: &str, raw: Value) -> Value {
+    let (content, filtered) = reply_text(response);
+    if let Some(reason) = filtered {
+        tracing::warn!("LLM returned no usable content ({}), model {}", reason, model);
+    }
+    
+    let mut output = serde_json::json!({
+        "content": content,
+        "model": model,
+        "provider": provider,
+        "finish_reason": response.finish_reason,
+        "raw": raw
+    });
+    if let Some(usage) = &response.usage {
+        output["usage"] = serde_json::json!(usage);
+    }
+    if let Some(reason) = filtered {
+        output["filtered"] = serde_json::json!(reason);
+    }
+    output
+}
+
+/// Heuristic extraction for response shapes no typed parser recognizes
 fn extract_assistant_content(parsed: &Value, provider: &str) -> String {
     // Anthropic format: content is usually an array of blocks with .text
     if provider == "anthropic" {
@@ -240,56 +1068,412 @@ fn extract_assistant_content(parsed: &Value, provider: &str) -> String {
     if !text.trim().is_empty() {
         return text;
     }
-
-    let text = extract_text_from_value(&parsed["output"]);
-    if !text.trim().is_empty() {
-        return text;
+
+    let text = extract_text_from_value(&parsed["output"]);
+    if !text.trim().is_empty() {
+        return text;
+    }
+
+    // Provider-agnostic named fields seen in compatible APIs.
+    for key in ["response", "answer", "assistant", "final"] {
+        let text = extract_text_from_value(&parsed[key]);
+        if !text.trim().is_empty() {
+            return text;
+        }
+    }
+
+    // Last resort: deep scan for prose-looking strings and pick the longest.
+    let mut strings = Vec::new();
+    collect_string_values(parsed, &mut strings);
+    if let Some(best) = strings.into_iter().max_by_key(|s| s.len()) {
+        return best.chars().take(DEEP_SCAN_MAX_CHARS).collect();
+    }
+
+    String::new()
+}
+
+impl Executor for LlmExecutor {
+    fn manifest(&self) -> ExecutorManifest {
+        ExecutorManifest {
+            id: "llm.chat".to_string(),
+            version: "0.1.0".to_string(),
+            description: "Chat with LLM (OpenRouter/Anthropic/OpenAI)".to_string(),
+            permissions: vec!["network".to_string(), "llm".to_string()],
+        }
+    }
+
+    fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult {
+        self.run_checked(input, config, None)
+    }
+
+    /// Requests the completion with `stream: true`. Streamed tool calls aren't assembled,
+    /// so this is for plain text replies.
+    fn execute_streaming(&self, input: Value, config: &crate::config::NodeConfig, on_delta: OnDelta) -> ExecutorResult {
+        self.run_checked(input, config, Some(on_delta))
+    }
+}
+
+/// Normalized `response_format` of an `llm.chat` input, in OpenAI's shape: `"json_object"` or
+/// `{ "type": "json_object" }`, or `{ "type": "json_schema", "json_schema": { "name", "schema" } }`.
+/// `None` for plain text replies.
+fn json_response_format(input: &Value) -> Result<Option<Value>, String> {
+    let format = match &input["response_format"] {
+        Value::Null => return Ok(None),
+        Value::String(kind) => serde_json::json!({ "type": kind }),
+        other => other.clone(),
+    };
+    match format["type"].as_str() {
+        Some("text") => Ok(None),
+        Some("json_object") => Ok(Some(serde_json::json!({ "type": "json_object" }))),
+        Some("json_schema") => {
+            let schema = &format["json_schema"]["schema"];
+            if !schema.is_object() {
+                return Err("response_format json_schema.schema required".to_string());
+            }
+            jsonschema::validator_for(schema).map_err(|e| format!("Invalid response_format schema: {}", e))?;
+            Ok(Some(format))
+        }
+        _ => Err("response_format must be text, json_object or json_schema".to_string()),
+    }
+}
+
+/// The JSON object in a reply for a JSON `response_format`, tolerating a code fence around it
+fn parse_json_reply(content: &str, format: &Value) -> Result<Value, String> {
+    let text = content.trim();
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|t| t.strip_suffix("```"))
+        .unwrap_or(text);
+    let value: Value = serde_json::from_str(text.trim()).map_err(|e| format!("it isn't valid JSON ({})", e))?;
+    if !value.is_object() {
+        return Err("it isn't a JSON object".to_string());
+    }
+    let schema = &format["json_schema"]["schema"];
+    if schema.is_object() {
+        let validator = jsonschema::validator_for(schema).map_err(|e| e.to_string())?;
+        let mismatch = validator.iter_errors(&value).next().map(|e| {
+            let path = e.instance_path.to_string();
+            format!("it doesn't match the schema at {}: {}", if path.is_empty() { "(root)" } else { &path }, e)
+        });
+        if let Some(mismatch) = mismatch {
+            return Err(mismatch);
+        }
+    }
+    Ok(value)
+}
+
+/// Added to the system prompt for every provider; Anthropic has no `response_format` parameter, and
+/// OpenAI's `json_object` mode rejects requests whose messages never mention JSON
+fn json_instruction(format: &Value) -> String {
+    let mut instruction = "Reply with a single JSON object and nothing else: no prose, no code fences.".to_string();
+    if let Some(schema) = format["json_schema"]["schema"].as_object() {
+        instruction.push_str(&format!(" The object must match this JSON Schema: {}", Value::Object(schema.clone())));
+    }
+    instruction
+}
+
+/// Ask for a JSON reply in `body`: the instruction goes into the system prompt (the top-level
+/// `system` when the body has one, else the first system message), plus `response_format` for
+/// providers that take it
+fn apply_json_format(body: &mut Value, provider: &str, format: Value) {
+    let instruction = json_instruction(&format);
+    if let Some(system) = body["system"].as_str() {
+        body["system"] = serde_json::json!(format!("{}\n\n{}", system, instruction));
+    } else if provider == "anthropic" {
+        body["system"] = serde_json::json!(instruction);
+    } else if let Some(messages) = body["messages"].as_array_mut() {
+        match messages.iter_mut().find(|m| m["role"] == "system" && m["content"].is_string()) {
+            Some(system) => {
+                let prompt = system["content"].as_str().unwrap_or_default();
+                system["content"] = serde_json::json!(format!("{}\n\n{}", prompt, instruction));
+            }
+            None => messages.insert(0, serde_json::json!({ "role": "system", "content": instruction })),
+        }
+    }
+    if provider != "anthropic" {
+        body["response_format"] = format;
+    }
+}
+
+/// Read a `stream: true` completion, passing each text delta to `on_delta` until it breaks
+fn stream_completion(
+    resp: reqwest::blocking::Response,
+    on_delta: OnDelta,
+    model: &str,
+    provider: &str,
+    start: std::time::Instant,
+) -> ExecutorResult {
+    use std::io::BufRead;
+    
+    let status = resp.status().as_u16();
+    let mut streamed = provider_response::Streamed::default();
+    let mut stopped_early = false;
+    for line in std::io::BufReader::new(resp).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return ExecutorResult::error(ExecutorError::Network, format!("LLM stream interrupted: {}", e)),
+        };
+        if let Some(delta) = streamed.apply(&line) {
+            if on_delta(&delta).is_break() {
+                stopped_early = true;
+                break;
+            }
+        }
+    }
+    if let Some(error) = streamed.error {
+        return ExecutorResult::error(ExecutorError::Upstream { status }, format!("LLM stream error: {}", error));
+    }
+    
+    let mut output = response_output(&streamed.response, model, provider, Value::Null);
+    output["streamed"] = serde_json::json!(true);
+    if stopped_early {
+        output["stopped_early"] = serde_json::json!(true);
     }
+    ExecutorResult::Executed { output, duration_ms: start.elapsed().as_millis() as u64 }
+}
 
-    // Provider-agnostic named fields seen in compatible APIs.
-    for key in ["response", "answer", "assistant", "final", "reasoning"] {
-        let text = extract_text_from_value(&parsed[key]);
-        if !text.trim().is_empty() {
-            return text;
-        }
+/// Input fields that change what the model replies; anything else (`stream`, ...) stays out of
+/// the cache key
+const LLM_CACHE_KEY_FIELDS: &[&str] = &["messages", "tools", "response_format", "temperature", "max_tokens", "top_p"];
+
+/// Cache entry path for a request: hash of provider, model, messages and sampling params
+fn llm_cache_path(input: &Value, config: &crate::config::NodeConfig) -> std::path::PathBuf {
+    let request: serde_json::Map<String, Value> = LLM_CACHE_KEY_FIELDS.iter()
+        .filter_map(|field| input.get(*field).map(|v| (field.to_string(), v.clone())))
+        .collect();
+    let key = serde_json::json!({
+        "provider": config.llm.provider,
+        "model": config.llm.model,
+        "request": request,
+    });
+    let hash = format!("{:x}", md5::compute(key.to_string().as_bytes()));
+    crate::config::expand_path(&config.artifacts.path).join(".llm-cache").join(format!("{}.json", hash))
+}
+
+/// Cached output, if present and younger than `ttl_secs`
+fn read_llm_cache(path: &std::path::Path, ttl_secs: u64) -> Option<Value> {
+    let entry: Value = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+    let stored_at = chrono::DateTime::parse_from_rfc3339(entry["stored_at"].as_str()?).ok()?;
+    let age = chrono::Utc::now().signed_duration_since(stored_at);
+    if age.num_seconds() < 0 || age.num_seconds() as u64 >= ttl_secs {
+        return None;
     }
+    Some(entry["output"].clone())
+}
 
-    // Last resort: deep scan for non-trivial strings and pick the longest.
-    let mut strings = Vec::new();
-    collect_string_values(parsed, &mut strings);
-    strings.retain(|s| s.len() >= 8);
-    if let Some(best) = strings.into_iter().max_by_key(|s| s.len()) {
-        return best;
+/// Only a complete, real reply is worth serving again; `filtered` marks the stand-in for a
+/// filtered, refused or empty one
+fn is_cacheable_llm_output(output: &Value) -> bool {
+    output["stopped_early"] != true && output.get("filtered").is_none()
+}
+
+fn write_llm_cache(path: &std::path::Path, output: &Value) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
     }
+    let entry = serde_json::json!({ "stored_at": chrono::Utc::now().to_rfc3339(), "output": output });
+    std::fs::write(path, entry.to_string())?;
+    Ok(())
+}
 
-    String::new()
+/// Anthropic's limit for one image
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Images beyond this many on one message are left out
+const MAX_IMAGES_PER_MESSAGE: usize = 4;
+
+/// Image attachments are only fetched (or handed to the provider) from http(s) URLs on
+/// `http.allowed_domains`, like `http.request`
+fn image_url_allowed(url: &str, allowed_domains: &[String]) -> bool {
+    url::Url::parse(url).is_ok_and(|parsed| {
+        matches!(parsed.scheme(), "http" | "https") && is_domain_allowed(allowed_domains, parsed.host_str().unwrap_or_default())
+    })
 }
 
-impl Executor for LlmExecutor {
-    fn manifest(&self) -> ExecutorManifest {
-        ExecutorManifest {
-            id: "llm.chat".to_string(),
-            version: "0.1.0".to_string(),
-            description: "Chat with LLM (OpenRouter/Anthropic/OpenAI)".to_string(),
-            permissions: vec!["network".to_string(), "llm".to_string()],
+/// Replace `images` (`[{url, content_type}]`) on chat messages with the provider's multimodal
+/// content: `image_url` parts for OpenAI/OpenRouter, base64 `image` blocks (fetched with `fetch`)
+/// for Anthropic. Without `llm.vision`, the model only gets a note that images were attached.
+/// Attachments on disallowed hosts or declared as something other than an image are dropped.
+fn attach_images(
+    messages: &mut Value,
+    llm: &crate::config::LlmConfig,
+    allowed_domains: &[String],
+    fetch: impl Fn(&str) -> anyhow::Result<Vec<u8>>,
+) {
+    use base64::{engine::general_purpose, Engine as _};
+    
+    let Some(messages) = messages.as_array_mut() else {
+        return;
+    };
+    for msg in messages {
+        let Some(images) = msg.as_object_mut().and_then(|m| m.remove("images")) else {
+            continue;
+        };
+        let images: Vec<crate::channels::Attachment> = serde_json::from_value(images).unwrap_or_default();
+        if images.is_empty() {
+            continue;
+        }
+        let text = msg["content"].as_str().unwrap_or_default().to_string();
+        if !llm.vision {
+            tracing::debug!("Model {} has no vision (llm.vision); ignoring {} image(s)", llm.model, images.len());
+            msg["content"] = serde_json::json!(format!("{}\n\n[The user attached {} image(s), which you can't view]", text, images.len()).trim_start());
+            continue;
+        }
+        
+        let mut parts = Vec::new();
+        for image in images.iter().take(MAX_IMAGES_PER_MESSAGE) {
+            if image.content_type.is_some() && !image.is_image() {
+                tracing::warn!("Skipping attachment that isn't an image: {:?}", image.content_type);
+                continue;
+            }
+            if !image_url_allowed(&image.url, allowed_domains) {
+                tracing::warn!("Skipping image attachment from a host not in http.allowed_domains: {}", image.url);
+                continue;
+            }
+            if llm.provider != "anthropic" {
+                parts.push(serde_json::json!({ "type": "image_url", "image_url": { "url": image.url } }));
+                continue;
+            }
+            match fetch(&image.url) {
+                Ok(bytes) if bytes.len() <= MAX_IMAGE_BYTES => parts.push(serde_json::json!({
+                    "type": "image",
+                    "source": {
+                        "type": "base64",
+                        "media_type": image.content_type.clone().unwrap_or_else(|| mime_guess::from_path(&image.url).first_or_octet_stream().to_string()),
+                        "data": general_purpose::STANDARD.encode(&bytes),
+                    }
+                })),
+                Ok(bytes) => tracing::warn!("Skipping image of {} bytes (max {})", bytes.len(), MAX_IMAGE_BYTES),
+                Err(e) => tracing::warn!("Could not fetch image attachment: {}", e),
+            }
+        }
+        // Providers reject empty text parts
+        if !text.is_empty() {
+            parts.push(serde_json::json!({ "type": "text", "text": text }));
         }
+        msg["content"] = serde_json::json!(parts);
     }
+}
 
-    fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult {
+impl LlmExecutor {
+    /// `run`, checking the reply when the input asks for a JSON `response_format`: a reply that
+    /// doesn't parse (or match the schema) is retried once with a corrective message, and the
+    /// parsed object is returned as `json`.
+    fn run_checked(&self, input: Value, config: &crate::config::NodeConfig, mut on_delta: Option<OnDelta>) -> ExecutorResult {
+        let format = match json_response_format(&input) {
+            Ok(Some(format)) => format,
+            Ok(None) => return self.run(input, config, on_delta),
+            Err(e) => return ExecutorResult::error(ExecutorError::BadInput, e),
+        };
+        let mut input = input;
+        let mut problem = String::new();
+        let streaming = on_delta.is_some();
+        for attempt in 1..=2 {
+            // Each attempt streams through the same callback
+            let mut forward = |delta: &str| on_delta.as_mut().map_or(ControlFlow::Continue(()), |f| f(delta));
+            let result = self.run(input.clone(), config, if streaming { Some(&mut forward) } else { None });
+            let ExecutorResult::Executed { mut output, duration_ms } = result else {
+                return result;
+            };
+            let content = output["content"].as_str().unwrap_or_default().to_string();
+            match parse_json_reply(&content, &format) {
+                Ok(json) => {
+                    output["json"] = json;
+                    return ExecutorResult::Executed { output, duration_ms };
+                }
+                Err(e) => {
+                    tracing::warn!("LLM reply is not the requested JSON (attempt {}): {}", attempt, e);
+                    if let Some(messages) = input["messages"].as_array_mut() {
+                        // Providers reject empty assistant messages
+                        let reply = if content.trim().is_empty() { "[empty reply]".to_string() } else { content };
+                        messages.push(serde_json::json!({ "role": "assistant", "content": reply }));
+                        messages.push(serde_json::json!({
+                            "role": "user",
+                            "content": format!("That reply can't be used because {}. Reply again with only the JSON object.", e),
+                        }));
+                    }
+                    problem = e;
+                }
+            }
+        }
+        ExecutorResult::error(ExecutorError::UnexpectedOutput, format!("LLM reply is not the requested JSON after a retry: {}", problem))
+    }
+    
+    fn run(&self, input: Value, config: &crate::config::NodeConfig, on_delta: Option<OnDelta>) -> ExecutorResult {
+        // A provider/model switched at runtime applies to every call after it
+        let config = &*crate::config::with_live_llm(config);
+        if !config.llm.cache_enabled {
+            return self.call_provider(input, config, on_delta);
+        }
+        
+        let cache_path = llm_cache_path(&input, config);
+        if let Some(mut output) = read_llm_cache(&cache_path, config.llm.cache_ttl_secs) {
+            output["cached"] = serde_json::json!(true);
+            // A cached reply arrives as one delta
+            if let (Some(on_delta), Some(content)) = (on_delta, output["content"].as_str()) {
+                let _ = on_delta(content);
+            }
+            return ExecutorResult::Executed { output, duration_ms: 0 };
+        }
+        
+        let result = self.call_provider(input, config, on_delta);
+        // A reply cut short, filtered or empty isn't what the same request would get next time
+        if let ExecutorResult::Executed { output, .. } = &result {
+            if !is_cacheable_llm_output(output) {
+                return result;
+            }
+            if let Err(e) = write_llm_cache(&cache_path, output) {
+                tracing::warn!("Failed to write LLM cache entry: {}", e);
+            }
+        }
+        result
+    }
+    
+    /// Download an image attachment, reading at most `MAX_IMAGE_BYTES`
+    fn fetch_image(&self, url: &str, allowed_domains: &[String]) -> anyhow::Result<Vec<u8>> {
+        use std::io::Read;
+        
+        let resp = self.client.get().get(url)
+            .timeout(std::time::Duration::from_secs(20))
+            .send()?
+            .error_for_status()?;
+        // A redirect may have left the allowed hosts
+        if !image_url_allowed(resp.url().as_str(), allowed_domains) {
+            anyhow::bail!("image redirected to a host not in http.allowed_domains: {}", resp.url());
+        }
+        let content_type = resp.headers().get(reqwest::header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default();
+        if !content_type.starts_with("image/") {
+            anyhow::bail!("not an image (content type '{}')", content_type);
+        }
+        if resp.content_length().is_some_and(|len| len > MAX_IMAGE_BYTES as u64) {
+            anyhow::bail!("image is larger than {} bytes", MAX_IMAGE_BYTES);
+        }
+        let mut bytes = Vec::new();
+        resp.take(MAX_IMAGE_BYTES as u64 + 1).read_to_end(&mut bytes)?;
+        if bytes.len() > MAX_IMAGE_BYTES {
+            anyhow::bail!("image is larger than {} bytes", MAX_IMAGE_BYTES);
+        }
+        Ok(bytes)
+    }
+    
+    fn call_provider(&self, input: Value, config: &crate::config::NodeConfig, mut on_delta: Option<OnDelta>) -> ExecutorResult {
         let start = std::time::Instant::now();
         
         // Get messages from input
-        let messages = match input.get("messages") {
+        let mut messages = match input.get("messages") {
             Some(m) => m.clone(),
-            None => return ExecutorResult::Error { error: "messages required".to_string() },
+            None => return ExecutorResult::error(ExecutorError::BadInput, "messages required"),
         };
+        attach_images(&mut messages, &config.llm, &config.http.allowed_domains, |url| self.fetch_image(url, &config.http.allowed_domains));
         
         // Get API key from environment
         let api_key = match std::env::var(&config.llm.api_key_env) {
             Ok(k) => k,
-            Err(_) => return ExecutorResult::Error { 
-                error: format!("API key not found in env: {}", config.llm.api_key_env) 
-            },
+            Err(_) => return ExecutorResult::error(
+                ExecutorError::Unauthorized,
+                format!("API key not found in env: {}", config.llm.api_key_env),
+            ),
         };
         
         // Build request based on provider
@@ -304,7 +1488,13 @@ impl Executor for LlmExecutor {
                 if let Some(msgs) = messages.as_array() {
                     for msg in msgs {
                         if msg["role"].as_str() == Some("system") {
-                            system_content = msg["content"].as_str().map(|s| s.to_string());
+                            // The prompt plus any one-shot context notes become a single system block
+                            if let Some(text) = msg["content"].as_str() {
+                                system_content = Some(match system_content.take() {
+                                    Some(prev) => format!("{}\n\n{}", prev, text),
+                                    None => text.to_string(),
+                                });
+                            }
                         } else if msg["role"].as_str() == Some("tool") {
                             // Skip "tool" messages - Claude doesn't support them
                             // Convert to assistant message instead
@@ -352,7 +1542,13 @@ impl Executor for LlmExecutor {
                 if let Some(msgs) = messages.as_array() {
                     for msg in msgs {
                         if msg["role"].as_str() == Some("system") {
-                            system_content = msg["content"].as_str().map(|s| s.to_string());
+                            // The prompt plus any one-shot context notes become a single system block
+                            if let Some(text) = msg["content"].as_str() {
+                                system_content = Some(match system_content.take() {
+                                    Some(prev) => format!("{}\n\n{}", prev, text),
+                                    None => text.to_string(),
+                                });
+                            }
                         } else if msg["role"].as_str() == Some("tool") {
                             // Skip "tool" messages - Claude doesn't support them
                             // Convert to assistant message instead
@@ -398,29 +1594,32 @@ impl Executor for LlmExecutor {
                 });
                 (url, body, format!("Bearer {}", api_key))
             }
-            _ => return ExecutorResult::Error { 
-                error: format!("Unknown provider: {}", config.llm.provider) 
-            },
+            _ => return ExecutorResult::error(ExecutorError::Internal, format!("Unknown provider: {}", config.llm.provider)),
         };
+        if let Ok(Some(format)) = json_response_format(&input) {
+            apply_json_format(&mut body, &config.llm.provider, format);
+        }
+        if on_delta.is_some() {
+            body["stream"] = serde_json::json!(true);
+            if config.llm.provider != "anthropic" {
+                body["stream_options"] = serde_json::json!({ "include_usage": true });
+            }
+        }
 
         // Optional fallback model for transient provider failures.
         let fallback_model = std::env::var("LLM_FALLBACK_MODEL").ok();
 
         // Timeouts + retry to avoid hanging when provider has transient 5xx issues.
-        let client = match reqwest::blocking::Client::builder()
-            .connect_timeout(std::time::Duration::from_secs(10))
-            .timeout(std::time::Duration::from_secs(45))
-            .build() {
-            Ok(c) => c,
-            Err(e) => return ExecutorResult::Error { error: format!("Failed to build HTTP client: {}", e) },
-        };
+        let timeout = request_timeout(config, "llm.chat", 45);
 
         let max_attempts = 3;
         let mut attempt_error = String::new();
+        let mut attempt_code = ExecutorError::Network;
         let mut used_model = config.llm.model.clone();
 
         for attempt in 1..=max_attempts {
-            let mut req = client.post(url)
+            let mut req = crate::trace::tag(self.client.get().post(url))
+                .timeout(timeout)
                 .header("Content-Type", "application/json")
                 .json(&body);
 
@@ -435,12 +1634,20 @@ impl Executor for LlmExecutor {
             match req.send() {
                 Ok(resp) => {
                     let status = resp.status().as_u16();
+                    let retry_after = resp
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(parse_retry_after);
+                    if let (Some(on_delta), true) = (on_delta.as_deref_mut(), status < 400) {
+                        return stream_completion(resp, on_delta, &used_model, &config.llm.provider, start);
+                    }
                     let body_text = resp.text().unwrap_or_default();
 
-                    // Retry on provider-side errors.
-                    if status >= 500 && attempt < max_attempts {
+                    // Retry on rate limits and provider-side errors.
+                    if (status >= 500 || status == 429) && attempt < max_attempts {
                         attempt_error = format!("LLM API error {} on attempt {}", status, attempt);
-                        std::thread::sleep(std::time::Duration::from_millis(400 * attempt as u64));
+                        std::thread::sleep(retry_delay(attempt, &config.llm, retry_after));
                         continue;
                     }
 
@@ -452,7 +1659,8 @@ impl Executor for LlmExecutor {
                                 body["model"] = serde_json::Value::String(used_model.clone());
                                 attempt_error = format!("Primary model failed with {}, retrying once with fallback model {}", status, used_model);
                                 // One additional fallback request.
-                                let mut fb_req = client.post(url)
+                                let mut fb_req = crate::trace::tag(self.client.get().post(url))
+                                    .timeout(timeout)
                                     .header("Content-Type", "application/json")
                                     .json(&body);
                                 if config.llm.provider == "anthropic" {
@@ -465,82 +1673,89 @@ impl Executor for LlmExecutor {
                                 match fb_req.send() {
                                     Ok(fb_resp) => {
                                         let fb_status = fb_resp.status().as_u16();
+                                        if let (Some(on_delta), true) = (on_delta.as_deref_mut(), fb_status < 400) {
+                                            return stream_completion(fb_resp, on_delta, &used_model, &config.llm.provider, start);
+                                        }
                                         let fb_body_text = fb_resp.text().unwrap_or_default();
                                         if fb_status >= 400 {
                                             let snippet = fb_body_text.chars().take(500).collect::<String>();
-                                            return ExecutorResult::Error {
-                                                error: format!("LLM API error {} (fallback model {}): {}", fb_status, used_model, snippet),
-                                            };
+                                            return ExecutorResult::error(
+                                                ExecutorError::from_status(fb_status),
+                                                format!("LLM API error {} (fallback model {}): {}", fb_status, used_model, snippet),
+                                            );
                                         }
 
                                         let parsed: Value = match serde_json::from_str(&fb_body_text) {
                                             Ok(v) => v,
-                                            Err(e) => return ExecutorResult::Error { error: format!("Parse error (fallback): {}", e) },
+                                            Err(e) => return ExecutorResult::error(ExecutorError::Upstream { status: fb_status }, format!("Parse error (fallback): {}", e)),
                                         };
 
-                                        let content = extract_assistant_content(&parsed, &config.llm.provider);
-
                                         return ExecutorResult::Executed {
-                                            output: serde_json::json!({
-                                                "content": content,
-                                                "model": used_model,
-                                                "provider": config.llm.provider,
-                                                "raw": parsed
-                                            }),
+                                            output: llm_output(parsed, &used_model, &config.llm.provider),
                                             duration_ms: start.elapsed().as_millis() as u64,
                                         };
                                     }
                                     Err(e) => {
-                                        return ExecutorResult::Error { error: format!("Fallback request failed: {}", e) };
+                                        return ExecutorResult::error(ExecutorError::from_reqwest(&e), format!("Fallback request failed: {}", e));
                                     }
                                 }
                             }
                         }
                     }
 
+                    if status == 429 {
+                        // Worded so callers (e.g. the job monitor) can tell it apart from hard failures
+                        let snippet = body_text.chars().take(500).collect::<String>();
+                        return ExecutorResult::error(
+                            ExecutorError::RateLimited,
+                            format!("LLM provider rate limited (429) after {} attempts: {}", attempt, snippet),
+                        );
+                    }
+                    
                     if status >= 400 {
                         let snippet = body_text.chars().take(500).collect::<String>();
-                        return ExecutorResult::Error { 
-                            error: format!("LLM API error {}: {}", status, snippet) 
-                        };
+                        return ExecutorResult::error(ExecutorError::from_status(status), format!("LLM API error {}: {}", status, snippet));
                     }
 
                     // Parse response to extract content
                     let parsed: Value = match serde_json::from_str(&body_text) {
                         Ok(v) => v,
-                        Err(e) => return ExecutorResult::Error { error: format!("Parse error: {}", e) },
+                        Err(e) => return ExecutorResult::error(ExecutorError::Upstream { status }, format!("Parse error: {}", e)),
                     };
 
                     // Extract assistant message based on provider format
-                    let content = extract_assistant_content(&parsed, &config.llm.provider);
-
                     return ExecutorResult::Executed {
-                        output: serde_json::json!({
-                            "content": content,
-                            "model": used_model,
-                            "provider": config.llm.provider,
-                            "raw": parsed
-                        }),
+                        output: llm_output(parsed, &used_model, &config.llm.provider),
                         duration_ms: start.elapsed().as_millis() as u64,
                     };
                 }
                 Err(e) => {
-                    attempt_error = format!("LLM request failed on attempt {}: {}", attempt, e);
+                    // Timeouts and connect/reset errors are transient; anything else (bad URL, TLS setup) is not
+                    let kind = if e.is_timeout() {
+                        "timed out"
+                    } else if e.is_connect() || e.is_request() {
+                        "connection failed"
+                    } else {
+                        return ExecutorResult::error(ExecutorError::from_reqwest(&e), format!("LLM request failed: {}", e));
+                    };
+                    attempt_error = format!("LLM request {} after {} attempts: {}", kind, attempt, e);
+                    attempt_code = ExecutorError::from_reqwest(&e);
                     if attempt < max_attempts {
-                        std::thread::sleep(std::time::Duration::from_millis(400 * attempt as u64));
+                        std::thread::sleep(retry_delay(attempt, &config.llm, None));
                         continue;
                     }
                 }
             }
         }
 
-        ExecutorResult::Error {
-            error: if attempt_error.is_empty() {
+        ExecutorResult::error(
+            attempt_code,
+            if attempt_error.is_empty() {
                 "LLM request failed after retries".to_string()
             } else {
                 attempt_error
             },
-        }
+        )
     }
 }
 
@@ -548,7 +1763,9 @@ impl Executor for LlmExecutor {
 // Google Gmail Executor
 // ============================================
 
-pub struct GoogleGmailExecutor;
+pub struct GoogleGmailExecutor {
+    pub client: HttpClient,
+}
 
 // ============================================
 // Harness Executor - Bridge to TypeScript
@@ -556,11 +1773,12 @@ pub struct GoogleGmailExecutor;
 
 pub struct HarnessExecutor {
     pub harness_url: String,
+    client: HttpClient,
 }
 
 impl HarnessExecutor {
-    pub fn new(harness_url: String) -> Self {
-        Self { harness_url }
+    pub fn new(harness_url: String, client: HttpClient) -> Self {
+        Self { harness_url, client }
     }
 }
 
@@ -574,22 +1792,31 @@ impl Executor for HarnessExecutor {
         }
     }
     
-    fn execute(&self, input: Value, _config: &crate::config::NodeConfig) -> ExecutorResult {
+    fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult {
         let start = std::time::Instant::now();
         
         let executor_id = match input["executor"].as_str() {
             Some(e) => e,
-            None => return ExecutorResult::Error { error: "executor required".to_string() },
+            None => return ExecutorResult::error(ExecutorError::BadInput, "executor required"),
         };
         
+        if !is_executor_allowed(&config.harness.allowed_executors, executor_id) {
+            return ExecutorResult::Denied {
+                denial_reason: DenialReason {
+                    rule: "harness.allowed_executors".to_string(),
+                    attempted: executor_id.to_string(),
+                    policy: format!("Harness executor '{}' is not in harness.allowed_executors", executor_id),
+                },
+            };
+        }
+        
         let params = input.get("params").cloned().unwrap_or(serde_json::json!({}));
         let tenant_id = input["tenant_id"].as_str().unwrap_or("default");
         let tier = input["tier"].as_str().unwrap_or("pro");
         
-        let client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(300)) // 5 min timeout for long workflows
-            .build()
-            .unwrap_or_else(|_| reqwest::blocking::Client::new());
+        // The step's `timeout`, otherwise harness config
+        let timeout = request_timeout(config, "harness.execute", config.harness.timeout_secs);
+        let max_bytes = config.harness.max_response_bytes;
         
         // Collect secrets from environment variables
         let mut secrets = serde_json::Map::new();
@@ -616,29 +1843,57 @@ impl Executor for HarnessExecutor {
         
         let url = format!("{}/tools/{}/execute", self.harness_url, executor_id);
         
-        match client.post(&url)
+        match crate::trace::tag(self.client.get().post(&url))
+            .timeout(timeout)
             .header("Content-Type", "application/json")
             .json(&payload)
             .send()
         {
             Ok(resp) => {
+                use std::io::Read;
+                
                 let status = resp.status().as_u16();
-                let body_text = resp.text().unwrap_or_default();
+                if resp.content_length().is_some_and(|len| len > max_bytes as u64) {
+                    return ExecutorResult::error(
+                        ExecutorError::Upstream { status },
+                        format!("Harness response exceeds {} bytes", max_bytes),
+                    );
+                }
+                
+                // Read one byte past the cap so an oversized body is detected without buffering it all
+                let mut body = Vec::new();
+                if let Err(e) = resp.take(max_bytes as u64 + 1).read_to_end(&mut body) {
+                    return ExecutorResult::error(ExecutorError::Network, format!("Failed to read harness response: {}", e));
+                }
+                if body.len() > max_bytes {
+                    return ExecutorResult::error(
+                        ExecutorError::Upstream { status },
+                        format!("Harness response exceeds {} bytes", max_bytes),
+                    );
+                }
+                let body_text = String::from_utf8_lossy(&body).into_owned();
                 
                 if status >= 400 {
-                    return ExecutorResult::Error { 
-                        error: format!("Harness error {}: {}", status, body_text) 
-                    };
+                    return ExecutorResult::error(ExecutorError::from_status(status), format!("Harness error {}: {}", status, body_text));
                 }
                 
-                let parsed: Value = serde_json::from_str(&body_text)
+                let mut parsed: Value = serde_json::from_str(&body_text)
                     .unwrap_or_else(|_| serde_json::json!({ "raw": body_text }));
                 
                 // Check for error in response
                 if let Some(err) = parsed.get("error") {
-                    return ExecutorResult::Error { 
-                        error: err.as_str().unwrap_or("Unknown error").to_string()
-                    };
+                    return ExecutorResult::error(
+                        ExecutorError::Upstream { status },
+                        err.as_str().unwrap_or("Unknown error"),
+                    );
+                }
+                
+                // Surface the harness job id at the top level so callers/monitor can correlate
+                let job_id = parsed.get("jobId")
+                    .or_else(|| parsed.get("output").and_then(|o| o.get("jobId")))
+                    .cloned();
+                if let (Some(job_id), Some(obj)) = (job_id, parsed.as_object_mut()) {
+                    obj.insert("job_id".to_string(), job_id);
                 }
                 
                 // NOTE: Monitor+heal spawn removed from here - execute() runs in spawn_blocking,
@@ -650,7 +1905,73 @@ impl Executor for HarnessExecutor {
                     duration_ms: start.elapsed().as_millis() as u64,
                 }
             }
-            Err(e) => ExecutorResult::Error { error: e.to_string() },
+            Err(e) => ExecutorResult::error(ExecutorError::from_reqwest(&e), e.to_string()),
+        }
+    }
+}
+
+/// `harness.job_status` and `harness.cancel`. Input: `{ "job_id": "..." }` (the `job_id` of a
+/// `harness.execute` output). Both output the job's status in the `monitor::JobStatus` shape;
+/// `harness.cancel` asks the harness to cancel the job first.
+pub struct HarnessJobExecutor {
+    pub harness_url: String,
+    client: HttpClient,
+    cancel: bool,
+}
+
+impl HarnessJobExecutor {
+    fn id(&self) -> &'static str {
+        if self.cancel { "harness.cancel" } else { "harness.job_status" }
+    }
+    
+    fn send(&self, request: reqwest::blocking::RequestBuilder, timeout: Duration) -> Result<Value, ExecutorResult> {
+        let resp = crate::trace::tag(request)
+            .timeout(timeout)
+            .send()
+            .map_err(|e| ExecutorResult::error(ExecutorError::from_reqwest(&e), e.to_string()))?;
+        let status = resp.status().as_u16();
+        let body = resp.text().unwrap_or_default();
+        if status >= 400 {
+            return Err(ExecutorResult::error(ExecutorError::from_status(status), format!("Harness error {}: {}", status, body)));
+        }
+        Ok(serde_json::from_str(&body).unwrap_or(Value::Null))
+    }
+}
+
+impl Executor for HarnessJobExecutor {
+    fn manifest(&self) -> ExecutorManifest {
+        ExecutorManifest {
+            id: self.id().to_string(),
+            version: "0.1.0".to_string(),
+            description: if self.cancel { "Cancel a harness job" } else { "Status, progress and logs of a harness job" }.to_string(),
+            permissions: vec!["network".to_string(), "harness".to_string()],
+        }
+    }
+    
+    fn execute(&self, input: Value, config: &crate::config::NodeConfig) -> ExecutorResult {
+        let start = std::time::Instant::now();
+        // The id goes into the URL path, so only plain ids are accepted
+        let Some(job_id) = input["job_id"]
+            .as_str()
+            .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        else {
+            return ExecutorResult::error(ExecutorError::BadInput, "job_id required (letters, digits, '-' and '_')");
+        };
+        let timeout = request_timeout(config, self.id(), config.harness.timeout_secs);
+        let url = format!("{}/jobs/{}", self.harness_url, job_id);
+        
+        if self.cancel {
+            if let Err(result) = self.send(self.client.get().post(format!("{}/cancel", url)), timeout) {
+                return result;
+            }
+            tracing::info!("Cancelled harness job {}", job_id);
+        }
+        match self.send(self.client.get().get(&url), timeout) {
+            Ok(body) => ExecutorResult::Executed {
+                output: serde_json::json!(crate::monitor::JobStatus::from_harness(job_id, &body["job"])),
+                duration_ms: start.elapsed().as_millis() as u64,
+            },
+            Err(result) => result,
         }
     }
 }
@@ -671,18 +1992,18 @@ impl Executor for GoogleGmailExecutor {
         // Extract required fields
         let user_id = match input["user_id"].as_str() {
             Some(u) => u,
-            None => return ExecutorResult::Error { error: "user_id required".to_string() },
+            None => return ExecutorResult::error(ExecutorError::BadInput, "user_id required"),
         };
         
         let to = match input["to"].as_str() {
             Some(t) => t,
-            None => return ExecutorResult::Error { error: "to email required".to_string() },
+            None => return ExecutorResult::error(ExecutorError::BadInput, "to email required"),
         };
         
         let subject = input["subject"].as_str().unwrap_or("(No Subject)");
         let body = match input["body"].as_str() {
             Some(b) => b,
-            None => return ExecutorResult::Error { error: "body required".to_string() },
+            None => return ExecutorResult::error(ExecutorError::BadInput, "body required"),
         };
         
         let from_name = input["from_name"].as_str();
@@ -691,13 +2012,11 @@ impl Executor for GoogleGmailExecutor {
         // Get control plane URL
         let control_plane_url = match &config.control_plane.url {
             Some(url) => url,
-            None => return ExecutorResult::Error { 
-                error: "control_plane.url not configured".to_string() 
-            },
+            None => return ExecutorResult::error(ExecutorError::Internal, "control_plane.url not configured"),
         };
         
         // Call Harness API to send email
-        let client = reqwest::blocking::Client::new();
+        let timeout = request_timeout(config, "google.gmail", config.executors.default_timeout_secs);
         let mut payload = serde_json::json!({
             "user_id": user_id,
             "to": to,
@@ -713,8 +2032,9 @@ impl Executor for GoogleGmailExecutor {
             payload["gmail_account_id"] = Value::String(account_id.to_string());
         }
         
-        let result = client
+        let result = self.client.get()
             .post(format!("{}/api/v1/oauth/google/send", control_plane_url))
+            .timeout(timeout)
             .header("Content-Type", "application/json")
             .json(&payload)
             .send();
@@ -725,9 +2045,7 @@ impl Executor for GoogleGmailExecutor {
                 let body_text = resp.text().unwrap_or_default();
                 
                 if status >= 400 {
-                    return ExecutorResult::Error {
-                        error: format!("Gmail API error {}: {}", status, body_text),
-                    };
+                    return ExecutorResult::error(ExecutorError::from_status(status), format!("Gmail API error {}: {}", status, body_text));
                 }
                 
                 let parsed: Value = serde_json::from_str(&body_text)
@@ -744,8 +2062,551 @@ impl Executor for GoogleGmailExecutor {
                     duration_ms: start.elapsed().as_millis() as u64,
                 }
             }
-            Err(e) => ExecutorResult::Error { error: e.to_string() },
+            Err(e) => ExecutorResult::error(ExecutorError::from_reqwest(&e), e.to_string()),
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_output_checked_against_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["results"],
+            "properties": { "results": { "type": "array", "items": { "type": "object", "properties": { "price": { "type": "number" } } } } }
+        });
+        let executed = |output: Value| ExecutorResult::Executed { output, duration_ms: 1 };
+        
+        let ok = check_output_schema("golf.search", &schema, executed(serde_json::json!({ "results": [{ "price": 40 }] })));
+        assert!(matches!(ok, ExecutorResult::Executed { .. }));
+        
+        match check_output_schema("golf.search", &schema, executed(serde_json::json!({ "results": [{ "price": "40" }] }))) {
+            ExecutorResult::Error { error, code } => {
+                assert_eq!(code, ExecutorError::UnexpectedOutput);
+                assert!(error.starts_with("tool golf.search returned unexpected shape: /results/0/price:"), "{}", error);
+            }
+            other => panic!("expected an error, got {:?}", other),
+        }
+        
+        // Errors and invalid schemas pass through untouched
+        let failed = check_output_schema("golf.search", &schema, ExecutorResult::error(ExecutorError::Timeout, "slow"));
+        assert!(matches!(failed, ExecutorResult::Error { code: ExecutorError::Timeout, .. }));
+        let bad_schema = serde_json::json!({ "type": 12 });
+        assert!(matches!(check_output_schema("x", &bad_schema, executed(serde_json::json!(1))), ExecutorResult::Executed { .. }));
+    }
+    
+    #[test]
+    fn test_executor_allowlist_matching() {
+        let list = |ids: &[&str]| ids.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let chat = list(&["http.request", "harness.execute"]);
+        assert!(is_executor_allowed(&chat, "http.request"));
+        assert!(!is_executor_allowed(&chat, "llm.chat"));
+        assert!(!is_executor_allowed(&chat, "http.request.evil"));
+        assert!(!is_executor_allowed(&[], "http.request"));
+        
+        // "*" allows anything; other patterns are plain ids, not globs
+        assert!(is_executor_allowed(&list(&["*"]), "plugin.shell"));
+        assert!(is_executor_allowed(&list(&["http.request", "*"]), "workflow.call"));
+        assert!(!is_executor_allowed(&list(&["http.*"]), "http.request"));
+        assert!(is_executor_allowed(&list(&["http.*"]), "http.*"));
+    }
+    
+    #[test]
+    fn test_registry_honors_enabled_and_disabled() {
+        let config = crate::config::ExecutorsConfig {
+            enabled: vec!["*".to_string()],
+            disabled: vec!["harness.execute".to_string()],
+            plugins: Vec::new(),
+            timeouts: HashMap::new(),
+            default_timeout_secs: 30,
+            validate_output: false,
+            output_schemas: HashMap::new(),
+        };
+        let registry = Registry::load(&config).unwrap();
+        assert!(registry.get("harness.execute").is_none());
+        assert!(registry.get("llm.chat").is_some());
+        match registry.unavailable("harness.execute") {
+            ExecutorResult::Error { error, code } => {
+                assert_eq!(code, ExecutorError::Denied);
+                assert!(error.starts_with("executor disabled: harness.execute"));
+            }
+            other => panic!("expected an error, got {:?}", other),
+        }
+        assert!(!registry.is_disabled("web.nope"));
+        
+        let only_http = Registry::load(&crate::config::ExecutorsConfig { enabled: vec!["http.request".to_string()], disabled: Vec::new(), ..config }).unwrap();
+        assert!(only_http.get("http.request").is_some());
+        assert!(only_http.get("llm.chat").is_none() && only_http.is_disabled("llm.chat"));
+        
+        // A config from an older onboard wizard (only http.request, never enforced) keeps every built-in
+        let mut legacy: crate::config::ExecutorsConfig = serde_yaml::from_str("enabled: [http.request]").unwrap();
+        assert!(legacy.migrate_legacy_enabled());
+        assert!(Registry::load(&legacy).unwrap().get("llm.chat").is_some());
+        let unset: crate::config::ExecutorsConfig = serde_yaml::from_str("plugins: []").unwrap();
+        assert_eq!(unset.enabled, vec!["*".to_string()]);
+        let mut narrowed: crate::config::ExecutorsConfig = serde_yaml::from_str("enabled: [http.request, llm.chat]").unwrap();
+        assert!(!narrowed.migrate_legacy_enabled());
+    }
+    
+    #[test]
+    fn test_error_code_serialization() {
+        let result = ExecutorResult::error(ExecutorError::from_status(429), "slow down");
+        assert_eq!(
+            serde_json::to_value(&result).unwrap(),
+            serde_json::json!({ "status": "error", "error": "slow down", "code": "rate_limited" })
+        );
+        assert_eq!(
+            serde_json::to_value(ExecutorError::from_status(502)).unwrap(),
+            serde_json::json!({ "upstream": { "status": 502 } })
+        );
+        
+        // Results recorded before codes existed still parse
+        let old: ExecutorResult = serde_json::from_value(serde_json::json!({ "status": "error", "error": "boom" })).unwrap();
+        assert!(matches!(old, ExecutorResult::Error { code: ExecutorError::Internal, .. }));
+    }
+    
+    fn test_config(http: &str) -> crate::config::NodeConfig {
+        serde_yaml::from_str(&format!(
+            r#"
+node: {{ id: test, name: test, environment: dev }}
+llm: {{ provider: anthropic, api_key_env: ANTHROPIC_API_KEY, model: test }}
+security: {{ mode: strict, allowed_executors: [http.request] }}
+http: {}
+executors: {{ enabled: [http.request] }}
+memory: {{ session_max_messages: 10, preferences_path: /tmp/prefs.yaml }}
+artifacts: {{ storage: local, path: /tmp/artifacts }}
+logging: {{ level: info, path: /tmp/logs }}
+control_plane: {{}}
+"#,
+            http
+        ))
+        .unwrap()
+    }
+    
+    #[test]
+    fn test_external_executor_protocol() {
+        let config = test_config("{ allowed_domains: ['*'] }");
+        let plugin = |script: &str, timeout_secs| ExternalExecutor {
+            plugin: crate::config::PluginConfig {
+                id: "plugin.test".to_string(),
+                command: "sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                description: String::new(),
+                permissions: Vec::new(),
+                timeout_secs,
+                settings: serde_json::json!({ "greeting": "hi" }),
+                output_schema: None,
+            },
+        };
+        
+        // Echo the request back as the output
+        let echo = plugin(r#"read line; echo "{\"status\":\"executed\",\"output\":$line,\"duration_ms\":1}""#, 5);
+        match echo.execute(serde_json::json!({ "x": 1 }), &config) {
+            ExecutorResult::Executed { output, .. } => {
+                assert_eq!(output["input"]["x"], 1);
+                assert_eq!(output["config"]["settings"]["greeting"], "hi");
+                assert_eq!(output["config"]["node"]["id"], "test");
+            }
+            other => panic!("expected executed, got {:?}", other),
+        }
+        
+        let broken = plugin("echo 'not json'; echo 'boom' >&2; exit 3", 5);
+        match broken.execute(Value::Null, &config) {
+            ExecutorResult::Error { error, code } => {
+                assert_eq!(code, ExecutorError::Internal);
+                assert!(error.contains("boom"), "{}", error);
+            }
+            other => panic!("expected error, got {:?}", other),
+        }
+        
+        let slow = plugin("sleep 5", 1);
+        assert!(matches!(slow.execute(Value::Null, &config), ExecutorResult::Error { code: ExecutorError::Timeout, .. }));
+        
+        // An input bigger than the pipe buffer, never read, still times out on schedule
+        let started = std::time::Instant::now();
+        let big = serde_json::json!({ "blob": "x".repeat(1 << 20) });
+        assert!(matches!(slow.execute(big, &config), ExecutorResult::Error { code: ExecutorError::Timeout, .. }));
+        assert!(started.elapsed() < std::time::Duration::from_secs(4));
+    }
+    
+    #[test]
+    fn test_http_unknown_method_is_an_error() {
+        let config = test_config("{ allowed_domains: ['*'] }");
+        let result = HttpExecutor { client: HttpClient::shared() }.execute(serde_json::json!({ "url": "https://example.com", "method": "PACTH" }), &config);
+        match result {
+            ExecutorResult::Error { error, code } => {
+                assert_eq!(code, ExecutorError::BadInput);
+                assert!(error.contains("Unsupported HTTP method 'PACTH'"), "{}", error);
+            }
+            other => panic!("expected an error, got {:?}", other),
+        }
+        
+        assert_eq!(parse_http_method("patch").unwrap(), reqwest::Method::PATCH);
+        assert_eq!(parse_http_method("OPTIONS").unwrap(), reqwest::Method::OPTIONS);
+        
+        let restricted = test_config("{ allowed_domains: ['*'], allowed_methods: [GET, POST] }");
+        let result = HttpExecutor { client: HttpClient::shared() }.execute(serde_json::json!({ "url": "https://example.com", "method": "DELETE" }), &restricted);
+        assert!(matches!(result, ExecutorResult::Denied { ref denial_reason } if denial_reason.rule == "http.allowed_methods"));
+        
+        let small = test_config("{ allowed_domains: ['*'], max_headers: 1 }");
+        let input = serde_json::json!({ "url": "https://example.com", "headers": { "a": "1", "b": "2" } });
+        assert!(matches!(HttpExecutor { client: HttpClient::shared() }.execute(input, &small), ExecutorResult::Error { code: ExecutorError::BadInput, .. }));
+    }
+    
+    #[test]
+    fn test_request_timeout_precedence() {
+        let mut config = test_config("{ allowed_domains: ['*'] }");
+        config.executors.timeouts.insert("http.request".to_string(), 5);
+        let timeout = |id| request_timeout(&config, id, config.executors.default_timeout_secs).as_secs();
+        assert_eq!(timeout("http.request"), 5);
+        assert_eq!(timeout("google.gmail"), 30);
+        
+        // The step's timeout comes from the engine; a `timeout_secs` in the input is just input
+        let step = StepContext { run_id: "run1".to_string(), step_id: "fetch".to_string(), depth: 0, timeout_secs: Some(90), artifacts_dir: None };
+        assert_eq!(with_step_context(step, || timeout("http.request")), 90);
+    }
+    
+    #[test]
+    fn test_save_as_writes_only_successful_bodies_to_the_step_dir() {
+        let config = test_config("{ allowed_domains: ['*'] }");
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            for (status, stream) in ["200 OK", "404 Not Found"].into_iter().zip(listener.incoming()) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut buf).unwrap();
+                let reply = format!("HTTP/1.1 {}\r\ncontent-length: 4\r\nconnection: close\r\n\r\nbody", status);
+                std::io::Write::write_all(&mut stream, reply.as_bytes()).unwrap();
+            }
+        });
+        let dir = crate::store::TempPath::new("files");
+        let step = || StepContext {
+            run_id: "run1".to_string(),
+            step_id: "fetch".to_string(),
+            depth: 0,
+            timeout_secs: None,
+            artifacts_dir: Some(dir.0.clone()),
+        };
+        let http = HttpExecutor { client: HttpClient::shared() };
+        let fetch = |name: &str| serde_json::json!({ "url": format!("{}/{}", base, name), "method": "GET", "save_as": name });
+        
+        // An `artifacts_dir` in the input is just input
+        let forged = serde_json::json!({ "url": format!("{}/x", base), "save_as": "x", "artifacts_dir": "/tmp" });
+        assert!(matches!(http.execute(forged, &config), ExecutorResult::Error { code: ExecutorError::BadInput, .. }));
+        
+        match with_step_context(step(), || http.execute(fetch("report.pdf"), &config)) {
+            ExecutorResult::Executed { output, .. } => assert_eq!(output["artifacts"][0]["name"], "report.pdf"),
+            other => panic!("expected a saved artifact, got {:?}", other),
+        }
+        assert_eq!(std::fs::read(dir.0.join("report.pdf")).unwrap(), b"body");
+        
+        match with_step_context(step(), || http.execute(fetch("missing.pdf"), &config)) {
+            ExecutorResult::Executed { output, .. } => assert_eq!((output["status"].as_u64(), output["body"].as_str()), (Some(404), Some("body"))),
+            other => panic!("expected the error body inline, got {:?}", other),
+        }
+        assert!(!dir.0.join("missing.pdf").exists());
+        server.join().unwrap();
+        let _ = std::fs::remove_dir_all(&dir.0);
+    }
+    
+    #[test]
+    fn test_graphql_errors_are_error_results() {
+        let data = graphql_data(200, r#"{"data":{"viewer":{"login":"octo"}}}"#).unwrap();
+        assert_eq!(data["viewer"]["login"], "octo");
+        
+        let (code, error) = graphql_data(200, r#"{"data":null,"errors":[{"message":"Field 'x' doesn't exist"}]}"#).unwrap_err();
+        assert_eq!(code, ExecutorError::BadInput);
+        assert!(error.contains("Field 'x' doesn't exist"), "{}", error);
+        assert_eq!(graphql_data(502, "Bad Gateway").unwrap_err().0, ExecutorError::Upstream { status: 502 });
+        assert_eq!(graphql_data(401, r#"{"errors":[{"message":"no token"}]}"#).unwrap_err().0, ExecutorError::Unauthorized);
+        
+        let config = test_config("{ allowed_domains: [api.github.com] }");
+        let input = serde_json::json!({ "url": "https://example.com/graphql", "query": "{ viewer { login } }" });
+        let result = GraphqlExecutor { http: HttpExecutor { client: HttpClient::shared() } }.execute(input, &config);
+        assert!(matches!(result, ExecutorResult::Denied { ref denial_reason } if denial_reason.rule == "http.allowed_domains"));
+    }
+    
+    #[test]
+    fn test_filtered_and_empty_completions() {
+        let assistant_reply = |body: &Value, provider: &str| reply_text(&parse_response(body, provider));
+        
+        let filtered = serde_json::json!({
+            "choices": [{ "finish_reason": "content_filter", "message": { "content": null } }],
+            "error": { "message": "This response was blocked by the provider's safety system" }
+        });
+        assert_eq!(assistant_reply(&filtered, "openai"), (FILTERED_COMPLETION_MESSAGE.to_string(), Some("content_filter")));
+        
+        let refusal = serde_json::json!({
+            "choices": [{ "finish_reason": "stop", "message": { "content": null, "refusal": "I can't help with that." } }]
+        });
+        assert_eq!(assistant_reply(&refusal, "openai"), ("I can't help with that.".to_string(), Some("refusal")));
+        
+        // The deep scan must not surface reasoning, error objects or key-looking strings
+        let empty = serde_json::json!({
+            "id": "gen-1234567890",
+            "choices": [{ "message": { "content": "", "reasoning": "The user wants something, let me think it through" } }],
+            "meta": { "token": "sk-abcdefghijklmnopqrstuvwxyz" }
+        });
+        assert_eq!(assistant_reply(&empty, "openrouter"), (EMPTY_COMPLETION_MESSAGE.to_string(), Some("empty")));
+        
+        // Native tool calls carry no text and are not a failure
+        let tool_use = serde_json::json!({
+            "content": [{ "type": "tool_use", "id": "toolu_1", "name": "http.request", "input": { "url": "https://example.com" } }],
+            "stop_reason": "tool_use"
+        });
+        assert_eq!(assistant_reply(&tool_use, "anthropic").1, None);
+    }
+    
+    #[test]
+    fn test_llm_cache_key_and_cacheable_replies() {
+        let config = test_config("{ allowed_domains: [] }");
+        let messages = serde_json::json!([{ "role": "user", "content": "hi" }]);
+        let base = llm_cache_path(&serde_json::json!({ "messages": messages }), &config);
+        
+        // Fields that don't change the reply don't change the key
+        let same = serde_json::json!({ "messages": messages, "stream": true });
+        assert_eq!(llm_cache_path(&same, &config), base);
+        let sampled = serde_json::json!({ "messages": messages, "temperature": 0.9 });
+        assert_ne!(llm_cache_path(&sampled, &config), base);
+        let other = serde_json::json!({ "messages": [{ "role": "user", "content": "bye" }] });
+        assert_ne!(llm_cache_path(&other, &config), base);
+        
+        let reply = |body: Value| llm_output(body, "test", "openai");
+        assert!(is_cacheable_llm_output(&reply(serde_json::json!({
+            "choices": [{ "finish_reason": "stop", "message": { "content": "hello" } }]
+        }))));
+        assert!(!is_cacheable_llm_output(&reply(serde_json::json!({
+            "choices": [{ "finish_reason": "content_filter", "message": { "content": null } }]
+        }))));
+        assert!(!is_cacheable_llm_output(&reply(serde_json::json!({
+            "choices": [{ "finish_reason": "stop", "message": { "content": "" } }]
+        }))));
+        assert!(!is_cacheable_llm_output(&serde_json::json!({ "content": "partial", "stopped_early": true })));
+    }
+    
+    #[test]
+    fn test_json_response_format_replies_checked() {
+        assert_eq!(json_response_format(&serde_json::json!({})), Ok(None));
+        let object = json_response_format(&serde_json::json!({ "response_format": "json_object" })).unwrap().unwrap();
+        assert_eq!(object, serde_json::json!({ "type": "json_object" }));
+        assert!(json_response_format(&serde_json::json!({ "response_format": "yaml" })).is_err());
+        assert!(json_response_format(&serde_json::json!({ "response_format": { "type": "json_schema", "json_schema": {} } })).is_err());
+        
+        assert_eq!(parse_json_reply("```json\n{\"ok\": true}\n```", &object), Ok(serde_json::json!({ "ok": true })));
+        assert!(parse_json_reply("Sure! Here it is: {\"ok\": true}", &object).unwrap_err().contains("valid JSON"));
+        assert!(parse_json_reply("[1, 2]", &object).is_err());
+        
+        let schema = serde_json::json!({ "type": "json_schema", "json_schema": { "name": "score", "schema": {
+            "type": "object", "required": ["score"], "properties": { "score": { "type": "number" } }
+        } } });
+        let format = json_response_format(&serde_json::json!({ "response_format": schema })).unwrap().unwrap();
+        assert!(parse_json_reply("{\"score\": 7}", &format).is_ok());
+        assert!(parse_json_reply("{\"score\": \"high\"}", &format).unwrap_err().contains("/score"));
+        assert!(json_instruction(&format).contains("\"required\":[\"score\"]"));
+        
+        // OpenAI needs "JSON" in the messages for json_object mode, so the instruction goes there too
+        let mut openai = serde_json::json!({ "model": "gpt-4o", "messages": [{ "role": "user", "content": "Score this lead" }] });
+        apply_json_format(&mut openai, "openai", object.clone());
+        assert_eq!(openai["response_format"], object);
+        assert_eq!(openai["messages"][0]["role"], "system");
+        assert!(openai["messages"][0]["content"].as_str().unwrap().contains("JSON"));
+        assert_eq!(openai["messages"][1]["content"], "Score this lead");
+        
+        let mut prompted = serde_json::json!({ "messages": [{ "role": "system", "content": "You score leads." }, { "role": "user", "content": "x" }] });
+        apply_json_format(&mut prompted, "openrouter", format.clone());
+        let system = prompted["messages"][0]["content"].as_str().unwrap();
+        assert!(system.starts_with("You score leads.\n\n") && system.contains("\"score\""));
+        assert_eq!(prompted["messages"].as_array().unwrap().len(), 2);
+        
+        let mut anthropic = serde_json::json!({ "system": "You score leads.", "messages": [] });
+        apply_json_format(&mut anthropic, "anthropic", object);
+        assert!(anthropic["system"].as_str().unwrap().contains("single JSON object"));
+        assert!(anthropic.get("response_format").is_none());
+    }
+    
+    #[test]
+    fn test_oauth_token_bound_to_provider_hosts_and_refreshed_after_401() {
+        assert!(oauth_host_denial("google", "https://gmail.googleapis.com/gmail/v1/users/me/messages").is_none());
+        let denial = oauth_host_denial("google", "https://attacker.example/collect").unwrap();
+        assert_eq!((denial.rule.as_str(), denial.attempted.as_str()), ("oauth.api_hosts", "attacker.example"));
+        assert!(oauth_host_denial("github", "https://api.github.com.evil.example/").is_some());
+        
+        // Answers 401 unless the request carries the fresh token
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/me", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut fresh_tokens = Vec::new();
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = std::io::Read::read(&mut stream, &mut buf).unwrap();
+                let fresh = String::from_utf8_lossy(&buf[..n]).to_lowercase().contains("authorization: bearer fresh");
+                fresh_tokens.push(fresh);
+                let status = if fresh { "200 OK" } else { "401 Unauthorized" };
+                let reply = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                std::io::Write::write_all(&mut stream, reply.as_bytes()).unwrap();
+            }
+            fresh_tokens
+        });
+        let client = reqwest::blocking::Client::new();
+        let send = |token: Option<&str>| {
+            let mut req = client.get(&url);
+            if let Some(token) = token {
+                req = req.bearer_auth(token);
+            }
+            req.send()
+        };
+        let refreshes = std::cell::Cell::new(0);
+        let resp = send_with_refresh(Some("stale"), send, || {
+            refreshes.set(refreshes.get() + 1);
+            Ok("fresh".to_string())
+        })
+        .unwrap();
+        assert_eq!((resp.status().as_u16(), refreshes.get()), (200, 1));
+        assert_eq!(server.join().unwrap(), vec![false, true]);
+    }
+    
+    #[test]
+    fn test_attach_images_per_provider() {
+        let mut llm = crate::config::LlmConfig {
+            provider: "openai".to_string(),
+            api_key_env: "OPENAI_API_KEY".to_string(),
+            model: "test".to_string(),
+            retry_base_ms: 400,
+            retry_max_ms: 1000,
+            cache_enabled: false,
+            cache_ttl_secs: 0,
+            vision: false,
+        };
+        let messages = serde_json::json!([
+            { "role": "system", "content": "be nice" },
+            { "role": "user", "content": "what is this?", "images": [{ "url": "https://cdn.example/cat.png", "content_type": "image/png" }] },
+        ]);
+        let fetch = |_: &str| Ok(vec![1u8, 2, 3]);
+        let allowed = vec!["cdn.example".to_string()];
+        
+        let mut text_only = messages.clone();
+        attach_images(&mut text_only, &llm, &allowed, fetch);
+        assert_eq!(text_only[1]["content"], "what is this?\n\n[The user attached 1 image(s), which you can't view]");
+        assert!(text_only[1].get("images").is_none());
+        
+        llm.vision = true;
+        let mut openai = messages.clone();
+        attach_images(&mut openai, &llm, &allowed, fetch);
+        assert_eq!(openai[1]["content"][0], serde_json::json!({ "type": "image_url", "image_url": { "url": "https://cdn.example/cat.png" } }));
+        assert_eq!(openai[1]["content"][1]["text"], "what is this?");
+        assert_eq!(openai[0]["content"], "be nice");
+        
+        llm.provider = "anthropic".to_string();
+        let mut anthropic = messages.clone();
+        attach_images(&mut anthropic, &llm, &allowed, fetch);
+        assert_eq!(anthropic[1]["content"][0]["source"], serde_json::json!({ "type": "base64", "media_type": "image/png", "data": "AQID" }));
+        
+        // Internal hosts and non-images are never fetched or passed on
+        let unsafe_images = serde_json::json!([{ "role": "user", "content": "hi", "images": [
+            { "url": "http://169.254.169.254/latest/meta-data", "content_type": "image/png" },
+            { "url": "https://cdn.example/notes.txt", "content_type": "text/plain" },
+            { "url": "file:///etc/passwd" },
+        ] }]);
+        let mut skipped = unsafe_images.clone();
+        attach_images(&mut skipped, &llm, &allowed, |url: &str| panic!("fetched {}", url));
+        assert_eq!(skipped[0]["content"], serde_json::json!([{ "type": "text", "text": "hi" }]));
+    }
+    
+    #[test]
+    fn test_retry_delay_jitter_and_cap() {
+        let llm = crate::config::LlmConfig {
+            provider: "anthropic".to_string(),
+            api_key_env: "ANTHROPIC_API_KEY".to_string(),
+            model: "test".to_string(),
+            retry_base_ms: 400,
+            retry_max_ms: 1000,
+            cache_enabled: false,
+            cache_ttl_secs: 0,
+            vision: false,
+        };
+        
+        for attempt in 1..=5 {
+            assert!(retry_delay(attempt, &llm, None) <= std::time::Duration::from_millis(1000));
+        }
+        assert!(retry_delay(1, &llm, None) <= std::time::Duration::from_millis(400));
+        assert_eq!(
+            retry_delay(1, &llm, parse_retry_after("30")),
+            std::time::Duration::from_millis(1000)
+        );
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+    
+    #[test]
+    fn test_harness_disallowed_executor_is_denied() {
+        let mut config = test_config("{ allowed_domains: ['*'] }");
+        config.harness.allowed_executors = vec!["golf.search".to_string()];
+        // Nothing listens here; a denial must come back before any request is made
+        let harness = HarnessExecutor::new("http://127.0.0.1:9".to_string(), HttpClient::shared());
+        
+        let result = harness.execute(serde_json::json!({ "executor": "shell.run", "params": {} }), &config);
+        match result {
+            ExecutorResult::Denied { denial_reason } => {
+                assert_eq!(denial_reason.rule, "harness.allowed_executors");
+                assert_eq!(denial_reason.attempted, "shell.run");
+            }
+            other => panic!("expected a denial, got {:?}", other),
+        }
+        
+        let result = harness.execute(serde_json::json!({ "executor": "golf.search", "params": {} }), &config);
+        assert!(!matches!(result, ExecutorResult::Denied { .. }));
+    }
+    
+    #[test]
+    fn test_harness_job_id_checked_and_status_normalized() {
+        let config = test_config("{ allowed_domains: ['*'] }");
+        let cancel = HarnessJobExecutor { harness_url: "http://127.0.0.1:9".to_string(), client: HttpClient::shared(), cancel: true };
+        for input in [serde_json::json!({}), serde_json::json!({ "job_id": "../tools" })] {
+            assert!(matches!(cancel.execute(input, &config), ExecutorResult::Error { code: ExecutorError::BadInput, .. }));
+        }
+        
+        let job = serde_json::json!({
+            "status": "running",
+            "currentStep": "scrape",
+            "progress": 0.5,
+            "logs": [{ "level": "warn", "message": "429 from site", "timestamp": "2025-01-01T00:00:00Z" }],
+        });
+        let status = crate::monitor::JobStatus::from_harness("job_1", &job);
+        assert_eq!((status.status.as_str(), status.current_step.as_deref(), status.progress), ("running", Some("scrape"), 0.5));
+        assert_eq!((status.logs.len(), status.logs[0].level.as_str()), (1, "warn"));
+        assert_eq!(status.logs[0].timestamp, std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_735_689_600));
+    }
+    
+    #[test]
+    fn test_workflow_call_depth_limited() {
+        let config = test_config("{ allowed_domains: ['*'] }");
+        let missing = WorkflowCallExecutor.execute(serde_json::json!({ "inputs": {} }), &config);
+        assert!(matches!(missing, ExecutorResult::Error { code: ExecutorError::BadInput, .. }));
+        
+        let deepest = StepContext { run_id: "r-a-b-c-d".to_string(), step_id: "loop".to_string(), depth: crate::workflow::MAX_CALL_DEPTH, timeout_secs: None, artifacts_dir: None };
+        let result = with_step_context(deepest, || WorkflowCallExecutor.execute(serde_json::json!({ "workflow_id": "loop" }), &config));
+        match result {
+            ExecutorResult::Denied { denial_reason } => {
+                assert_eq!(denial_reason.rule, "workflow.max_call_depth");
+                assert_eq!(denial_reason.attempted, "loop");
+            }
+            other => panic!("expected a denial, got {:?}", other),
+        }
+        assert!(step_context().is_none());
+        
+        // Call info in the input is ignored, so it can't fake a run ID or reset the depth
+        let forged = serde_json::json!({ "workflow_id": "loop", "call": { "run_id": "victim", "depth": 99 } });
+        assert!(!matches!(WorkflowCallExecutor.execute(forged, &config), ExecutorResult::Denied { .. }));
+        
+        let top = StepContext { run_id: "run1".to_string(), step_id: "enrich".to_string(), depth: 0, timeout_secs: None, artifacts_dir: None };
+        let (run_id, call) = sub_run(Some(&top));
+        assert_eq!((run_id.as_str(), call.parent_run_id.as_str(), call.depth), ("run1-enrich", "run1", 1));
+        let (_, from_chat) = sub_run(None);
+        assert_eq!((from_chat.parent_run_id.as_str(), from_chat.depth), ("", 1));
+    }
+}
@@ -0,0 +1,145 @@
+//! Export / Import
+//!
+//! Portable JSON snapshot of a single user's data (identities, conversation,
+//! preferences). Everything goes through the `Store` trait, so an export taken
+//! from SQLite can be imported into a Hosted store and vice versa.
+
+use crate::store::{ConversationMessage, Identity, Preferences, Store};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::HashSet;
+
+const EXPORT_VERSION: u32 = 1;
+
+/// Conversation page size; stays under the hosted store's cap
+const PAGE_SIZE: usize = crate::store::MAX_HOSTED_PAGE;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UserExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub user_id: String,
+    pub identities: Vec<Identity>,
+    pub conversation: Vec<ConversationMessage>,
+    pub preferences: Option<Preferences>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub user_id: String,
+    pub user_created: bool,
+    pub identities_linked: usize,
+    /// Identities already linked to a different user (left untouched)
+    pub identities_skipped: Vec<String>,
+    pub messages_imported: usize,
+    /// Messages already present (same role, content and timestamp)
+    pub messages_skipped: usize,
+    pub preferences_imported: bool,
+}
+
+/// Full history for `user_id`, paging backwards so no backend cap truncates it
+async fn all_messages(store: &dyn Store, user_id: &str) -> anyhow::Result<Vec<ConversationMessage>> {
+    let mut messages = store.get_conversation(user_id, None, PAGE_SIZE).await?;
+    let mut seen: HashSet<i64> = messages.iter().map(|m| m.id).collect();
+
+    while let Some(oldest) = messages.first().map(ConversationMessage::cursor) {
+        let page: Vec<_> = store
+            .get_conversation_before(user_id, None, oldest, PAGE_SIZE)
+            .await?
+            .into_iter()
+            .filter(|m| seen.insert(m.id))
+            .collect();
+        if page.is_empty() {
+            break;
+        }
+        messages.splice(0..0, page);
+    }
+
+    Ok(messages)
+}
+
+/// Collect everything we hold for `user_id`; `None` if there's no such user
+pub async fn export_user(store: &dyn Store, user_id: &str) -> anyhow::Result<Option<UserExport>> {
+    if store.get_user(user_id).await?.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(UserExport {
+        version: EXPORT_VERSION,
+        exported_at: Utc::now(),
+        user_id: user_id.to_string(),
+        identities: store.get_user_identities(user_id).await?,
+        conversation: all_messages(store, user_id).await?,
+        preferences: store.get_preferences(user_id).await?,
+    }))
+}
+
+/// Re-create an export in `store`.
+///
+/// Safe to run more than once: an existing user is reused, identities owned by
+/// someone else are skipped, and messages already present are not duplicated.
+/// Message ids are always reassigned by the target store.
+pub async fn import_user(store: &dyn Store, export: &UserExport) -> anyhow::Result<ImportSummary> {
+    if export.version > EXPORT_VERSION {
+        anyhow::bail!("Unsupported export version {} (max {})", export.version, EXPORT_VERSION);
+    }
+
+    let user_id = export.user_id.as_str();
+    let mut summary = ImportSummary {
+        user_id: user_id.to_string(),
+        ..Default::default()
+    };
+
+    if store.get_user(user_id).await?.is_none() {
+        store.create_user(user_id).await?;
+        summary.user_created = true;
+    }
+
+    for identity in &export.identities {
+        match store.get_identity(&identity.provider, &identity.provider_id).await? {
+            Some(existing) if existing.user_id != user_id => {
+                summary.identities_skipped.push(format!("{}:{}", identity.provider, identity.provider_id));
+            }
+            Some(_) => {}
+            None => {
+                store.link_identity(user_id, &identity.provider, &identity.provider_id, identity.username.as_deref()).await?;
+                summary.identities_linked += 1;
+            }
+        }
+    }
+
+    let existing: HashSet<(String, String, i64)> = all_messages(store, user_id)
+        .await?
+        .into_iter()
+        .map(|m| (m.role, m.content, m.created_at.timestamp_millis()))
+        .collect();
+
+    for message in &export.conversation {
+        let key = (message.role.clone(), message.content.clone(), message.created_at.timestamp_millis());
+        if existing.contains(&key) {
+            summary.messages_skipped += 1;
+            continue;
+        }
+        let message = ConversationMessage {
+            user_id: user_id.to_string(),
+            ..message.clone()
+        };
+        store.import_message(&message).await?;
+        summary.messages_imported += 1;
+    }
+
+    if let Some(prefs) = &export.preferences {
+        store.set_preferences(user_id, prefs.data.clone()).await?;
+        summary.preferences_imported = true;
+    }
+
+    tracing::info!(
+        user_id = %user_id,
+        messages = summary.messages_imported,
+        identities = summary.identities_linked,
+        "Imported user data"
+    );
+
+    Ok(summary)
+}
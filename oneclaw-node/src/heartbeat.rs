@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::interval;
-use crate::{agent_os, config, conversation, executor, identity};
+use crate::{agent_os, config, conversation, executor, identity, services};
 
 pub struct HeartbeatConfig {
     pub enabled: bool,
@@ -32,7 +32,7 @@ pub struct HeartbeatService {
     executor_registry: Arc<executor::Registry>,
     conversation_manager: Arc<conversation::ConversationManager>,
     identity_manager: Arc<identity::IdentityManager>,
-    harness_tools: Vec<agent_os::ToolDefinition>,
+    harness_tools: agent_os::SharedTools,
     node_config: &'static config::NodeConfig,
 }
 
@@ -43,7 +43,7 @@ impl HeartbeatService {
         executor_registry: Arc<executor::Registry>,
         conversation_manager: Arc<conversation::ConversationManager>,
         identity_manager: Arc<identity::IdentityManager>,
-        harness_tools: Vec<agent_os::ToolDefinition>,
+        harness_tools: agent_os::SharedTools,
         node_config: &'static config::NodeConfig,
     ) -> Self {
         Self {
@@ -118,21 +118,39 @@ impl HeartbeatService {
             .get("llm.chat")
             .ok_or_else(|| anyhow::anyhow!("LLM executor not found"))?;
         
+        let budget = &services::get().await?.budget;
+        if let Some(denial) = budget.check(None, "llm.chat").await {
+            tracing::debug!("Skipping heartbeat: {}", denial.policy);
+            return Ok(());
+        }
+        
         let executor_registry = Arc::clone(&self.executor_registry);
         let config = self.node_config;
         let result = tokio::task::spawn_blocking(move || {
             let executor = executor_registry.get("llm.chat")
                 .ok_or_else(|| anyhow::anyhow!("LLM executor not found"))?;
-            Ok::<_, anyhow::Error>(executor.execute(input, config))
+            // Streamed, so a slow reply shows progress and one that opens with HEARTBEAT_OK ends there
+            let mut reply = String::new();
+            let mut on_delta = |text: &str| {
+                reply.push_str(text);
+                tracing::trace!(chars = reply.len(), "Heartbeat reply streaming");
+                if reply.trim_start().starts_with("HEARTBEAT_OK") {
+                    std::ops::ControlFlow::Break(())
+                } else {
+                    std::ops::ControlFlow::Continue(())
+                }
+            };
+            Ok::<_, anyhow::Error>(executor.execute_streaming(input, config, &mut on_delta))
         })
         .await??;
+        budget.charge(None, "llm.chat", &serde_json::Value::Null, &result).await;
 
         // Extract response
         let content = match result {
             executor::ExecutorResult::Executed { output, .. } => {
                 output["content"].as_str().unwrap_or("").to_string()
             }
-            executor::ExecutorResult::Error { error } => {
+            executor::ExecutorResult::Error { error, .. } => {
                 tracing::warn!("Heartbeat LLM error: {}", error);
                 return Ok(());
             }
@@ -176,7 +194,7 @@ impl HeartbeatService {
     }
 
     fn build_heartbeat_prompt(&self, heartbeat_md: &str) -> String {
-        let tools_section = self.agent_os.format_tool_registry(&self.harness_tools);
+        let tools_section = self.agent_os.format_tool_registry(&self.harness_tools.get());
         
         format!(
             r#"You are running a periodic heartbeat check.
@@ -241,6 +259,7 @@ impl HeartbeatService {
         self.conversation_manager
             .add_assistant_message(
                 user_id,
+                None,
                 &format!("🔔 Heartbeat: {}", content),
                 "heartbeat",
                 None,
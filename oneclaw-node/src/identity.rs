@@ -46,21 +46,24 @@ impl IdentityManager {
             anyhow::bail!("Identity not found and auto_create is disabled");
         }
         
-        // Create new user
-        let user_id = format!("user_{}", nanoid::nanoid!(12));
-        self.store.create_user(&user_id).await?;
+        // Create the user and link atomically; a concurrent first message may
+        // have won the race, in which case its user is returned instead
+        let candidate = format!("user_{}", nanoid::nanoid!(12));
+        let (user_id, created) = self
+            .store
+            .create_user_with_identity(&candidate, provider, provider_id, username)
+            .await?;
         
-        // Link identity
-        self.store.link_identity(&user_id, provider, provider_id, username).await?;
-        
-        tracing::info!(
-            provider = %provider,
-            provider_id = %provider_id,
-            user_id = %user_id,
-            "Created new user and linked identity"
-        );
+        if created {
+            tracing::info!(
+                provider = %provider,
+                provider_id = %provider_id,
+                user_id = %user_id,
+                "Created new user and linked identity"
+            );
+        }
         
-        Ok((user_id, true))
+        Ok((user_id, created))
     }
     
     /// Link an additional identity to an existing user
@@ -143,6 +146,26 @@ impl IdentityManager {
 mod tests {
     use super::*;
     
+    #[tokio::test]
+    async fn test_concurrent_resolve_creates_one_user() {
+        let (store, _db) = crate::store::temp_store().await;
+        let store: Arc<dyn Store> = Arc::new(store);
+        let manager = IdentityManager::new(store.clone(), true);
+        
+        let (a, b) = tokio::join!(
+            manager.resolve("discord", "42", Some("alice")),
+            manager.resolve("discord", "42", Some("alice")),
+        );
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(a.0, b.0);
+        assert!(a.1 ^ b.1, "exactly one resolve should create the user");
+        
+        // Linking someone else's identity must not steal it
+        store.create_user("user_other").await.unwrap();
+        assert!(store.link_identity("user_other", "discord", "42", None).await.is_err());
+        assert_eq!(store.get_identity("discord", "42").await.unwrap().unwrap().user_id, a.0);
+    }
+    
     #[test]
     fn test_link_code_format() {
         // Link codes should be parseable
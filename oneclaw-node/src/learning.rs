@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use crate::{agent_os, config, executor};
+use crate::{agent_os, config, executor, services};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StepRecord {
@@ -101,6 +101,11 @@ Be specific - include actual details (tool names, timings, user preferences).
         ]
     });
     
+    let budget = &services::get().await?.budget;
+    if budget.check(None, "llm.chat").await.is_some() {
+        return Ok(false);
+    }
+    
     let executor_registry = Arc::clone(executor_registry);
     let result = tokio::task::spawn_blocking(move || {
         let executor = executor_registry
@@ -109,6 +114,7 @@ Be specific - include actual details (tool names, timings, user preferences).
         Ok::<_, anyhow::Error>(executor.execute(input, config))
     })
     .await??;
+    budget.charge(None, "llm.chat", &serde_json::Value::Null, &result).await;
     
     let content = match result {
         executor::ExecutorResult::Executed { output, .. } => {
@@ -1,25 +1,39 @@
 mod agent_os;
 mod autonomous_jobs;
 mod autonomous_jobs_poller;
+mod budget;
+mod callback;
+mod cancel;
 mod channels;
 mod config;
 mod conversation;
 mod daemon;
 mod daemon_skills_sync;
+mod dead_letter;
+mod events;
 mod executor;
+mod export;
 mod heartbeat;
 mod identity;
 mod integration;
 mod learning;
 mod memory;
+mod moderation;
 mod monitor;
+mod oauth;
 mod oauth_config;
+mod openapi;
 mod ports;
+mod provider_response;
 mod receipt;
+mod scheduler;
+mod secrets;
+mod services;
 mod store;
+mod trace;
 mod workflow;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(name = "oneclaw")]
@@ -28,26 +42,202 @@ use clap::{Parser, Subcommand};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Log output format
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Also write logs to this file (default: `logging.path` from config)
+    #[arg(long, global = true)]
+    log_file: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    Text,
+    Json,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Start the node daemon (Web UI)
     Daemon {
-        /// Port to bind to (default: 8787)
-        #[arg(short, long, default_value_t = 8787)]
-        port: u16,
+        /// Port to bind to (overrides `channels.http.port`, default 8787)
+        #[arg(short, long)]
+        port: Option<u16>,
+        /// IP address to listen on (overrides `daemon.bind`, default 127.0.0.1; 0.0.0.0 for all interfaces)
+        #[arg(long)]
+        bind: Option<String>,
+        /// Agent OS workspace to run (overrides `agent.workspace_path`); also scopes node id and database
+        #[arg(long)]
+        workspace: Option<std::path::PathBuf>,
     },
-    /// Interactive onboarding wizard
-    Onboard,
+    /// Onboarding wizard (interactive unless `--non-interactive`)
+    Onboard(OnboardArgs),
     /// Run a workflow
     Run {
         workflow: String,
         #[arg(short, long)]
         input: Option<String>,
+        /// Print each step as it finishes, then a summary instead of the JSON receipt
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Re-run a previous run, reusing its recorded inputs and step outputs
+    Replay {
+        run_id: String,
     },
     /// Show current config
     Config,
+    /// Export a user's identities, conversation and preferences as JSON
+    Export {
+        user_id: String,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Import a file produced by `export`
+    Import {
+        path: String,
+    },
+    /// Print the OpenAPI 3 document for the daemon API
+    ExportOpenapi {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Manage messaging channels
+    Channels {
+        #[command(subcommand)]
+        command: ChannelsCommand,
+    },
+    /// Inspect workflow receipts
+    Receipts {
+        #[command(subcommand)]
+        command: ReceiptsCommand,
+    },
+    /// Inspect or create the Agent OS workspace (SOUL.md, IDENTITY.md, ...)
+    Agentos {
+        #[command(subcommand)]
+        command: AgentOsCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum AgentOsCommand {
+    /// Show which directory is used and which files are present, empty or placeholders
+    Check,
+    /// Copy the built-in Agent OS files into the workspace, keeping files already there
+    Init,
+}
+
+#[derive(clap::Args)]
+struct OnboardArgs {
+    /// Take every value from flags / `--from` and never prompt
+    #[arg(long)]
+    non_interactive: bool,
+    /// YAML or JSON file with any of: name, environment, provider, model, control_plane_url
+    #[arg(long)]
+    from: Option<String>,
+    #[arg(long)]
+    name: Option<String>,
+    /// private | managed | hybrid
+    #[arg(long)]
+    environment: Option<String>,
+    /// anthropic | openrouter | openai
+    #[arg(long)]
+    provider: Option<String>,
+    /// Defaults to the provider's default model
+    #[arg(long)]
+    model: Option<String>,
+    #[arg(long)]
+    control_plane_url: Option<String>,
+}
+
+/// Values accepted by `onboard --from`; flags take precedence
+#[derive(Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OnboardFile {
+    name: Option<String>,
+    environment: Option<String>,
+    provider: Option<String>,
+    model: Option<String>,
+    control_plane_url: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum ChannelsCommand {
+    /// Verify credentials for each enabled channel
+    Test,
+}
+
+#[derive(Subcommand)]
+enum ReceiptsCommand {
+    /// List run IDs with a stored receipt
+    List,
+    /// Show a single receipt
+    Show {
+        run_id: String,
+        #[arg(short, long, value_enum, default_value_t = ReceiptFormat::Json)]
+        format: ReceiptFormat,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReceiptFormat {
+    Json,
+    Yaml,
+    Summary,
+}
+
+/// Set up stdout logging plus an optional file appender.
+///
+/// `RUST_LOG` wins over `logging.level`; the log file falls back to
+/// `logging.path`, which may name a directory (logs go to `oneclaw.log` inside it).
+fn init_logging(
+    format: LogFormat,
+    log_file: Option<&str>,
+) -> anyhow::Result<Option<tracing_appender::non_blocking::WorkerGuard>> {
+    use tracing_subscriber::{fmt, prelude::*, EnvFilter, Layer, Registry};
+
+    // Config may not exist yet (e.g. before `onboard`), so logging must not depend on it
+    let logging = config::load().ok().map(|c| &c.logging);
+
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        let level = logging.map(|l| l.level.as_str()).filter(|l| !l.trim().is_empty()).unwrap_or("info");
+        EnvFilter::try_new(level).unwrap_or_else(|_| EnvFilter::new("info"))
+    });
+
+    let make_layer = |writer: fmt::writer::BoxMakeWriter, ansi: bool| -> Box<dyn Layer<Registry> + Send + Sync> {
+        match format {
+            LogFormat::Text => fmt::layer().with_target(false).with_ansi(ansi).with_writer(writer).boxed(),
+            LogFormat::Json => fmt::layer().json().with_writer(writer).boxed(),
+        }
+    };
+
+    let mut layers = vec![make_layer(fmt::writer::BoxMakeWriter::new(std::io::stdout), true)];
+    let mut guard = None;
+
+    let file_path = log_file
+        .map(str::to_string)
+        .or_else(|| logging.map(|l| l.path.clone()).filter(|p| !p.trim().is_empty()));
+    if let Some(path) = file_path {
+        let path = config::expand_path(&path);
+        let (dir, file_name) = if path.is_dir() || path.extension().is_none() {
+            (path.clone(), std::ffi::OsString::from("oneclaw.log"))
+        } else {
+            let dir = path.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+            (dir, path.file_name().unwrap_or_default().to_os_string())
+        };
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| anyhow::anyhow!("Cannot create log directory {}: {}", dir.display(), e))?;
+        let (writer, file_guard) = tracing_appender::non_blocking(tracing_appender::rolling::never(&dir, file_name));
+        layers.push(make_layer(fmt::writer::BoxMakeWriter::new(writer), false));
+        guard = Some(file_guard);
+    }
+
+    tracing_subscriber::registry().with(layers).with(filter).init();
+    Ok(guard)
 }
 
 #[tokio::main]
@@ -63,42 +253,193 @@ async fn main() -> anyhow::Result<()> {
             }
         }
     }
-    use tracing_subscriber::{fmt, prelude::*, EnvFilter};
-    tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
-        .with(fmt::layer().with_target(false))
-        .init();
     let cli = Cli::parse();
+    // Before anything loads the config, which scopes itself to the workspace
+    if let Commands::Daemon { workspace: Some(workspace), .. } = &cli.command {
+        config::set_workspace_override(workspace.clone());
+    }
+    // Keep the guard alive for the whole run so buffered file logs are flushed on exit
+    let _log_guard = init_logging(cli.log_format, cli.log_file.as_deref())?;
+    // OAuth credentials saved by a standalone node (see oauth_config), after logging so its
+    // warnings are seen
+    oauth_config::load_local_secrets();
 
     match cli.command {
-        Commands::Daemon { port } => {
-            daemon::start(port).await?;
+        Commands::Daemon { port, bind, .. } => {
+            daemon::start(port, bind).await?;
         }
-        Commands::Onboard => {
-            onboard().await?;
+        Commands::Onboard(args) => {
+            if args.non_interactive {
+                onboard_non_interactive(args)?;
+            } else {
+                onboard().await?;
+            }
         }
-        Commands::Run { workflow, input } => {
+        Commands::Run { workflow, input, watch } => {
             let input_json = input
                 .map(|s| serde_json::from_str(&s))
                 .transpose()?
                 .unwrap_or(serde_json::json!({}));
-            let receipt = workflow::run(&workflow, input_json).await?;
+            if watch {
+                let receipt = run_watched(workflow, input_json).await?;
+                print!("{}", receipt::format_summary(&receipt));
+            } else {
+                let receipt = workflow::run(&workflow, input_json).await?;
+                println!("{}", serde_json::to_string_pretty(&receipt)?);
+            }
+        }
+        Commands::Replay { run_id } => {
+            let receipt = workflow::replay(&run_id).await?;
             println!("{}", serde_json::to_string_pretty(&receipt)?);
         }
         Commands::Config => {
             let config = config::load()?;
             println!("{}", serde_yaml::to_string(&config)?);
         }
+        Commands::Export { user_id, output } => {
+            let config = config::load()?;
+            let store = store::create_store(store::StoreType::from_config(config)).await?;
+            let export = export::export_user(store.as_ref(), &user_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("User not found: {}", user_id))?;
+            let json = serde_json::to_string_pretty(&export)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)?;
+                    println!("✅ Exported {} messages to {}", export.conversation.len(), path);
+                }
+                None => println!("{}", json),
+            }
+        }
+        Commands::Import { path } => {
+            let config = config::load()?;
+            let store = store::create_store(store::StoreType::from_config(config)).await?;
+            let export: export::UserExport = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            let summary = export::import_user(store.as_ref(), &export).await?;
+            println!("{}", serde_json::to_string_pretty(&summary)?);
+        }
+        Commands::ExportOpenapi { output } => {
+            let json = serde_json::to_string_pretty(&daemon::openapi_document())?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)?;
+                    println!("✅ Wrote OpenAPI document to {}", path);
+                }
+                None => println!("{}", json),
+            }
+        }
+        Commands::Channels { command: ChannelsCommand::Test } => {
+            let config = config::load()?;
+            let checks = channels::test_credentials(&config.channels).await;
+            if checks.is_empty() {
+                println!("No channels enabled in config (channels.*.enabled)");
+            }
+            for check in &checks {
+                let mark = if check.ok { "✅" } else { "❌" };
+                println!("{} {:<9} {}", mark, check.channel.to_string(), check.detail);
+            }
+            let failed = checks.iter().filter(|c| !c.ok).count();
+            if failed > 0 {
+                anyhow::bail!("{} channel check(s) failed", failed);
+            }
+        }
+        Commands::Receipts { command } => match command {
+            ReceiptsCommand::List => {
+                for run_id in receipt::list_receipts().await? {
+                    println!("{}", run_id);
+                }
+            }
+            ReceiptsCommand::Show { run_id, format } => {
+                let receipt = receipt::read_receipt(&run_id).await?
+                    .ok_or_else(|| anyhow::anyhow!("Receipt not found: {}", run_id))?;
+                match format {
+                    ReceiptFormat::Json => println!("{}", serde_json::to_string_pretty(&receipt)?),
+                    ReceiptFormat::Yaml => println!("{}", serde_yaml::to_string(&receipt)?),
+                    ReceiptFormat::Summary => print!("{}", receipt::format_summary(&receipt)),
+                }
+            }
+        },
+        Commands::Agentos { command: AgentOsCommand::Init } => {
+            let dir = config::workspace_dir().ok_or_else(|| anyhow::anyhow!("Cannot determine the workspace directory"))?;
+            bootstrap_workspace(&dir)?;
+        }
+        Commands::Agentos { command: AgentOsCommand::Check } => {
+            let (dir, source) = match agent_os::AgentOS::resolve_dir(None) {
+                Ok(resolved) => resolved,
+                Err(_) => {
+                    println!("No Agent OS dir found: the daemon uses the built-in files (safe mode).");
+                    println!("Run `oneclaw-node agentos init` to copy them into the workspace and edit them.");
+                    return Ok(());
+                }
+            };
+            println!("Agent OS dir ({}): {}", source, dir.display());
+            let checks = agent_os::AgentOS::check_dir(&dir);
+            for check in &checks {
+                let (mark, detail) = match check.status {
+                    agent_os::FileStatus::Present => ("✅", "present"),
+                    agent_os::FileStatus::Missing => ("❌", "missing (left out of the system prompt)"),
+                    agent_os::FileStatus::Empty => ("⚠️", "empty (left out of the system prompt)"),
+                    agent_os::FileStatus::Placeholder => ("⚠️", "still contains placeholder text (left out of the system prompt)"),
+                };
+                if check.status == agent_os::FileStatus::Present {
+                    println!("{} {:<13} {}", mark, check.name, detail);
+                } else {
+                    println!("{} {:<13} {} [{}]", mark, check.name, detail, check.path.display());
+                }
+            }
+            let problems = checks.iter().filter(|c| c.status != agent_os::FileStatus::Present).count();
+            if problems > 0 {
+                println!("\n{} file(s) need attention; run `oneclaw-node agentos init` to add the missing ones from the defaults", problems);
+            }
+        }
     }
     Ok(())
 }
 
+/// Run a workflow in the background and tail its receipt, printing steps as they land
+async fn run_watched(workflow: String, inputs: serde_json::Value) -> anyhow::Result<receipt::WorkflowReceipt> {
+    let run_id = match workflow::new_run(&workflow, &inputs, &config::load()?.workflow).await? {
+        workflow::NewRun::Id(run_id) => run_id,
+        workflow::NewRun::Reuse(earlier) => return Ok(*earlier),
+    };
+    println!("Run {} ({})", run_id, workflow);
+    
+    let task = {
+        let run_id = run_id.clone();
+        tokio::spawn(async move { workflow::run_with_id(run_id, &workflow, inputs).await })
+    };
+    
+    let mut printed = 0;
+    let print_new = |steps: &[receipt::StepReceipt], printed: &mut usize| {
+        for step in steps.iter().skip(*printed) {
+            println!("  {:<20} {:<9} {:>7}ms  {}", step.step_id, step.status, step.duration_ms, step.executor);
+        }
+        *printed = (*printed).max(steps.len());
+    };
+    
+    while !task.is_finished() {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        // Until this run's first write, a rerun's ID still holds the earlier run's receipt
+        if let Ok(Some(receipt)) = receipt::read_receipt(&run_id).await {
+            if receipt.owner == Some(receipt::RunOwner::current()) {
+                print_new(&receipt.steps, &mut printed);
+            }
+        }
+    }
+    
+    let receipt = task.await??;
+    print_new(&receipt.steps, &mut printed);
+    println!();
+    Ok(receipt)
+}
+
+const DEFAULT_CONTROL_PLANE_URL: &str = "http://104.131.111.116:3000";
+const ENVIRONMENTS: &[&str] = &["private", "managed", "hybrid"];
+
 async fn onboard() -> anyhow::Result<()> {
     use std::io::{self, Write};
     
     println!("🦞 OneClaw Node Setup\n");
-    let node_id = nanoid::nanoid!();
-    println!("Generated node ID: {}", node_id);
     
     print!("Node name [My OneClaw Node]: ");
     io::stdout().flush()?;
@@ -123,32 +464,162 @@ async fn onboard() -> anyhow::Result<()> {
     io::stdout().flush()?;
     let mut llm_choice = String::new();
     io::stdin().read_line(&mut llm_choice)?;
-    let (provider, api_key_env, model) = match llm_choice.trim() {
-        "2" => ("openrouter", "OPENROUTER_API_KEY", "anthropic/claude-3.5-sonnet"),
-        "3" => ("openai", "OPENAI_API_KEY", "gpt-4o"),
-        _ => ("anthropic", "ANTHROPIC_API_KEY", "claude-3-5-sonnet-20241022"),
+    let provider = match llm_choice.trim() {
+        "2" => "openrouter",
+        "3" => "openai",
+        _ => "anthropic",
+    };
+    
+    let config = build_node_config(name, environment, provider, None, DEFAULT_CONTROL_PLANE_URL)?;
+    save_node_config(&config)?;
+    
+    if let Some(workspace) = config::default_workspace_dir().filter(|dir| !dir.exists()) {
+        print!("\nCreate the agent workspace at {} with the default SOUL.md, IDENTITY.md, ...? [Y/n]: ", workspace.display());
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("n") {
+            bootstrap_workspace(&workspace)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy the built-in Agent OS files into `dir` and say what was written
+fn bootstrap_workspace(dir: &std::path::Path) -> anyhow::Result<()> {
+    let written = agent_os::bootstrap(dir)?;
+    if written.is_empty() {
+        println!("All Agent OS files already exist in {}", dir.display());
+    } else {
+        println!("✅ Wrote {} to {}", written.join(", "), dir.display());
+    }
+    Ok(())
+}
+
+/// `onboard --non-interactive`: same config as the wizard, from flags and/or `--from`
+fn onboard_non_interactive(args: OnboardArgs) -> anyhow::Result<()> {
+    let file: OnboardFile = match &args.from {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path, e))?;
+            // JSON is valid YAML, so one parser covers both
+            serde_yaml::from_str(&contents).map_err(|e| anyhow::anyhow!("Invalid onboard file {}: {}", path, e))?
+        }
+        None => OnboardFile::default(),
     };
     
-    let config = config::NodeConfig {
-        node: config::Node { id: node_id, name: name.to_string(), environment: environment.to_string() },
-        llm: config::LlmConfig { provider: provider.to_string(), api_key_env: api_key_env.to_string(), model: model.to_string() },
-        security: config::SecurityConfig { mode: "strict".to_string(), allowed_executors: vec!["http.request".to_string()] },
-        http: config::HttpConfig { allowed_domains: vec!["*".to_string()] },
-        executors: config::ExecutorsConfig { enabled: vec!["http.request".to_string()] },
-        memory: config::MemoryConfig { session_max_messages: 50, preferences_path: "~/.oneclaw/memory/preferences.yaml".to_string() },
-        artifacts: config::ArtifactsConfig { storage: "local".to_string(), path: "~/.oneclaw/artifacts".to_string() },
+    let name = args.name.or(file.name);
+    let environment = args.environment.or(file.environment);
+    let provider = args.provider.or(file.provider);
+    let model = args.model.or(file.model);
+    let control_plane_url = args.control_plane_url.or(file.control_plane_url);
+    
+    let missing: Vec<&str> = [("--name", &name), ("--environment", &environment), ("--provider", &provider)]
+        .iter()
+        .filter(|(_, v)| v.as_deref().is_none_or(|v| v.trim().is_empty()))
+        .map(|(flag, _)| *flag)
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!("Missing required values for non-interactive onboarding: {}", missing.join(", "));
+    }
+    
+    let config = build_node_config(
+        name.as_deref().unwrap_or_default().trim(),
+        environment.as_deref().unwrap_or_default().trim(),
+        provider.as_deref().unwrap_or_default().trim(),
+        model.as_deref().map(str::trim).filter(|m| !m.is_empty()),
+        control_plane_url.as_deref().unwrap_or(DEFAULT_CONTROL_PLANE_URL),
+    )?;
+    save_node_config(&config)
+}
+
+fn build_node_config(
+    name: &str,
+    environment: &str,
+    provider: &str,
+    model: Option<&str>,
+    control_plane_url: &str,
+) -> anyhow::Result<config::NodeConfig> {
+    if !ENVIRONMENTS.contains(&environment) {
+        anyhow::bail!("Unknown environment '{}' (expected one of: {})", environment, ENVIRONMENTS.join(", "));
+    }
+    let (api_key_env, default_model) = config::provider_defaults(provider).ok_or_else(|| {
+        anyhow::anyhow!("Unknown LLM provider '{}' (expected one of: {})", provider, config::LLM_PROVIDERS.join(", "))
+    })?;
+    
+    Ok(config::NodeConfig {
+        node: config::Node { id: nanoid::nanoid!(), name: name.to_string(), environment: environment.to_string() },
+        llm: config::LlmConfig {
+            provider: provider.to_string(),
+            api_key_env: api_key_env.to_string(),
+            model: model.unwrap_or(default_model).to_string(),
+            retry_base_ms: 400,
+            retry_max_ms: 10_000,
+            cache_enabled: false,
+            cache_ttl_secs: 86_400,
+            vision: false,
+        },
+        security: config::SecurityConfig {
+            mode: "strict".to_string(),
+            allowed_executors: vec!["http.request".to_string()],
+            chat_allowed_executors: Some(["http.request", "harness.execute", "harness.job_status", "harness.cancel", "conversation.stats", "conversation.recall"].map(String::from).to_vec()),
+            requires_confirmation: Vec::new(),
+            confirmation_ttl_secs: 300,
+            admin_token_env: "ONECLAW_ADMIN_TOKEN".to_string(),
+        },
+        http: config::HttpConfig {
+            allowed_domains: vec!["*".to_string()],
+            allowed_methods: None,
+            max_headers: 32,
+            max_header_bytes: 16 * 1024,
+        },
+        executors: config::ExecutorsConfig {
+            enabled: ["http.request", "http.graphql", "json.transform", "llm.chat", "google.gmail", "harness.execute", "harness.job_status", "harness.cancel", "workflow.call"].map(String::from).to_vec(),
+            disabled: Vec::new(),
+            plugins: Vec::new(),
+            timeouts: Default::default(),
+            default_timeout_secs: 30,
+            validate_output: false,
+            output_schemas: Default::default(),
+        },
+        memory: config::MemoryConfig {
+            session_max_messages: 50,
+            preferences_path: "~/.oneclaw/memory/preferences.yaml".to_string(),
+            channel_limits: Default::default(),
+        },
+        artifacts: config::ArtifactsConfig {
+            storage: "local".to_string(),
+            path: config::default_artifacts_path(),
+            redact_headers: config::default_redact_headers(),
+        },
         logging: config::LoggingConfig { level: "info".to_string(), path: "~/.oneclaw/logs".to_string() },
-        control_plane: config::ControlPlaneConfig { url: Some("http://104.131.111.116:3000".to_string()), token: None },
+        control_plane: config::ControlPlaneConfig { url: Some(control_plane_url.to_string()), token: None, ..Default::default() },
         channels: config::ChannelsConfig::default(),
         store: config::StoreConfig::default(),
         identity: config::IdentityConfig::default(),
-    };
-    
+        conversation: Default::default(),
+        harness: Default::default(),
+        agent: Default::default(),
+        startup: Default::default(),
+        budget: Default::default(),
+        callbacks: Default::default(),
+        dead_letters: Default::default(),
+        moderation: Default::default(),
+        chat: Default::default(),
+        workflow: Default::default(),
+        daemon: Default::default(),
+        schedules: Vec::new(),
+        secrets: Default::default(),
+    })
+}
+
+fn save_node_config(config: &config::NodeConfig) -> anyhow::Result<()> {
     let config_path = config::config_path()?;
     std::fs::create_dir_all(config_path.parent().unwrap())?;
-    std::fs::write(&config_path, serde_yaml::to_string(&config)?)?;
+    std::fs::write(&config_path, serde_yaml::to_string(config)?)?;
     
-    println!("\n✅ Config saved to {:?}", config_path);
+    println!("Node ID: {}", config.node.id);
+    println!("✅ Config saved to {:?}", config_path);
     println!("\nRun: oneclaw daemon");
     Ok(())
 }
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use crate::config;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+/// Named apart from `store::Preferences` in the OpenAPI document
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[schemars(rename = "PreferencesFile")]
 pub struct Preferences {
     pub user_id: Option<String>,
     pub default_execution_mode: Option<String>,
@@ -16,6 +16,43 @@ pub struct JobStatus {
     pub warnings: Vec<String>,
 }
 
+impl JobStatus {
+    /// Normalize the `job` object of a harness `GET /jobs/{id}` response
+    pub fn from_harness(job_id: &str, job: &serde_json::Value) -> Self {
+        let now = std::time::SystemTime::now();
+        let logs = job["logs"]
+            .as_array()
+            .map(|logs| {
+                logs.iter()
+                    .map(|log| LogEntry {
+                        timestamp: log["timestamp"]
+                            .as_str()
+                            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                            .map_or(now, std::time::SystemTime::from),
+                        level: log["level"].as_str().unwrap_or("info").to_string(),
+                        message: log["message"].as_str().unwrap_or_default().to_string(),
+                        step: log["step"].as_str().map(str::to_string),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let warnings = job["warnings"]
+            .as_array()
+            .map(|w| w.iter().filter_map(|w| w.as_str().map(str::to_string)).collect())
+            .unwrap_or_default();
+        Self {
+            job_id: job_id.to_string(),
+            status: job["status"].as_str().unwrap_or_default().to_string(),
+            started_at: now,
+            last_update: now,
+            current_step: job["currentStep"].as_str().map(str::to_string),
+            progress: job["progress"].as_f64().unwrap_or(0.0) as f32,
+            logs,
+            warnings,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
     pub timestamp: std::time::SystemTime,
@@ -155,16 +192,7 @@ impl JobMonitor {
                                 
                                 // Update local cache
                                 let mut jobs_guard = jobs.write().await;
-                                jobs_guard.insert(job_id.clone(), JobStatus {
-                                    job_id: job_id.clone(),
-                                    status: status.to_string(),
-                                    started_at: std::time::SystemTime::now(),
-                                    last_update: std::time::SystemTime::now(),
-                                    current_step,
-                                    progress: parsed["job"]["progress"].as_f64().unwrap_or(0.0) as f32,
-                                    logs: vec![],
-                                    warnings: vec![],
-                                });
+                                jobs_guard.insert(job_id.clone(), JobStatus::from_harness(&job_id, &parsed["job"]));
                             }
                         }
                     }
@@ -0,0 +1,153 @@
+//! OAuth Tokens
+//!
+//! Access tokens for APIs a user connected (Google, GitHub, Slack), kept in the store so
+//! executors can call those APIs directly instead of through the control plane. A token
+//! that is about to expire, or that an API rejected with 401, is refreshed at the
+//! provider's token endpoint with the app credentials saved via `/api/oauth/config`
+//! (`<PROVIDER>_CLIENT_ID` / `<PROVIDER>_CLIENT_SECRET`).
+
+use crate::oauth_config::OAuthProvider;
+use crate::store::{OAuthToken, Store};
+use chrono::{DateTime, Utc};
+
+/// Refresh this long before `expires_at`, so a token doesn't lapse mid-request
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+fn provider(name: &str) -> anyhow::Result<OAuthProvider> {
+    OAuthProvider::parse(name).ok_or_else(|| anyhow::anyhow!("Unknown OAuth provider '{}' (expected google, slack or github)", name))
+}
+
+async fn stored(store: &dyn Store, user_id: &str, provider: &str) -> anyhow::Result<OAuthToken> {
+    store
+        .get_oauth_token(user_id, provider)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("User {} has not connected {}", user_id, provider))
+}
+
+fn expiring(token: &OAuthToken, now: DateTime<Utc>) -> bool {
+    token.expires_at.is_some_and(|at| at <= now + chrono::Duration::seconds(EXPIRY_MARGIN_SECS))
+}
+
+/// A usable access token for `(user_id, provider)`, refreshed first if it's about to expire
+pub async fn access_token(store: &dyn Store, user_id: &str, provider_name: &str) -> anyhow::Result<String> {
+    provider(provider_name)?;
+    let token = stored(store, user_id, provider_name).await?;
+    if !expiring(&token, Utc::now()) {
+        return Ok(token.access_token);
+    }
+    Ok(refresh_token(store, token).await?.access_token)
+}
+
+/// Refresh the token now, e.g. after an API answered 401
+pub async fn refresh(store: &dyn Store, user_id: &str, provider_name: &str) -> anyhow::Result<String> {
+    provider(provider_name)?;
+    let token = stored(store, user_id, provider_name).await?;
+    Ok(refresh_token(store, token).await?.access_token)
+}
+
+async fn refresh_token(store: &dyn Store, token: OAuthToken) -> anyhow::Result<OAuthToken> {
+    let provider = provider(&token.provider)?;
+    let Some(refresh_token) = token.refresh_token.as_deref() else {
+        anyhow::bail!("The {} token for {} expired and has no refresh token; connect it again", token.provider, token.user_id);
+    };
+    let credential = |suffix: &str| {
+        let key = format!("{}_{}", provider.env_prefix(), suffix);
+        crate::oauth_config::credential(&key).ok_or_else(|| anyhow::anyhow!("{} is not set; save the app credentials via /api/oauth/config", key))
+    };
+    let (client_id, client_secret) = (credential("CLIENT_ID")?, credential("CLIENT_SECRET")?);
+
+    let body: serde_json::Value = reqwest::Client::new()
+        .post(provider.token_url())
+        .header(reqwest::header::ACCEPT, "application/json")
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", &client_id),
+            ("client_secret", &client_secret),
+        ])
+        .send()
+        .await?
+        .json()
+        .await?;
+    let refreshed = apply_refresh(&token, &body, Utc::now())?;
+    store.set_oauth_token(&refreshed).await?;
+    tracing::info!(user_id = %token.user_id, provider = %token.provider, "Refreshed OAuth token");
+    Ok(refreshed)
+}
+
+/// The token after a refresh response. Providers that don't rotate refresh tokens leave
+/// `refresh_token` out, so the old one is kept.
+fn apply_refresh(token: &OAuthToken, body: &serde_json::Value, now: DateTime<Utc>) -> anyhow::Result<OAuthToken> {
+    if let Some(error) = body.get("error") {
+        let detail = body["error_description"].as_str().or_else(|| error.as_str()).unwrap_or("unknown error");
+        anyhow::bail!("{} token refresh failed: {}", token.provider, detail);
+    }
+    let access_token = body["access_token"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("{} token refresh returned no access_token", token.provider))?;
+    Ok(OAuthToken {
+        access_token: access_token.to_string(),
+        refresh_token: body["refresh_token"].as_str().map(str::to_string).or_else(|| token.refresh_token.clone()),
+        expires_at: body["expires_in"].as_i64().map(|secs| now + chrono::Duration::seconds(secs)),
+        updated_at: now,
+        ..token.clone()
+    })
+}
+
+/// `access_token` (or `refresh` when `force_refresh`) for executors, which run on blocking threads
+pub fn bearer_blocking(user_id: &str, provider: &str, force_refresh: bool) -> anyhow::Result<String> {
+    let store = &crate::services::installed()
+        .ok_or_else(|| anyhow::anyhow!("OAuth tokens need the node's store, which isn't open"))?
+        .store;
+    let handle = tokio::runtime::Handle::try_current()
+        .ok()
+        .filter(|h| h.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread)
+        .ok_or_else(|| anyhow::anyhow!("OAuth tokens need the node's multi-threaded runtime"))?;
+    tokio::task::block_in_place(|| {
+        handle.block_on(async {
+            if force_refresh {
+                refresh(store.as_ref(), user_id, provider).await
+            } else {
+                access_token(store.as_ref(), user_id, provider).await
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tokens_stored_and_refresh_applied() {
+        let (store, _db) = crate::store::temp_store().await;
+        let now = Utc::now();
+        let token = OAuthToken {
+            user_id: "u1".to_string(),
+            provider: "google".to_string(),
+            access_token: "ya29.old".to_string(),
+            refresh_token: Some("1//refresh".to_string()),
+            expires_at: Some(now + chrono::Duration::hours(1)),
+            updated_at: now,
+        };
+        store.set_oauth_token(&token).await.unwrap();
+        assert_eq!(access_token(&store, "u1", "google").await.unwrap(), "ya29.old");
+        assert!(access_token(&store, "u2", "google").await.unwrap_err().to_string().contains("has not connected google"));
+        assert!(access_token(&store, "u1", "dropbox").await.is_err());
+
+        assert!(!expiring(&token, now));
+        assert!(expiring(&token, now + chrono::Duration::minutes(59) + chrono::Duration::seconds(30)));
+
+        let refreshed = apply_refresh(&token, &serde_json::json!({ "access_token": "ya29.new", "expires_in": 3599 }), now).unwrap();
+        assert_eq!(refreshed.access_token, "ya29.new");
+        assert_eq!(refreshed.refresh_token.as_deref(), Some("1//refresh"));
+        assert_eq!(refreshed.expires_at, Some(now + chrono::Duration::seconds(3599)));
+        let revoked = serde_json::json!({ "error": "invalid_grant", "error_description": "Token has been expired or revoked." });
+        assert!(apply_refresh(&token, &revoked, now).unwrap_err().to_string().contains("expired or revoked"));
+
+        store.set_oauth_token(&refreshed).await.unwrap();
+        let reloaded = store.get_oauth_token("u1", "google").await.unwrap().unwrap();
+        assert_eq!(reloaded.access_token, "ya29.new");
+        assert_eq!(reloaded.expires_at.map(|t| t.timestamp()), refreshed.expires_at.map(|t| t.timestamp()));
+    }
+}
@@ -1,63 +1,238 @@
 use axum::{extract::State, http::StatusCode, Json};
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::fs;
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{OnceLock, RwLock};
 
-#[derive(Deserialize)]
+/// App credentials from `~/.oneclaw/secrets/*.env` and `/api/oauth/config`, kept in-process
+/// rather than in the environment (which mustn't be changed while other threads read it)
+static SAVED_CREDENTIALS: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+
+fn saved_credentials() -> &'static RwLock<HashMap<String, String>> {
+    SAVED_CREDENTIALS.get_or_init(Default::default)
+}
+
+/// An app credential such as `GOOGLE_CLIENT_ID`: saved via the API, else from the environment
+pub fn credential(key: &str) -> Option<String> {
+    let saved = saved_credentials().read().unwrap().get(key).cloned();
+    saved.or_else(|| std::env::var(key).ok())
+}
+
+/// OAuth providers whose app credentials can be saved from the UI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OAuthProvider {
+    #[default]
+    Google,
+    Slack,
+    Github,
+}
+
+impl OAuthProvider {
+    /// By the lowercase name used in config and tool input
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "google" => Some(OAuthProvider::Google),
+            "slack" => Some(OAuthProvider::Slack),
+            "github" => Some(OAuthProvider::Github),
+            _ => None,
+        }
+    }
+    
+    /// Endpoint that trades a refresh token for a new access token
+    pub fn token_url(self) -> &'static str {
+        match self {
+            OAuthProvider::Google => "https://oauth2.googleapis.com/token",
+            OAuthProvider::Slack => "https://slack.com/api/oauth.v2.access",
+            OAuthProvider::Github => "https://github.com/login/oauth/access_token",
+        }
+    }
+    
+    /// API hosts (`http.allowed_domains` patterns) this provider's tokens may be sent to
+    pub fn api_hosts(self) -> &'static [&'static str] {
+        match self {
+            OAuthProvider::Google => &["*.googleapis.com"],
+            OAuthProvider::Slack => &["slack.com"],
+            OAuthProvider::Github => &["api.github.com", "uploads.github.com"],
+        }
+    }
+    
+    /// Env key prefix: `<PREFIX>_CLIENT_ID`, `<PREFIX>_CLIENT_SECRET`, `<PREFIX>_REDIRECT_URI`
+    pub fn env_prefix(self) -> &'static str {
+        match self {
+            OAuthProvider::Google => "GOOGLE",
+            OAuthProvider::Slack => "SLACK",
+            OAuthProvider::Github => "GITHUB",
+        }
+    }
+    
+    /// File name under `~/.oneclaw/secrets/`
+    fn file_stem(self) -> &'static str {
+        match self {
+            OAuthProvider::Google => "google",
+            OAuthProvider::Slack => "slack",
+            OAuthProvider::Github => "github",
+        }
+    }
+    
+    fn label(self) -> &'static str {
+        match self {
+            OAuthProvider::Google => "Google OAuth for Gmail Integration",
+            OAuthProvider::Slack => "Slack OAuth",
+            OAuthProvider::Github => "GitHub OAuth",
+        }
+    }
+    
+    /// Sanity-check credential formats so obvious copy/paste mistakes are caught early
+    fn validate(self, client_id: &str, client_secret: &str) -> Result<(), String> {
+        let matches = |pattern: &str, value: &str| regex::Regex::new(pattern).map(|re| re.is_match(value)).unwrap_or(false);
+        
+        let (id_ok, secret_ok) = match self {
+            OAuthProvider::Google => (
+                client_id.ends_with(".apps.googleusercontent.com"),
+                client_secret.starts_with("GOCSPX-"),
+            ),
+            OAuthProvider::Slack => {
+                if client_secret.starts_with("xoxb-") || client_secret.starts_with("xoxp-") {
+                    return Err("That looks like a Slack token (xoxb-/xoxp-), not the app's client secret".to_string());
+                }
+                (
+                    matches(r"^\d+\.\d+$", client_id),
+                    matches(r"^[0-9a-f]{32}$", client_secret),
+                )
+            }
+            OAuthProvider::Github => (
+                matches(r"^(Iv1\.[0-9a-f]{16}|(Ov|Iv)23[A-Za-z0-9]{16}|[0-9a-f]{20})$", client_id),
+                matches(r"^[0-9a-f]{40}$", client_secret),
+            ),
+        };
+        
+        if !id_ok {
+            return Err("Invalid client ID format".to_string());
+        }
+        if !secret_ok {
+            return Err("Invalid client secret format".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Set `key=value` in dotenv content: the first existing line is replaced in
+/// place, later duplicates are dropped, and a missing key is appended.
+fn upsert_env_key(content: &str, key: &str, value: &str) -> String {
+    let prefix = format!("{}=", key);
+    let mut replaced = false;
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        if line.starts_with(&prefix) {
+            if !replaced {
+                lines.push(format!("{}{}", prefix, value));
+                replaced = true;
+            }
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    if !replaced {
+        lines.push(format!("{}{}", prefix, value));
+    }
+    
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+/// `apps/api/.env` in the monorepo, if the node is running from `oneclaw-node/`
+fn harness_env_path() -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?;
+    let api_dir = current_dir.parent()?.join("apps").join("api");
+    api_dir.is_dir().then(|| api_dir.join(".env"))
+}
+
+/// Local OAuth credential store for standalone nodes
+pub fn local_secrets_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|h| h.join(".oneclaw").join("secrets"))
+}
+
+/// Load every `~/.oneclaw/secrets/*.env` so `credential` finds `<PROVIDER>_CLIENT_ID` etc.
+/// Existing env vars win.
+pub fn load_local_secrets() {
+    let Some(dir) = local_secrets_dir() else { return };
+    let Ok(entries) = fs::read_dir(&dir) else { return };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.extension().and_then(|e| e.to_str()) != Some("env") {
+            continue;
+        }
+        let loaded = dotenvy::from_path_iter(&path).and_then(|items| items.collect::<Result<Vec<_>, _>>());
+        match loaded {
+            Ok(items) => {
+                let mut saved = saved_credentials().write().unwrap();
+                for (key, value) in items.into_iter().filter(|(key, _)| std::env::var(key).is_err()) {
+                    saved.insert(key, value);
+                }
+                tracing::debug!("Loaded OAuth credentials from {}", path.display());
+            }
+            Err(e) => tracing::warn!("Could not load {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Write a credentials file readable only by the current user
+fn write_private(path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    fs::write(path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, JsonSchema)]
 pub struct OAuthConfigRequest {
+    /// Defaults to Google for older clients that don't send it
+    #[serde(default)]
+    provider: OAuthProvider,
     client_id: String,
     client_secret: String,
+    #[serde(default)]
     redirect_uri: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct OAuthConfigResponse {
     success: bool,
     message: String,
 }
 
 /// POST /api/oauth/config
-/// Save OAuth credentials to .env file in Harness directory
+/// Save OAuth credentials (Google by default, or `provider: slack|github`) to the
+/// .env file in the Harness directory, or to `~/.oneclaw/secrets/<provider>.env`
+/// when running outside the monorepo
 pub async fn save_oauth_config_handler(
     Json(req): Json<OAuthConfigRequest>,
 ) -> Result<Json<OAuthConfigResponse>, (StatusCode, String)> {
     // Validate inputs
-    if !req.client_id.ends_with(".apps.googleusercontent.com") {
+    if let Err(message) = req.provider.validate(&req.client_id, &req.client_secret) {
         return Ok(Json(OAuthConfigResponse {
             success: false,
-            message: "Invalid client ID format".to_string(),
+            message,
         }));
     }
     
-    if !req.client_secret.starts_with("GOCSPX-") {
-        return Ok(Json(OAuthConfigResponse {
-            success: false,
-            message: "Invalid client secret format".to_string(),
-        }));
-    }
-    
-    // Determine .env file location
-    // Try to find apps/api/.env relative to project root
-    let current_dir = std::env::current_dir()
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-    
-    // Go up to project root (oneclaw-node -> oneclaw)
-    let project_root = current_dir.parent()
-        .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "Cannot find project root".to_string()))?;
-    
-    let env_path = project_root.join("apps").join("api").join(".env");
-    
-    // Check if Harness directory exists
-    if !project_root.join("apps").join("api").exists() {
-        return Ok(Json(OAuthConfigResponse {
-            success: false,
-            message: format!(
-                "Harness directory not found. Please add credentials manually to: {}",
-                env_path.display()
-            ),
-        }));
-    }
+    // Inside the monorepo the TypeScript API owns OAuth; an installed node keeps them locally
+    let (env_path, harness) = match harness_env_path() {
+        Some(path) => (path, true),
+        None => {
+            let dir = local_secrets_dir()
+                .ok_or((StatusCode::INTERNAL_SERVER_ERROR, "No home dir".to_string()))?;
+            fs::create_dir_all(&dir)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            (dir.join(format!("{}.env", req.provider.file_stem())), false)
+        }
+    };
     
     // Read existing .env or create new
     let mut env_content = if env_path.exists() {
@@ -67,31 +242,22 @@ pub async fn save_oauth_config_handler(
         String::new()
     };
     
-    // Remove existing Google OAuth lines
-    let lines: Vec<String> = env_content
-        .lines()
-        .filter(|line| {
-            !line.starts_with("GOOGLE_CLIENT_ID=") &&
-            !line.starts_with("GOOGLE_CLIENT_SECRET=") &&
-            !line.starts_with("GOOGLE_REDIRECT_URI=")
-        })
-        .map(|s| s.to_string())
-        .collect();
-    
-    env_content = lines.join("\n");
-    
-    // Add new credentials
-    if !env_content.is_empty() && !env_content.ends_with('\n') {
-        env_content.push('\n');
+    // Upsert this provider's credentials
+    let prefix = req.provider.env_prefix();
+    if !env_content.contains(&format!("{}_CLIENT_ID=", prefix)) {
+        if !env_content.is_empty() && !env_content.ends_with('\n') {
+            env_content.push('\n');
+        }
+        env_content.push_str(&format!("\n# {}\n", req.provider.label()));
+    }
+    env_content = upsert_env_key(&env_content, &format!("{}_CLIENT_ID", prefix), &req.client_id);
+    env_content = upsert_env_key(&env_content, &format!("{}_CLIENT_SECRET", prefix), &req.client_secret);
+    if !req.redirect_uri.is_empty() {
+        env_content = upsert_env_key(&env_content, &format!("{}_REDIRECT_URI", prefix), &req.redirect_uri);
     }
     
-    env_content.push_str(&format!("\n# Google OAuth for Gmail Integration\n"));
-    env_content.push_str(&format!("GOOGLE_CLIENT_ID={}\n", req.client_id));
-    env_content.push_str(&format!("GOOGLE_CLIENT_SECRET={}\n", req.client_secret));
-    env_content.push_str(&format!("GOOGLE_REDIRECT_URI={}\n", req.redirect_uri));
-    
-    // Generate encryption key if not exists
-    if !env_content.contains("TOKEN_ENCRYPTION_KEY=") {
+    // The API encrypts stored tokens with this; generate it if not exists
+    if harness && !env_content.contains("TOKEN_ENCRYPTION_KEY=") {
         use rand::Rng;
         use base64::{engine::general_purpose, Engine as _};
         let key: [u8; 32] = rand::thread_rng().gen();
@@ -100,11 +266,53 @@ pub async fn save_oauth_config_handler(
     }
     
     // Write to file
-    fs::write(&env_path, env_content)
+    write_private(&env_path, &env_content)
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
     
+    // Make the new credentials visible to executors without a restart
+    if !harness {
+        let mut saved = saved_credentials().write().unwrap();
+        saved.insert(format!("{}_CLIENT_ID", prefix), req.client_id.clone());
+        saved.insert(format!("{}_CLIENT_SECRET", prefix), req.client_secret.clone());
+        if !req.redirect_uri.is_empty() {
+            saved.insert(format!("{}_REDIRECT_URI", prefix), req.redirect_uri.clone());
+        }
+    }
+    
     Ok(Json(OAuthConfigResponse {
         success: true,
         message: format!("OAuth credentials saved to {}", env_path.display()),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_upsert_env_key_replaces_and_dedupes() {
+        let content = "A=1\nGOOGLE_CLIENT_ID=old\nB=2\nGOOGLE_CLIENT_ID=older\n";
+        let out = upsert_env_key(content, "GOOGLE_CLIENT_ID", "new");
+        assert_eq!(out, "A=1\nGOOGLE_CLIENT_ID=new\nB=2\n");
+        
+        let out = upsert_env_key(&out, "SLACK_CLIENT_ID", "123.456");
+        assert_eq!(out, "A=1\nGOOGLE_CLIENT_ID=new\nB=2\nSLACK_CLIENT_ID=123.456\n");
+    }
+    
+    #[test]
+    fn test_saved_credentials_read_without_touching_env() {
+        assert_eq!(credential("ONECLAW_TEST_SAVED_CLIENT_ID"), None);
+        saved_credentials().write().unwrap().insert("ONECLAW_TEST_SAVED_CLIENT_ID".to_string(), "abc".to_string());
+        assert_eq!(credential("ONECLAW_TEST_SAVED_CLIENT_ID").as_deref(), Some("abc"));
+        assert!(std::env::var("ONECLAW_TEST_SAVED_CLIENT_ID").is_err());
+    }
+    
+    #[test]
+    fn test_provider_validation() {
+        assert!(OAuthProvider::Google.validate("abc.apps.googleusercontent.com", "GOCSPX-x").is_ok());
+        assert!(OAuthProvider::Slack.validate("1234567890.0987654321", "0123456789abcdef0123456789abcdef").is_ok());
+        assert!(OAuthProvider::Slack.validate("1234567890.0987654321", "xoxb-123").is_err());
+        assert!(OAuthProvider::Github.validate("Ov23liABCDEFGHIJKLMN", &"a".repeat(40)).is_ok());
+        assert!(OAuthProvider::Github.validate("not-a-client-id", &"a".repeat(40)).is_err());
+    }
+}
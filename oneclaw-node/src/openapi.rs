@@ -0,0 +1,173 @@
+//! OpenAPI Document
+//!
+//! Builds an OpenAPI 3 description of the daemon API. Request/response schemas are
+//! derived from the serde types with `schemars`, so they follow the code; the route
+//! list lives next to the router (`daemon::openapi_document`).
+
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+pub struct ApiDoc {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+/// One method on one path; added to the document by `done`
+pub struct Operation<'a> {
+    doc: &'a mut ApiDoc,
+    path: String,
+    method: &'static str,
+    op: Map<String, Value>,
+}
+
+impl ApiDoc {
+    pub fn new() -> Self {
+        Self { generator: SchemaSettings::openapi3().into_generator(), paths: Map::new() }
+    }
+
+    pub fn get(&mut self, path: &str, summary: &str) -> Operation<'_> {
+        self.operation("get", path, summary)
+    }
+
+    pub fn post(&mut self, path: &str, summary: &str) -> Operation<'_> {
+        self.operation("post", path, summary)
+    }
+
+    pub fn delete(&mut self, path: &str, summary: &str) -> Operation<'_> {
+        self.operation("delete", path, summary)
+    }
+
+    /// Axum-style `:name` segments become OpenAPI `{name}` path parameters
+    fn operation(&mut self, method: &'static str, path: &str, summary: &str) -> Operation<'_> {
+        let mut parameters = Vec::new();
+        let segments: Vec<String> = path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => {
+                    parameters.push(json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" }
+                    }));
+                    format!("{{{}}}", name)
+                }
+                None => segment.to_string(),
+            })
+            .collect();
+
+        let mut op = Map::new();
+        op.insert("summary".to_string(), json!(summary));
+        if !parameters.is_empty() {
+            op.insert("parameters".to_string(), Value::Array(parameters));
+        }
+        Operation { doc: self, path: segments.join("/"), method, op }
+    }
+
+    fn schema_for<T: JsonSchema>(&mut self) -> Value {
+        serde_json::to_value(self.generator.subschema_for::<T>()).unwrap_or_default()
+    }
+
+    pub fn finish(self, title: &str, version: &str) -> Value {
+        let schemas = serde_json::to_value(self.generator.definitions()).unwrap_or_default();
+        json!({
+            "openapi": "3.0.3",
+            "info": { "title": title, "version": version },
+            "paths": self.paths,
+            "components": { "schemas": schemas }
+        })
+    }
+}
+
+impl Default for ApiDoc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Operation<'_> {
+    pub fn query(mut self, name: &str, description: &str) -> Self {
+        let parameters = self.op.entry("parameters").or_insert_with(|| json!([]));
+        if let Some(parameters) = parameters.as_array_mut() {
+            parameters.push(json!({
+                "name": name,
+                "in": "query",
+                "required": false,
+                "description": description,
+                "schema": { "type": "string" }
+            }));
+        }
+        self
+    }
+
+    /// JSON request body
+    pub fn body<T: JsonSchema>(mut self) -> Self {
+        let schema = self.doc.schema_for::<T>();
+        self.op.insert(
+            "requestBody".to_string(),
+            json!({ "required": true, "content": { "application/json": { "schema": schema } } }),
+        );
+        self
+    }
+
+    /// 200 with a JSON body
+    pub fn json<T: JsonSchema>(self) -> Self {
+        let schema = self.doc.schema_for::<T>();
+        self.response(200, "OK", Some(("application/json", schema)))
+    }
+
+    /// Another success status with a JSON body (e.g. `202 Accepted`)
+    pub fn json_status<T: JsonSchema>(self, status: u16, description: &str) -> Self {
+        let schema = self.doc.schema_for::<T>();
+        self.response(status, description, Some(("application/json", schema)))
+    }
+
+    /// 200 with a non-JSON body (files, HTML redirects, ...)
+    pub fn content(self, content_type: &str, description: &str) -> Self {
+        let schema = json!({ "type": "string", "format": "binary" });
+        self.response(200, description, Some((content_type, schema)))
+    }
+
+    /// Non-200 status; error bodies are plain text
+    pub fn status(self, status: u16, description: &str) -> Self {
+        self.response(status, description, Some(("text/plain", json!({ "type": "string" }))))
+    }
+
+    fn response(mut self, status: u16, description: &str, content: Option<(&str, Value)>) -> Self {
+        let responses = self.op.entry("responses").or_insert_with(|| json!({}));
+        let mut response = json!({ "description": description });
+        if let Some((content_type, schema)) = content {
+            response["content"] = json!({ content_type: { "schema": schema } });
+        }
+        responses[status.to_string()] = response;
+        self
+    }
+
+    pub fn done(mut self) {
+        if !self.op.contains_key("responses") {
+            self = self.response(200, "OK", None);
+        }
+        let item = self.doc.paths.entry(self.path).or_insert_with(|| json!({}));
+        item[self.method] = Value::Object(self.op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_daemon_document_refs_resolve() {
+        let doc = crate::daemon::openapi_document();
+        let schemas = doc["components"]["schemas"].as_object().unwrap();
+        let text = doc.to_string();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = &reference[..reference.find('"').unwrap()];
+            assert!(schemas.contains_key(name), "dangling $ref {}", name);
+        }
+        let artifact = &doc["paths"]["/receipts/{run_id}/artifacts/{name}"]["get"];
+        assert_eq!(artifact["parameters"][1]["name"], "name");
+        let chat = &doc["paths"]["/chat"]["post"];
+        assert_eq!(chat["requestBody"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/ChatRequest");
+        assert_eq!(chat["responses"]["200"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/ChatResponse");
+    }
+}
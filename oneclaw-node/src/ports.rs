@@ -1,12 +1,12 @@
 /// PORT CONFIGURATION
 /// 
-/// Daemon port is configurable via CLI: `daemon --port 8787`
+/// Daemon port is configurable via `channels.http.port` or CLI `daemon --port 8787` (which wins)
 /// Harness and API ports are hardcoded below.
 /// 
 /// If you need to change Harness/API ports, change them HERE and rebuild.
 
 /// Daemon - Rust agent runtime (Web UI)
-/// Default: 8787, but configurable via CLI
+/// Default: 8787, but configurable via config or CLI
 pub const DAEMON_PORT: u16 = 8787;
 
 /// Harness - workflow execution engine (Tools API)
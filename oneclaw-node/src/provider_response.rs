@@ -0,0 +1,335 @@
+//! Typed LLM provider responses
+//!
+//! One parser per supported provider, built on that API's documented response shape.
+//! `parse` returns `None` when a body doesn't match; the executor then falls back to its
+//! heuristic scan (meant for unknown OpenAI-compatible endpoints).
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What the executor needs from a completion, whatever the provider
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderResponse {
+    /// Assistant text (text blocks/parts joined by newlines); empty when there is none
+    pub content: String,
+    /// Provider's stop reason as sent (`stop`, `end_turn`, `content_filter`, `refusal`, ...)
+    pub finish_reason: Option<String>,
+    /// Structured refusal text (OpenAI)
+    pub refusal: Option<String>,
+    /// The model asked for native tool calls (these come back without text)
+    pub has_tool_calls: bool,
+    pub usage: Option<Usage>,
+}
+
+/// Token counts, normalized across providers
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Parse `body` with the parser for `provider`
+pub fn parse(provider: &str, body: &Value) -> Option<ProviderResponse> {
+    match provider {
+        "anthropic" => parse_anthropic(body),
+        "openai" => parse_openai(body),
+        "openrouter" => parse_openrouter(body),
+        _ => None,
+    }
+}
+
+// ============================================
+// Anthropic Messages API
+// ============================================
+
+#[derive(Deserialize)]
+struct AnthropicMessage {
+    content: Vec<AnthropicBlock>,
+    stop_reason: Option<String>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum AnthropicBlock {
+    #[serde(rename = "text")]
+    Text { text: String },
+    #[serde(rename = "tool_use")]
+    ToolUse {},
+    /// `thinking`, `redacted_thinking`, ... never shown to the user
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct AnthropicUsage {
+    input_tokens: u64,
+    output_tokens: u64,
+}
+
+pub fn parse_anthropic(body: &Value) -> Option<ProviderResponse> {
+    let message = AnthropicMessage::deserialize(body).ok()?;
+
+    let mut texts = Vec::new();
+    let mut has_tool_calls = false;
+    for block in message.content {
+        match block {
+            AnthropicBlock::Text { text } if !text.trim().is_empty() => texts.push(text),
+            AnthropicBlock::ToolUse {} => has_tool_calls = true,
+            _ => {}
+        }
+    }
+
+    Some(ProviderResponse {
+        content: texts.join("\n"),
+        finish_reason: message.stop_reason,
+        refusal: None,
+        has_tool_calls,
+        usage: message.usage.map(|u| Usage { input_tokens: u.input_tokens, output_tokens: u.output_tokens }),
+    })
+}
+
+// ============================================
+// OpenAI Chat Completions (OpenRouter uses the same shape)
+// ============================================
+
+#[derive(Deserialize)]
+struct ChatCompletion {
+    choices: Vec<ChatChoice>,
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+    finish_reason: Option<String>,
+    /// OpenRouter: per-choice upstream error
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+/// `reasoning` and other extra fields are deliberately not read
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: Option<ChatContent>,
+    refusal: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<Value>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChatContent {
+    Text(String),
+    Parts(Vec<ChatContentPart>),
+}
+
+#[derive(Deserialize)]
+struct ChatContentPart {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+fn parse_chat_completion(body: &Value) -> Option<(ProviderResponse, Option<Value>)> {
+    let completion = ChatCompletion::deserialize(body).ok()?;
+    let choice = completion.choices.into_iter().next()?;
+
+    let content = match choice.message.content {
+        Some(ChatContent::Text(text)) => text,
+        Some(ChatContent::Parts(parts)) => parts
+            .into_iter()
+            .filter_map(|p| p.text)
+            .filter(|t| !t.trim().is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => String::new(),
+    };
+
+    let response = ProviderResponse {
+        content,
+        finish_reason: choice.finish_reason,
+        refusal: choice.message.refusal.filter(|r| !r.trim().is_empty()),
+        has_tool_calls: choice.message.tool_calls.is_some_and(|calls| !calls.is_empty()),
+        usage: completion.usage.map(|u| Usage { input_tokens: u.prompt_tokens, output_tokens: u.completion_tokens }),
+    };
+    Some((response, choice.error))
+}
+
+pub fn parse_openai(body: &Value) -> Option<ProviderResponse> {
+    parse_chat_completion(body).map(|(response, _)| response)
+}
+
+pub fn parse_openrouter(body: &Value) -> Option<ProviderResponse> {
+    let (mut response, error) = parse_chat_completion(body)?;
+    if let Some(error) = error {
+        tracing::warn!("OpenRouter choice carried an upstream error: {}", error);
+        response.finish_reason = Some("error".to_string());
+    }
+    Some(response)
+}
+
+/// A completion requested with `stream: true`, built up from its SSE lines. Understands
+/// Anthropic's event stream and OpenAI-style `chat.completion.chunk`s.
+#[derive(Debug, Default)]
+pub struct Streamed {
+    pub response: ProviderResponse,
+    /// Error event sent mid-stream
+    pub error: Option<String>,
+}
+
+impl Streamed {
+    /// Apply one line of the stream; returns the text it added, if any
+    pub fn apply(&mut self, line: &str) -> Option<String> {
+        let data = line.strip_prefix("data:")?.trim();
+        if data == "[DONE]" {
+            return None;
+        }
+        let event: Value = serde_json::from_str(data).ok()?;
+        let usage = self.response.usage.get_or_insert_with(Usage::default);
+        
+        // Anthropic
+        match event["type"].as_str() {
+            Some("message_start") => {
+                usage.input_tokens = event["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0);
+                return None;
+            }
+            Some("content_block_start") => {
+                self.response.has_tool_calls |= event["content_block"]["type"] == "tool_use";
+                return None;
+            }
+            Some("content_block_delta") => return self.push(event["delta"]["text"].as_str()),
+            Some("message_delta") => {
+                usage.output_tokens = event["usage"]["output_tokens"].as_u64().unwrap_or(usage.output_tokens);
+                self.response.finish_reason = event["delta"]["stop_reason"].as_str().map(str::to_string);
+                return None;
+            }
+            Some("error") => {
+                self.error = Some(event["error"]["message"].as_str().unwrap_or("stream error").to_string());
+                return None;
+            }
+            _ => {}
+        }
+        
+        // OpenAI / OpenRouter; usage comes in a final chunk without choices
+        if let Some(counts) = event["usage"].as_object() {
+            usage.input_tokens = counts.get("prompt_tokens").and_then(Value::as_u64).unwrap_or(0);
+            usage.output_tokens = counts.get("completion_tokens").and_then(Value::as_u64).unwrap_or(0);
+        }
+        if let Some(message) = event["error"]["message"].as_str() {
+            self.error = Some(message.to_string());
+        }
+        let choice = &event["choices"][0];
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.response.finish_reason = Some(reason.to_string());
+        }
+        if let Some(refusal) = choice["delta"]["refusal"].as_str() {
+            self.response.refusal.get_or_insert_with(String::new).push_str(refusal);
+        }
+        self.response.has_tool_calls |= choice["delta"]["tool_calls"].as_array().is_some_and(|calls| !calls.is_empty());
+        self.push(choice["delta"]["content"].as_str())
+    }
+    
+    fn push(&mut self, text: Option<&str>) -> Option<String> {
+        let text = text.filter(|t| !t.is_empty())?;
+        self.response.content.push_str(text);
+        Some(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_anthropic_skips_thinking() {
+        let body = serde_json::json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                { "type": "thinking", "thinking": "Let me work out what they want", "signature": "abc" },
+                { "type": "text", "text": "Hello!" },
+                { "type": "tool_use", "id": "toolu_1", "name": "http.request", "input": {} }
+            ],
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 12, "output_tokens": 5 }
+        });
+        let response = parse("anthropic", &body).unwrap();
+        assert_eq!(response.content, "Hello!");
+        assert_eq!(response.finish_reason.as_deref(), Some("tool_use"));
+        assert!(response.has_tool_calls);
+        assert_eq!(response.usage, Some(Usage { input_tokens: 12, output_tokens: 5 }));
+    }
+
+    #[test]
+    fn test_parse_openai_and_openrouter() {
+        let body = serde_json::json!({
+            "choices": [{
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "Hi there", "reasoning": "internal notes" }
+            }],
+            "usage": { "prompt_tokens": 7, "completion_tokens": 2, "total_tokens": 9 }
+        });
+        for provider in ["openai", "openrouter"] {
+            let response = parse(provider, &body).unwrap();
+            assert_eq!(response.content, "Hi there");
+            assert_eq!(response.finish_reason.as_deref(), Some("stop"));
+            assert_eq!(response.usage, Some(Usage { input_tokens: 7, output_tokens: 2 }));
+        }
+
+        let parts = serde_json::json!({
+            "choices": [{ "finish_reason": null, "message": { "content": [{ "type": "text", "text": "a" }, { "type": "text", "text": "b" }] } }]
+        });
+        assert_eq!(parse_openai(&parts).unwrap().content, "a\nb");
+
+        let upstream_error = serde_json::json!({
+            "choices": [{ "finish_reason": null, "message": { "content": "" }, "error": { "code": 502, "message": "Upstream down" } }]
+        });
+        assert_eq!(parse_openrouter(&upstream_error).unwrap().finish_reason.as_deref(), Some("error"));
+
+        // Not a chat completion: left to the heuristic fallback
+        assert!(parse_openai(&serde_json::json!({ "output_text": "hi" })).is_none());
+        assert!(parse("some_compatible_api", &body).is_none());
+    }
+
+    #[test]
+    fn test_streamed_deltas_accumulate() {
+        let anthropic = [
+            "event: message_start",
+            r#"data: {"type":"message_start","message":{"usage":{"input_tokens":12,"output_tokens":1}}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"HEARTBEAT"}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"_OK"}}"#,
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":4}}"#,
+            "",
+        ];
+        let mut streamed = Streamed::default();
+        let deltas: Vec<String> = anthropic.iter().filter_map(|line| streamed.apply(line)).collect();
+        assert_eq!(deltas, ["HEARTBEAT", "_OK"]);
+        assert_eq!(streamed.response.content, "HEARTBEAT_OK");
+        assert_eq!(streamed.response.finish_reason.as_deref(), Some("end_turn"));
+        assert_eq!(streamed.response.usage, Some(Usage { input_tokens: 12, output_tokens: 4 }));
+
+        let openai = [
+            r#"data: {"choices":[{"delta":{"role":"assistant","content":""}}]}"#,
+            r#"data: {"choices":[{"delta":{"content":"Disk is 95% full"}}]}"#,
+            r#"data: {"choices":[{"delta":{},"finish_reason":"stop"}]}"#,
+            r#"data: {"choices":[],"usage":{"prompt_tokens":30,"completion_tokens":6}}"#,
+            "data: [DONE]",
+        ];
+        let mut streamed = Streamed::default();
+        let deltas: Vec<String> = openai.iter().filter_map(|line| streamed.apply(line)).collect();
+        assert_eq!(deltas, ["Disk is 95% full"]);
+        assert_eq!(streamed.response.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(streamed.response.usage, Some(Usage { input_tokens: 30, output_tokens: 6 }));
+        assert!(streamed.error.is_none());
+
+        streamed.apply(r#"data: {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#);
+        assert_eq!(streamed.error.as_deref(), Some("Overloaded"));
+    }
+}
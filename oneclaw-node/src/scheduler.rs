@@ -0,0 +1,205 @@
+//! Scheduled Workflows
+//!
+//! Runs the workflows listed under `schedules` in node.yaml on their cron
+//! expressions (UTC). Each run goes through `workflow::run_with_id`, so it
+//! writes a normal receipt. Overlapping fires follow the schedule's
+//! `overlap` policy.
+
+use crate::config::{OverlapPolicy, ScheduleConfig};
+use crate::workflow;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use schemars::JsonSchema;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+pub struct Scheduler {
+    entries: Vec<Arc<ScheduledWorkflow>>,
+}
+
+struct ScheduledWorkflow {
+    config: ScheduleConfig,
+    schedule: cron::Schedule,
+    state: Mutex<ScheduleState>,
+}
+
+#[derive(Default)]
+struct ScheduleState {
+    running: bool,
+    queued: bool,
+    skipped_runs: u64,
+    last_run_at: Option<DateTime<Utc>>,
+    last_run_id: Option<String>,
+    last_status: Option<String>,
+}
+
+/// One schedule as reported by `/schedules`
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ScheduleStatus {
+    pub id: String,
+    pub workflow_id: String,
+    pub cron: String,
+    pub overlap: OverlapPolicy,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_run_id: Option<String>,
+    pub last_status: Option<String>,
+    pub running: bool,
+    pub queued: bool,
+    pub skipped_runs: u64,
+}
+
+/// Parse a cron expression; 5-field expressions get a leading `0` seconds field
+pub fn parse_cron(expr: &str) -> anyhow::Result<cron::Schedule> {
+    let expr = expr.trim();
+    let full = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    cron::Schedule::from_str(&full).map_err(|e| anyhow::anyhow!("Invalid cron expression '{}': {}", expr, e))
+}
+
+impl Scheduler {
+    /// Validate every schedule up front so a typo fails startup instead of never firing
+    pub fn new(schedules: &[ScheduleConfig]) -> anyhow::Result<Self> {
+        let mut entries: Vec<Arc<ScheduledWorkflow>> = Vec::new();
+        for config in schedules {
+            if entries.iter().any(|e| e.config.id() == config.id()) {
+                anyhow::bail!("Duplicate schedule id '{}'", config.id());
+            }
+            let schedule = parse_cron(&config.cron)
+                .map_err(|e| e.context(format!("Schedule '{}'", config.id())))?;
+            entries.push(Arc::new(ScheduledWorkflow {
+                config: config.clone(),
+                schedule,
+                state: Mutex::new(ScheduleState::default()),
+            }));
+        }
+        Ok(Self { entries })
+    }
+
+    /// Spawn one timer task per schedule
+    pub fn start(&self) {
+        for entry in &self.entries {
+            tracing::info!(
+                "Schedule '{}' ({}) runs workflow '{}'",
+                entry.config.id(),
+                entry.config.cron,
+                entry.config.workflow_id
+            );
+            let entry = Arc::clone(entry);
+            tokio::spawn(async move {
+                while let Some(next) = entry.schedule.upcoming(Utc).next() {
+                    let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                    tokio::time::sleep(wait).await;
+                    entry.fire();
+                }
+                tracing::warn!("Schedule '{}' has no upcoming times; stopping", entry.config.id());
+            });
+        }
+    }
+
+    pub fn list(&self) -> Vec<ScheduleStatus> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let state = entry.state.lock().unwrap();
+                ScheduleStatus {
+                    id: entry.config.id().to_string(),
+                    workflow_id: entry.config.workflow_id.clone(),
+                    cron: entry.config.cron.clone(),
+                    overlap: entry.config.overlap,
+                    next_run_at: entry.schedule.upcoming(Utc).next(),
+                    last_run_at: state.last_run_at,
+                    last_run_id: state.last_run_id.clone(),
+                    last_status: state.last_status.clone(),
+                    running: state.running,
+                    queued: state.queued,
+                    skipped_runs: state.skipped_runs,
+                }
+            })
+            .collect()
+    }
+}
+
+impl ScheduledWorkflow {
+    /// Start a run now, or apply the overlap policy if one is in progress
+    fn fire(self: &Arc<Self>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.running {
+                match self.config.overlap {
+                    OverlapPolicy::Skip => {
+                        state.skipped_runs += 1;
+                        tracing::warn!("Schedule '{}' fired while still running; skipped", self.config.id());
+                    }
+                    OverlapPolicy::Queue => {
+                        state.queued = true;
+                        tracing::info!("Schedule '{}' fired while still running; queued", self.config.id());
+                    }
+                }
+                return;
+            }
+            state.running = true;
+        }
+
+        let entry = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                entry.run_once().await;
+                let mut state = entry.state.lock().unwrap();
+                if !std::mem::take(&mut state.queued) {
+                    state.running = false;
+                    break;
+                }
+            }
+        });
+    }
+
+    async fn run_once(&self) {
+        let run_id = nanoid::nanoid!();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.last_run_at = Some(Utc::now());
+            state.last_run_id = Some(run_id.clone());
+            state.last_status = Some("running".to_string());
+        }
+        tracing::info!("Schedule '{}' starting run {}", self.config.id(), run_id);
+
+        let status = match workflow::run_with_id(run_id.clone(), &self.config.workflow_id, self.config.inputs.clone()).await {
+            Ok(receipt) => receipt.status,
+            Err(e) => {
+                tracing::error!("Scheduled run {} of '{}' failed: {}", run_id, self.config.workflow_id, e);
+                "error".to_string()
+            }
+        };
+        tracing::info!("Schedule '{}' run {} finished: {}", self.config.id(), run_id, status);
+        self.state.lock().unwrap().last_status = Some(status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cron() {
+        let nightly = parse_cron("0 2 * * *").unwrap();
+        let next = nightly.upcoming(Utc).next().unwrap();
+        assert_eq!(next.format("%H:%M:%S").to_string(), "02:00:00");
+
+        assert!(parse_cron("*/30 * * * * *").is_ok());
+        assert!(parse_cron("every night").is_err());
+
+        let schedule = |id: Option<&str>| ScheduleConfig {
+            id: id.map(str::to_string),
+            workflow_id: "report".to_string(),
+            cron: "0 2 * * *".to_string(),
+            inputs: serde_json::Value::Null,
+            overlap: OverlapPolicy::Skip,
+        };
+        assert!(Scheduler::new(&[schedule(None), schedule(None)]).is_err());
+        assert_eq!(Scheduler::new(&[schedule(None), schedule(Some("weekly"))]).unwrap().list().len(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+//! Secrets
+//!
+//! Resolves named secrets from the `secrets` section of node config.
+//! Resolved values are never logged; callers are expected to pass them to
+//! `receipt::redact_secrets` before anything is persisted.
+
+use crate::config::{self, SecretRef};
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a `command` backend may run before it is killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resolve a secret by name using its configured backend. A `command` backend blocks for
+/// up to `COMMAND_TIMEOUT`, so async callers should run this on a blocking thread.
+pub fn resolve(name: &str) -> anyhow::Result<String> {
+    let config = config::load()?;
+    let secret = config
+        .secrets
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("Secret not configured: {}", name))?;
+    resolve_ref(name, secret)
+}
+
+fn resolve_ref(name: &str, secret: &SecretRef) -> anyhow::Result<String> {
+    let value = match secret {
+        SecretRef::Env { var } => std::env::var(var)
+            .map_err(|_| anyhow::anyhow!("Secret '{}': env var {} not set", name, var))?,
+        SecretRef::File { path } => std::fs::read_to_string(config::expand_path(path))
+            .map_err(|e| anyhow::anyhow!("Secret '{}': failed to read file: {}", name, e))?
+            .trim()
+            .to_string(),
+        SecretRef::Command { command, args } => run_command(name, command, args, COMMAND_TIMEOUT)?,
+    };
+
+    if value.is_empty() {
+        anyhow::bail!("Secret '{}' resolved to an empty value", name);
+    }
+    Ok(value)
+}
+
+/// Run a secret helper and return its trimmed stdout, killing it if it hasn't exited
+/// within `timeout`
+fn run_command(name: &str, command: &str, args: &[String], timeout: Duration) -> anyhow::Result<String> {
+    let mut child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        // stderr may echo the secret, so it is dropped and only the exit status reported
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Secret '{}': failed to run {}: {}", name, command, e))?;
+
+    // Read on another thread so a helper that writes a lot can't stall on a full pipe
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = tx.send(stdout.read_to_end(&mut buf).map(|_| buf));
+    });
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = || anyhow::anyhow!("Secret '{}': {} timed out after {:?}", name, command, timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(timed_out());
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    if !status.success() {
+        anyhow::bail!("Secret '{}': {} exited with {}", name, command, status);
+    }
+
+    // A background process the helper left holding stdout counts against the same deadline
+    let stdout = rx
+        .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .map_err(|_| timed_out())?
+        .map_err(|e| anyhow::anyhow!("Secret '{}': failed to read output of {}: {}", name, command, e))?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_output_trimmed() {
+        let secret = SecretRef::Command { command: "echo".to_string(), args: vec!["tok-123".to_string()] };
+        assert_eq!(resolve_ref("github", &secret).unwrap(), "tok-123");
+    }
+
+    #[test]
+    fn test_hanging_command_times_out() {
+        let started = Instant::now();
+        let err = run_command("github", "sleep", &["30".to_string()], Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,62 @@
+//! Node Services
+//!
+//! The store, receipt backend, budget, cancellations, events and channel statuses, for
+//! code that has no `AppState`: workflow runs, executors (on blocking threads) and
+//! channels. The daemon installs the instances it keeps in `AppState`; any other process
+//! (`oneclaw-node run`) opens them from config on first use, so budgets and receipts
+//! apply to CLI runs too.
+
+use crate::agent_os::SharedTools;
+use crate::budget::Budget;
+use crate::cancel::Cancellations;
+use crate::channels::status::ChannelStatuses;
+use crate::events::Events;
+use crate::receipt::{self, ReceiptStore};
+use crate::{config, store};
+use std::sync::Arc;
+
+static SERVICES: tokio::sync::OnceCell<Services> = tokio::sync::OnceCell::const_new();
+
+pub struct Services {
+    pub store: Arc<dyn store::Store>,
+    pub receipts: Arc<dyn ReceiptStore>,
+    pub budget: Arc<Budget>,
+    pub cancellations: Arc<Cancellations>,
+    pub events: Arc<Events>,
+    pub channel_statuses: Arc<ChannelStatuses>,
+}
+
+impl Services {
+    /// Fresh services for `config`. Without the harness tool list, `harness.execute` calls
+    /// are costed at 0.
+    pub async fn open(config: &config::NodeConfig) -> anyhow::Result<Self> {
+        let store: Arc<dyn store::Store> = Arc::from(store::create_store(store::StoreType::from_config(config)).await?);
+        Ok(Self {
+            receipts: receipt::open(config, Some(Arc::clone(&store))).await?,
+            budget: Arc::new(Budget::new(Arc::clone(&store), config.budget.clone(), SharedTools::new(Vec::new()))),
+            store,
+            cancellations: Arc::default(),
+            events: Arc::default(),
+            channel_statuses: Arc::default(),
+        })
+    }
+}
+
+/// Use `services` for the rest of the process. The daemon calls this at startup, before
+/// any workflow runs.
+pub fn install(services: Services) {
+    if SERVICES.set(services).is_err() {
+        tracing::warn!("Node services were already set up; keeping the first ones");
+    }
+}
+
+/// The services, opened from config if nothing was installed
+pub async fn get() -> anyhow::Result<&'static Services> {
+    SERVICES.get_or_try_init(|| async { Services::open(config::load()?).await }).await
+}
+
+/// The services if they are set up already, for synchronous callers that have nothing to
+/// do otherwise (e.g. no events are emitted before the first run opens them)
+pub fn installed() -> Option<&'static Services> {
+    SERVICES.get()
+}
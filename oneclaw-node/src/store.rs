@@ -4,10 +4,14 @@
 //! - SqliteStore: Local SQLite database (free tier, fully private)
 //! - HostedStore: OneClaw Harness API (paid tier, synced)
 
+use crate::channels::OutgoingMessage;
+use crate::receipt::WorkflowReceipt;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::path::PathBuf;
+use rusqlite::OptionalExtension;
 use tokio_rusqlite::Connection;
 
 // ============================================
@@ -21,7 +25,7 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Identity {
     pub user_id: String,
     pub provider: String,         // "discord", "slack", "telegram", "http"
@@ -30,7 +34,7 @@ pub struct Identity {
     pub linked_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConversationMessage {
     pub id: i64,
     pub user_id: String,
@@ -39,15 +43,122 @@ pub struct ConversationMessage {
     pub channel: String,          // Which channel this came from
     pub tool_calls: Option<String>, // JSON string of tool calls
     pub created_at: DateTime<Utc>,
+    /// Set when history is scoped per channel (`"discord:<channel_id>"`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    /// Id of the message this one replies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ConversationMessage {
+    /// Where to continue paging backwards from this message
+    pub fn cursor(&self) -> MessageCursor {
+        MessageCursor { created_at: self.created_at, id: self.id }
+    }
+}
+
+/// A position in history, which is ordered by `created_at` and then `id`. Paging by both keeps
+/// pages consistent when ids and timestamps disagree, as they do for imported messages.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MessageCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+/// One message of a batch written with `Store::add_messages`
+#[derive(Debug, Clone, Serialize)]
+pub struct NewMessage {
+    pub role: String,
+    pub content: String,
+    pub tool_calls: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<ReplyTo>,
+}
+
+impl NewMessage {
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        Self { role: role.to_string(), content: content.into(), tool_calls: None, reply_to: None }
+    }
+    
+    pub fn replying_to(self, reply_to: Option<ReplyTo>) -> Self {
+        Self { reply_to, ..self }
+    }
+}
+
+/// Parent of a `NewMessage`. Serialized as `{"message": 12}` or `{"batch": 0}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplyTo {
+    /// A message that is already stored
+    Message(i64),
+    /// The message at this index of the same `add_messages` batch
+    Batch(usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Preferences {
     pub user_id: String,
     pub data: serde_json::Value,  // Flexible JSON preferences
     pub updated_at: DateTime<Utc>,
 }
 
+/// Title, tags and pin flag for one conversation thread (`thread_id: None` is the unthreaded history)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationMeta {
+    pub user_id: String,
+    #[serde(default)]
+    pub thread_id: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Standing per-user instruction added to every LLM turn; survives conversation clears
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PinnedDirective {
+    pub id: i64,
+    pub user_id: String,
+    /// Only applies in this thread; every thread when unset
+    #[serde(default)]
+    pub thread_id: Option<String>,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// OAuth token a user connected for a provider (`"google"`, `"github"`, `"slack"`); see `oauth`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OAuthToken {
+    pub user_id: String,
+    pub provider: String,
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// `None` for tokens that don't expire
+    pub expires_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A channel reply that failed to send, kept for the retrier in `dead_letter`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeadLetter {
+    pub id: String,
+    pub message: OutgoingMessage,
+    /// Error from the most recent attempt
+    pub error: String,
+    /// Sends tried so far, including the original one
+    pub attempts: u32,
+    /// `pending` (will be retried), `delivered` or `failed` (retries used up)
+    pub status: String,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 // ============================================
 // Store Trait
 // ============================================
@@ -60,23 +171,302 @@ pub trait Store: Send + Sync {
     
     // Identity operations
     async fn get_identity(&self, provider: &str, provider_id: &str) -> anyhow::Result<Option<Identity>>;
+    /// Link an identity to a user. Fails if it's already linked to a different user.
     async fn link_identity(&self, user_id: &str, provider: &str, provider_id: &str, username: Option<&str>) -> anyhow::Result<()>;
+    
+    /// Create `user_id` and link the identity to it, unless the identity is
+    /// already linked. Returns the owning user ID and whether it was created
+    /// here, so concurrent first contacts resolve to a single user.
+    async fn create_user_with_identity(&self, user_id: &str, provider: &str, provider_id: &str, username: Option<&str>) -> anyhow::Result<(String, bool)> {
+        if let Some(existing) = self.get_identity(provider, provider_id).await? {
+            return Ok((existing.user_id, false));
+        }
+        self.create_user(user_id).await?;
+        if let Err(e) = self.link_identity(user_id, provider, provider_id, username).await {
+            // Lost the race: whoever linked first owns the identity
+            return match self.get_identity(provider, provider_id).await? {
+                Some(winner) if winner.user_id != user_id => Ok((winner.user_id, false)),
+                _ => Err(e),
+            };
+        }
+        Ok((user_id.to_string(), true))
+    }
+    
     async fn get_user_identities(&self, user_id: &str) -> anyhow::Result<Vec<Identity>>;
     
     // Conversation operations
-    async fn get_conversation(&self, user_id: &str, limit: usize) -> anyhow::Result<Vec<ConversationMessage>>;
-    async fn add_message(&self, user_id: &str, role: &str, content: &str, channel: &str, tool_calls: Option<&str>) -> anyhow::Result<i64>;
-    async fn clear_conversation(&self, user_id: &str) -> anyhow::Result<()>;
+    /// `thread_id: None` covers all of the user's messages, threaded or not
+    async fn get_conversation(&self, user_id: &str, thread_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<ConversationMessage>>;
+    /// Page backwards: up to `limit` messages older than `before`, chronological order
+    async fn get_conversation_before(&self, user_id: &str, thread_id: Option<&str>, before: MessageCursor, limit: usize) -> anyhow::Result<Vec<ConversationMessage>>;
+    /// One of the user's messages by id; `None` if it doesn't exist or belongs to someone else.
+    /// The default walks the user's history; backends that can look a message up override it.
+    async fn get_message(&self, user_id: &str, id: i64) -> anyhow::Result<Option<ConversationMessage>> {
+        const PAGE: usize = 200;
+        let mut page = self.get_conversation(user_id, None, PAGE).await?;
+        loop {
+            if let Some(found) = page.iter().find(|m| m.id == id) {
+                return Ok(Some(found.clone()));
+            }
+            let Some(oldest) = page.first().filter(|_| page.len() == PAGE).map(ConversationMessage::cursor) else {
+                return Ok(None);
+            };
+            page = self.get_conversation_before(user_id, None, oldest, PAGE).await?;
+        }
+    }
+    async fn add_message(&self, user_id: &str, thread_id: Option<&str>, role: &str, content: &str, channel: &str, tool_calls: Option<&str>) -> anyhow::Result<i64>;
+    /// Add several messages (e.g. a whole chat turn) in order, all or none. Returns their ids.
+    /// A `ReplyTo::Batch` index must point at an earlier message of the batch.
+    async fn add_messages(&self, user_id: &str, thread_id: Option<&str>, channel: &str, messages: &[NewMessage]) -> anyhow::Result<Vec<i64>>;
+    async fn clear_conversation(&self, user_id: &str, thread_id: Option<&str>) -> anyhow::Result<()>;
+    /// Insert a previously exported message, keeping its original `created_at`.
+    /// The store assigns a fresh id; the returned value is that new id. `reply_to` is
+    /// dropped, since the parent's id changes on import too.
+    async fn import_message(&self, message: &ConversationMessage) -> anyhow::Result<i64>;
     
     // Preferences operations
     async fn get_preferences(&self, user_id: &str) -> anyhow::Result<Option<Preferences>>;
     async fn set_preferences(&self, user_id: &str, data: serde_json::Value) -> anyhow::Result<()>;
+    
+    // Conversation metadata operations
+    async fn get_conversation_meta(&self, user_id: &str, thread_id: Option<&str>) -> anyhow::Result<Option<ConversationMeta>>;
+    /// Replace the metadata for `(meta.user_id, meta.thread_id)`; the store sets `updated_at`
+    async fn set_conversation_meta(&self, meta: &ConversationMeta) -> anyhow::Result<()>;
+    
+    // Pinned directive operations
+    /// All of the user's directives, thread-scoped ones included; oldest first
+    async fn get_directives(&self, user_id: &str) -> anyhow::Result<Vec<PinnedDirective>>;
+    /// Returns the new directive's id
+    async fn add_directive(&self, user_id: &str, thread_id: Option<&str>, content: &str) -> anyhow::Result<i64>;
+    /// `false` when the user has no directive with that id
+    async fn remove_directive(&self, user_id: &str, id: i64) -> anyhow::Result<bool>;
+    
+    // Spend tracking (budget)
+    /// Spend recorded for `scope` (`"node"`, `"user:<id>"`) on `day` (`YYYY-MM-DD`, UTC)
+    async fn get_spend(&self, scope: &str, day: &str) -> anyhow::Result<f64>;
+    /// Add `usd` to the day's spend and return the new total
+    async fn add_spend(&self, scope: &str, day: &str, usd: f64) -> anyhow::Result<f64>;
+    
+    // OAuth tokens
+    async fn get_oauth_token(&self, user_id: &str, provider: &str) -> anyhow::Result<Option<OAuthToken>>;
+    /// Insert or replace the token for `(token.user_id, token.provider)`
+    async fn set_oauth_token(&self, token: &OAuthToken) -> anyhow::Result<()>;
+    
+    // Dead letters (failed channel sends)
+    /// Insert or replace by `id`
+    async fn save_dead_letter(&self, letter: &DeadLetter) -> anyhow::Result<()>;
+    /// Soonest `next_attempt_at` first; all statuses when `status` is `None`
+    async fn list_dead_letters(&self, status: Option<&str>, limit: usize) -> anyhow::Result<Vec<DeadLetter>>;
+    
+    // Workflow receipts (`artifacts.storage: store`, see `receipt::StoreReceipts`)
+    /// Insert or replace by `run_id`
+    async fn save_receipt(&self, receipt: &WorkflowReceipt) -> anyhow::Result<()>;
+    async fn get_receipt(&self, run_id: &str) -> anyhow::Result<Option<WorkflowReceipt>>;
+    /// Run IDs, most recently started first
+    async fn list_receipt_ids(&self) -> anyhow::Result<Vec<String>>;
+    
+    /// Cheap round-trip used by deep health checks
+    async fn ping(&self) -> anyhow::Result<()> {
+        self.get_user("__health_check__").await.map(|_| ())
+    }
 }
 
 // ============================================
 // SQLite Store (Local/Private)
 // ============================================
 
+/// One schema change. Applied in order, each in its own transaction, with the
+/// database's `PRAGMA user_version` recording how many have run.
+struct Migration {
+    name: &'static str,
+    sql: &'static str,
+    /// Query that succeeds if a database from before versioning already has this change
+    already_applied: Option<&'static str>,
+}
+
+/// Append only: never edit or reorder a migration that has shipped
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        name: "initial schema",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS users (
+                id TEXT PRIMARY KEY,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS identities (
+                user_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                provider_id TEXT NOT NULL,
+                username TEXT,
+                linked_at TEXT NOT NULL,
+                PRIMARY KEY (provider, provider_id),
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_identities_user ON identities(user_id);
+            
+            CREATE TABLE IF NOT EXISTS conversations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                channel TEXT NOT NULL,
+                tool_calls TEXT,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_conversations_user ON conversations(user_id, created_at DESC);
+            
+            CREATE TABLE IF NOT EXISTS preferences (
+                user_id TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+        "#,
+        already_applied: None,
+    },
+    Migration {
+        name: "conversation threads",
+        sql: r#"
+            ALTER TABLE conversations ADD COLUMN thread_id TEXT;
+            CREATE INDEX IF NOT EXISTS idx_conversations_thread ON conversations(user_id, thread_id, created_at DESC);
+        "#,
+        already_applied: Some("SELECT thread_id FROM conversations LIMIT 0"),
+    },
+    Migration {
+        name: "conversation metadata",
+        // thread_id is '' for the unthreaded history (NULLs don't collide in a primary key)
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS conversation_meta (
+                user_id TEXT NOT NULL,
+                thread_id TEXT NOT NULL DEFAULT '',
+                title TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                pinned INTEGER NOT NULL DEFAULT 0,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, thread_id),
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+        "#,
+        already_applied: None,
+    },
+    Migration {
+        name: "spend tracking",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS spend (
+                scope TEXT NOT NULL,
+                day TEXT NOT NULL,
+                usd REAL NOT NULL DEFAULT 0,
+                PRIMARY KEY (scope, day)
+            );
+        "#,
+        already_applied: None,
+    },
+    Migration {
+        name: "pinned directives",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS pinned_directives (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                user_id TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users(id)
+            );
+        "#,
+        already_applied: None,
+    },
+    Migration {
+        name: "oauth tokens",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS oauth_tokens (
+                user_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                access_token TEXT NOT NULL,
+                refresh_token TEXT,
+                expires_at TEXT,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, provider)
+            );
+        "#,
+        already_applied: None,
+    },
+    Migration {
+        name: "dead letters",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS dead_letters (
+                id TEXT PRIMARY KEY,
+                message TEXT NOT NULL,
+                error TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                next_attempt_at TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_dead_letters_due ON dead_letters(status, next_attempt_at);
+        "#,
+        already_applied: None,
+    },
+    Migration {
+        name: "receipts",
+        // The full receipt is `data`; the other columns are copies for filtering
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS receipts (
+                run_id TEXT PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                status TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                data TEXT NOT NULL
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_receipts_started ON receipts(started_at DESC);
+        "#,
+        already_applied: None,
+    },
+    Migration {
+        name: "message replies",
+        sql: r#"
+            ALTER TABLE conversations ADD COLUMN reply_to INTEGER;
+        "#,
+        already_applied: Some("SELECT reply_to FROM conversations LIMIT 0"),
+    },
+    Migration {
+        name: "directive threads",
+        sql: r#"
+            ALTER TABLE pinned_directives ADD COLUMN thread_id TEXT;
+        "#,
+        already_applied: Some("SELECT thread_id FROM pinned_directives LIMIT 0"),
+    },
+];
+
+/// Bring the schema up to `MIGRATIONS.len()`. A failed migration rolls back and
+/// leaves the version at the last one that succeeded.
+fn migrate(conn: &mut rusqlite::Connection) -> rusqlite::Result<()> {
+    let current: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if current > MIGRATIONS.len() {
+        tracing::warn!("Store schema version {} is newer than this build knows ({})", current, MIGRATIONS.len());
+        return Ok(());
+    }
+    
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+        let version = index + 1;
+        let tx = conn.transaction()?;
+        let applied = migration.already_applied.is_some_and(|probe| tx.prepare(probe).is_ok());
+        if !applied {
+            tx.execute_batch(migration.sql)?;
+        }
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+        tracing::info!("Store schema migrated to version {} ({})", version, migration.name);
+    }
+    Ok(())
+}
+
 pub struct SqliteStore {
     conn: Connection,
 }
@@ -90,56 +480,65 @@ impl SqliteStore {
         
         let conn = Connection::open(path).await?;
         
-        // Initialize schema
-        conn.call(|conn| {
-            conn.execute_batch(r#"
-                CREATE TABLE IF NOT EXISTS users (
-                    id TEXT PRIMARY KEY,
-                    created_at TEXT NOT NULL,
-                    updated_at TEXT NOT NULL
-                );
-                
-                CREATE TABLE IF NOT EXISTS identities (
-                    user_id TEXT NOT NULL,
-                    provider TEXT NOT NULL,
-                    provider_id TEXT NOT NULL,
-                    username TEXT,
-                    linked_at TEXT NOT NULL,
-                    PRIMARY KEY (provider, provider_id),
-                    FOREIGN KEY (user_id) REFERENCES users(id)
-                );
-                
-                CREATE INDEX IF NOT EXISTS idx_identities_user ON identities(user_id);
-                
-                CREATE TABLE IF NOT EXISTS conversations (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    user_id TEXT NOT NULL,
-                    role TEXT NOT NULL,
-                    content TEXT NOT NULL,
-                    channel TEXT NOT NULL,
-                    tool_calls TEXT,
-                    created_at TEXT NOT NULL,
-                    FOREIGN KEY (user_id) REFERENCES users(id)
-                );
-                
-                CREATE INDEX IF NOT EXISTS idx_conversations_user ON conversations(user_id, created_at DESC);
-                
-                CREATE TABLE IF NOT EXISTS preferences (
-                    user_id TEXT PRIMARY KEY,
-                    data TEXT NOT NULL,
-                    updated_at TEXT NOT NULL,
-                    FOREIGN KEY (user_id) REFERENCES users(id)
-                );
-            "#)?;
-            Ok(())
-        }).await?;
+        conn.call(|conn| Ok(migrate(conn)?)).await?;
         
         Ok(Self { conn })
     }
+    
+    /// Newest `limit` messages (optionally older than `before`), returned oldest-first
+    async fn query_conversation(&self, user_id: &str, thread_id: Option<&str>, before: Option<MessageCursor>, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
+        let user_id = user_id.to_string();
+        let thread_id = thread_id.map(|t| t.to_string());
+        // Same format the rows are written in, so the text comparison orders like ORDER BY
+        let before_at = before.map(|c| c.created_at.to_rfc3339());
+        let before_id = before.map(|c| c.id);
+        
+        self.conn.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, user_id, role, content, channel, tool_calls, created_at, thread_id, reply_to 
+                 FROM conversations 
+                 WHERE user_id = ?1 AND (?2 IS NULL OR thread_id = ?2) 
+                   AND (?3 IS NULL OR created_at < ?3 OR (created_at = ?3 AND id < ?4)) 
+                 ORDER BY created_at DESC, id DESC 
+                 LIMIT ?5"
+            )?;
+            let rows = stmt.query_map(rusqlite::params![user_id, thread_id, before_at, before_id, limit], conversation_row)?;
+            
+            let mut messages: Vec<ConversationMessage> = Vec::new();
+            for row in rows {
+                messages.push(row?);
+            }
+            // Reverse to get chronological order
+            messages.reverse();
+            Ok(messages)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+}
+
+/// A `conversations` row selected as `id, user_id, role, content, channel, tool_calls, created_at, thread_id, reply_to`
+fn conversation_row(row: &rusqlite::Row) -> rusqlite::Result<ConversationMessage> {
+    Ok(ConversationMessage {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        role: row.get(2)?,
+        content: row.get(3)?,
+        channel: row.get(4)?,
+        tool_calls: row.get(5)?,
+        created_at: row.get::<_, String>(6)?.parse().unwrap_or_else(|_| Utc::now()),
+        thread_id: row.get(7)?,
+        reply_to: row.get(8)?,
+    })
 }
 
 #[async_trait]
 impl Store for SqliteStore {
+    async fn ping(&self) -> anyhow::Result<()> {
+        self.conn.call(|conn| {
+            conn.query_row("SELECT 1", [], |_| Ok(()))?;
+            Ok(())
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
     async fn get_user(&self, user_id: &str) -> anyhow::Result<Option<User>> {
         let user_id = user_id.to_string();
         self.conn.call(move |conn| {
@@ -208,14 +607,55 @@ impl Store for SqliteStore {
         let now = Utc::now().to_rfc3339();
         
         self.conn.call(move |conn| {
-            conn.execute(
-                "INSERT OR REPLACE INTO identities (user_id, provider, provider_id, username, linked_at) VALUES (?, ?, ?, ?, ?)",
+            // Re-linking to the same user refreshes the username; another user's identity is left alone
+            let changed = conn.execute(
+                "INSERT INTO identities (user_id, provider, provider_id, username, linked_at) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(provider, provider_id) DO UPDATE SET username = excluded.username
+                 WHERE identities.user_id = excluded.user_id",
                 rusqlite::params![user_id, provider, provider_id, username, now],
             )?;
+            if changed == 0 {
+                return Err(tokio_rusqlite::Error::Other(
+                    format!("Identity {}:{} is already linked to another user", provider, provider_id).into(),
+                ));
+            }
             Ok(())
         }).await.map_err(|e| anyhow::anyhow!("{}", e))
     }
     
+    async fn create_user_with_identity(&self, user_id: &str, provider: &str, provider_id: &str, username: Option<&str>) -> anyhow::Result<(String, bool)> {
+        let user_id = user_id.to_string();
+        let provider = provider.to_string();
+        let provider_id = provider_id.to_string();
+        let username = username.map(|s| s.to_string());
+        let now = Utc::now().to_rfc3339();
+        
+        self.conn.call(move |conn| {
+            let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+            let existing: Option<String> = tx
+                .query_row(
+                    "SELECT user_id FROM identities WHERE provider = ? AND provider_id = ?",
+                    [&provider, &provider_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(owner) = existing {
+                return Ok((owner, false));
+            }
+            
+            tx.execute(
+                "INSERT INTO users (id, created_at, updated_at) VALUES (?, ?, ?)",
+                [&user_id, &now, &now],
+            )?;
+            tx.execute(
+                "INSERT INTO identities (user_id, provider, provider_id, username, linked_at) VALUES (?, ?, ?, ?, ?)",
+                rusqlite::params![user_id, provider, provider_id, username, now],
+            )?;
+            tx.commit()?;
+            Ok((user_id, true))
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
     async fn get_user_identities(&self, user_id: &str) -> anyhow::Result<Vec<Identity>> {
         let user_id = user_id.to_string();
         
@@ -241,41 +681,29 @@ impl Store for SqliteStore {
         }).await.map_err(|e| anyhow::anyhow!("{}", e))
     }
     
-    async fn get_conversation(&self, user_id: &str, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
+    async fn get_conversation(&self, user_id: &str, thread_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
+        self.query_conversation(user_id, thread_id, None, limit).await
+    }
+    
+    async fn get_conversation_before(&self, user_id: &str, thread_id: Option<&str>, before: MessageCursor, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
+        self.query_conversation(user_id, thread_id, Some(before), limit).await
+    }
+    
+    async fn get_message(&self, user_id: &str, id: i64) -> anyhow::Result<Option<ConversationMessage>> {
         let user_id = user_id.to_string();
-        
         self.conn.call(move |conn| {
-            let mut stmt = conn.prepare(
-                "SELECT id, user_id, role, content, channel, tool_calls, created_at 
-                 FROM conversations 
-                 WHERE user_id = ? 
-                 ORDER BY created_at DESC 
-                 LIMIT ?"
-            )?;
-            let rows = stmt.query_map(rusqlite::params![user_id, limit], |row| {
-                Ok(ConversationMessage {
-                    id: row.get(0)?,
-                    user_id: row.get(1)?,
-                    role: row.get(2)?,
-                    content: row.get(3)?,
-                    channel: row.get(4)?,
-                    tool_calls: row.get(5)?,
-                    created_at: row.get::<_, String>(6)?.parse().unwrap_or_else(|_| Utc::now()),
-                })
-            })?;
-            
-            let mut messages: Vec<ConversationMessage> = Vec::new();
-            for row in rows {
-                messages.push(row?);
-            }
-            // Reverse to get chronological order
-            messages.reverse();
-            Ok(messages)
+            Ok(conn.query_row(
+                "SELECT id, user_id, role, content, channel, tool_calls, created_at, thread_id, reply_to 
+                 FROM conversations WHERE user_id = ?1 AND id = ?2",
+                rusqlite::params![user_id, id],
+                conversation_row,
+            ).optional()?)
         }).await.map_err(|e| anyhow::anyhow!("{}", e))
     }
     
-    async fn add_message(&self, user_id: &str, role: &str, content: &str, channel: &str, tool_calls: Option<&str>) -> anyhow::Result<i64> {
+    async fn add_message(&self, user_id: &str, thread_id: Option<&str>, role: &str, content: &str, channel: &str, tool_calls: Option<&str>) -> anyhow::Result<i64> {
         let user_id = user_id.to_string();
+        let thread_id = thread_id.map(|t| t.to_string());
         let role = role.to_string();
         let content = content.to_string();
         let channel = channel.to_string();
@@ -284,22 +712,66 @@ impl Store for SqliteStore {
         
         self.conn.call(move |conn| {
             conn.execute(
-                "INSERT INTO conversations (user_id, role, content, channel, tool_calls, created_at) VALUES (?, ?, ?, ?, ?, ?)",
-                rusqlite::params![user_id, role, content, channel, tool_calls, now],
+                "INSERT INTO conversations (user_id, role, content, channel, tool_calls, created_at, thread_id) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                rusqlite::params![user_id, role, content, channel, tool_calls, now, thread_id],
             )?;
             Ok(conn.last_insert_rowid())
         }).await.map_err(|e| anyhow::anyhow!("{}", e))
     }
     
-    async fn clear_conversation(&self, user_id: &str) -> anyhow::Result<()> {
+    async fn add_messages(&self, user_id: &str, thread_id: Option<&str>, channel: &str, messages: &[NewMessage]) -> anyhow::Result<Vec<i64>> {
         let user_id = user_id.to_string();
+        let thread_id = thread_id.map(|t| t.to_string());
+        let channel = channel.to_string();
+        let messages = messages.to_vec();
+        let now = Utc::now().to_rfc3339();
         
         self.conn.call(move |conn| {
-            conn.execute("DELETE FROM conversations WHERE user_id = ?", [&user_id])?;
+            let tx = conn.transaction()?;
+            let mut ids = Vec::with_capacity(messages.len());
+            for message in &messages {
+                let reply_to = match message.reply_to {
+                    Some(ReplyTo::Message(id)) => Some(id),
+                    Some(ReplyTo::Batch(index)) => ids.get(index).copied(),
+                    None => None,
+                };
+                tx.execute(
+                    "INSERT INTO conversations (user_id, role, content, channel, tool_calls, created_at, thread_id, reply_to) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                    rusqlite::params![user_id, message.role, message.content, channel, message.tool_calls, now, thread_id, reply_to],
+                )?;
+                ids.push(tx.last_insert_rowid());
+            }
+            tx.commit()?;
+            Ok(ids)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn clear_conversation(&self, user_id: &str, thread_id: Option<&str>) -> anyhow::Result<()> {
+        let user_id = user_id.to_string();
+        let thread_id = thread_id.map(|t| t.to_string());
+        
+        self.conn.call(move |conn| {
+            conn.execute(
+                "DELETE FROM conversations WHERE user_id = ?1 AND (?2 IS NULL OR thread_id = ?2)",
+                rusqlite::params![user_id, thread_id],
+            )?;
             Ok(())
         }).await.map_err(|e| anyhow::anyhow!("{}", e))
     }
     
+    async fn import_message(&self, message: &ConversationMessage) -> anyhow::Result<i64> {
+        let message = message.clone();
+        let created_at = message.created_at.to_rfc3339();
+        
+        self.conn.call(move |conn| {
+            conn.execute(
+                "INSERT INTO conversations (user_id, role, content, channel, tool_calls, created_at, thread_id) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                rusqlite::params![message.user_id, message.role, message.content, message.channel, message.tool_calls, created_at, message.thread_id],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
     async fn get_preferences(&self, user_id: &str) -> anyhow::Result<Option<Preferences>> {
         let user_id = user_id.to_string();
         
@@ -334,24 +806,376 @@ impl Store for SqliteStore {
             Ok(())
         }).await.map_err(|e| anyhow::anyhow!("{}", e))
     }
+    
+    async fn get_conversation_meta(&self, user_id: &str, thread_id: Option<&str>) -> anyhow::Result<Option<ConversationMeta>> {
+        let user_id = user_id.to_string();
+        let thread_id = thread_id.unwrap_or_default().to_string();
+        
+        self.conn.call(move |conn| {
+            let meta = conn.query_row(
+                "SELECT title, tags, pinned, updated_at FROM conversation_meta WHERE user_id = ?1 AND thread_id = ?2",
+                rusqlite::params![user_id, thread_id],
+                |row| {
+                    let tags: String = row.get(1)?;
+                    Ok(ConversationMeta {
+                        user_id: user_id.clone(),
+                        thread_id: (!thread_id.is_empty()).then(|| thread_id.clone()),
+                        title: row.get(0)?,
+                        tags: serde_json::from_str(&tags).unwrap_or_default(),
+                        pinned: row.get(2)?,
+                        updated_at: row.get::<_, String>(3)?.parse().ok(),
+                    })
+                },
+            ).optional()?;
+            Ok(meta)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn set_conversation_meta(&self, meta: &ConversationMeta) -> anyhow::Result<()> {
+        let meta = meta.clone();
+        let tags = serde_json::to_string(&meta.tags)?;
+        let now = Utc::now().to_rfc3339();
+        
+        self.conn.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO conversation_meta (user_id, thread_id, title, tags, pinned, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+                rusqlite::params![meta.user_id, meta.thread_id.unwrap_or_default(), meta.title, tags, meta.pinned, now],
+            )?;
+            Ok(())
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn get_directives(&self, user_id: &str) -> anyhow::Result<Vec<PinnedDirective>> {
+        let user_id = user_id.to_string();
+        
+        self.conn.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, user_id, thread_id, content, created_at FROM pinned_directives WHERE user_id = ? ORDER BY id",
+            )?;
+            let directives = stmt
+                .query_map([&user_id], |row| {
+                    Ok(PinnedDirective {
+                        id: row.get(0)?,
+                        user_id: row.get(1)?,
+                        thread_id: row.get(2)?,
+                        content: row.get(3)?,
+                        created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(directives)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn add_directive(&self, user_id: &str, thread_id: Option<&str>, content: &str) -> anyhow::Result<i64> {
+        let user_id = user_id.to_string();
+        let thread_id = thread_id.map(String::from);
+        let content = content.to_string();
+        let now = Utc::now().to_rfc3339();
+        
+        self.conn.call(move |conn| {
+            conn.execute(
+                "INSERT INTO pinned_directives (user_id, thread_id, content, created_at) VALUES (?, ?, ?, ?)",
+                rusqlite::params![user_id, thread_id, content, now],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn remove_directive(&self, user_id: &str, id: i64) -> anyhow::Result<bool> {
+        let user_id = user_id.to_string();
+        
+        self.conn.call(move |conn| {
+            let removed = conn.execute(
+                "DELETE FROM pinned_directives WHERE user_id = ?1 AND id = ?2",
+                rusqlite::params![user_id, id],
+            )?;
+            Ok(removed > 0)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn get_spend(&self, scope: &str, day: &str) -> anyhow::Result<f64> {
+        let scope = scope.to_string();
+        let day = day.to_string();
+        
+        self.conn.call(move |conn| {
+            let usd = conn.query_row(
+                "SELECT usd FROM spend WHERE scope = ?1 AND day = ?2",
+                rusqlite::params![scope, day],
+                |row| row.get(0),
+            ).optional()?;
+            Ok(usd.unwrap_or(0.0))
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn add_spend(&self, scope: &str, day: &str, usd: f64) -> anyhow::Result<f64> {
+        let scope = scope.to_string();
+        let day = day.to_string();
+        
+        self.conn.call(move |conn| {
+            let total = conn.query_row(
+                "INSERT INTO spend (scope, day, usd) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(scope, day) DO UPDATE SET usd = usd + excluded.usd
+                 RETURNING usd",
+                rusqlite::params![scope, day, usd],
+                |row| row.get(0),
+            )?;
+            Ok(total)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn get_oauth_token(&self, user_id: &str, provider: &str) -> anyhow::Result<Option<OAuthToken>> {
+        let user_id = user_id.to_string();
+        let provider = provider.to_string();
+        
+        self.conn.call(move |conn| {
+            let token = conn.query_row(
+                "SELECT access_token, refresh_token, expires_at, updated_at FROM oauth_tokens WHERE user_id = ?1 AND provider = ?2",
+                rusqlite::params![user_id, provider],
+                |row| {
+                    Ok(OAuthToken {
+                        user_id: user_id.clone(),
+                        provider: provider.clone(),
+                        access_token: row.get(0)?,
+                        refresh_token: row.get(1)?,
+                        expires_at: row.get::<_, Option<String>>(2)?.and_then(|t| t.parse().ok()),
+                        updated_at: row.get::<_, String>(3)?.parse().unwrap_or_else(|_| Utc::now()),
+                    })
+                },
+            ).optional()?;
+            Ok(token)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn set_oauth_token(&self, token: &OAuthToken) -> anyhow::Result<()> {
+        let token = token.clone();
+        
+        self.conn.call(move |conn| {
+            conn.execute(
+                "INSERT INTO oauth_tokens (user_id, provider, access_token, refresh_token, expires_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT(user_id, provider) DO UPDATE SET
+                     access_token = excluded.access_token, refresh_token = excluded.refresh_token,
+                     expires_at = excluded.expires_at, updated_at = excluded.updated_at",
+                rusqlite::params![
+                    token.user_id,
+                    token.provider,
+                    token.access_token,
+                    token.refresh_token,
+                    token.expires_at.map(|t| t.to_rfc3339()),
+                    token.updated_at.to_rfc3339(),
+                ],
+            )?;
+            Ok(())
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn save_dead_letter(&self, letter: &DeadLetter) -> anyhow::Result<()> {
+        let letter = letter.clone();
+        let message = serde_json::to_string(&letter.message)?;
+        
+        self.conn.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO dead_letters (id, message, error, attempts, status, next_attempt_at, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                rusqlite::params![
+                    letter.id,
+                    message,
+                    letter.error,
+                    letter.attempts,
+                    letter.status,
+                    letter.next_attempt_at.to_rfc3339(),
+                    letter.created_at.to_rfc3339(),
+                    letter.updated_at.to_rfc3339(),
+                ],
+            )?;
+            Ok(())
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn list_dead_letters(&self, status: Option<&str>, limit: usize) -> anyhow::Result<Vec<DeadLetter>> {
+        let status = status.map(str::to_string);
+        
+        let rows = self.conn.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, message, error, attempts, status, next_attempt_at, created_at, updated_at FROM dead_letters
+                 WHERE ?1 IS NULL OR status = ?1
+                 ORDER BY next_attempt_at ASC
+                 LIMIT ?2",
+            )?;
+            let rows = stmt
+                .query_map(rusqlite::params![status, limit as i64], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, u32>(3)?,
+                        row.get::<_, String>(4)?,
+                        [row.get::<_, String>(5)?, row.get::<_, String>(6)?, row.get::<_, String>(7)?],
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(rows)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))?;
+        
+        rows.into_iter()
+            .map(|(id, message, error, attempts, status, [next_attempt_at, created_at, updated_at])| {
+                let date = |t: &str| t.parse().unwrap_or_else(|_| Utc::now());
+                Ok(DeadLetter {
+                    id,
+                    message: serde_json::from_str(&message)?,
+                    error,
+                    attempts,
+                    status,
+                    next_attempt_at: date(&next_attempt_at),
+                    created_at: date(&created_at),
+                    updated_at: date(&updated_at),
+                })
+            })
+            .collect()
+    }
+    
+    async fn save_receipt(&self, receipt: &WorkflowReceipt) -> anyhow::Result<()> {
+        let data = serde_json::to_string(receipt)?;
+        let (run_id, workflow_id, status, started_at) =
+            (receipt.run_id.clone(), receipt.workflow_id.clone(), receipt.status.clone(), receipt.started_at.clone());
+        
+        self.conn.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO receipts (run_id, workflow_id, status, started_at, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![run_id, workflow_id, status, started_at, data],
+            )?;
+            Ok(())
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
+    
+    async fn get_receipt(&self, run_id: &str) -> anyhow::Result<Option<WorkflowReceipt>> {
+        let run_id = run_id.to_string();
+        
+        let data: Option<String> = self.conn.call(move |conn| {
+            let data = conn.query_row(
+                "SELECT data FROM receipts WHERE run_id = ?1",
+                rusqlite::params![run_id],
+                |row| row.get(0),
+            ).optional()?;
+            Ok(data)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))?;
+        
+        data.map(|d| serde_json::from_str(&d).map_err(Into::into)).transpose()
+    }
+    
+    async fn list_receipt_ids(&self) -> anyhow::Result<Vec<String>> {
+        self.conn.call(|conn| {
+            let mut stmt = conn.prepare("SELECT run_id FROM receipts ORDER BY started_at DESC")?;
+            let ids = stmt.query_map([], |row| row.get(0))?.collect::<Result<Vec<String>, _>>()?;
+            Ok(ids)
+        }).await.map_err(|e| anyhow::anyhow!("{}", e))
+    }
 }
 
 // ============================================
 // Hosted Store (Harness API)
 // ============================================
 
+/// Upper bound on a single hosted conversation fetch, whatever the caller asks for
+pub const MAX_HOSTED_PAGE: usize = 500;
+
 pub struct HostedStore {
     api_url: String,
     token: String,
     client: reqwest::Client,
+    max_retries: u32,
+    retry_backoff: std::time::Duration,
 }
 
 impl HostedStore {
-    pub fn new(api_url: String, token: String) -> Self {
-        Self {
-            api_url,
-            token,
-            client: reqwest::Client::new(),
+    pub fn new(control_plane: &crate::config::ControlPlaneConfig) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(control_plane.connect_timeout_secs))
+            .timeout(std::time::Duration::from_secs(control_plane.request_timeout_secs))
+            .build()?;
+        
+        Ok(Self {
+            api_url: control_plane.url.clone()
+                .unwrap_or_else(|| "http://localhost:3000".to_string()),
+            token: control_plane.token.clone().unwrap_or_default(),
+            client,
+            max_retries: control_plane.max_retries,
+            retry_backoff: std::time::Duration::from_millis(control_plane.retry_backoff_ms),
+        })
+    }
+    
+    /// `GET .../conversations` with `limit` capped at `MAX_HOSTED_PAGE`
+    async fn fetch_conversation(&self, user_id: &str, thread_id: Option<&str>, before: Option<MessageCursor>, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
+        let limit = limit.min(MAX_HOSTED_PAGE);
+        let resp = self
+            .send_idempotent(|| {
+                let mut req = self.client
+                    .get(format!("{}/api/v1/users/{}/conversations", self.api_url, user_id))
+                    .query(&[("limit", limit.to_string())]);
+                if let Some(thread_id) = thread_id {
+                    req = req.query(&[("thread_id", thread_id)]);
+                }
+                if let Some(before) = before {
+                    req = req.query(&[("before", before.id.to_string()), ("before_created_at", before.created_at.to_rfc3339())]);
+                }
+                req
+            })
+            .await?;
+        
+        let status = resp.status();
+        let body = resp.text().await?;
+        if !status.is_success() {
+            anyhow::bail!("Control plane returned {} for conversation: {}", status, body);
+        }
+        
+        // Accept a bare array or `{ "messages": [...] }`
+        let value: serde_json::Value = serde_json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Invalid conversation response: {}", e))?;
+        let messages = match value {
+            serde_json::Value::Array(_) => value,
+            serde_json::Value::Object(mut obj) if obj.get("messages").is_some_and(|m| m.is_array()) => {
+                obj.remove("messages").unwrap_or_default()
+            }
+            other => anyhow::bail!("Unexpected conversation response: {}", other),
+        };
+        Ok(serde_json::from_value(messages)?)
+    }
+    
+    /// Send a request that must not be repeated (POST)
+    async fn send_once(&self, req: reqwest::RequestBuilder) -> anyhow::Result<reqwest::Response> {
+        Ok(req.header("Authorization", format!("Bearer {}", self.token)).send().await?)
+    }
+    
+    /// Send an idempotent request, retrying with backoff on timeouts, connection
+    /// errors, 429 and 5xx. Other statuses (notably 404) are returned untouched.
+    async fn send_idempotent(
+        &self,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> anyhow::Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = build()
+                .header("Authorization", format!("Bearer {}", self.token))
+                .send()
+                .await;
+            
+            let error = match result {
+                Ok(resp) if resp.status().is_server_error() || resp.status() == 429 => {
+                    anyhow::anyhow!("Control plane returned {}", resp.status())
+                }
+                Ok(resp) => return Ok(resp),
+                Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => e.into(),
+                Err(e) => return Err(e.into()),
+            };
+            
+            if attempt >= self.max_retries {
+                return Err(error.context(format!("Control plane request failed after {} attempts", attempt + 1)));
+            }
+            let delay = self.retry_backoff * 2u32.pow(attempt);
+            tracing::warn!(attempt = attempt + 1, "Control plane request failed ({}), retrying in {:?}", error, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
     }
 }
@@ -359,10 +1183,8 @@ impl HostedStore {
 #[async_trait]
 impl Store for HostedStore {
     async fn get_user(&self, user_id: &str) -> anyhow::Result<Option<User>> {
-        let resp = self.client
-            .get(format!("{}/api/v1/users/{}", self.api_url, user_id))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .send()
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/users/{}", self.api_url, user_id)))
             .await?;
         
         if resp.status() == 404 {
@@ -374,22 +1196,18 @@ impl Store for HostedStore {
     }
     
     async fn create_user(&self, user_id: &str) -> anyhow::Result<User> {
-        let resp = self.client
+        let req = self.client
             .post(format!("{}/api/v1/users", self.api_url))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .json(&serde_json::json!({ "id": user_id }))
-            .send()
-            .await?;
+            .json(&serde_json::json!({ "id": user_id }));
+        let resp = self.send_once(req).await?;
         
         let user: User = resp.json().await?;
         Ok(user)
     }
     
     async fn get_identity(&self, provider: &str, provider_id: &str) -> anyhow::Result<Option<Identity>> {
-        let resp = self.client
-            .get(format!("{}/api/v1/identities/{}:{}", self.api_url, provider, provider_id))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .send()
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/identities/{}:{}", self.api_url, provider, provider_id)))
             .await?;
         
         if resp.status() == 404 {
@@ -401,75 +1219,106 @@ impl Store for HostedStore {
     }
     
     async fn link_identity(&self, user_id: &str, provider: &str, provider_id: &str, username: Option<&str>) -> anyhow::Result<()> {
-        self.client
+        let req = self.client
             .post(format!("{}/api/v1/identities", self.api_url))
-            .header("Authorization", format!("Bearer {}", self.token))
             .json(&serde_json::json!({
                 "user_id": user_id,
                 "provider": provider,
                 "provider_id": provider_id,
                 "username": username
-            }))
-            .send()
-            .await?;
+            }));
+        self.send_once(req).await?;
         
         Ok(())
     }
     
     async fn get_user_identities(&self, user_id: &str) -> anyhow::Result<Vec<Identity>> {
-        let resp = self.client
-            .get(format!("{}/api/v1/users/{}/identities", self.api_url, user_id))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .send()
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/users/{}/identities", self.api_url, user_id)))
             .await?;
         
         let identities: Vec<Identity> = resp.json().await?;
         Ok(identities)
     }
     
-    async fn get_conversation(&self, user_id: &str, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
-        let resp = self.client
-            .get(format!("{}/api/v1/users/{}/conversations?limit={}", self.api_url, user_id, limit))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .send()
-            .await?;
-        
-        let messages: Vec<ConversationMessage> = resp.json().await?;
-        Ok(messages)
+    async fn get_conversation(&self, user_id: &str, thread_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
+        self.fetch_conversation(user_id, thread_id, None, limit).await
+    }
+    
+    async fn get_conversation_before(&self, user_id: &str, thread_id: Option<&str>, before: MessageCursor, limit: usize) -> anyhow::Result<Vec<ConversationMessage>> {
+        self.fetch_conversation(user_id, thread_id, Some(before), limit).await
     }
     
-    async fn add_message(&self, user_id: &str, role: &str, content: &str, channel: &str, tool_calls: Option<&str>) -> anyhow::Result<i64> {
-        let resp = self.client
+    async fn add_message(&self, user_id: &str, thread_id: Option<&str>, role: &str, content: &str, channel: &str, tool_calls: Option<&str>) -> anyhow::Result<i64> {
+        let req = self.client
             .post(format!("{}/api/v1/users/{}/conversations", self.api_url, user_id))
-            .header("Authorization", format!("Bearer {}", self.token))
             .json(&serde_json::json!({
                 "role": role,
                 "content": content,
                 "channel": channel,
-                "tool_calls": tool_calls
-            }))
-            .send()
-            .await?;
+                "tool_calls": tool_calls,
+                "thread_id": thread_id
+            }));
+        let resp = self.send_once(req).await?;
         
         let result: serde_json::Value = resp.json().await?;
         Ok(result["id"].as_i64().unwrap_or(0))
     }
     
-    async fn clear_conversation(&self, user_id: &str) -> anyhow::Result<()> {
-        self.client
-            .delete(format!("{}/api/v1/users/{}/conversations", self.api_url, user_id))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .send()
+    /// One request to the batch endpoint, which the control plane applies atomically.
+    /// Not retried: a lost response could otherwise record the turn twice.
+    async fn add_messages(&self, user_id: &str, thread_id: Option<&str>, channel: &str, messages: &[NewMessage]) -> anyhow::Result<Vec<i64>> {
+        let req = self.client
+            .post(format!("{}/api/v1/users/{}/conversations/batch", self.api_url, user_id))
+            .json(&serde_json::json!({
+                "thread_id": thread_id,
+                "channel": channel,
+                "messages": messages
+            }));
+        let resp = self.send_once(req).await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Control plane returned {} for conversation batch", resp.status());
+        }
+        
+        let result: serde_json::Value = resp.json().await?;
+        Ok(result["ids"].as_array().map(|ids| ids.iter().filter_map(|id| id.as_i64()).collect()).unwrap_or_default())
+    }
+    
+    async fn clear_conversation(&self, user_id: &str, thread_id: Option<&str>) -> anyhow::Result<()> {
+        self
+            .send_idempotent(|| {
+                let req = self.client
+                    .delete(format!("{}/api/v1/users/{}/conversations", self.api_url, user_id));
+                match thread_id {
+                    Some(thread_id) => req.query(&[("thread_id", thread_id)]),
+                    None => req,
+                }
+            })
             .await?;
         
         Ok(())
     }
     
+    async fn import_message(&self, message: &ConversationMessage) -> anyhow::Result<i64> {
+        let req = self.client
+            .post(format!("{}/api/v1/users/{}/conversations", self.api_url, message.user_id))
+            .json(&serde_json::json!({
+                "role": message.role,
+                "content": message.content,
+                "channel": message.channel,
+                "tool_calls": message.tool_calls,
+                "created_at": message.created_at,
+                "thread_id": message.thread_id
+            }));
+        let resp = self.send_once(req).await?.error_for_status()?;
+        
+        let result: serde_json::Value = resp.json().await?;
+        Ok(result["id"].as_i64().unwrap_or(0))
+    }
+    
     async fn get_preferences(&self, user_id: &str) -> anyhow::Result<Option<Preferences>> {
-        let resp = self.client
-            .get(format!("{}/api/v1/users/{}/preferences", self.api_url, user_id))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .send()
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/users/{}/preferences", self.api_url, user_id)))
             .await?;
         
         if resp.status() == 404 {
@@ -481,15 +1330,190 @@ impl Store for HostedStore {
     }
     
     async fn set_preferences(&self, user_id: &str, data: serde_json::Value) -> anyhow::Result<()> {
-        self.client
-            .put(format!("{}/api/v1/users/{}/preferences", self.api_url, user_id))
-            .header("Authorization", format!("Bearer {}", self.token))
-            .json(&data)
-            .send()
+        self
+            .send_idempotent(|| {
+                self.client
+                    .put(format!("{}/api/v1/users/{}/preferences", self.api_url, user_id))
+                    .json(&data)
+            })
             .await?;
         
         Ok(())
     }
+    
+    async fn get_conversation_meta(&self, user_id: &str, thread_id: Option<&str>) -> anyhow::Result<Option<ConversationMeta>> {
+        let resp = self
+            .send_idempotent(|| {
+                let req = self.client
+                    .get(format!("{}/api/v1/users/{}/conversation-meta", self.api_url, user_id));
+                match thread_id {
+                    Some(thread_id) => req.query(&[("thread_id", thread_id)]),
+                    None => req,
+                }
+            })
+            .await?;
+        
+        if resp.status() == 404 {
+            return Ok(None);
+        }
+        
+        let meta: ConversationMeta = resp.json().await?;
+        Ok(Some(meta))
+    }
+    
+    async fn set_conversation_meta(&self, meta: &ConversationMeta) -> anyhow::Result<()> {
+        let resp = self
+            .send_idempotent(|| {
+                self.client
+                    .put(format!("{}/api/v1/users/{}/conversation-meta", self.api_url, meta.user_id))
+                    .json(meta)
+            })
+            .await?;
+        
+        if !resp.status().is_success() {
+            anyhow::bail!("Control plane returned {} for conversation meta", resp.status());
+        }
+        Ok(())
+    }
+    
+    async fn get_directives(&self, user_id: &str) -> anyhow::Result<Vec<PinnedDirective>> {
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/users/{}/directives", self.api_url, user_id)))
+            .await?;
+        
+        if resp.status() == 404 {
+            return Ok(Vec::new());
+        }
+        
+        Ok(resp.json().await?)
+    }
+    
+    async fn add_directive(&self, user_id: &str, thread_id: Option<&str>, content: &str) -> anyhow::Result<i64> {
+        let req = self.client
+            .post(format!("{}/api/v1/users/{}/directives", self.api_url, user_id))
+            .json(&serde_json::json!({ "thread_id": thread_id, "content": content }));
+        let resp = self.send_once(req).await?;
+        
+        let result: serde_json::Value = resp.json().await?;
+        Ok(result["id"].as_i64().unwrap_or(0))
+    }
+    
+    async fn remove_directive(&self, user_id: &str, id: i64) -> anyhow::Result<bool> {
+        let resp = self
+            .send_idempotent(|| self.client.delete(format!("{}/api/v1/users/{}/directives/{}", self.api_url, user_id, id)))
+            .await?;
+        
+        Ok(resp.status() != 404)
+    }
+    
+    async fn get_spend(&self, scope: &str, day: &str) -> anyhow::Result<f64> {
+        let resp = self
+            .send_idempotent(|| {
+                self.client
+                    .get(format!("{}/api/v1/spend/{}", self.api_url, scope))
+                    .query(&[("day", day)])
+            })
+            .await?;
+        
+        if resp.status() == 404 {
+            return Ok(0.0);
+        }
+        
+        let result: serde_json::Value = resp.json().await?;
+        Ok(result["usd"].as_f64().unwrap_or(0.0))
+    }
+    
+    async fn add_spend(&self, scope: &str, day: &str, usd: f64) -> anyhow::Result<f64> {
+        let req = self.client
+            .post(format!("{}/api/v1/spend/{}", self.api_url, scope))
+            .json(&serde_json::json!({ "day": day, "usd": usd }));
+        let resp = self.send_once(req).await?;
+        
+        let result: serde_json::Value = resp.json().await?;
+        Ok(result["usd"].as_f64().unwrap_or(usd))
+    }
+    
+    async fn get_oauth_token(&self, user_id: &str, provider: &str) -> anyhow::Result<Option<OAuthToken>> {
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/users/{}/oauth/{}", self.api_url, user_id, provider)))
+            .await?;
+        
+        if resp.status() == 404 {
+            return Ok(None);
+        }
+        
+        Ok(Some(resp.json().await?))
+    }
+    
+    async fn set_oauth_token(&self, token: &OAuthToken) -> anyhow::Result<()> {
+        self.send_idempotent(|| {
+            self.client
+                .put(format!("{}/api/v1/users/{}/oauth/{}", self.api_url, token.user_id, token.provider))
+                .json(token)
+        })
+        .await?;
+        Ok(())
+    }
+    
+    async fn save_dead_letter(&self, letter: &DeadLetter) -> anyhow::Result<()> {
+        self.send_idempotent(|| {
+            self.client
+                .put(format!("{}/api/v1/dead-letters/{}", self.api_url, letter.id))
+                .json(letter)
+        })
+        .await?;
+        Ok(())
+    }
+    
+    async fn list_dead_letters(&self, status: Option<&str>, limit: usize) -> anyhow::Result<Vec<DeadLetter>> {
+        let resp = self
+            .send_idempotent(|| {
+                let mut req = self.client
+                    .get(format!("{}/api/v1/dead-letters", self.api_url))
+                    .query(&[("limit", limit.to_string())]);
+                if let Some(status) = status {
+                    req = req.query(&[("status", status)]);
+                }
+                req
+            })
+            .await?;
+        
+        Ok(resp.json().await?)
+    }
+    
+    async fn save_receipt(&self, receipt: &WorkflowReceipt) -> anyhow::Result<()> {
+        self.send_idempotent(|| {
+            self.client
+                .put(format!("{}/api/v1/receipts/{}", self.api_url, receipt.run_id))
+                .json(receipt)
+        })
+        .await?;
+        Ok(())
+    }
+    
+    async fn get_receipt(&self, run_id: &str) -> anyhow::Result<Option<WorkflowReceipt>> {
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/receipts/{}", self.api_url, run_id)))
+            .await?;
+        
+        if resp.status() == 404 {
+            return Ok(None);
+        }
+        
+        Ok(Some(resp.json().await?))
+    }
+    
+    async fn list_receipt_ids(&self) -> anyhow::Result<Vec<String>> {
+        let resp = self
+            .send_idempotent(|| self.client.get(format!("{}/api/v1/receipts", self.api_url)))
+            .await?;
+        
+        let result: serde_json::Value = resp.json().await?;
+        Ok(result["run_ids"]
+            .as_array()
+            .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+            .unwrap_or_default())
+    }
 }
 
 // ============================================
@@ -498,7 +1522,18 @@ impl Store for HostedStore {
 
 pub enum StoreType {
     Sqlite(PathBuf),
-    Hosted { api_url: String, token: String },
+    Hosted(crate::config::ControlPlaneConfig),
+}
+
+impl StoreType {
+    /// Pick the backend described by `store.store_type` in node config
+    pub fn from_config(config: &crate::config::NodeConfig) -> Self {
+        match config.store.store_type.as_str() {
+            "hosted" => StoreType::Hosted(config.control_plane.clone()),
+            // Default to SQLite
+            _ => StoreType::Sqlite(crate::config::expand_path(&config.store.sqlite_path)),
+        }
+    }
 }
 
 pub async fn create_store(store_type: StoreType) -> anyhow::Result<Box<dyn Store>> {
@@ -507,9 +1542,163 @@ pub async fn create_store(store_type: StoreType) -> anyhow::Result<Box<dyn Store
             let store = SqliteStore::new(path).await?;
             Ok(Box::new(store))
         }
-        StoreType::Hosted { api_url, token } => {
-            let store = HostedStore::new(api_url, token);
+        StoreType::Hosted(control_plane) => {
+            let store = HostedStore::new(&control_plane)?;
             Ok(Box::new(store))
         }
     }
 }
+
+/// A unique file under the temp dir, deleted when dropped
+#[cfg(test)]
+pub struct TempPath(pub PathBuf);
+
+#[cfg(test)]
+impl TempPath {
+    pub fn new(extension: &str) -> Self {
+        static NEXT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+        let n = NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self(std::env::temp_dir().join(format!(
+            "oneclaw-test-{}-{}-{}.{}",
+            std::process::id(),
+            n,
+            Utc::now().timestamp_nanos_opt().unwrap_or(0),
+            extension
+        )))
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// A fresh SQLite store for a test; keep the guard alive for as long as the store is used
+#[cfg(test)]
+pub async fn temp_store() -> (SqliteStore, TempPath) {
+    let path = TempPath::new("db");
+    let store = SqliteStore::new(path.0.clone()).await.expect("temp SQLite store");
+    (store, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[tokio::test]
+    async fn test_threads_partition_history() {
+        let (store, _db) = temp_store().await;
+        store.create_user("u1").await.unwrap();
+        
+        store.add_message("u1", Some("discord:a"), "user", "in a", "discord", None).await.unwrap();
+        store.add_message("u1", Some("discord:b"), "user", "in b", "discord", None).await.unwrap();
+        store.add_message("u1", None, "user", "unthreaded", "http", None).await.unwrap();
+        
+        let a = store.get_conversation("u1", Some("discord:a"), 10).await.unwrap();
+        assert_eq!(a.len(), 1);
+        assert_eq!(a[0].content, "in a");
+        assert_eq!(store.get_conversation("u1", None, 10).await.unwrap().len(), 3);
+        
+        store.clear_conversation("u1", Some("discord:b")).await.unwrap();
+        assert_eq!(store.get_conversation("u1", None, 10).await.unwrap().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_add_messages_is_all_or_nothing() {
+        let (store, _db) = temp_store().await;
+        store.create_user("u1").await.unwrap();
+        
+        let turn = [NewMessage::new("user", "hi"), NewMessage::new("tool", "[search result]"), NewMessage::new("assistant", "hello")];
+        let ids = store.add_messages("u1", Some("t1"), "http", &turn).await.unwrap();
+        assert_eq!(ids.len(), 3);
+        let stored = store.get_conversation("u1", Some("t1"), 10).await.unwrap();
+        assert_eq!(stored.iter().map(|m| m.role.as_str()).collect::<Vec<_>>(), vec!["user", "tool", "assistant"]);
+        
+        // A failure part-way through leaves none of the batch behind
+        store.conn.call(|conn| Ok(conn.execute_batch(
+            "CREATE TRIGGER fail_insert BEFORE INSERT ON conversations WHEN NEW.content = 'boom' BEGIN SELECT RAISE(ABORT, 'boom'); END;"
+        )?)).await.unwrap();
+        let failing = [NewMessage::new("user", "second"), NewMessage::new("assistant", "boom")];
+        assert!(store.add_messages("u1", Some("t1"), "http", &failing).await.is_err());
+        assert_eq!(store.get_conversation("u1", Some("t1"), 10).await.unwrap().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_migrating_unversioned_db_keeps_rows() {
+        let path = TempPath::new("db");
+        {
+            // A database from before versioning and threads
+            let old = rusqlite::Connection::open(&path.0).unwrap();
+            old.execute_batch(MIGRATIONS[0].sql).unwrap();
+            old.execute_batch(r#"
+                INSERT INTO users (id, created_at, updated_at) VALUES ('u1', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');
+                INSERT INTO conversations (user_id, role, content, channel, created_at) VALUES ('u1', 'user', 'hello from before', 'http', '2024-01-01T00:00:00Z');
+            "#).unwrap();
+        }
+        
+        let store = SqliteStore::new(path.0.clone()).await.unwrap();
+        let history = store.get_conversation("u1", None, 10).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].content, "hello from before");
+        store.add_message("u1", Some("t1"), "user", "threaded", "http", None).await.unwrap();
+        assert_eq!(store.get_conversation("u1", Some("t1"), 10).await.unwrap().len(), 1);
+        drop(store);
+        
+        // Reopening is a no-op at the current version
+        let store = SqliteStore::new(path.0.clone()).await.unwrap();
+        let version: usize = store.conn.call(|conn| Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)).await.unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+        assert_eq!(store.get_conversation("u1", None, 10).await.unwrap().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_conversation_pages_backwards() {
+        let (store, _db) = temp_store().await;
+        store.create_user("u1").await.unwrap();
+        for i in 0..5 {
+            store.add_message("u1", None, "user", &format!("m{}", i), "http", None).await.unwrap();
+        }
+        
+        let latest = store.get_conversation("u1", None, 2).await.unwrap();
+        assert_eq!(latest.iter().map(|m| m.content.as_str()).collect::<Vec<_>>(), vec!["m3", "m4"]);
+        
+        let older = store.get_conversation_before("u1", None, latest[0].cursor(), 10).await.unwrap();
+        assert_eq!(older.iter().map(|m| m.content.as_str()).collect::<Vec<_>>(), vec!["m0", "m1", "m2"]);
+        
+        // An imported message gets a new id but keeps its old timestamp: it pages by timestamp
+        let imported = ConversationMessage { content: "imported".to_string(), created_at: older[0].created_at - chrono::Duration::days(1), ..older[0].clone() };
+        store.import_message(&imported).await.unwrap();
+        let mut pages = vec![];
+        let mut page = store.get_conversation("u1", None, 2).await.unwrap();
+        while !page.is_empty() {
+            pages.splice(0..0, page.iter().map(|m| m.content.clone()));
+            page = store.get_conversation_before("u1", None, page[0].cursor(), 2).await.unwrap();
+        }
+        assert_eq!(pages, vec!["imported", "m0", "m1", "m2", "m3", "m4"]);
+    }
+    
+    #[tokio::test]
+    async fn test_conversation_meta_per_thread() {
+        let (store, _db) = temp_store().await;
+        store.create_user("u1").await.unwrap();
+        assert!(store.get_conversation_meta("u1", None).await.unwrap().is_none());
+        
+        let meta = ConversationMeta {
+            user_id: "u1".to_string(),
+            thread_id: Some("discord:a".to_string()),
+            title: Some("Golf plans".to_string()),
+            tags: vec!["golf".to_string()],
+            pinned: true,
+            updated_at: None,
+        };
+        store.set_conversation_meta(&meta).await.unwrap();
+        store.set_conversation_meta(&ConversationMeta { user_id: "u1".to_string(), ..Default::default() }).await.unwrap();
+        
+        let stored = store.get_conversation_meta("u1", Some("discord:a")).await.unwrap().unwrap();
+        assert_eq!((stored.title.as_deref(), stored.tags.as_slice(), stored.pinned), (Some("Golf plans"), &["golf".to_string()][..], true));
+        assert!(stored.updated_at.is_some());
+        assert!(store.get_conversation_meta("u1", None).await.unwrap().unwrap().title.is_none());
+    }
+}
@@ -0,0 +1,100 @@
+//! Trace IDs
+//!
+//! Each `/chat` and `/run` gets a `trace_id` (the caller's `X-Trace-Id` if it sent a
+//! sane one) that tags its log span, is recorded in the workflow receipt and chat
+//! response, and is forwarded as `X-Trace-Id` on harness and LLM requests, so one id
+//! ties together the node, harness and control-plane logs.
+
+use std::cell::RefCell;
+use std::future::Future;
+use tracing::Instrument;
+
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+tokio::task_local! {
+    /// Trace of the request being polled
+    static CURRENT: String;
+}
+
+thread_local! {
+    /// Trace of the executor call running on this blocking thread
+    static BLOCKING: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// The incoming `X-Trace-Id` if present and well-formed, otherwise a new id
+pub fn from_headers(headers: &axum::http::HeaderMap) -> String {
+    headers
+        .get(TRACE_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(str::to_string)
+        .unwrap_or_else(new_id)
+}
+
+pub fn new_id() -> String {
+    nanoid::nanoid!(16)
+}
+
+/// Trace IDs end up in logs and outbound headers, so keep them plain
+fn is_valid(trace_id: &str) -> bool {
+    (1..=128).contains(&trace_id.len()) && trace_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Run `work` under `trace_id`, inside a `trace` span carrying it. The span is opened
+/// here, so it nests under the caller's (the access log's `request` span) even when the
+/// future is polled on another task.
+pub fn scope<F: Future>(trace_id: String, work: F) -> impl Future<Output = F::Output> {
+    let span = tracing::info_span!("trace", trace_id = %trace_id);
+    CURRENT.scope(trace_id, work).instrument(span)
+}
+
+/// Trace of the current request, on its task or on a blocking thread started with `spawn_blocking`
+pub fn current() -> Option<String> {
+    CURRENT
+        .try_with(Clone::clone)
+        .ok()
+        .or_else(|| BLOCKING.with(|trace| trace.borrow().clone()))
+}
+
+/// `tokio::task::spawn_blocking` that carries the current trace (and span) onto the blocking thread
+pub fn spawn_blocking<F, R>(f: F) -> tokio::task::JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let trace_id = current();
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || {
+        let previous = BLOCKING.with(|trace| trace.replace(trace_id));
+        let result = span.in_scope(f);
+        BLOCKING.with(|trace| *trace.borrow_mut() = previous);
+        result
+    })
+}
+
+/// Add `X-Trace-Id` to an outbound request when there is a current trace
+pub fn tag(req: reqwest::blocking::RequestBuilder) -> reqwest::blocking::RequestBuilder {
+    match current() {
+        Some(trace_id) => req.header(TRACE_ID_HEADER, trace_id),
+        None => req,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trace_follows_request_onto_blocking_threads() {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(TRACE_ID_HEADER, "abc-123".parse().unwrap());
+        assert_eq!(from_headers(&headers), "abc-123");
+        headers.insert(TRACE_ID_HEADER, "bad id\twith spaces".parse().unwrap());
+        assert_ne!(from_headers(&headers), "bad id\twith spaces");
+
+        assert_eq!(current(), None);
+        let seen = scope("abc-123".to_string(), async { spawn_blocking(current).await.unwrap() }).await;
+        assert_eq!(seen.as_deref(), Some("abc-123"));
+        assert_eq!(spawn_blocking(current).await.unwrap(), None);
+    }
+}
@@ -7,8 +7,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
-use crate::{config, executor, receipt};
+use crate::{budget, config, events, executor, receipt, services};
 
 /// Workflow specification
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +26,10 @@ pub struct WorkflowSpec {
     
     #[serde(default)]
     pub outputs: HashMap<String, String>,
+    
+    /// Stop at the first failed step (unless it has `continue_on_error`)
+    #[serde(default)]
+    pub fail_fast: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,12 +62,56 @@ pub struct Step {
     #[serde(default)]
     pub checkpoint: bool,
     
-    /// Timeout in seconds
+    /// Timeout in seconds, handed to the executor through its `StepContext`
     pub timeout: Option<u64>,
     
     /// Condition (skip if false)
     #[serde(rename = "if")]
     pub condition: Option<String>,
+    
+    /// Node environments this step may run in (e.g. `["private"]`); any if unset
+    pub environments: Option<Vec<String>>,
+    
+    /// A failure of this step doesn't affect the run status or trigger `fail_fast`
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+/// A failed (errored or denied) step receipt whose step doesn't have `continue_on_error`
+fn is_blocking_failure(spec: &WorkflowSpec, step: &receipt::StepReceipt) -> bool {
+    if matches!(step.status.as_str(), "executed" | "replayed" | "skipped") {
+        return false;
+    }
+    !spec.steps.iter().any(|s| s.id == step.step_id && s.continue_on_error)
+}
+
+/// Overall run status; failures of `continue_on_error` steps are ignored
+fn overall_status(spec: &WorkflowSpec, steps: &[receipt::StepReceipt]) -> &'static str {
+    if !steps.iter().any(|s| is_blocking_failure(spec, s)) {
+        "success"
+    } else if steps.iter().any(|s| s.status == "executed" || s.status == "replayed") {
+        "partial"
+    } else {
+        "failed"
+    }
+}
+
+/// Denial for a step whose `environments` list excludes the node's environment
+fn environment_denial(step: &Step, environment: &str) -> Option<executor::DenialReason> {
+    let allowed = step.environments.as_ref()?;
+    if allowed.iter().any(|e| e == environment) {
+        return None;
+    }
+    Some(executor::DenialReason {
+        rule: "node.environment".to_string(),
+        attempted: environment.to_string(),
+        policy: format!(
+            "Step '{}' only runs in environments [{}], node.environment is '{}'",
+            step.id,
+            allowed.join(", "),
+            environment
+        ),
+    })
 }
 
 /// Load workflow spec from file or registry
@@ -85,14 +134,37 @@ pub fn load_spec(workflow_id: &str) -> anyhow::Result<WorkflowSpec> {
     anyhow::bail!("Workflow not found: {}", workflow_id);
 }
 
+/// Expand `${env.VAR}` (or bare `${VAR}`) in the strings of an input default.
+/// Unknown variables are left as-is; other `${...}` references are untouched.
+fn expand_env(value: &Value) -> Value {
+    static ENV_VAR: OnceLock<regex::Regex> = OnceLock::new();
+    let re = ENV_VAR.get_or_init(|| regex::Regex::new(r"\$\{(?:env\.)?([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+    
+    match value {
+        Value::String(s) => Value::String(
+            re.replace_all(s, |caps: &regex::Captures| match std::env::var(&caps[1]) {
+                Ok(val) => val,
+                Err(_) => {
+                    tracing::warn!(var = %&caps[1], "Input default references unset env var; leaving as-is");
+                    caps[0].to_string()
+                }
+            })
+            .into_owned(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(expand_env).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), expand_env(v))).collect()),
+        other => other.clone(),
+    }
+}
+
 /// Merge user-provided inputs with defaults from the workflow spec
 fn merge_inputs_with_defaults(spec: &WorkflowSpec, provided: Value) -> Value {
     let mut merged = serde_json::Map::new();
     
-    // First, apply all defaults
+    // First, apply all defaults (with env vars expanded so they're concrete before any step runs)
     for (name, def) in &spec.inputs {
         if let Some(default_val) = &def.default {
-            merged.insert(name.clone(), default_val.clone());
+            merged.insert(name.clone(), expand_env(default_val));
         }
     }
     
@@ -106,11 +178,231 @@ fn merge_inputs_with_defaults(spec: &WorkflowSpec, provided: Value) -> Value {
     Value::Object(merged)
 }
 
-/// Run a workflow
+/// A single problem with a workflow input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputViolation {
+    pub input: String,
+    pub message: String,
+}
+
+/// All input problems found for a run, reported together before any step executes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputValidationError {
+    pub workflow_id: String,
+    pub violations: Vec<InputViolation>,
+}
+
+impl std::fmt::Display for InputValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid inputs for workflow '{}':", self.workflow_id)?;
+        for v in &self.violations {
+            write!(f, "\n  - {}: {}", v.input, v.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InputValidationError {}
+
+fn value_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Check merged inputs against the spec's `InputDef`s.
+/// Unknown declared types are not checked.
+fn validate_inputs(spec: &WorkflowSpec, merged: &Value) -> Result<(), InputValidationError> {
+    let mut violations = Vec::new();
+    
+    let mut names: Vec<&String> = spec.inputs.keys().collect();
+    names.sort();
+    
+    for name in names {
+        let def = &spec.inputs[name];
+        let value = merged.get(name).filter(|v| !v.is_null());
+        
+        let Some(value) = value else {
+            if def.required {
+                violations.push(InputViolation {
+                    input: name.clone(),
+                    message: "required input is missing".to_string(),
+                });
+            }
+            continue;
+        };
+        
+        let matches = match def.input_type.as_str() {
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            _ => true,
+        };
+        
+        if !matches {
+            violations.push(InputViolation {
+                input: name.clone(),
+                message: format!("expected {}, got {}", def.input_type, value_type_name(value)),
+            });
+        }
+    }
+    
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(InputValidationError {
+            workflow_id: spec.id.clone(),
+            violations,
+        })
+    }
+}
+
+/// Fail on an unknown workflow or invalid inputs, as `execute` would, before a run ID is
+/// claimed for them
+pub fn check_inputs(workflow_id: &str, inputs: &Value) -> anyhow::Result<()> {
+    let spec = load_spec(workflow_id)?;
+    validate_inputs(&spec, &merge_inputs_with_defaults(&spec, inputs.clone()))?;
+    Ok(())
+}
+
+/// How deep `workflow.call` steps may nest, so a workflow that (indirectly) calls itself stops
+pub const MAX_CALL_DEPTH: u32 = 5;
+
+/// Run a workflow from the CLI
 pub async fn run(workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::WorkflowReceipt> {
+    match new_run(workflow_id, &inputs, &config::load()?.workflow).await? {
+        NewRun::Id(run_id) => execute(run_id, workflow_id, inputs, None, None).await,
+        NewRun::Reuse(earlier) => Ok(*earlier),
+    }
+}
+
+/// What a new run should do, from `new_run`
+pub enum NewRun {
+    /// Run under this ID, already reserved
+    Id(String),
+    /// Return the receipt of an earlier identical run (`workflow.reuse_receipts`)
+    Reuse(Box<receipt::WorkflowReceipt>),
+}
+
+/// An identical run (same `deterministic_run_ids` ID) hasn't finished yet
+#[derive(Debug)]
+pub struct RunInProgress {
+    pub run_id: String,
+}
+
+impl std::fmt::Display for RunInProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "An identical run ({}) is still in progress; wait for it to finish", self.run_id)
+    }
+}
+
+impl std::error::Error for RunInProgress {}
+
+/// Pick the run ID for a new run: random, or with `deterministic_run_ids` derived from
+/// `workflow_id` and `inputs`. Identical runs share one ID: a rerun overwrites the earlier
+/// receipt (or with `reuse_receipts` returns it, if that run succeeded), and fails with
+/// `RunInProgress` while the earlier run is still going.
+pub async fn new_run(workflow_id: &str, inputs: &Value, settings: &config::WorkflowConfig) -> anyhow::Result<NewRun> {
+    if !settings.deterministic_run_ids {
+        return Ok(NewRun::Id(nanoid::nanoid!()));
+    }
+    check_inputs(workflow_id, inputs)?;
+    let run_id = receipt::content_run_id(workflow_id, inputs);
+    if receipt::claim_run_id(&run_id).await? {
+        return Ok(NewRun::Id(run_id));
+    }
+    match receipt::read_receipt(&run_id).await? {
+        Some(earlier) if settings.reuse_receipts && earlier.status == "success" => {
+            tracing::info!(run_id = %run_id, workflow_id = %workflow_id, "Returning the receipt of an identical earlier run");
+            Ok(NewRun::Reuse(Box::new(earlier)))
+        }
+        Some(earlier) if earlier.status != "running" => {
+            tracing::info!(run_id = %run_id, workflow_id = %workflow_id, "Running again; replacing the receipt of an identical earlier run");
+            Ok(NewRun::Id(run_id))
+        }
+        // Running, or claimed and about to write its first receipt
+        _ => Err(RunInProgress { run_id }.into()),
+    }
+}
+
+/// Run a workflow under a caller-chosen run ID, so its receipt can be watched while it runs
+pub async fn run_with_id(run_id: String, workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::WorkflowReceipt> {
+    execute(run_id, workflow_id, inputs, None, None).await
+}
+
+/// A run started by a `workflow.call` step
+pub struct Call {
+    pub parent_run_id: String,
+    /// 1 for a workflow called from a top-level run
+    pub depth: u32,
+}
+
+/// Run a workflow for a `workflow.call` step of another run
+pub async fn run_call(run_id: String, workflow_id: &str, inputs: Value, call: Call) -> anyhow::Result<receipt::WorkflowReceipt> {
+    execute(run_id, workflow_id, inputs, None, Some(call)).await
+}
+
+/// A run that fails before its first receipt gives its ID back, so a retry can claim it
+async fn release_run_id(run_id: &str, error: anyhow::Error) -> anyhow::Error {
+    if let Err(e) = receipt::release_run_id(run_id).await {
+        tracing::warn!(run_id = %run_id, "Could not release run ID: {}", e);
+    }
+    error
+}
+
+/// Outputs recorded by a previous run, keyed by step ID
+struct Replay {
+    run_id: String,
+    outputs: HashMap<String, Value>,
+}
+
+/// Re-run a previous run with its recorded inputs. The checkpointed steps that
+/// produced output return it (marked `"replayed"`) instead of calling their
+/// executor, up to the first step that can't be replayed; from there on every
+/// step executes live.
+pub async fn replay(run_id: &str) -> anyhow::Result<receipt::WorkflowReceipt> {
+    let prior = receipt::read_receipt(run_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Receipt not found: {}", run_id))?;
+    
+    let outputs = replayable_outputs(prior.steps);
+    let replay = Replay { run_id: prior.run_id, outputs };
+    execute(nanoid::nanoid!(), &prior.workflow_id, prior.inputs, Some(replay), None).await
+}
+
+/// Recorded outputs a replay can reuse: those of the leading steps that succeeded. A step
+/// that failed, or whose stored output was redacted (and so isn't what it returned), ends
+/// the prefix, since later steps may have used its real output.
+fn replayable_outputs(steps: Vec<receipt::StepReceipt>) -> HashMap<String, Value> {
+    let mut outputs = HashMap::new();
+    for step in steps {
+        match step.status.as_str() {
+            "skipped" => continue,
+            "executed" | "replayed" if !receipt::is_redacted(&step.response) => {
+                outputs.insert(step.step_id, step.response);
+            }
+            _ => break,
+        }
+    }
+    outputs
+}
+
+async fn execute(
+    run_id: String,
+    workflow_id: &str,
+    inputs: Value,
+    replay: Option<Replay>,
+    call: Option<Call>,
+) -> anyhow::Result<receipt::WorkflowReceipt> {
     let start_time = chrono::Utc::now();
-    let run_id = nanoid::nanoid!();
-    let config = config::load()?;
     
     tracing::info!(
         run_id = %run_id,
@@ -118,25 +410,86 @@ pub async fn run(workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::Wo
         "Starting workflow"
     );
     
-    // Load workflow spec
-    let spec = load_spec(workflow_id)?;
-    
-    // Initialize executor registry
-    let registry = executor::Registry::load()?;
+    let prepared = (|| -> anyhow::Result<_> {
+        let config = config::load()?;
+        // Load workflow spec
+        let spec = load_spec(workflow_id)?;
+        // Initialize executor registry
+        let registry = executor::Registry::load(&config.executors)?;
+        // Merge provided inputs with defaults from spec
+        let merged_inputs = merge_inputs_with_defaults(&spec, inputs.clone());
+        validate_inputs(&spec, &merged_inputs)?;
+        Ok((config, spec, registry, merged_inputs))
+    })();
+    let (config, spec, registry, merged_inputs) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => return Err(release_run_id(&run_id, e).await),
+    };
+    let services = services::get().await?;
     
-    // Merge provided inputs with defaults from spec
-    let merged_inputs = merge_inputs_with_defaults(&spec, inputs.clone());
+    // Receipt is written up front and after every step, so a crash or hang
+    // still leaves a record of the steps completed so far.
+    let mut receipt = receipt::WorkflowReceipt {
+        run_id: run_id.clone(),
+        workflow_id: workflow_id.to_string(),
+        node_id: config.node.id.clone(),
+        started_at: start_time.to_rfc3339(),
+        completed_at: String::new(),
+        status: "running".to_string(),
+        mode: config.node.environment.clone(),
+        steps: Vec::new(),
+        inputs: merged_inputs.clone(),
+        outputs: serde_json::json!({}),
+        debug: receipt::DebugInfo {
+            config_snapshot: receipt::config_snapshot_hash(),
+            executor_versions: HashMap::from([
+                ("http.request".to_string(), "0.1.0".to_string()),
+                ("json.transform".to_string(), "0.1.0".to_string()),
+            ]),
+            total_duration_ms: 0,
+            trace_id: crate::trace::current(),
+        },
+        replay_of: replay.as_ref().map(|r| r.run_id.clone()),
+        parent_run_id: call.as_ref().map(|c| c.parent_run_id.clone()),
+        owner: Some(receipt::RunOwner::current()),
+    };
+    if let Err(e) = receipt::write_receipt(&receipt).await {
+        return Err(release_run_id(&run_id, e).await);
+    }
     
     // Execute steps
-    let mut step_receipts = Vec::new();
     let mut outputs = serde_json::json!({});
     let mut context = Context::new(merged_inputs.clone());
+    let mut reported_steps = 0;
     
     for step in &spec.steps {
+        // Persist skipped or denied steps before starting the next one (executed steps are
+        // checkpointed as soon as they finish)
+        if receipt.steps.len() > reported_steps {
+            checkpoint_receipt(&mut receipt, start_time).await;
+            report_steps(&receipt, &mut reported_steps);
+        }
+        
+        if spec.fail_fast {
+            if let Some(failed) = receipt.steps.iter().find(|s| is_blocking_failure(&spec, s)) {
+                tracing::warn!(
+                    run_id = %run_id,
+                    step_id = %failed.step_id,
+                    "Step failed with fail_fast set; skipping remaining steps"
+                );
+                break;
+            }
+        }
+        events::emit(events::Event::StepStarted {
+            run_id: run_id.clone(),
+            step_id: step.id.clone(),
+            executor: step.executor.clone(),
+        });
+        
         // Check condition
         if let Some(condition) = &step.condition {
             if !evaluate_condition(condition, &context) {
-                step_receipts.push(receipt::StepReceipt {
+                receipt.steps.push(receipt::StepReceipt {
                     step_id: step.id.clone(),
                     executor: step.executor.clone(),
                     status: "skipped".to_string(),
@@ -144,32 +497,83 @@ pub async fn run(workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::Wo
                     response: serde_json::json!(null),
                     denial_reason: None,
                     error: None,
+                    code: None,
                     duration_ms: 0,
                 });
                 continue;
             }
         }
         
+        // Check the step is permitted in this deployment mode
+        if let Some(denial) = environment_denial(step, &config.node.environment) {
+            receipt.steps.push(receipt::StepReceipt::from_result(
+                &step.id,
+                &step.executor,
+                step.input.clone(),
+                executor::ExecutorResult::Denied { denial_reason: denial },
+                &[],
+            ));
+            continue;
+        }
+        
+        // A secret that can't be resolved fails the step rather than sending `${secret.NAME}`
+        if let Err(e) = context.load_secrets(step).await {
+            receipt.steps.push(receipt::StepReceipt::from_result(
+                &step.id,
+                &step.executor,
+                step.input.clone(),
+                executor::ExecutorResult::error(executor::ExecutorError::Internal, format!("{:#}", e)),
+                &[],
+            ));
+            continue;
+        }
+        
         // Resolve input with variable substitution
-        let resolved_input = resolve_variables(&step.input, &step.uses, &context)?;
+        let mut secret_values = Vec::new();
+        let mut resolved_input = match resolve_variables(&step.input, &step.uses, &context, &mut secret_values) {
+            Ok(v) => v,
+            Err(e) => {
+                receipt.status = "crashed".to_string();
+                receipt.completed_at = chrono::Utc::now().to_rfc3339();
+                checkpoint_receipt(&mut receipt, start_time).await;
+                report_run(&receipt);
+                return Err(e.context(format!("Step '{}' failed to resolve input", step.id)));
+            }
+        };
+        
+        // `workflow.call` reads the run it belongs to from the step context, never the input
+        if let (true, Some(obj)) = (step.executor == "workflow.call", resolved_input.as_object_mut()) {
+            obj.remove("call");
+        }
+        
+        // Replays reuse the recorded output rather than calling the executor again
+        if let Some(output) = replay.as_ref().and_then(|r| r.outputs.get(&step.id)) {
+            context.set_step_output(&step.id, output.clone());
+            outputs = output.clone();
+            receipt.steps.push(receipt::StepReceipt::replayed(
+                &step.id,
+                &step.executor,
+                resolved_input,
+                output.clone(),
+                &secret_values,
+            ));
+            continue;
+        }
         
         // Check if executor is allowed
-        if !config.security.allowed_executors.contains(&step.executor) {
+        if !executor::is_executor_allowed(&config.security.allowed_executors, &step.executor) {
             let denial = executor::DenialReason {
                 rule: "security.allowed_executors".to_string(),
                 attempted: step.executor.clone(),
                 policy: format!("Executor '{}' is not in allowed_executors list", step.executor),
             };
-            step_receipts.push(receipt::StepReceipt {
-                step_id: step.id.clone(),
-                executor: step.executor.clone(),
-                status: "denied".to_string(),
-                request: resolved_input.clone(),
-                response: serde_json::json!(null),
-                denial_reason: Some(denial),
-                error: None,
-                duration_ms: 0,
-            });
+            receipt.steps.push(receipt::StepReceipt::from_result(
+                &step.id,
+                &step.executor,
+                resolved_input,
+                executor::ExecutorResult::Denied { denial_reason: denial },
+                &secret_values,
+            ));
             continue;
         }
         
@@ -177,28 +581,70 @@ pub async fn run(workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::Wo
         let executor = match registry.get(&step.executor) {
             Some(e) => e,
             None => {
-                step_receipts.push(receipt::StepReceipt {
-                    step_id: step.id.clone(),
-                    executor: step.executor.clone(),
-                    status: "error".to_string(),
-                    request: resolved_input.clone(),
-                    response: serde_json::json!(null),
-                    denial_reason: None,
-                    error: Some(format!("Executor not found: {}", step.executor)),
-                    duration_ms: 0,
-                });
+                receipt.steps.push(receipt::StepReceipt::from_result(
+                    &step.id,
+                    &step.executor,
+                    resolved_input,
+                    registry.unavailable(&step.executor),
+                    &secret_values,
+                ));
                 continue;
             }
         };
         
-        // Execute
-        let result = executor.execute(resolved_input.clone(), config);
+        // Paid executors stop once the node's budget is spent
+        if budget::is_paid(&step.executor) {
+            if let Some(denial) = services.budget.check(None, &step.executor).await {
+                receipt.steps.push(receipt::StepReceipt::from_result(
+                    &step.id,
+                    &step.executor,
+                    resolved_input,
+                    executor::ExecutorResult::Denied { denial_reason: denial },
+                    &secret_values,
+                ));
+                continue;
+            }
+        }
+        
+        // Execute; `stream: true` logs an LLM step's progress as its reply is generated
+        let step_context = executor::StepContext {
+            run_id: run_id.clone(),
+            step_id: step.id.clone(),
+            depth: call.as_ref().map_or(0, |c| c.depth),
+            timeout_secs: step.timeout,
+            // Executors that produce files write them here (see `receipt::write_artifact`)
+            artifacts_dir: receipt::artifacts_dir(&run_id).ok(),
+        };
+        let result = executor::with_step_context(step_context, || {
+            if resolved_input["stream"] == true {
+                let mut chars = 0;
+                executor.execute_streaming(resolved_input.clone(), config, &mut |text: &str| {
+                    chars += text.chars().count();
+                    tracing::debug!(run_id = %run_id, step_id = %step.id, chars, "Step output streaming");
+                    std::ops::ControlFlow::Continue(())
+                })
+            } else {
+                executor.execute(resolved_input.clone(), config)
+            }
+        });
+        crate::cancel::note_result(&result);
         let step_receipt = receipt::StepReceipt::from_result(
             &step.id,
             &step.executor,
-            resolved_input,
+            resolved_input.clone(),
             result.clone(),
+            &secret_values,
         );
+        receipt.steps.push(step_receipt);
+        
+        // Record the step before anything else can await: a run cancelled or killed from here on
+        // keeps what it already did, and a rerun can replay it
+        checkpoint_receipt(&mut receipt, start_time).await;
+        report_steps(&receipt, &mut reported_steps);
+        
+        if budget::is_paid(&step.executor) {
+            services.budget.charge(None, &step.executor, &resolved_input, &result).await;
+        }
         
         // Store output in context
         if let executor::ExecutorResult::Executed { output, .. } = result {
@@ -207,45 +653,24 @@ pub async fn run(workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::Wo
             // If this is the last step, use as outputs
             outputs = output;
         }
-        
-        step_receipts.push(step_receipt);
     }
     
     let end_time = chrono::Utc::now();
     let total_duration = (end_time - start_time).num_milliseconds() as u64;
     
     // Determine overall status
-    let status = if step_receipts.iter().all(|s| s.status == "executed" || s.status == "skipped") {
-        "success"
-    } else if step_receipts.iter().any(|s| s.status == "executed") {
-        "partial"
-    } else {
-        "failed"
-    };
+    let status = overall_status(&spec, &receipt.steps);
     
-    // Build receipt
-    let receipt = receipt::WorkflowReceipt {
-        run_id: run_id.clone(),
-        workflow_id: workflow_id.to_string(),
-        node_id: config.node.id.clone(),
-        started_at: start_time.to_rfc3339(),
-        completed_at: end_time.to_rfc3339(),
-        status: status.to_string(),
-        mode: config.node.environment.clone(),
-        steps: step_receipts,
-        inputs: merged_inputs,
-        outputs,
-        debug: receipt::DebugInfo {
-            config_snapshot: receipt::config_snapshot_hash(),
-            executor_versions: HashMap::from([
-                ("http.request".to_string(), "0.1.0".to_string()),
-            ]),
-            total_duration_ms: total_duration,
-        },
-    };
+    // Finalize receipt
+    receipt.completed_at = end_time.to_rfc3339();
+    receipt.status = status.to_string();
+    receipt.outputs = outputs;
+    receipt.debug.total_duration_ms = total_duration;
     
     // Write receipt
-    receipt::write_receipt(&receipt)?;
+    receipt::write_receipt(&receipt).await?;
+    report_steps(&receipt, &mut reported_steps);
+    report_run(&receipt);
     
     tracing::info!(
         run_id = %run_id,
@@ -257,6 +682,37 @@ pub async fn run(workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::Wo
     Ok(receipt)
 }
 
+/// Publish `StepCompleted` for the steps added to the receipt since the last call
+fn report_steps(receipt: &receipt::WorkflowReceipt, reported: &mut usize) {
+    for step in &receipt.steps[*reported..] {
+        events::emit(events::Event::StepCompleted {
+            run_id: receipt.run_id.clone(),
+            step_id: step.step_id.clone(),
+            executor: step.executor.clone(),
+            status: step.status.clone(),
+            duration_ms: step.duration_ms,
+        });
+    }
+    *reported = receipt.steps.len();
+}
+
+fn report_run(receipt: &receipt::WorkflowReceipt) {
+    events::emit(events::Event::RunCompleted {
+        run_id: receipt.run_id.clone(),
+        workflow_id: receipt.workflow_id.clone(),
+        status: receipt.status.clone(),
+        duration_ms: receipt.debug.total_duration_ms,
+    });
+}
+
+/// Best-effort intermediate write; a failed checkpoint shouldn't abort the run
+async fn checkpoint_receipt(receipt: &mut receipt::WorkflowReceipt, start_time: chrono::DateTime<chrono::Utc>) {
+    receipt.debug.total_duration_ms = (chrono::Utc::now() - start_time).num_milliseconds() as u64;
+    if let Err(e) = receipt::write_receipt(receipt).await {
+        tracing::warn!(run_id = %receipt.run_id, "Failed to checkpoint receipt: {}", e);
+    }
+}
+
 // ============================================
 // Context for variable resolution
 // ============================================
@@ -264,6 +720,8 @@ pub async fn run(workflow_id: &str, inputs: Value) -> anyhow::Result<receipt::Wo
 struct Context {
     inputs: Value,
     steps: HashMap<String, Value>,
+    /// Secrets resolved so far, so each is resolved once per run
+    secrets: HashMap<String, String>,
 }
 
 impl Context {
@@ -271,7 +729,25 @@ impl Context {
         Self {
             inputs,
             steps: HashMap::new(),
+            secrets: HashMap::new(),
+        }
+    }
+    
+    /// Resolve the secrets `step` refers to that this run hasn't resolved yet. Backends
+    /// can run a helper command, so they run on a blocking thread.
+    async fn load_secrets(&mut self, step: &Step) -> anyhow::Result<()> {
+        for name in secret_names(&step.input, &step.uses)? {
+            if self.secrets.contains_key(&name) {
+                continue;
+            }
+            let value = tokio::task::spawn_blocking({
+                let name = name.clone();
+                move || crate::secrets::resolve(&name)
+            })
+            .await??;
+            self.secrets.insert(name, value);
         }
+        Ok(())
     }
     
     fn set_step_output(&mut self, step_id: &str, output: Value) {
@@ -309,15 +785,38 @@ impl Context {
                 }
                 std::env::var(parts[1]).ok().map(Value::String)
             }
+            "secret" => {
+                if parts.len() < 2 {
+                    return None;
+                }
+                self.secrets.get(parts[1]).cloned().map(Value::String)
+            }
             _ => None,
         }
     }
 }
 
+/// Names of the secrets `input` and `uses` refer to
+fn secret_names(input: &Value, uses: &HashMap<String, String>) -> anyhow::Result<Vec<String>> {
+    let json_str = serde_json::to_string(input)?;
+    let re = regex::Regex::new(r"\$\{secret\.([^}.]+)")?;
+    let mut names: Vec<String> = re.captures_iter(&json_str).map(|cap| cap[1].to_string()).collect();
+    names.extend(uses.values().filter_map(|path| {
+        path.strip_prefix("secret.").map(|rest| rest.split('.').next().unwrap_or(rest).to_string())
+    }));
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Substitute `${path}` references in `input`.
+/// Any resolved `${secret.*}` values are appended to `secret_values` so the
+/// caller can redact them before the request is stored.
 fn resolve_variables(
     input: &Value,
     uses: &HashMap<String, String>,
     context: &Context,
+    secret_values: &mut Vec<String>,
 ) -> anyhow::Result<Value> {
     // Simple variable substitution in strings
     let json_str = serde_json::to_string(input)?;
@@ -330,11 +829,10 @@ fn resolve_variables(
         let path = &cap[1];
         
         if let Some(value) = context.get(path) {
-            let replacement = match value {
-                Value::String(s) => s,
-                _ => serde_json::to_string(&value)?,
-            };
-            resolved = resolved.replace(full_match, &replacement);
+            if path.starts_with("secret.") {
+                secret_values.push(value_text(&value)?);
+            }
+            resolved = substitute(&resolved, full_match, &value)?;
         }
     }
     
@@ -342,11 +840,10 @@ fn resolve_variables(
     for (key, path) in uses {
         if let Some(value) = context.get(path) {
             let placeholder = format!("${{{}}}", key);
-            let replacement = match value {
-                Value::String(s) => s,
-                _ => serde_json::to_string(&value)?,
-            };
-            resolved = resolved.replace(&placeholder, &replacement);
+            if path.starts_with("secret.") {
+                secret_values.push(value_text(&value)?);
+            }
+            resolved = substitute(&resolved, &placeholder, &value)?;
         }
     }
     
@@ -354,6 +851,20 @@ fn resolve_variables(
     Ok(result)
 }
 
+fn value_text(value: &Value) -> anyhow::Result<String> {
+    Ok(match value {
+        Value::String(s) => s.clone(),
+        _ => serde_json::to_string(value)?,
+    })
+}
+
+/// Replace `placeholder` in serialized JSON with the value's text, escaped so quotes in it
+/// can't break the document
+fn substitute(json: &str, placeholder: &str, value: &Value) -> anyhow::Result<String> {
+    let escaped = serde_json::to_string(&value_text(value)?)?;
+    Ok(json.replace(placeholder, &escaped[1..escaped.len() - 1]))
+}
+
 fn evaluate_condition(condition: &str, context: &Context) -> bool {
     // Simple condition evaluation (just check if value exists and is truthy)
     if let Some(value) = context.get(condition) {
@@ -368,3 +879,158 @@ fn evaluate_condition(condition: &str, context: &Context) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn spec_with_inputs(inputs: &str) -> WorkflowSpec {
+        serde_yaml::from_str(&format!(
+            "version: \"1.0\"\nid: test\nname: Test\ndescription: null\ninputs:\n{}\nsteps: []\n",
+            inputs
+        ))
+        .unwrap()
+    }
+    
+    #[test]
+    fn test_missing_required_input() {
+        let spec = spec_with_inputs("  count:\n    type: integer\n    required: true\n  label:\n    type: string\n    required: true\n");
+        let merged = merge_inputs_with_defaults(&spec, serde_json::json!({}));
+        
+        let err = validate_inputs(&spec, &merged).unwrap_err();
+        assert_eq!(err.violations.len(), 2);
+        assert!(err.violations.iter().all(|v| v.message == "required input is missing"));
+    }
+    
+    #[test]
+    fn test_type_mismatch() {
+        let spec = spec_with_inputs("  count:\n    type: integer\n    required: true\n  ratio:\n    type: number\n");
+        let merged = merge_inputs_with_defaults(&spec, serde_json::json!({ "count": "ten", "ratio": 0.5 }));
+        
+        let err = validate_inputs(&spec, &merged).unwrap_err();
+        assert_eq!(err.violations, vec![InputViolation {
+            input: "count".to_string(),
+            message: "expected integer, got string".to_string(),
+        }]);
+    }
+    
+    #[test]
+    fn test_defaults_satisfy_required() {
+        let spec = spec_with_inputs("  url:\n    type: string\n    required: true\n    default: \"https://example.com\"\n");
+        let merged = merge_inputs_with_defaults(&spec, serde_json::json!({}));
+        
+        assert!(validate_inputs(&spec, &merged).is_ok());
+    }
+    
+    #[test]
+    fn test_defaults_expand_env() {
+        std::env::set_var("ONECLAW_TEST_DATA_DIR", "/srv/data");
+        let spec = spec_with_inputs(
+            "  dir:\n    type: string\n    default: \"${env.ONECLAW_TEST_DATA_DIR}/in\"\n  out:\n    type: string\n    default: \"${ONECLAW_TEST_DATA_DIR}/${ONECLAW_TEST_UNSET_VAR}\"\n",
+        );
+        let merged = merge_inputs_with_defaults(&spec, serde_json::json!({}));
+        
+        assert_eq!(merged["dir"], "/srv/data/in");
+        assert_eq!(merged["out"], "/srv/data/${ONECLAW_TEST_UNSET_VAR}");
+    }
+    
+    fn step_receipt(step_id: &str, status: &str) -> receipt::StepReceipt {
+        receipt::StepReceipt {
+            step_id: step_id.to_string(),
+            executor: "http.request".to_string(),
+            status: status.to_string(),
+            request: Value::Null,
+            response: Value::Null,
+            denial_reason: None,
+            error: None,
+            code: None,
+            duration_ms: 0,
+        }
+    }
+    
+    fn spec_with_steps(steps: &str) -> WorkflowSpec {
+        serde_yaml::from_str(&format!("version: \"1.0\"\nid: test\nname: Test\ndescription: null\n{}", steps)).unwrap()
+    }
+    
+    #[test]
+    fn test_continue_on_error_keeps_success() {
+        let spec = spec_with_steps(
+            "steps:\n  - id: optional\n    executor: http.request\n    input: {}\n    continue_on_error: true\n  - id: main\n    executor: http.request\n    input: {}\n",
+        );
+        let steps = vec![step_receipt("optional", "error"), step_receipt("main", "executed")];
+        
+        assert!(!is_blocking_failure(&spec, &steps[0]));
+        assert_eq!(overall_status(&spec, &steps), "success");
+    }
+    
+    #[test]
+    fn test_replay_stops_at_redacted_or_failed_step() {
+        let with_response = |id: &str, status: &str, response: Value| receipt::StepReceipt { response, ..step_receipt(id, status) };
+        let steps = vec![
+            with_response("fetch", "executed", serde_json::json!({ "status": 200 })),
+            step_receipt("maybe", "skipped"),
+            with_response("login", "executed", serde_json::json!({ "session": receipt::REDACTED })),
+            with_response("report", "executed", serde_json::json!({ "rows": 3 })),
+        ];
+        let outputs = replayable_outputs(steps);
+        assert_eq!(outputs.keys().collect::<Vec<_>>(), vec!["fetch"]);
+        
+        let steps = vec![step_receipt("first", "error"), with_response("second", "executed", serde_json::json!(1))];
+        assert!(replayable_outputs(steps).is_empty());
+    }
+    
+    #[test]
+    fn test_fail_fast_halts_on_blocking_failure() {
+        let spec = spec_with_steps(
+            "fail_fast: true\nsteps:\n  - id: first\n    executor: http.request\n    input: {}\n  - id: second\n    executor: http.request\n    input: {}\n",
+        );
+        assert!(spec.fail_fast);
+        
+        let steps = vec![step_receipt("first", "error")];
+        assert!(is_blocking_failure(&spec, &steps[0]));
+        assert_eq!(overall_status(&spec, &steps), "failed");
+        
+        let steps = vec![step_receipt("first", "replayed"), step_receipt("second", "denied")];
+        assert_eq!(overall_status(&spec, &steps), "partial");
+    }
+    
+    #[test]
+    fn test_resolve_substitutes_escaped_text() {
+        let mut context = Context::new(serde_json::json!({}));
+        context.set_step_output("fetch", serde_json::json!({ "status": 200, "body": "{\"items\":[1]}" }));
+        let input = serde_json::json!({
+            "input": "${steps.fetch.body}",
+            "status": "${steps.fetch.status}",
+            "label": "got ${steps.fetch.body}",
+        });
+        
+        let resolved = resolve_variables(&input, &HashMap::new(), &context, &mut Vec::new()).unwrap();
+        assert_eq!(resolved["input"], "{\"items\":[1]}");
+        assert_eq!(resolved["status"], "200");
+        assert_eq!(resolved["label"], "got {\"items\":[1]}");
+    }
+    
+    #[test]
+    fn test_secret_names_from_input_and_uses() {
+        let input = serde_json::json!({
+            "headers": { "Authorization": "Bearer ${secret.github}" },
+            "body": "${secret.github} ${inputs.q} ${secret.brave}",
+        });
+        let uses = HashMap::from([("token".to_string(), "secret.stripe".to_string())]);
+        
+        assert_eq!(secret_names(&input, &uses).unwrap(), vec!["brave", "github", "stripe"]);
+    }
+    
+    #[test]
+    fn test_private_step_denied_in_managed() {
+        let step: Step = serde_yaml::from_str(
+            "id: run_script\nexecutor: shell.exec\ninput: {}\nenvironments: [\"private\"]\n",
+        )
+        .unwrap();
+        
+        let denial = environment_denial(&step, "managed").expect("step should be denied");
+        assert_eq!(denial.rule, "node.environment");
+        assert_eq!(denial.attempted, "managed");
+        assert!(environment_denial(&step, "private").is_none());
+    }
+}